@@ -0,0 +1,20 @@
+-- One row per run of the stats collection with statistics about the
+-- requests made to the Bitcoin Core REST API.
+CREATE TABLE sync_runs (
+	id                                INTEGER   PRIMARY KEY   AUTOINCREMENT   NOT NULL,
+	started_at                        TEXT      NOT NULL,
+	finished_at                       TEXT      NOT NULL,
+
+	requests                          BIGINT    NOT NULL,
+	failed_requests                   BIGINT    NOT NULL,
+	bytes_downloaded                  BIGINT    NOT NULL,
+	latency_sum_ms                    BIGINT    NOT NULL,
+	latency_0_10ms                    BIGINT    NOT NULL,
+	latency_10_50ms                   BIGINT    NOT NULL,
+	latency_50_100ms                  BIGINT    NOT NULL,
+	latency_100_250ms                 BIGINT    NOT NULL,
+	latency_250_500ms                 BIGINT    NOT NULL,
+	latency_500_1000ms                BIGINT    NOT NULL,
+	latency_1000_5000ms               BIGINT    NOT NULL,
+	latency_5000ms_plus               BIGINT    NOT NULL
+);
@@ -0,0 +1,31 @@
+ALTER TABLE sync_runs RENAME TO sync_runs_new;
+
+CREATE TABLE sync_runs (
+	id                                INTEGER   PRIMARY KEY   AUTOINCREMENT   NOT NULL,
+	started_at                        TEXT      NOT NULL,
+	finished_at                       TEXT      NOT NULL,
+
+	requests                          BIGINT    NOT NULL,
+	failed_requests                   BIGINT    NOT NULL,
+	bytes_downloaded                  BIGINT    NOT NULL,
+	latency_sum_ms                    BIGINT    NOT NULL,
+	latency_0_10ms                    BIGINT    NOT NULL,
+	latency_10_50ms                   BIGINT    NOT NULL,
+	latency_50_100ms                  BIGINT    NOT NULL,
+	latency_100_250ms                 BIGINT    NOT NULL,
+	latency_250_500ms                 BIGINT    NOT NULL,
+	latency_500_1000ms                BIGINT    NOT NULL,
+	latency_1000_5000ms               BIGINT    NOT NULL,
+	latency_5000ms_plus               BIGINT    NOT NULL
+);
+
+INSERT INTO sync_runs
+SELECT
+	id, started_at, finished_at, requests, failed_requests, bytes_downloaded,
+	latency_sum_ms, latency_0_10ms, latency_10_50ms, latency_50_100ms,
+	latency_100_250ms, latency_250_500ms, latency_500_1000ms,
+	latency_1000_5000ms, latency_5000ms_plus
+FROM sync_runs_new
+WHERE finished_at IS NOT NULL;
+
+DROP TABLE sync_runs_new;
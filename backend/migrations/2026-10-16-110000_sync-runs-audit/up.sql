@@ -0,0 +1,45 @@
+-- Turn sync_runs into an audit log of every stats collection run. A row is
+-- inserted when a run starts and completed once it finishes. A run that
+-- didn't finish (e.g. it crashed) has a NULL finished_at.
+ALTER TABLE sync_runs RENAME TO sync_runs_old;
+
+CREATE TABLE sync_runs (
+	id                                INTEGER   PRIMARY KEY   AUTOINCREMENT   NOT NULL,
+	started_at                        TEXT      NOT NULL,
+	finished_at                       TEXT,
+	software_version                  TEXT,
+	stats_version                     INTEGER,
+
+	min_height                        BIGINT,
+	max_height                        BIGINT,
+	blocks_processed                  BIGINT    NOT NULL    DEFAULT (0),
+	error                             TEXT,
+
+	requests                          BIGINT    NOT NULL    DEFAULT (0),
+	failed_requests                   BIGINT    NOT NULL    DEFAULT (0),
+	bytes_downloaded                  BIGINT    NOT NULL    DEFAULT (0),
+	latency_sum_ms                    BIGINT    NOT NULL    DEFAULT (0),
+	latency_0_10ms                    BIGINT    NOT NULL    DEFAULT (0),
+	latency_10_50ms                   BIGINT    NOT NULL    DEFAULT (0),
+	latency_50_100ms                  BIGINT    NOT NULL    DEFAULT (0),
+	latency_100_250ms                 BIGINT    NOT NULL    DEFAULT (0),
+	latency_250_500ms                 BIGINT    NOT NULL    DEFAULT (0),
+	latency_500_1000ms                BIGINT    NOT NULL    DEFAULT (0),
+	latency_1000_5000ms               BIGINT    NOT NULL    DEFAULT (0),
+	latency_5000ms_plus               BIGINT    NOT NULL    DEFAULT (0)
+);
+
+INSERT INTO sync_runs (
+	id, started_at, finished_at, requests, failed_requests, bytes_downloaded,
+	latency_sum_ms, latency_0_10ms, latency_10_50ms, latency_50_100ms,
+	latency_100_250ms, latency_250_500ms, latency_500_1000ms,
+	latency_1000_5000ms, latency_5000ms_plus
+)
+SELECT
+	id, started_at, finished_at, requests, failed_requests, bytes_downloaded,
+	latency_sum_ms, latency_0_10ms, latency_10_50ms, latency_50_100ms,
+	latency_100_250ms, latency_250_500ms, latency_500_1000ms,
+	latency_1000_5000ms, latency_5000ms_plus
+FROM sync_runs_old;
+
+DROP TABLE sync_runs_old;
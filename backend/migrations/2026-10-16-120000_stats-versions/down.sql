@@ -0,0 +1,11 @@
+ALTER TABLE sync_runs DROP COLUMN stats_versions;
+
+ALTER TABLE block_stats ADD COLUMN stats_version INT NOT NULL DEFAULT (0);
+
+UPDATE block_stats SET stats_version = (
+	SELECT MIN(block_stats, tx_stats, input_stats, output_stats, script_stats, feerate_stats)
+	FROM stats_versions v
+	WHERE v.height = block_stats.height
+);
+
+DROP TABLE stats_versions;
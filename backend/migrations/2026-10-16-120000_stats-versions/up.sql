@@ -0,0 +1,25 @@
+-- The version of the stats in each of the stats tables per block height.
+-- The stats of a table at a height are recomputed when the version is older
+-- than the version the backend wants for that table. This replaces the
+-- block_stats.stats_version column which was shared between all tables.
+CREATE TABLE stats_versions (
+	height                            BIGINT    PRIMARY KEY   NOT NULL,
+	block_stats                       INTEGER   NOT NULL      DEFAULT (0),
+	tx_stats                          INTEGER   NOT NULL      DEFAULT (0),
+	input_stats                       INTEGER   NOT NULL      DEFAULT (0),
+	output_stats                      INTEGER   NOT NULL      DEFAULT (0),
+	script_stats                      INTEGER   NOT NULL      DEFAULT (0),
+	feerate_stats                     INTEGER   NOT NULL      DEFAULT (0)
+);
+
+INSERT INTO stats_versions (
+	height, block_stats, tx_stats, input_stats, output_stats, script_stats, feerate_stats
+)
+SELECT
+	height, stats_version, stats_version, stats_version, stats_version, stats_version, stats_version
+FROM block_stats;
+
+ALTER TABLE block_stats DROP COLUMN stats_version;
+
+-- The per-table versions wanted by the backend during a sync run as JSON object.
+ALTER TABLE sync_runs ADD COLUMN stats_versions TEXT;
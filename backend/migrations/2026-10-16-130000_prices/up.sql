@@ -0,0 +1,6 @@
+-- Daily BTC prices in USD. Optional and imported from a user-supplied
+-- CSV file or URL. Used for the fiat denominated exports.
+CREATE TABLE prices (
+	date                              TEXT      PRIMARY KEY   NOT NULL,
+	usd                               DOUBLE    NOT NULL
+);
@@ -0,0 +1,3 @@
+ALTER TABLE stats_versions DROP COLUMN truc_stats;
+
+DROP TABLE truc_stats;
@@ -0,0 +1,22 @@
+CREATE TABLE truc_stats (
+	height                            BIGINT    PRIMARY KEY   NOT NULL,
+	date                              TEXT      NOT NULL,
+
+	tx_v3                             INTEGER   NOT NULL,
+	tx_v3_with_parent_in_block        INTEGER   NOT NULL,
+	tx_v3_with_v3_parent_in_block     INTEGER   NOT NULL,
+	tx_v3_creating_p2a                INTEGER   NOT NULL,
+	tx_v3_spending_p2a                INTEGER   NOT NULL,
+	tx_v3_above_1000_vbyte            INTEGER   NOT NULL,
+
+	v3_vsize_avg                      FLOAT     NOT NULL,
+	v3_vsize_median                   FLOAT     NOT NULL,
+	v3_vsize_max                      BIGINT    NOT NULL,
+
+	v3_package_vsize_avg              FLOAT     NOT NULL,
+	v3_package_vsize_max              BIGINT    NOT NULL
+);
+
+-- The truc_stats of blocks we already have stats for are generated on the
+-- next run, as their version defaults to 0.
+ALTER TABLE stats_versions ADD COLUMN truc_stats INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1 @@
+ALTER TABLE tx_stats DROP COLUMN tx_ephemeral_dust_unspent;
@@ -0,0 +1 @@
+ALTER TABLE tx_stats ADD COLUMN tx_ephemeral_dust_unspent INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats DROP COLUMN tx_spending_p2a_ln_anchor;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_p2a_truc_child;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_p2a_other;
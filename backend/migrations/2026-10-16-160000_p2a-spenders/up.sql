@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats ADD COLUMN tx_spending_p2a_ln_anchor INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_p2a_truc_child INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_p2a_other INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+ALTER TABLE output_stats DROP COLUMN outputs_burned;
+ALTER TABLE output_stats DROP COLUMN outputs_burned_amount;
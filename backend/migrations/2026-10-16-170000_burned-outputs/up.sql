@@ -0,0 +1,2 @@
+ALTER TABLE output_stats ADD COLUMN outputs_burned INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_burned_amount BIGINT NOT NULL DEFAULT (0);
@@ -0,0 +1,4 @@
+ALTER TABLE output_stats DROP COLUMN outputs_opreturn_counterparty;
+ALTER TABLE output_stats DROP COLUMN outputs_opreturn_timestamping;
+ALTER TABLE output_stats DROP COLUMN outputs_p2ms_counterparty;
+ALTER TABLE output_stats DROP COLUMN outputs_p2ms_data;
@@ -0,0 +1,4 @@
+ALTER TABLE output_stats ADD COLUMN outputs_opreturn_counterparty INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_opreturn_timestamping INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_p2ms_counterparty INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_p2ms_data INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1 @@
+ALTER TABLE block_stats DROP COLUMN feerate_min_nonzero;
@@ -0,0 +1 @@
+ALTER TABLE block_stats ADD COLUMN feerate_min_nonzero REAL NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+ALTER TABLE sync_runs DROP COLUMN blocks_failed_validation;
+ALTER TABLE sync_runs DROP COLUMN unexpected_bits_changes;
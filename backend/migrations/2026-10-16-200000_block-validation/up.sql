@@ -0,0 +1,5 @@
+-- Results of the soft integrity checks of the fetched blocks. The number of
+-- unexpected difficulty changes is NULL when it wasn't checked (e.g. on the
+-- test networks, which allow minimum difficulty blocks).
+ALTER TABLE sync_runs ADD COLUMN blocks_failed_validation BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE sync_runs ADD COLUMN unexpected_bits_changes BIGINT;
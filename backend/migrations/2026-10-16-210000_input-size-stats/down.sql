@@ -0,0 +1,3 @@
+ALTER TABLE stats_versions DROP COLUMN input_size_stats;
+
+DROP TABLE input_size_stats;
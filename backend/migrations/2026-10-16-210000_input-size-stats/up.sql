@@ -0,0 +1,48 @@
+CREATE TABLE input_size_stats (
+	height                            BIGINT    PRIMARY KEY   NOT NULL,
+	date                              TEXT      NOT NULL,
+
+	size_p2pk_10th_percentile         FLOAT     NOT NULL,
+	size_p2pk_50th_percentile         FLOAT     NOT NULL,
+	size_p2pk_90th_percentile         FLOAT     NOT NULL,
+
+	size_p2pkh_10th_percentile        FLOAT     NOT NULL,
+	size_p2pkh_50th_percentile        FLOAT     NOT NULL,
+	size_p2pkh_90th_percentile        FLOAT     NOT NULL,
+
+	size_nested_p2wpkh_10th_percentile FLOAT     NOT NULL,
+	size_nested_p2wpkh_50th_percentile FLOAT     NOT NULL,
+	size_nested_p2wpkh_90th_percentile FLOAT     NOT NULL,
+
+	size_p2wpkh_10th_percentile       FLOAT     NOT NULL,
+	size_p2wpkh_50th_percentile       FLOAT     NOT NULL,
+	size_p2wpkh_90th_percentile       FLOAT     NOT NULL,
+
+	size_p2ms_10th_percentile         FLOAT     NOT NULL,
+	size_p2ms_50th_percentile         FLOAT     NOT NULL,
+	size_p2ms_90th_percentile         FLOAT     NOT NULL,
+
+	size_p2sh_10th_percentile         FLOAT     NOT NULL,
+	size_p2sh_50th_percentile         FLOAT     NOT NULL,
+	size_p2sh_90th_percentile         FLOAT     NOT NULL,
+
+	size_nested_p2wsh_10th_percentile FLOAT     NOT NULL,
+	size_nested_p2wsh_50th_percentile FLOAT     NOT NULL,
+	size_nested_p2wsh_90th_percentile FLOAT     NOT NULL,
+
+	size_p2wsh_10th_percentile        FLOAT     NOT NULL,
+	size_p2wsh_50th_percentile        FLOAT     NOT NULL,
+	size_p2wsh_90th_percentile        FLOAT     NOT NULL,
+
+	size_p2tr_keypath_10th_percentile FLOAT     NOT NULL,
+	size_p2tr_keypath_50th_percentile FLOAT     NOT NULL,
+	size_p2tr_keypath_90th_percentile FLOAT     NOT NULL,
+
+	size_p2tr_scriptpath_10th_percentile FLOAT     NOT NULL,
+	size_p2tr_scriptpath_50th_percentile FLOAT     NOT NULL,
+	size_p2tr_scriptpath_90th_percentile FLOAT     NOT NULL
+);
+
+-- The input_size_stats of blocks we already have stats for are generated on
+-- the next run, as their version defaults to 0.
+ALTER TABLE stats_versions ADD COLUMN input_size_stats INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,3 @@
+ALTER TABLE stats_versions DROP COLUMN dormant_spends;
+
+DROP TABLE dormant_spends;
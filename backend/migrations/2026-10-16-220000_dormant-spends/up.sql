@@ -0,0 +1,15 @@
+CREATE TABLE dormant_spends (
+	height                            BIGINT    PRIMARY KEY   NOT NULL,
+	date                              TEXT      NOT NULL,
+
+	spends_5y                         INTEGER   NOT NULL,
+	spends_5y_amount                  BIGINT    NOT NULL,
+	spends_10y                        INTEGER   NOT NULL,
+	spends_10y_amount                 BIGINT    NOT NULL,
+	spends_pre_2011                   INTEGER   NOT NULL,
+	spends_pre_2011_amount            BIGINT    NOT NULL
+);
+
+-- The dormant_spends of blocks we already have stats for are generated on
+-- the next run, as their version defaults to 0.
+ALTER TABLE stats_versions ADD COLUMN dormant_spends INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1 @@
+DROP TABLE coinbase_spends;
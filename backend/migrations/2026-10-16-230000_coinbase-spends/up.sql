@@ -0,0 +1,11 @@
+-- Only filled when the coinbase spend tracking is enabled.
+CREATE TABLE coinbase_spends (
+	coinbase_height                   BIGINT    NOT NULL,
+	vout                              INTEGER   NOT NULL,
+	spend_height                      BIGINT    NOT NULL,
+	txid                              TEXT      NOT NULL,
+	value                             BIGINT    NOT NULL,
+	outputs                           INTEGER   NOT NULL,
+	destination_type                  TEXT      NOT NULL,
+	PRIMARY KEY (coinbase_height, vout)
+);
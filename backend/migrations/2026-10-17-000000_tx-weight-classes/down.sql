@@ -0,0 +1,7 @@
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_0_140;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_141_250;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_251_500;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_501_1000;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_1001_10000;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_10001_100000;
+ALTER TABLE tx_stats DROP COLUMN tx_vsize_100001_plus;
@@ -0,0 +1,7 @@
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_0_140 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_141_250 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_251_500 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_501_1000 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_1001_10000 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_10001_100000 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_vsize_100001_plus INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+ALTER TABLE script_stats DROP COLUMN multisig_dummy_null;
+ALTER TABLE script_stats DROP COLUMN multisig_dummy_not_null;
@@ -0,0 +1,2 @@
+ALTER TABLE script_stats ADD COLUMN multisig_dummy_null INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN multisig_dummy_not_null INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_64byte;
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_65byte;
@@ -0,0 +1,2 @@
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_64byte INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_65byte INTEGER NOT NULL DEFAULT (0);
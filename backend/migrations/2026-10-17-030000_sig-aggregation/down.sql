@@ -0,0 +1,3 @@
+ALTER TABLE script_stats DROP COLUMN inputs_p2tr_keypath_in_multi_keypath_tx;
+ALTER TABLE script_stats DROP COLUMN tx_multiple_p2tr_keypath_inputs;
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_aggregatable;
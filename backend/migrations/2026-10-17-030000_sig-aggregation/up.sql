@@ -0,0 +1,3 @@
+ALTER TABLE script_stats ADD COLUMN inputs_p2tr_keypath_in_multi_keypath_tx INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN tx_multiple_p2tr_keypath_inputs INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_aggregatable INTEGER NOT NULL DEFAULT (0);
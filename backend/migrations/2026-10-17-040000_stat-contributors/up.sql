@@ -0,0 +1,7 @@
+-- Only filled when capturing the contributors of a tx_stats counter.
+CREATE TABLE stat_contributors (
+	height                            BIGINT    NOT NULL,
+	stat                              TEXT      NOT NULL,
+	txid                              TEXT      NOT NULL,
+	PRIMARY KEY (height, stat, txid)
+);
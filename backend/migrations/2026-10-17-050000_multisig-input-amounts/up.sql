@@ -0,0 +1,10 @@
+ALTER TABLE input_stats ADD COLUMN inputs_spending_multisig_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_spending_p2ms_multisig_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_spending_p2sh_multisig_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_spending_nested_p2wsh_multisig_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_spending_p2wsh_multisig_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_p2pk_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_p2pkh_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_nested_p2wpkh_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_p2wpkh_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE input_stats ADD COLUMN inputs_p2tr_keypath_amount BIGINT NOT NULL DEFAULT (0);
@@ -0,0 +1,5 @@
+ALTER TABLE tx_stats DROP COLUMN tx_spending_segwit_amount;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_taproot_amount;
+ALTER TABLE tx_stats DROP COLUMN tx_signaling_explicit_rbf_amount;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_multisig;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_multisig_amount;
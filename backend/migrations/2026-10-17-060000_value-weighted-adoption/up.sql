@@ -0,0 +1,5 @@
+ALTER TABLE tx_stats ADD COLUMN tx_spending_segwit_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_taproot_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_signaling_explicit_rbf_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_multisig INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_multisig_amount BIGINT NOT NULL DEFAULT (0);
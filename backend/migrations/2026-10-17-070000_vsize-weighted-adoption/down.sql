@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats DROP COLUMN tx_spending_segwit_vsize;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_taproot_vsize;
+ALTER TABLE tx_stats DROP COLUMN tx_spending_only_legacy_vsize;
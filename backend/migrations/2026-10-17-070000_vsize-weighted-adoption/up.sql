@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats ADD COLUMN tx_spending_segwit_vsize INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_taproot_vsize INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_spending_only_legacy_vsize INTEGER NOT NULL DEFAULT (0);
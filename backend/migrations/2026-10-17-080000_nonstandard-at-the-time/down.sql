@@ -0,0 +1,2 @@
+ALTER TABLE output_stats DROP COLUMN outputs_opreturn_nonstandard_at_the_time;
+ALTER TABLE output_stats DROP COLUMN outputs_dust_at_the_time;
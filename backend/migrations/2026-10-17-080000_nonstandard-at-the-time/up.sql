@@ -0,0 +1,2 @@
+ALTER TABLE output_stats ADD COLUMN outputs_opreturn_nonstandard_at_the_time INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_dust_at_the_time INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1 @@
+DROP TABLE opcode_stats;
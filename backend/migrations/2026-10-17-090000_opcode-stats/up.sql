@@ -0,0 +1,8 @@
+-- Only filled when the opcode tracking is enabled.
+CREATE TABLE opcode_stats (
+	height                            BIGINT    NOT NULL,
+	script_type                       TEXT      NOT NULL,
+	opcode                            TEXT      NOT NULL,
+	count                             INTEGER   NOT NULL,
+	PRIMARY KEY (height, script_type, opcode)
+);
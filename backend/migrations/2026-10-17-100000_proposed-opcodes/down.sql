@@ -0,0 +1,7 @@
+ALTER TABLE script_stats DROP COLUMN outputs_bare_ctv;
+ALTER TABLE script_stats DROP COLUMN outputs_nop4;
+ALTER TABLE script_stats DROP COLUMN outputs_reserved_nop;
+ALTER TABLE script_stats DROP COLUMN scripts_nop4;
+ALTER TABLE script_stats DROP COLUMN scripts_reserved_nop;
+ALTER TABLE script_stats DROP COLUMN tapscripts_op_success;
+ALTER TABLE script_stats DROP COLUMN tapscripts_op_cat;
@@ -0,0 +1,7 @@
+ALTER TABLE script_stats ADD COLUMN outputs_bare_ctv INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN outputs_nop4 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN outputs_reserved_nop INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN scripts_nop4 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN scripts_reserved_nop INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN tapscripts_op_success INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN tapscripts_op_cat INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats DROP COLUMN tx_txid_leading_zero_bytes_2;
+ALTER TABLE tx_stats DROP COLUMN tx_txid_leading_zero_bytes_3;
+ALTER TABLE tx_stats DROP COLUMN tx_txid_leading_zero_bytes_4;
@@ -0,0 +1,3 @@
+ALTER TABLE tx_stats ADD COLUMN tx_txid_leading_zero_bytes_2 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_txid_leading_zero_bytes_3 INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_txid_leading_zero_bytes_4 INTEGER NOT NULL DEFAULT (0);
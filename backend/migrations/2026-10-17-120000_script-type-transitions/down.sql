@@ -0,0 +1 @@
+DROP TABLE script_type_transitions;
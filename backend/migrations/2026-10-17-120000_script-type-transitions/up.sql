@@ -0,0 +1,8 @@
+-- Only filled when the script type transition tracking is enabled.
+CREATE TABLE script_type_transitions (
+	height                            BIGINT    NOT NULL,
+	from_type                         TEXT      NOT NULL,
+	to_type                           TEXT      NOT NULL,
+	count                             INTEGER   NOT NULL,
+	PRIMARY KEY (height, from_type, to_type)
+);
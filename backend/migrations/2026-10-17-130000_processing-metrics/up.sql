@@ -0,0 +1,8 @@
+-- How long fetching, calculating the stats of and inserting each block took
+-- when it was last processed.
+CREATE TABLE processing_metrics (
+	height                            BIGINT    PRIMARY KEY   NOT NULL,
+	fetch_ms                          BIGINT    NOT NULL,
+	stats_ms                          BIGINT    NOT NULL,
+	insert_ms                         BIGINT    NOT NULL
+);
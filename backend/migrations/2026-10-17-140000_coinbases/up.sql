@@ -0,0 +1,6 @@
+-- The raw coinbase transaction of each processed block, kept to re-identify
+-- pools or analyze coinbase tags without refetching the blocks.
+CREATE TABLE coinbases (
+	height                            BIGINT    NOT NULL PRIMARY KEY,
+	raw                               BLOB      NOT NULL
+);
@@ -0,0 +1 @@
+DROP TABLE signet_challenge;
@@ -0,0 +1,5 @@
+-- The challenge of the signet the database has stats for. Only filled on
+-- signet, where the challenge identifies the network.
+CREATE TABLE signet_challenge (
+	challenge                         BLOB      NOT NULL PRIMARY KEY
+);
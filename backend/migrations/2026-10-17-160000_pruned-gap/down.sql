@@ -0,0 +1,2 @@
+ALTER TABLE sync_runs DROP COLUMN pruned_min_height;
+ALTER TABLE sync_runs DROP COLUMN pruned_max_height;
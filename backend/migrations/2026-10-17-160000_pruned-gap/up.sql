@@ -0,0 +1,4 @@
+-- The heights skipped because the node pruned the blocks (--skip-pruned).
+-- NULL when no heights were skipped.
+ALTER TABLE sync_runs ADD COLUMN pruned_min_height BIGINT;
+ALTER TABLE sync_runs ADD COLUMN pruned_max_height BIGINT;
@@ -0,0 +1,6 @@
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible;
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible_outputs_p2tr;
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible_inputs_p2tr;
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible_inputs_p2wpkh;
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible_inputs_p2sh_p2wpkh;
+ALTER TABLE tx_stats DROP COLUMN tx_silent_payments_eligible_inputs_p2pkh;
@@ -0,0 +1,6 @@
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible_outputs_p2tr INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible_inputs_p2tr INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible_inputs_p2wpkh INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible_inputs_p2sh_p2wpkh INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_silent_payments_eligible_inputs_p2pkh INTEGER NOT NULL DEFAULT (0);
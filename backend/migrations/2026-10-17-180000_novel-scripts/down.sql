@@ -0,0 +1 @@
+DROP TABLE novel_scripts;
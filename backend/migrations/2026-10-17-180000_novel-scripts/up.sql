@@ -0,0 +1,10 @@
+-- The first output of each non-standard script template. Only filled when the
+-- novel script tracking is enabled.
+CREATE TABLE novel_scripts (
+	template                          TEXT      NOT NULL PRIMARY KEY,
+	height                            BIGINT    NOT NULL,
+	txid                              TEXT      NOT NULL,
+	vout                              INTEGER   NOT NULL,
+	script                            TEXT      NOT NULL
+);
+CREATE INDEX novel_scripts_height ON novel_scripts (height);
@@ -0,0 +1,5 @@
+ALTER TABLE script_stats DROP COLUMN witness_bytes;
+ALTER TABLE script_stats DROP COLUMN witness_bytes_signatures;
+ALTER TABLE script_stats DROP COLUMN witness_bytes_pubkeys;
+ALTER TABLE script_stats DROP COLUMN witness_bytes_scripts;
+ALTER TABLE script_stats DROP COLUMN witness_bytes_data;
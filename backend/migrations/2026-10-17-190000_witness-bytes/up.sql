@@ -0,0 +1,5 @@
+ALTER TABLE script_stats ADD COLUMN witness_bytes BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN witness_bytes_signatures BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN witness_bytes_pubkeys BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN witness_bytes_scripts BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN witness_bytes_data BIGINT NOT NULL DEFAULT (0);
@@ -0,0 +1 @@
+DROP TABLE feerate_percentiles;
@@ -0,0 +1,7 @@
+-- Only filled for the percentiles configured with --feerate-percentiles.
+CREATE TABLE feerate_percentiles (
+	height                            BIGINT    NOT NULL,
+	percentile                        INTEGER   NOT NULL,
+	value                             REAL      NOT NULL,
+	PRIMARY KEY (height, percentile)
+);
@@ -0,0 +1,7 @@
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_5th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_10th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_25th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_50th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_75th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_90th_percentile;
+ALTER TABLE block_stats DROP COLUMN feerate_vsize_weighted_95th_percentile;
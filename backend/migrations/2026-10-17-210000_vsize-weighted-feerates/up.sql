@@ -0,0 +1,7 @@
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_5th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_10th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_25th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_50th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_75th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_90th_percentile REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN feerate_vsize_weighted_95th_percentile REAL NOT NULL DEFAULT (0);
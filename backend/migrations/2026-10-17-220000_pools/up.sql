@@ -0,0 +1,9 @@
+-- The mining pool metadata of the pool identification data. Refreshed before
+-- writing the CSV files, so pool_id columns can be joined to pool names.
+CREATE TABLE pools (
+	id                                INTEGER   PRIMARY KEY NOT NULL,
+	name                              TEXT      NOT NULL,
+	link                              TEXT      NOT NULL,
+	-- JSON array of the coinbase tags the pool uses or used
+	tags                              TEXT      NOT NULL
+);
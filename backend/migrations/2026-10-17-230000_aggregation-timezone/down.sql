@@ -0,0 +1,2 @@
+ALTER TABLE block_stats DROP COLUMN time;
+DROP TABLE aggregation_timezone;
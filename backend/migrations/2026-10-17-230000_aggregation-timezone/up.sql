@@ -0,0 +1,5 @@
+ALTER TABLE block_stats ADD COLUMN time BIGINT NOT NULL DEFAULT (0);
+-- The UTC offset in seconds the date columns are calculated in. UTC if empty.
+CREATE TABLE aggregation_timezone (
+	utc_offset_seconds                INTEGER   PRIMARY KEY NOT NULL
+);
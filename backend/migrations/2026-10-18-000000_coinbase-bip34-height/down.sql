@@ -0,0 +1,2 @@
+ALTER TABLE block_stats DROP COLUMN coinbase_script_height;
+ALTER TABLE block_stats DROP COLUMN coinbase_bip34_height;
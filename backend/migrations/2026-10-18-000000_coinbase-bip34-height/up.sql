@@ -0,0 +1,2 @@
+ALTER TABLE block_stats ADD COLUMN coinbase_script_height BIGINT NOT NULL DEFAULT (-1);
+ALTER TABLE block_stats ADD COLUMN coinbase_bip34_height BOOLEAN NOT NULL DEFAULT (FALSE);
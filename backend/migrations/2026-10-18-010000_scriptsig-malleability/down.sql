@@ -0,0 +1,2 @@
+ALTER TABLE tx_stats DROP COLUMN tx_scriptsig_non_push;
+ALTER TABLE tx_stats DROP COLUMN tx_scriptsig_non_minimal_push;
@@ -0,0 +1,2 @@
+ALTER TABLE tx_stats ADD COLUMN tx_scriptsig_non_push INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_scriptsig_non_minimal_push INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,4 @@
+ALTER TABLE tx_stats DROP COLUMN tx_payment_like;
+ALTER TABLE tx_stats DROP COLUMN tx_data_embedding;
+ALTER TABLE tx_stats DROP COLUMN tx_data_embedding_inscription;
+ALTER TABLE tx_stats DROP COLUMN tx_data_embedding_opreturn;
@@ -0,0 +1,4 @@
+ALTER TABLE tx_stats ADD COLUMN tx_payment_like INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_data_embedding INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_data_embedding_inscription INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE tx_stats ADD COLUMN tx_data_embedding_opreturn INTEGER NOT NULL DEFAULT (0);
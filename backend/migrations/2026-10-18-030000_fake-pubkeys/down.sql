@@ -0,0 +1,4 @@
+ALTER TABLE output_stats DROP COLUMN outputs_p2pk_fake_pubkey;
+ALTER TABLE output_stats DROP COLUMN outputs_p2pkh_fake_pubkey_hash;
+ALTER TABLE output_stats DROP COLUMN outputs_p2ms_fake_pubkey;
+ALTER TABLE output_stats DROP COLUMN outputs_fake_pubkey_amount;
@@ -0,0 +1,4 @@
+ALTER TABLE output_stats ADD COLUMN outputs_p2pk_fake_pubkey INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_p2pkh_fake_pubkey_hash INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_p2ms_fake_pubkey INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_fake_pubkey_amount BIGINT NOT NULL DEFAULT (0);
@@ -0,0 +1,5 @@
+ALTER TABLE output_stats DROP COLUMN outputs_utxo_net_inscription;
+ALTER TABLE output_stats DROP COLUMN outputs_utxo_net_runestone;
+ALTER TABLE output_stats DROP COLUMN outputs_utxo_net_counterparty;
+ALTER TABLE output_stats DROP COLUMN outputs_utxo_net_other_data;
+ALTER TABLE output_stats DROP COLUMN outputs_utxo_net_ordinary;
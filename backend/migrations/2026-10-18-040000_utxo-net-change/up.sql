@@ -0,0 +1,5 @@
+ALTER TABLE output_stats ADD COLUMN outputs_utxo_net_inscription INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_utxo_net_runestone INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_utxo_net_counterparty INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_utxo_net_other_data INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE output_stats ADD COLUMN outputs_utxo_net_ordinary INTEGER NOT NULL DEFAULT (0);
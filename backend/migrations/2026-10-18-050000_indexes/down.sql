@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS block_stats_date;
+DROP INDEX IF EXISTS tx_stats_date;
+DROP INDEX IF EXISTS input_stats_date;
+DROP INDEX IF EXISTS output_stats_date;
+DROP INDEX IF EXISTS script_stats_date;
+DROP INDEX IF EXISTS feerate_stats_date;
+DROP INDEX IF EXISTS truc_stats_date;
+DROP INDEX IF EXISTS input_size_stats_date;
+DROP INDEX IF EXISTS dormant_spends_date;
+DROP INDEX IF EXISTS block_stats_pool_id;
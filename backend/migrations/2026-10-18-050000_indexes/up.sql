@@ -0,0 +1,11 @@
+-- The CSV exports group the stats by date and the mining pool exports by pool.
+CREATE INDEX IF NOT EXISTS block_stats_date ON block_stats (date);
+CREATE INDEX IF NOT EXISTS tx_stats_date ON tx_stats (date);
+CREATE INDEX IF NOT EXISTS input_stats_date ON input_stats (date);
+CREATE INDEX IF NOT EXISTS output_stats_date ON output_stats (date);
+CREATE INDEX IF NOT EXISTS script_stats_date ON script_stats (date);
+CREATE INDEX IF NOT EXISTS feerate_stats_date ON feerate_stats (date);
+CREATE INDEX IF NOT EXISTS truc_stats_date ON truc_stats (date);
+CREATE INDEX IF NOT EXISTS input_size_stats_date ON input_size_stats (date);
+CREATE INDEX IF NOT EXISTS dormant_spends_date ON dormant_spends (date);
+CREATE INDEX IF NOT EXISTS block_stats_pool_id ON block_stats (pool_id);
@@ -0,0 +1,4 @@
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_keypath_sighash_default;
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_keypath_sighash_all;
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_scriptpath_sighash_default;
+ALTER TABLE script_stats DROP COLUMN sigs_schnorr_scriptpath_sighash_all;
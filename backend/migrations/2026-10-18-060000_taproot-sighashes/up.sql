@@ -0,0 +1,4 @@
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_keypath_sighash_default INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_keypath_sighash_all INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_scriptpath_sighash_default INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN sigs_schnorr_scriptpath_sighash_all INTEGER NOT NULL DEFAULT (0);
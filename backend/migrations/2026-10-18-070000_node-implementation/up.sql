@@ -0,0 +1,3 @@
+-- The node implementation the stats were collected from (--node-implementation).
+-- NULL for runs before it was recorded.
+ALTER TABLE sync_runs ADD COLUMN node_implementation TEXT;
@@ -0,0 +1,12 @@
+-- The relay and mempool policy the node advertised at the start of a sync
+-- run, as context for the mempool-dependent stats. The feerates are in
+-- sat/vB.
+CREATE TABLE node_policy_snapshots (
+	sync_run_id                       INTEGER   PRIMARY KEY   NOT NULL,
+	recorded_at                       TEXT      NOT NULL,
+	max_mempool_bytes                 BIGINT    NOT NULL,
+	mempool_min_fee                   DOUBLE    NOT NULL,
+	min_relay_tx_fee                  DOUBLE    NOT NULL,
+	incremental_relay_fee             DOUBLE,
+	full_rbf                          BOOLEAN
+);
@@ -0,0 +1,4 @@
+ALTER TABLE script_stats DROP COLUMN redeem_scripts_nonstandard_witness_program;
+ALTER TABLE script_stats DROP COLUMN redeem_scripts_p2sh;
+ALTER TABLE script_stats DROP COLUMN witness_scripts_nested_program;
+ALTER TABLE script_stats DROP COLUMN tapscripts_nested_program;
@@ -0,0 +1,4 @@
+ALTER TABLE script_stats ADD COLUMN redeem_scripts_nonstandard_witness_program INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN redeem_scripts_p2sh INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN witness_scripts_nested_program INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE script_stats ADD COLUMN tapscripts_nested_program INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+-- The difficulty calculated exactly from the bits, as a decimal string.
+ALTER TABLE block_stats ADD COLUMN difficulty_exact TEXT NOT NULL DEFAULT ('');
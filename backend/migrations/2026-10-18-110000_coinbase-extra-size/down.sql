@@ -0,0 +1 @@
+ALTER TABLE block_stats DROP COLUMN coinbase_extra_size;
@@ -0,0 +1 @@
+ALTER TABLE block_stats ADD COLUMN coinbase_extra_size INTEGER NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+ALTER TABLE block_stats DROP COLUMN version_rolling;
+ALTER TABLE block_stats DROP COLUMN version_rolling_bits;
@@ -0,0 +1,2 @@
+ALTER TABLE block_stats ADD COLUMN version_rolling_bits INTEGER NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN version_rolling BOOLEAN NOT NULL DEFAULT (FALSE);
@@ -0,0 +1,2 @@
+ALTER TABLE block_stats DROP COLUMN coinbase_large_displacing;
+ALTER TABLE block_stats DROP COLUMN coinbase_weight_share;
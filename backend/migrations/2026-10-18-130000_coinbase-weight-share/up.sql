@@ -0,0 +1,2 @@
+ALTER TABLE block_stats ADD COLUMN coinbase_weight_share REAL NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN coinbase_large_displacing BOOLEAN NOT NULL DEFAULT (FALSE);
@@ -0,0 +1,3 @@
+ALTER TABLE block_stats DROP COLUMN inputs_per_output;
+ALTER TABLE block_stats DROP COLUMN output_amount;
+ALTER TABLE block_stats DROP COLUMN input_amount;
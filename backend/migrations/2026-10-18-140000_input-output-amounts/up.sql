@@ -0,0 +1,3 @@
+ALTER TABLE block_stats ADD COLUMN input_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN output_amount BIGINT NOT NULL DEFAULT (0);
+ALTER TABLE block_stats ADD COLUMN inputs_per_output REAL NOT NULL DEFAULT (0);
@@ -0,0 +1,2 @@
+DROP TABLE pruned_height;
+DROP TABLE daily_metrics;
@@ -0,0 +1,16 @@
+-- Per-date aggregates of the metrics of pruned blocks, combined with the
+-- aggregates of the remaining blocks when exporting the metrics.
+CREATE TABLE daily_metrics (
+	table_name                        TEXT      NOT NULL,
+	column_name                       TEXT      NOT NULL,
+	date                              TEXT      NOT NULL,
+	avg                               DOUBLE    NOT NULL,
+	sum                               BIGINT    NOT NULL,
+	blocks                            BIGINT    NOT NULL,
+	PRIMARY KEY (table_name, column_name, date)
+);
+
+-- The per-block rows below this height were pruned and aren't synced again.
+CREATE TABLE pruned_height (
+	below_height                      BIGINT    PRIMARY KEY NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE daily_metrics DROP COLUMN utc_offset_seconds;
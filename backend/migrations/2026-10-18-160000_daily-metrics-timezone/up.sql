@@ -0,0 +1,4 @@
+-- The UTC offset in seconds the dates of the pruned daily aggregates were
+-- calculated in, i.e. the aggregation timezone at the time of the pruning.
+ALTER TABLE daily_metrics ADD COLUMN utc_offset_seconds INTEGER NOT NULL DEFAULT (0);
+UPDATE daily_metrics SET utc_offset_seconds = COALESCE((SELECT utc_offset_seconds FROM aggregation_timezone), 0);
@@ -0,0 +1,2 @@
+ALTER TABLE node_policy_snapshots DROP COLUMN permit_bare_multisig;
+ALTER TABLE node_policy_snapshots DROP COLUMN max_data_carrier_size;
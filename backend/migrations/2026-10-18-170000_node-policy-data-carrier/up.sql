@@ -0,0 +1,4 @@
+-- The OP_RETURN and bare multisig relay policy, only advertised by nodes from
+-- v29 on.
+ALTER TABLE node_policy_snapshots ADD COLUMN max_data_carrier_size BIGINT;
+ALTER TABLE node_policy_snapshots ADD COLUMN permit_bare_multisig BOOLEAN;
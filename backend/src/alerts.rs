@@ -0,0 +1,187 @@
+use crate::db;
+use crate::rest::{RestClient, RestError};
+use crate::stats::DormantSpends;
+use clap::ValueEnum;
+use diesel::SqliteConnection;
+use log::{debug, info, warn};
+use std::sync::{Arc, Mutex};
+use std::{error, fmt};
+
+#[derive(Debug)]
+pub enum AlertError {
+    DB(diesel::result::Error),
+    Rest(RestError),
+    MinReq(minreq::Error),
+    Http(i32, String),
+}
+
+impl fmt::Display for AlertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlertError::DB(e) => write!(f, "Database Error: {:?}", e),
+            AlertError::Rest(e) => write!(f, "REST error: {}", e),
+            AlertError::MinReq(e) => write!(f, "MinReq HTTP POST request error: {:?}", e),
+            AlertError::Http(code, msg) => write!(f, "HTTP error: {} {}", code, msg),
+        }
+    }
+}
+
+impl error::Error for AlertError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            AlertError::DB(ref e) => Some(e),
+            AlertError::Rest(ref e) => Some(e),
+            AlertError::MinReq(ref e) => Some(e),
+            AlertError::Http(_, _) => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for AlertError {
+    fn from(e: diesel::result::Error) -> Self {
+        AlertError::DB(e)
+    }
+}
+
+impl From<RestError> for AlertError {
+    fn from(e: RestError) -> Self {
+        AlertError::Rest(e)
+    }
+}
+
+impl From<minreq::Error> for AlertError {
+    fn from(e: minreq::Error) -> Self {
+        AlertError::MinReq(e)
+    }
+}
+
+/// The minimum age of the spent outputs a block alert is sent for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DormantThreshold {
+    /// outputs created at least 5 years before the spending block
+    #[value(name = "5y")]
+    FiveYears,
+    /// outputs created at least 10 years before the spending block
+    #[value(name = "10y")]
+    TenYears,
+    /// outputs created before 2011
+    #[value(name = "pre-2011")]
+    Pre2011,
+}
+
+impl DormantThreshold {
+    /// The number of spends and their value in sat above the threshold.
+    fn spends(&self, s: &DormantSpends) -> (i32, i64) {
+        match self {
+            DormantThreshold::FiveYears => (s.spends_5y, s.spends_5y_amount),
+            DormantThreshold::TenYears => (s.spends_10y, s.spends_10y_amount),
+            DormantThreshold::Pre2011 => (s.spends_pre_2011, s.spends_pre_2011_amount),
+        }
+    }
+}
+
+impl fmt::Display for DormantThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DormantThreshold::FiveYears => write!(f, "5y"),
+            DormantThreshold::TenYears => write!(f, "10y"),
+            DormantThreshold::Pre2011 => write!(f, "pre-2011"),
+        }
+    }
+}
+
+fn post_json(url: &str, body: &serde_json::Value) -> Result<(), AlertError> {
+    let response = minreq::post(url)
+        .with_header("Content-Type", "application/json")
+        .with_body(body.to_string())
+        .send()?;
+    if !(200..300).contains(&response.status_code) {
+        return Err(AlertError::Http(
+            response.status_code,
+            response.reason_phrase,
+        ));
+    }
+    Ok(())
+}
+
+/// Compares the height of the latest block in the database with the node's
+/// tip. Logs a warning and, if a webhook `url` is set, POSTs a JSON alert to
+/// it when the database lags more than `max_lag` blocks behind, e.g. because
+/// the runs fail or stopped. Returns the lag.
+pub fn check_sync_lag(
+    client: &RestClient,
+    connection: Arc<Mutex<SqliteConnection>>,
+    max_lag: u64,
+    url: Option<&str>,
+) -> Result<u64, AlertError> {
+    let tip = client.chain_info()?.blocks;
+    let db_height = {
+        let mut conn = connection.lock().unwrap();
+        db::get_db_block_height(&mut conn)?
+    };
+    let lag = match db_height {
+        Some(height) => tip.saturating_sub(height as u64),
+        // an empty database lags behind by all blocks including the genesis block
+        None => tip + 1,
+    };
+
+    if lag > max_lag {
+        warn!(
+            "The database is {} blocks behind the node's tip at height {} (allowed: {})",
+            lag, tip, max_lag
+        );
+        if let Some(url) = url {
+            post_json(
+                url,
+                &serde_json::json!({
+                    "tip": tip,
+                    "db_height": db_height,
+                    "lag": lag,
+                    "max_lag": max_lag,
+                }),
+            )?;
+        }
+    } else {
+        debug!("The database is {} blocks behind the node's tip", lag);
+    }
+    Ok(lag)
+}
+
+/// POSTs a JSON alert to the webhook `url` for each block above
+/// `since_height` that spends outputs older than the `threshold`.
+pub fn send_dormant_alerts(
+    url: &str,
+    threshold: DormantThreshold,
+    connection: Arc<Mutex<SqliteConnection>>,
+    since_height: i64,
+) -> Result<(), AlertError> {
+    let spends = {
+        let mut conn = connection.lock().unwrap();
+        db::get_dormant_spends_since(&mut conn, since_height)?
+    };
+
+    let mut alerts: u64 = 0;
+    for s in spends.iter() {
+        let (count, amount) = threshold.spends(s);
+        if count == 0 {
+            continue;
+        }
+        debug!("Sending dormant spend alert for block {}", s.height);
+        post_json(
+            url,
+            &serde_json::json!({
+                "height": s.height,
+                "date": s.date,
+                "threshold": threshold.to_string(),
+                "spends": count,
+                "amount": amount,
+            }),
+        )?;
+        alerts += 1;
+    }
+    info!(
+        "Sent {} dormant spend alerts ({}) for blocks above height {}",
+        alerts, threshold, since_height
+    );
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use crate::gen_csv::HALVING_INTERVAL;
+use crate::validation::RETARGET_INTERVAL;
+use crate::{db, MainError};
+use bitcoin::{CompactTarget, Target};
+use diesel::SqliteConnection;
+use log::info;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub const ANNOTATIONS_FILENAME: &str = "annotations.json";
+
+/// Mainnet soft forks: name, BIPs and activation height.
+const SOFT_FORKS: [(&str, &str, i64); 7] = [
+    ("P2SH", "BIP16", 173_805),
+    ("Height in coinbase", "BIP34", 227_931),
+    ("Strict DER signatures", "BIP66", 363_725),
+    ("CHECKLOCKTIMEVERIFY", "BIP65", 388_381),
+    ("CHECKSEQUENCEVERIFY", "BIP68, BIP112, BIP113", 419_328),
+    ("SegWit", "BIP141, BIP143, BIP147", 481_824),
+    ("Taproot", "BIP340, BIP341, BIP342", 709_632),
+];
+
+/// Events marked at the first block where the column is non-zero: name,
+/// table and column.
+const FIRST_SEEN_EVENTS: [(&str, &str, &str); 5] = [
+    (
+        "First SegWit spend",
+        "input_stats",
+        "inputs_spending_segwit",
+    ),
+    ("First Taproot output", "output_stats", "outputs_p2tr"),
+    (
+        "First Taproot spend",
+        "input_stats",
+        "inputs_spending_taproot",
+    ),
+    ("First TRUC transaction", "truc_stats", "tx_v3"),
+    ("First P2A output", "output_stats", "outputs_p2a"),
+];
+
+#[derive(Serialize, Debug)]
+pub struct Halving {
+    pub height: i64,
+    pub date: String,
+    /// the number of the halving, 1 for the first
+    pub halving: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SoftFork {
+    pub name: &'static str,
+    pub bips: &'static str,
+    pub height: i64,
+    pub date: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DifficultyAth {
+    pub height: i64,
+    pub date: String,
+    pub difficulty: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Event {
+    pub name: &'static str,
+    pub height: i64,
+    pub date: String,
+}
+
+/// Annotation markers for the charts. Only markers for blocks in the
+/// database are included. The soft fork heights are the mainnet ones.
+#[derive(Serialize, Debug)]
+pub struct Annotations {
+    pub halvings: Vec<Halving>,
+    pub soft_forks: Vec<SoftFork>,
+    /// the retargets raising the difficulty to a new all-time high
+    pub difficulty_aths: Vec<DifficultyAth>,
+    pub events: Vec<Event>,
+}
+
+pub fn annotations(conn: &mut SqliteConnection) -> Result<Annotations, MainError> {
+    let max_height = db::get_db_block_height(conn)?.unwrap_or(-1);
+    let halving_heights: Vec<i64> = (1..=max_height / HALVING_INTERVAL)
+        .map(|halving| halving * HALVING_INTERVAL)
+        .collect();
+    let halvings = db::dates_at_heights(conn, &halving_heights)?
+        .into_iter()
+        .map(|row| Halving {
+            height: row.height,
+            date: row.date,
+            halving: row.height / HALVING_INTERVAL,
+        })
+        .collect();
+
+    let soft_fork_heights: Vec<i64> = SOFT_FORKS.iter().map(|(_, _, h)| *h).collect();
+    let soft_forks = db::dates_at_heights(conn, &soft_fork_heights)?
+        .into_iter()
+        .filter_map(|row| {
+            let (name, bips, _) = SOFT_FORKS.iter().find(|(_, _, h)| *h == row.height)?;
+            Some(SoftFork {
+                name,
+                bips,
+                height: row.height,
+                date: row.date,
+            })
+        })
+        .collect();
+
+    let mut difficulty_aths = Vec::new();
+    let mut max_difficulty = 0.0;
+    for retarget in db::retarget_bits(conn, RETARGET_INTERVAL)? {
+        let difficulty = Target::from_compact(CompactTarget::from_consensus(retarget.bits as u32))
+            .difficulty_float();
+        if difficulty > max_difficulty {
+            max_difficulty = difficulty;
+            difficulty_aths.push(DifficultyAth {
+                height: retarget.height,
+                date: retarget.date,
+                difficulty,
+            });
+        }
+    }
+
+    let mut events = Vec::new();
+    for (name, table, column) in FIRST_SEEN_EVENTS.iter() {
+        if let Some(row) = db::first_nonzero(conn, table, column)? {
+            events.push(Event {
+                name,
+                height: row.height,
+                date: row.date,
+            });
+        }
+    }
+    events.sort_by_key(|event| event.height);
+
+    Ok(Annotations {
+        halvings,
+        soft_forks,
+        difficulty_aths,
+        events,
+    })
+}
+
+// Generates an annotations.json file with the halvings, soft fork
+// activations, difficulty all-time highs and notable first-seen events, so
+// the charts can mark them without hardcoding them.
+pub fn write_annotations(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    info!("Generating {} file...", ANNOTATIONS_FILENAME);
+    let annotations = {
+        let mut conn = connection.lock().unwrap();
+        annotations(&mut conn)?
+    };
+    let file = std::fs::File::create(csv_path.join(ANNOTATIONS_FILENAME))?;
+    serde_json::to_writer_pretty(file, &annotations)?;
+    Ok(vec![ANNOTATIONS_FILENAME.to_string()])
+}
@@ -0,0 +1,93 @@
+use crate::rest::{Block, RestClient};
+use crate::stats::{Stats, StatsError};
+use crate::MainError;
+use rawtx_rs::tx::TxInfo;
+use serde_json::{json, Value};
+
+/// Fetches the block at `height` and returns a JSON report with the stats of
+/// all tables and how each transaction, input and output was classified.
+pub fn block_audit(rest_host: &str, rest_port: u16, height: u64) -> Result<Value, MainError> {
+    let client = RestClient::new(rest_host, rest_port, None);
+    let block = client.block_at_height(height)?;
+    let hash = block.hash;
+    let transactions = transaction_details(&block)?;
+    let stats = Stats::from_block(block)?;
+
+    Ok(json!({
+        "height": height,
+        "hash": hash.to_string(),
+        "stats": stats,
+        "transactions": transactions,
+    }))
+}
+
+fn transaction_details(block: &Block) -> Result<Vec<Value>, StatsError> {
+    let mut details = Vec::with_capacity(block.txdata.len());
+    for tx in block.txdata.iter() {
+        let transaction = tx.transaction()?;
+        let tx_info = TxInfo::new(&transaction)?;
+        let inputs: Vec<Value> = tx_info
+            .input_infos
+            .iter()
+            .map(|input| {
+                json!({
+                    "type": format!("{:?}", input.in_type),
+                    "sequence": input.sequence.to_consensus_u32(),
+                    "multisig": input.multisig_info.as_ref().map(|m| format!("{}-of-{}", m.m_of_n.0, m.m_of_n.1)),
+                    "signatures": input.signature_info.len(),
+                    "pubkeys": input.pubkey_stats.len(),
+                })
+            })
+            .collect();
+        let outputs: Vec<Value> = tx_info
+            .output_infos
+            .iter()
+            .map(|output| {
+                json!({
+                    "type": format!("{:?}", output.out_type),
+                    "value": output.value.to_sat(),
+                })
+            })
+            .collect();
+        details.push(json!({
+            "txid": tx.txid.to_string(),
+            "version": tx.version,
+            "size": tx.size,
+            "vsize": tx.vsize,
+            "weight": tx.weight.to_wu(),
+            "fee": tx.fee.map(|fee| fee.to_sat()),
+            "feerate": tx.fee.map(|fee| fee.to_sat() as f64 / tx.vsize as f64),
+            "locktime": tx.lock_time.to_consensus_u32(),
+            "signaling_explicit_rbf": tx_info.is_signaling_explicit_rbf_replicability(),
+            "bip69_compliant": tx_info.is_bip69_compliant(),
+            "spending_segwit": tx_info.is_spending_segwit(),
+            "spending_taproot": tx_info.is_spending_taproot(),
+            "spending_multisig": tx_info.is_spending_multisig(),
+            "potentially_coinjoin": tx_info.potentially_coinjoin(),
+            "potentially_consolidation": tx_info.potentially_consolidation(),
+            "inputs": inputs,
+            "outputs": outputs,
+        }));
+    }
+    Ok(details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::transaction_details;
+    use crate::rest::Block;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_transaction_details() {
+        let buffer = BufReader::new(File::open("./testdata/739990.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let details = transaction_details(&block).expect("testdata blocks should not error");
+        assert_eq!(details.len(), block.txdata.len());
+        // the coinbase transaction doesn't pay a fee
+        assert!(details[0]["fee"].is_null());
+        assert_eq!(details[0]["inputs"][0]["type"], "CoinbaseWitness");
+        assert!(details[1]["feerate"].as_f64().unwrap() > 0.0);
+    }
+}
@@ -1,17 +1,28 @@
-use crate::gen_csv::PROXY_POOL_GROUP_ANTPOOL;
+use crate::gen_csv::{COLUMN_NAMES_THAT_ARENT_METRICS, PROXY_POOL_GROUP_ANTPOOL};
+use crate::prices::Price;
+use crate::rest::{FetchStatsSnapshot, MempoolInfo};
 use crate::schema;
 use crate::stats::{
-    BlockStats, FeerateStats, InputStats, OutputStats, ScriptStats, Stats, TxStats,
+    BlockStats, Coinbase, CoinbaseSpend, DormantSpends, FeeratePercentile, FeerateStats,
+    InputSizeStats, InputStats, NovelScript, OpcodeCount, OutputStats, PartialStats, ScriptStats,
+    ScriptTypeTransition, StatContributor, StatsTable, TrucStats, TxStats,
 };
 use crate::MainError;
+use bitcoin::Network;
+use bitcoin_pool_identification::Pool;
+use chrono::FixedOffset;
 use diesel::prelude::*;
+use diesel::query_builder::ReplaceStatement;
+use diesel::query_dsl::methods::ExecuteDsl;
 use diesel::sql_query;
-use diesel::sql_types::{BigInt, Float, Integer, Nullable, Text};
+use diesel::sql_types::{BigInt, Bool, Double, Float, Integer, Nullable, Text};
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use log::{debug, info};
-use std::collections::BTreeSet;
+use log::{debug, info, warn};
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations/");
 
@@ -25,6 +36,8 @@ pub struct TableInfo {
 
 #[derive(Debug, QueryableByName)]
 pub struct AvgAndSum {
+    #[diesel(sql_type = Text)]
+    pub date: String,
     #[diesel(sql_type = Float)]
     pub avg: f32,
     #[diesel(sql_type = BigInt)]
@@ -37,12 +50,240 @@ pub struct DateColumn {
     pub date: String,
 }
 
-pub fn open_db_and_run_migrations(database_path: &str) -> Result<SqliteConnection, MainError> {
-    debug!("trying to open database: {}", database_path);
-    let mut conn = SqliteConnection::establish(database_path)?;
+/// The database file for the network. Each network has its own database file
+/// so that one deployment can observe multiple networks. Mainnet uses the
+/// `database_path` as is, the other networks add the network name before the
+/// file extension, e.g. `db.sqlite` becomes `db.signet.sqlite`.
+pub fn network_database_path(database_path: &Path, network: Network) -> PathBuf {
+    if network == Network::Bitcoin {
+        return database_path.to_path_buf();
+    }
+    let path = database_path;
+    let file_name = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => format!(
+            "{}.{}.{}",
+            stem.to_string_lossy(),
+            network,
+            extension.to_string_lossy()
+        ),
+        (Some(stem), None) => format!("{}.{}", stem.to_string_lossy(), network),
+        _ => network.to_string(),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Takes an exclusive advisory lock on `<database_path>.lock`, so that two
+/// instances can't write to the same database at the same time. The lock is
+/// held until the returned file is dropped or the process exits. With `wait`,
+/// blocks until the other instance releases the lock instead of failing.
+/// Creates the directory of the database if it doesn't exist yet.
+pub fn lock_database(database_path: &Path, wait: bool) -> Result<File, MainError> {
+    if let Some(dir) = database_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut lock_path = database_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) if wait => {
+            info!(
+                "waiting for another instance to release {}..",
+                lock_path.display()
+            );
+            file.lock()?;
+            Ok(file)
+        }
+        Err(TryLockError::WouldBlock) => {
+            Err(MainError::DatabaseLocked(lock_path.display().to_string()))
+        }
+        Err(TryLockError::Error(e)) => Err(MainError::IOError(e)),
+    }
+}
+
+// How long a connection waits for the write lock of another connection (e.g.
+// of a separate `sync` and `export csv` process) before failing.
+const BUSY_TIMEOUT_MS: u32 = 60_000;
+
+/// The maximum number of bound parameters per statement
+/// (SQLITE_MAX_VARIABLE_NUMBER) of SQLite since v3.32.
+const SQLITE_MAX_BOUND_PARAMETERS: usize = 32_766;
+
+/// The number of columns in a tuple of columns, e.g. [Table::AllColumns].
+trait ColumnCount {
+    const COUNT: usize;
+}
+
+macro_rules! impl_column_count {
+    ($count:expr; $($column:ident),+) => {
+        impl<$($column: Column),+> ColumnCount for ($($column,)+) {
+            const COUNT: usize = $count;
+        }
+    };
+}
+
+impl_column_count!(1; A);
+impl_column_count!(2; A, B);
+impl_column_count!(3; A, B, C);
+impl_column_count!(4; A, B, C, D);
+impl_column_count!(5; A, B, C, D, E);
+impl_column_count!(6; A, B, C, D, E, F);
+impl_column_count!(7; A, B, C, D, E, F, G);
+impl_column_count!(8; A, B, C, D, E, F, G, H);
+
+/// Inserts the rows into the table, replacing rows with the same primary key.
+/// SQLite limits the number of bound parameters per statement, so the rows
+/// are inserted in chunks of as many rows as the limit allows for the
+/// table's number of columns.
+fn replace_chunked<'a, R, T>(
+    conn: &mut SqliteConnection,
+    table: T,
+    rows: &'a [R],
+) -> Result<(), diesel::result::Error>
+where
+    T: Table + Copy,
+    T::AllColumns: ColumnCount,
+    &'a [R]: Insertable<T>,
+    ReplaceStatement<T, <&'a [R] as Insertable<T>>::Values>: ExecuteDsl<SqliteConnection>,
+{
+    for chunk in rows.chunks(SQLITE_MAX_BOUND_PARAMETERS / T::AllColumns::COUNT) {
+        diesel::replace_into(table).values(chunk).execute(conn)?;
+    }
+    Ok(())
+}
+
+pub fn open_db_and_run_migrations(database_path: &Path) -> Result<SqliteConnection, MainError> {
+    debug!("trying to open database: {}", database_path.display());
+    let mut conn = SqliteConnection::establish(&database_path.to_string_lossy())?;
+    sql_query(format!("pragma busy_timeout = {};", BUSY_TIMEOUT_MS)).execute(&mut conn)?;
     debug!("trying to run pending migrations..");
     conn.run_pending_migrations(MIGRATIONS)?;
-    info!("database {} opened", database_path);
+    audit_indexes(&mut conn)?;
+    create_metrics_long_view(&mut conn)?;
+    info!("database {} opened", database_path.display());
+    Ok(conn)
+}
+
+/// The indexes the exports rely on as (table, column): the date of each stats
+/// table and the mining pool of the blocks. They are created by a migration.
+fn expected_indexes() -> Vec<(&'static str, &'static str)> {
+    let mut indexes: Vec<(&str, &str)> = StatsTable::ALL
+        .iter()
+        .map(|table| (table.table_name(), "date"))
+        .collect();
+    indexes.push(("block_stats", "pool_id"));
+    indexes
+}
+
+#[derive(Debug, QueryableByName)]
+struct IndexName {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+/// Checks that the expected indexes exist, i.e. an index starting with the
+/// column, and recreates missing ones (e.g. dropped by hand) with a warning.
+/// Returns the names of the created indexes.
+pub fn audit_indexes(conn: &mut SqliteConnection) -> Result<Vec<String>, diesel::result::Error> {
+    let mut created = vec![];
+    for (table, column) in expected_indexes() {
+        let existing: Vec<IndexName> = sql_query(
+            "SELECT il.name AS name FROM pragma_index_list(?) il
+            JOIN pragma_index_info(il.name) ii
+            WHERE ii.seqno = 0 AND ii.name = ?",
+        )
+        .bind::<Text, _>(table)
+        .bind::<Text, _>(column)
+        .load(conn)?;
+        if let Some(index) = existing.first() {
+            debug!("{}.{} is indexed by {}", table, column, index.name);
+        } else {
+            let name = format!("{}_{}", table, column);
+            warn!(
+                "The index on {}.{} is missing, creating it as {}",
+                table, column, name
+            );
+            sql_query(format!("CREATE INDEX {} ON {} ({})", name, table, column)).execute(conn)?;
+            created.push(name);
+        }
+    }
+    Ok(created)
+}
+
+/// The SQL of the metrics_long view: one row per block and numeric metric
+/// column of the stats tables, i.e. (height, date, metric_name, value).
+fn metrics_long_view_sql() -> String {
+    let selects: Vec<String> = StatsTable::ALL
+        .iter()
+        .filter_map(|table| {
+            let metrics: Vec<&str> = table
+                .columns()
+                .iter()
+                .filter(|c| matches!(c.sql_type, "Integer" | "BigInt" | "Float" | "Double"))
+                .filter(|c| !COLUMN_NAMES_THAT_ARENT_METRICS.contains(&c.name))
+                .map(|c| c.name)
+                .collect();
+            if metrics.is_empty() {
+                return None;
+            }
+            let names: Vec<String> = metrics
+                .iter()
+                .map(|name| format!("SELECT '{}' AS metric_name", name))
+                .collect();
+            let cases: String = metrics
+                .iter()
+                .map(|name| format!(" WHEN '{name}' THEN t.{name}", name = name))
+                .collect();
+            Some(format!(
+                "SELECT t.height AS height, t.date AS date, m.metric_name AS metric_name, CASE m.metric_name{} END AS value
+                FROM {} t CROSS JOIN ({}) m",
+                cases,
+                table.table_name(),
+                names.join(" UNION ALL ")
+            ))
+        })
+        .collect();
+    format!(
+        "CREATE VIEW metrics_long AS {}",
+        selects.join(" UNION ALL ")
+    )
+}
+
+/// (Re)creates the metrics_long view, so that it always covers the columns
+/// of the current stats tables. Generic tools can query any metric from it
+/// without knowing the schema.
+fn create_metrics_long_view(conn: &mut SqliteConnection) -> Result<(), diesel::result::Error> {
+    sql_query("DROP VIEW IF EXISTS metrics_long").execute(conn)?;
+    sql_query(metrics_long_view_sql()).execute(conn)?;
+    Ok(())
+}
+
+/// Opens the database for exporting without running migrations, so that it
+/// can be used while a `sync` process writes to it. Fails if the database
+/// doesn't exist or its schema is outdated.
+pub fn open_db_for_export(database_path: &Path) -> Result<SqliteConnection, MainError> {
+    if !database_path.exists() {
+        return Err(MainError::SchemaOutdated(
+            database_path.display().to_string(),
+        ));
+    }
+    debug!("trying to open database: {}", database_path.display());
+    let mut conn = SqliteConnection::establish(&database_path.to_string_lossy())?;
+    sql_query(format!("pragma busy_timeout = {};", BUSY_TIMEOUT_MS)).execute(&mut conn)?;
+    if conn.has_pending_migration(MIGRATIONS)? {
+        return Err(MainError::SchemaOutdated(
+            database_path.display().to_string(),
+        ));
+    }
+    info!("database {} opened for export", database_path.display());
     Ok(conn)
 }
 
@@ -59,29 +300,329 @@ pub fn performance_tune(conn: &mut SqliteConnection) -> Result<(), diesel::resul
     Ok(())
 }
 
+/// The migration that added each column of the given tables, keyed by table
+/// and column name. Found by replaying the migrations on an in-memory database.
+pub fn column_migrations(tables: &[&str]) -> Result<BTreeMap<(String, String), String>, MainError> {
+    let mut conn = SqliteConnection::establish(":memory:")?;
+    let mut added = BTreeMap::new();
+    while conn.has_pending_migration(MIGRATIONS)? {
+        let version = conn.run_next_migration(MIGRATIONS)?.to_string();
+        for table in tables {
+            for column in list_column_names(&mut conn, table)? {
+                added
+                    .entry((table.to_string(), column.name))
+                    .or_insert_with(|| version.clone());
+            }
+        }
+    }
+    Ok(added)
+}
+
+/// The version of the last migration applied to the database.
+pub fn schema_version(conn: &mut SqliteConnection) -> Result<Option<String>, MainError> {
+    Ok(conn
+        .applied_migrations()?
+        .iter()
+        .map(|version| version.to_string())
+        .max())
+}
+
 pub fn get_db_block_height(
     conn: &mut SqliteConnection,
 ) -> Result<Option<i64>, diesel::result::Error> {
-    schema::block_stats::dsl::block_stats
-        .select(diesel::dsl::max(schema::block_stats::height))
+    crate::stats::block_stats::dsl::block_stats
+        .select(diesel::dsl::max(crate::stats::block_stats::height))
+        .first(conn)
+}
+
+/// The signet challenge stored for the database, if any.
+pub fn get_signet_challenge(
+    conn: &mut SqliteConnection,
+) -> Result<Option<Vec<u8>>, diesel::result::Error> {
+    schema::signet_challenge::dsl::signet_challenge
+        .select(schema::signet_challenge::challenge)
+        .first(conn)
+        .optional()
+}
+
+/// Stores the signet challenge, unless one is already stored.
+pub fn set_signet_challenge(
+    conn: &mut SqliteConnection,
+    challenge: &[u8],
+) -> Result<(), diesel::result::Error> {
+    if get_signet_challenge(conn)?.is_none() {
+        diesel::insert_into(schema::signet_challenge::table)
+            .values(schema::signet_challenge::challenge.eq(challenge))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// The UTC offset in seconds the date columns are calculated in. UTC if none
+/// is stored.
+pub fn get_aggregation_timezone(conn: &mut SqliteConnection) -> Result<i32, diesel::result::Error> {
+    schema::aggregation_timezone::table
+        .select(schema::aggregation_timezone::utc_offset_seconds)
         .first(conn)
+        .optional()
+        .map(|offset| offset.unwrap_or(0))
+}
+
+/// Recalculates the date columns of all stats tables in the `offset`
+/// timezone, if they were calculated in a different one, and stores the
+/// offset. Rows of blocks without a stored timestamp keep their date.
+/// Returns whether the dates were recalculated.
+pub fn rebucket_dates(
+    conn: &mut SqliteConnection,
+    offset: FixedOffset,
+) -> Result<bool, diesel::result::Error> {
+    let seconds = offset.local_minus_utc();
+    if get_aggregation_timezone(conn)? == seconds {
+        return Ok(false);
+    }
+    recalculate_dates(conn, seconds)?;
+    Ok(true)
+}
+
+/// Recalculates the date columns of all stats tables with the given UTC offset
+/// in seconds and stores the offset.
+pub fn recalculate_dates(
+    conn: &mut SqliteConnection,
+    seconds: i32,
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        sql_query("UPDATE block_stats SET date = date(time + ?, 'unixepoch') WHERE time > 0")
+            .bind::<Integer, _>(seconds)
+            .execute(conn)?;
+        for table in StatsTable::ALL.iter().filter(|t| **t != StatsTable::Block) {
+            sql_query(format!(
+                "UPDATE {table} SET date = (SELECT b.date FROM block_stats b WHERE b.height = {table}.height)
+                WHERE height IN (SELECT height FROM block_stats WHERE time > 0)",
+                table = table.table_name()
+            ))
+            .execute(conn)?;
+        }
+        diesel::delete(schema::aggregation_timezone::table).execute(conn)?;
+        diesel::insert_into(schema::aggregation_timezone::table)
+            .values(schema::aggregation_timezone::utc_offset_seconds.eq(seconds))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// A mining pool of the pool identification data.
+#[derive(Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::pools)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PoolInfo {
+    pub id: i32,
+    pub name: String,
+    pub link: String,
+    /// JSON array of the coinbase tags
+    pub tags: String,
+}
+
+/// Replaces the pools table with the given pool data.
+pub fn replace_pools(conn: &mut SqliteConnection, pools: &[Pool]) -> Result<(), MainError> {
+    let rows = pools
+        .iter()
+        .map(|pool| {
+            Ok(PoolInfo {
+                id: pool.id as i32,
+                name: pool.name.clone(),
+                link: pool.link.clone(),
+                tags: serde_json::to_string(&pool.tags)?,
+            })
+        })
+        .collect::<Result<Vec<PoolInfo>, serde_json::Error>>()?;
+    conn.transaction(|conn| {
+        diesel::delete(schema::pools::table).execute(conn)?;
+        replace_chunked::<PoolInfo, _>(conn, schema::pools::table, &rows)
+    })?;
+    Ok(())
+}
+
+/// All pools in the pools table, ordered by id.
+pub fn get_pools(conn: &mut SqliteConnection) -> Result<Vec<PoolInfo>, diesel::result::Error> {
+    schema::pools::table
+        .select(PoolInfo::as_select())
+        .order(schema::pools::id.asc())
+        .load(conn)
+}
+
+/// The pool names by pool id.
+pub fn pool_names(
+    conn: &mut SqliteConnection,
+) -> Result<BTreeMap<i32, String>, diesel::result::Error> {
+    schema::pools::table
+        .select((schema::pools::id, schema::pools::name))
+        .load(conn)
+        .map(|rows: Vec<(i32, String)>| rows.into_iter().collect())
+}
+
+/// The dormant spends of the blocks above `height`.
+pub fn get_dormant_spends_since(
+    conn: &mut SqliteConnection,
+    height: i64,
+) -> Result<Vec<DormantSpends>, diesel::result::Error> {
+    use crate::stats::dormant_spends::dsl;
+    dsl::dormant_spends
+        .filter(dsl::height.gt(height))
+        .order(dsl::height.asc())
+        .select(DormantSpends::as_select())
+        .load(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct StatsVersions {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Integer)]
+    pub block_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub tx_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub input_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub output_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub script_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub feerate_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub truc_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub input_size_stats: i32,
+    #[diesel(sql_type = Integer)]
+    pub dormant_spends: i32,
+}
+
+impl StatsVersions {
+    pub fn version(&self, table: StatsTable) -> i32 {
+        match table {
+            StatsTable::Block => self.block_stats,
+            StatsTable::Tx => self.tx_stats,
+            StatsTable::Input => self.input_stats,
+            StatsTable::Output => self.output_stats,
+            StatsTable::Script => self.script_stats,
+            StatsTable::Feerate => self.feerate_stats,
+            StatsTable::Truc => self.truc_stats,
+            StatsTable::InputSize => self.input_size_stats,
+            StatsTable::DormantSpends => self.dormant_spends,
+        }
+    }
 }
 
-/// Returns block heights that have stats at or above the given version.
+/// Returns the block heights we have stats for and the stats tables with
+/// a version older than the version we want for the table.
 ///
 /// Used to identify blocks that are already up-to-date and should be
-/// skipped during re-processing. Blocks NOT in this list need their
-/// stats recalculated (or have yet to be processed)
-pub fn block_heights_greater_equals_version(
+/// skipped during re-processing. Heights with an empty set of tables are
+/// up-to-date. Heights not in the map have yet to be processed.
+pub fn outdated_stats_tables(
     conn: &mut SqliteConnection,
-    min_version: i32,
-) -> Result<Vec<i64>, diesel::result::Error> {
-    use crate::schema::block_stats::dsl::*;
+) -> Result<BTreeMap<i64, BTreeSet<StatsTable>>, diesel::result::Error> {
+    let versions: Vec<StatsVersions> = sql_query("SELECT * FROM stats_versions").load(conn)?;
+    Ok(versions
+        .iter()
+        .map(|v| {
+            let outdated = StatsTable::ALL
+                .iter()
+                .filter(|table| v.version(**table) < table.version())
+                .copied()
+                .collect();
+            (v.height, outdated)
+        })
+        .collect())
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct HeightAndDateRange {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub min_height: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub max_height: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub first_date: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub last_date: Option<String>,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct HeightAndDate {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Text)]
+    pub date: String,
+}
+
+/// The dates of the blocks at the given heights. Heights without stats are
+/// left out.
+pub fn dates_at_heights(
+    conn: &mut SqliteConnection,
+    heights: &[i64],
+) -> Result<Vec<HeightAndDate>, diesel::result::Error> {
+    if heights.is_empty() {
+        return Ok(vec![]);
+    }
+    let heights: Vec<String> = heights.iter().map(|h| h.to_string()).collect();
+    sql_query(format!(
+        "SELECT height, date FROM block_stats WHERE height IN ({}) ORDER BY height;",
+        heights.join(", ")
+    ))
+    .get_results(conn)
+}
+
+/// The first block where the column of the table is non-zero.
+pub fn first_nonzero(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+) -> Result<Option<HeightAndDate>, diesel::result::Error> {
+    sql_query(format!(
+        "SELECT height, date FROM {} WHERE {} > 0 ORDER BY height LIMIT 1;",
+        table, column
+    ))
+    .get_result(conn)
+    .optional()
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct RetargetBits {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = Integer)]
+    pub bits: i32,
+}
+
+/// The bits of the blocks at the retarget heights.
+pub fn retarget_bits(
+    conn: &mut SqliteConnection,
+    retarget_interval: i64,
+) -> Result<Vec<RetargetBits>, diesel::result::Error> {
+    sql_query("SELECT height, date, bits FROM block_stats WHERE height % ? = 0 ORDER BY height;")
+        .bind::<BigInt, _>(retarget_interval)
+        .get_results(conn)
+}
 
-    block_stats
-        .filter(stats_version.ge(min_version))
-        .select(height)
-        .load::<i64>(conn)
+/// Returns the height and date range covered by the block_stats table.
+/// All fields are None if the table is empty.
+pub fn height_and_date_range(
+    conn: &mut SqliteConnection,
+) -> Result<HeightAndDateRange, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            MIN(height) AS min_height,
+            MAX(height) AS max_height,
+            MIN(date) AS first_date,
+            MAX(date) AS last_date
+        FROM block_stats;
+        "#,
+    )
+    .get_result(conn)
 }
 
 pub fn list_column_names(
@@ -91,23 +632,57 @@ pub fn list_column_names(
     sql_query(format!("PRAGMA table_info({})", table)).get_results(conn)
 }
 
+/// The daily average and sum of a column, including the daily aggregates of
+/// the pruned blocks.
 pub fn column_sum_and_avg_by_date(
     conn: &mut SqliteConnection,
     colname: &str,
     table: &str,
 ) -> Vec<AvgAndSum> {
     sql_query(format!(
-        "SELECT avg({}) as avg, sum({}) as sum FROM {} GROUP BY date",
-        colname, colname, table
+        "SELECT date, SUM(avg * blocks) / SUM(blocks) as avg, SUM(sum) as sum FROM (
+            SELECT date, avg({col}) as avg, sum({col}) as sum, COUNT(*) as blocks FROM {table} GROUP BY date
+            UNION ALL
+            SELECT date, avg, sum, blocks FROM daily_metrics WHERE table_name = ? AND column_name = ?
+        ) GROUP BY date ORDER BY date",
+        col = colname,
+        table = table
     ))
+    .bind::<Text, _>(table)
+    .bind::<Text, _>(colname)
     .get_results(conn)
     .unwrap()
 }
 
 pub fn date_column(conn: &mut SqliteConnection) -> Vec<DateColumn> {
-    sql_query("SELECT date as date FROM block_stats GROUP BY date".to_string())
-        .get_results(conn)
-        .unwrap()
+    sql_query(
+        "SELECT date as date FROM block_stats GROUP BY date
+        UNION SELECT date FROM daily_metrics WHERE table_name = 'block_stats'
+        ORDER BY date"
+            .to_string(),
+    )
+    .get_results(conn)
+    .unwrap()
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct DateHeights {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub min_height: i64,
+    #[diesel(sql_type = BigInt)]
+    pub max_height: i64,
+}
+
+/// The first and last height of each date.
+pub fn heights_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<DateHeights>, diesel::result::Error> {
+    sql_query(
+        "SELECT date, MIN(height) AS min_height, MAX(height) AS max_height FROM block_stats GROUP BY date ORDER BY date",
+    )
+    .get_results(conn)
 }
 
 #[derive(Debug, QueryableByName)]
@@ -334,10 +909,13 @@ pub struct PoolsMiningEphemeralDust {
     pub pool_id: i64,
     #[diesel(sql_type = BigInt)]
     pub count: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub first_ephemeral_dust_height: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub first_ephemeral_dust_date: Option<String>,
+    /// number of ephemeral dust outputs mined but not spent in the same block
     #[diesel(sql_type = BigInt)]
-    pub first_ephemeral_dust_height: i64,
-    #[diesel(sql_type = Text)]
-    pub first_ephemeral_dust_date: String,
+    pub unspent: i64,
 }
 
 pub fn get_pools_mining_ephemeral_dust(
@@ -347,21 +925,23 @@ pub fn get_pools_mining_ephemeral_dust(
     r#"
         SELECT
             t.pool_id,
-            COUNT(t.tx_spending_ephemeral_dust) as count,
+            SUM(CASE WHEN t.tx_spending_ephemeral_dust > 0 THEN 1 ELSE 0 END) as count,
             MIN(CASE WHEN t.tx_spending_ephemeral_dust > 0 THEN t.height END) AS first_ephemeral_dust_height,
-            MIN(CASE WHEN t.tx_spending_ephemeral_dust > 0 THEN t.date END) AS first_ephemeral_dust_date
+            MIN(CASE WHEN t.tx_spending_ephemeral_dust > 0 THEN t.date END) AS first_ephemeral_dust_date,
+            SUM(t.tx_ephemeral_dust_unspent) as unspent
         FROM (
             SELECT
                 bs.date,
                 bs.height,
                 ts.tx_spending_ephemeral_dust,
+                ts.tx_ephemeral_dust_unspent,
                 bs.pool_id
             FROM tx_stats ts
             JOIN block_stats bs ON ts.height = bs.height
-            WHERE ts.tx_spending_ephemeral_dust > 0
+            WHERE ts.tx_spending_ephemeral_dust > 0 OR ts.tx_ephemeral_dust_unspent > 0
         ) t
         GROUP BY t.pool_id
-        ORDER BY first_ephemeral_dust_date;
+        ORDER BY first_ephemeral_dust_date IS NULL, first_ephemeral_dust_date;
     "#,
     )
     .get_results(conn)
@@ -496,67 +1076,1199 @@ pub fn get_blocks_per_day_per_pool(
     .get_results(conn)
 }
 
-pub fn insert_stats(
-    conn: &mut SqliteConnection,
-    stats: &[Stats],
-) -> Result<(), diesel::result::Error> {
-    insert_block_stats(conn, &stats.iter().map(|s| s.block.clone()).collect())?;
-    insert_tx_stats(conn, &stats.iter().map(|s| s.tx.clone()).collect())?;
-    insert_input_stats(conn, &stats.iter().map(|s| s.input.clone()).collect())?;
-    insert_output_stats(conn, &stats.iter().map(|s| s.output.clone()).collect())?;
-    insert_script_stats(conn, &stats.iter().map(|s| s.script.clone()).collect())?;
-    insert_feerate_stats(conn, &stats.iter().map(|s| s.feerate.clone()).collect())?;
-    Ok(())
+#[derive(Debug, QueryableByName)]
+pub struct FeeRevenueAndOutputVolume {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub price_usd: Option<f64>,
+    #[diesel(sql_type = BigInt)]
+    pub fee_revenue: i64,
+    #[diesel(sql_type = BigInt)]
+    pub output_volume: i64,
 }
 
-fn insert_block_stats(
+/// Returns the daily fee revenue and output volume in sat along with the
+/// price for the day, if known.
+pub fn fee_revenue_and_output_volume(
     conn: &mut SqliteConnection,
-    stats: &Vec<BlockStats>,
-) -> Result<(), diesel::result::Error> {
-    use crate::schema::block_stats;
-    debug!("Inserting a batch of {} block stats", stats.len());
+) -> Result<Vec<FeeRevenueAndOutputVolume>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.date,
+            p.usd AS price_usd,
+            SUM(f.fee_sum) AS fee_revenue,
+            SUM(t.tx_output_amount) AS output_volume
+        FROM block_stats b
+        JOIN feerate_stats f ON f.height = b.height
+        JOIN tx_stats t ON t.height = b.height
+        LEFT JOIN prices p ON p.date = b.date
+        GROUP BY b.date
+        ORDER BY b.date;
+        "#,
+    )
+    .get_results(conn)
+}
 
-    diesel::replace_into(block_stats::table)
-        .values(stats)
-        .execute(conn)?;
-    Ok(())
+#[derive(Debug, QueryableByName)]
+pub struct MinerRevenue {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub min_height: i64,
+    #[diesel(sql_type = BigInt)]
+    pub max_height: i64,
+    #[diesel(sql_type = BigInt)]
+    pub fees: i64,
+    /// the subsidy actually claimed by the miners, i.e. the coinbase output
+    /// amount minus the fees
+    #[diesel(sql_type = BigInt)]
+    pub subsidy: i64,
 }
 
-fn insert_tx_stats(
+/// Returns the daily fees and subsidy in sat.
+pub fn miner_revenue_by_date(
     conn: &mut SqliteConnection,
-    stats: &Vec<TxStats>,
-) -> Result<(), diesel::result::Error> {
-    use crate::schema::tx_stats;
-    debug!("Inserting a batch of {} tx stats", stats.len());
+) -> Result<Vec<MinerRevenue>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.date,
+            MIN(b.height) AS min_height,
+            MAX(b.height) AS max_height,
+            SUM(f.fee_sum) AS fees,
+            SUM(b.coinbase_output_amount - f.fee_sum) AS subsidy
+        FROM block_stats b
+        JOIN feerate_stats f ON f.height = b.height
+        GROUP BY b.date
+        ORDER BY b.date;
+        "#,
+    )
+    .get_results(conn)
+}
 
-    diesel::replace_into(tx_stats::table)
-        .values(stats)
-        .execute(conn)?;
-    Ok(())
+#[derive(Debug, QueryableByName)]
+pub struct FeeCliff {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    /// 50th minus 5th package feerate percentile in sat/vB
+    #[diesel(sql_type = Double)]
+    pub p50_p5: f64,
+    /// median minus minimum package feerate in sat/vB
+    #[diesel(sql_type = Double)]
+    pub median_min: f64,
 }
 
-fn insert_input_stats(
+/// The per-block feerate gradient. Blocks with only a coinbase transaction
+/// don't have feerates and are skipped.
+pub fn fee_cliff_by_block(
     conn: &mut SqliteConnection,
-    stats: &Vec<InputStats>,
-) -> Result<(), diesel::result::Error> {
-    use crate::schema::input_stats;
-    debug!("Inserting a batch of {} input stats", stats.len());
+) -> Result<Vec<FeeCliff>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.height,
+            b.date,
+            CAST(f.feerate_package_50th_percentile - f.feerate_package_5th_percentile AS DOUBLE) AS p50_p5,
+            CAST(f.feerate_package_50th_percentile - f.feerate_package_min AS DOUBLE) AS median_min
+        FROM block_stats b
+        JOIN feerate_stats f ON f.height = b.height
+        WHERE b.transactions > 1
+        ORDER BY b.height;
+        "#,
+    )
+    .get_results(conn)
+}
 
-    diesel::insert_into(input_stats::table)
-        .values(stats)
-        .execute(conn)?;
-    Ok(())
+#[derive(Debug, QueryableByName)]
+pub struct OutlierBlock {
+    #[diesel(sql_type = Text)]
+    pub month: String,
+    #[diesel(sql_type = BigInt)]
+    pub rank: i64,
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = Double)]
+    pub value: f64,
 }
 
-fn insert_output_stats(
+/// Returns the top `limit` blocks per month (YYYY-MM) by the given column of
+/// the given table, in descending order. Blocks where the column is zero are
+/// ignored.
+pub fn top_blocks_per_month(
     conn: &mut SqliteConnection,
-    stats: &Vec<OutputStats>,
-) -> Result<(), diesel::result::Error> {
-    use crate::schema::output_stats;
-    debug!("Inserting a batch of {} output stats", stats.len());
-
-    diesel::replace_into(output_stats::table)
-        .values(stats)
+    table: &str,
+    column: &str,
+    limit: i64,
+) -> Result<Vec<OutlierBlock>, diesel::result::Error> {
+    sql_query(format!(
+        r#"
+        SELECT month, rank, height, date, value
+        FROM (
+            SELECT
+                SUBSTR(b.date, 1, 7) AS month,
+                ROW_NUMBER() OVER (
+                    PARTITION BY SUBSTR(b.date, 1, 7)
+                    ORDER BY t.{column} DESC, b.height
+                ) AS rank,
+                b.height,
+                b.date,
+                CAST(t.{column} AS DOUBLE) AS value
+            FROM block_stats b
+            JOIN {table} t ON t.height = b.height
+            WHERE t.{column} > 0
+        )
+        WHERE rank <= ?
+        ORDER BY month, rank;
+        "#,
+        column = column,
+        table = table,
+    ))
+    .bind::<BigInt, _>(limit)
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct NonceBucket {
+    #[diesel(sql_type = Text)]
+    pub month: String,
+    /// the upper 4 bits of the nonce
+    #[diesel(sql_type = BigInt)]
+    pub bucket: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+    #[diesel(sql_type = BigInt)]
+    pub coinbase_extra_size: i64,
+}
+
+/// The number of blocks per month (YYYY-MM) and nonce bucket, with the summed
+/// coinbase extra size. The nonce is stored as a signed integer, its upper 4
+/// bits are the bucket.
+pub fn nonce_buckets_by_month(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<NonceBucket>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            SUBSTR(date, 1, 7) AS month,
+            (nonce & 4294967295) >> 28 AS bucket,
+            COUNT(*) AS blocks,
+            SUM(coinbase_extra_size) AS coinbase_extra_size
+        FROM block_stats
+        GROUP BY month, bucket
+        ORDER BY month, bucket;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(QueryableByName)]
+pub struct PoolVersionRolling {
+    #[diesel(sql_type = BigInt)]
+    pub pool_id: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks_version_rolling: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub first_version_rolling_height: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub first_version_rolling_date: Option<String>,
+}
+
+/// The number of blocks per pool and how many of them have BIP320
+/// version-rolling bits set, with the first such block.
+pub fn pools_version_rolling(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PoolVersionRolling>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            pool_id,
+            COUNT(*) AS blocks,
+            SUM(version_rolling) AS blocks_version_rolling,
+            MIN(CASE WHEN version_rolling THEN height END) AS first_version_rolling_height,
+            MIN(CASE WHEN version_rolling THEN date END) AS first_version_rolling_date
+        FROM block_stats
+        GROUP BY pool_id
+        ORDER BY blocks DESC;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(QueryableByName)]
+pub struct PoolCoinbaseWeight {
+    #[diesel(sql_type = BigInt)]
+    pub pool_id: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+    #[diesel(sql_type = Double)]
+    pub avg_coinbase_weight: f64,
+    #[diesel(sql_type = Double)]
+    pub avg_coinbase_weight_share: f64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks_large_coinbase: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks_large_coinbase_displacing: i64,
+}
+
+/// The coinbase weight per pool and the number of blocks with a large
+/// coinbase, overall and displacing fee-paying transactions.
+pub fn pools_coinbase_weight(
+    conn: &mut SqliteConnection,
+    large_coinbase_weight: i64,
+) -> Result<Vec<PoolCoinbaseWeight>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            pool_id,
+            COUNT(*) AS blocks,
+            AVG(coinbase_weight) AS avg_coinbase_weight,
+            AVG(coinbase_weight_share) AS avg_coinbase_weight_share,
+            SUM(coinbase_weight > ?) AS blocks_large_coinbase,
+            SUM(coinbase_large_displacing) AS blocks_large_coinbase_displacing
+        FROM block_stats
+        GROUP BY pool_id
+        ORDER BY blocks DESC;
+        "#,
+    )
+    .bind::<BigInt, _>(large_coinbase_weight)
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct EcdsaSignatureSizes {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub sigs_ecdsa: i64,
+    /// signatures of 71 bytes or less, including the sighash byte
+    #[diesel(sql_type = BigInt)]
+    pub sigs_71byte_or_less: i64,
+    #[diesel(sql_type = BigInt)]
+    pub low_r: i64,
+    #[diesel(sql_type = BigInt)]
+    pub high_r: i64,
+    #[diesel(sql_type = BigInt)]
+    pub low_s: i64,
+    #[diesel(sql_type = BigInt)]
+    pub high_s: i64,
+}
+
+/// The daily ECDSA signature length and low-R and low-S counts.
+pub fn ecdsa_signature_sizes_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<EcdsaSignatureSizes>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(sigs_ecdsa) AS sigs_ecdsa,
+            SUM(sigs_ecdsa_length_less_70byte + sigs_ecdsa_length_70byte + sigs_ecdsa_length_71byte) AS sigs_71byte_or_less,
+            SUM(sigs_ecdsa_low_r) AS low_r,
+            SUM(sigs_ecdsa_high_r) AS high_r,
+            SUM(sigs_ecdsa_low_s) AS low_s,
+            SUM(sigs_ecdsa_high_s) AS high_s
+        FROM script_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct InputOutputVolume {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub input_amount: i64,
+    #[diesel(sql_type = BigInt)]
+    pub output_amount: i64,
+    /// inputs of the non-coinbase transactions
+    #[diesel(sql_type = BigInt)]
+    pub inputs: i64,
+    /// outputs of the non-coinbase transactions
+    #[diesel(sql_type = BigInt)]
+    pub outputs: i64,
+}
+
+/// The daily input and output amounts and counts of the non-coinbase
+/// transactions.
+pub fn input_output_volume_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<InputOutputVolume>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.date,
+            SUM(b.input_amount) AS input_amount,
+            SUM(b.output_amount) AS output_amount,
+            SUM(b.inputs - 1) AS inputs,
+            SUM(b.outputs - o.outputs_coinbase) AS outputs
+        FROM block_stats b
+        JOIN output_stats o ON o.height = b.height
+        GROUP BY b.date
+        ORDER BY b.date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct TrucAdoption {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub tx_v3: i64,
+    #[diesel(sql_type = BigInt)]
+    pub tx_v3_with_parent_in_block: i64,
+    #[diesel(sql_type = BigInt)]
+    pub tx_v3_creating_p2a: i64,
+}
+
+pub fn truc_adoption_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<TrucAdoption>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.date,
+            SUM(b.transactions) AS transactions,
+            SUM(t.tx_v3) AS tx_v3,
+            SUM(t.tx_v3_with_parent_in_block) AS tx_v3_with_parent_in_block,
+            SUM(t.tx_v3_creating_p2a) AS tx_v3_creating_p2a
+        FROM block_stats b
+        JOIN truc_stats t ON t.height = b.height
+        GROUP BY b.date
+        ORDER BY b.date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct CoinbasePayoutTypes {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub p2pk: i64,
+    #[diesel(sql_type = BigInt)]
+    pub p2pkh: i64,
+    #[diesel(sql_type = BigInt)]
+    pub p2sh: i64,
+    #[diesel(sql_type = BigInt)]
+    pub p2wpkh: i64,
+    #[diesel(sql_type = BigInt)]
+    pub p2wsh: i64,
+    #[diesel(sql_type = BigInt)]
+    pub p2tr: i64,
+    /// P2MS and unknown scripts
+    #[diesel(sql_type = BigInt)]
+    pub other: i64,
+}
+
+/// The daily number of coinbase outputs by payout script type. OP_RETURN
+/// outputs, e.g. the witness commitment, aren't payouts and are left out.
+pub fn coinbase_payout_types_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<CoinbasePayoutTypes>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            b.date,
+            SUM(o.outputs_coinbase_p2pk) AS p2pk,
+            SUM(o.outputs_coinbase_p2pkh) AS p2pkh,
+            SUM(o.outputs_coinbase_p2sh) AS p2sh,
+            SUM(o.outputs_coinbase_p2wpkh) AS p2wpkh,
+            SUM(o.outputs_coinbase_p2wsh) AS p2wsh,
+            SUM(o.outputs_coinbase_p2tr) AS p2tr,
+            SUM(o.outputs_coinbase_p2ms + o.outputs_coinbase_unknown) AS other
+        FROM block_stats b
+        JOIN output_stats o ON o.height = b.height
+        GROUP BY b.date
+        ORDER BY b.date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct P2aSpenders {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub ln_anchor: i64,
+    #[diesel(sql_type = BigInt)]
+    pub truc_child: i64,
+    #[diesel(sql_type = BigInt)]
+    pub other: i64,
+}
+
+pub fn p2a_spenders_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<P2aSpenders>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(tx_spending_p2a_ln_anchor) AS ln_anchor,
+            SUM(tx_spending_p2a_truc_child) AS truc_child,
+            SUM(tx_spending_p2a_other) AS other
+        FROM tx_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct PoolCoinbaseSpends {
+    /// the pool that mined the spent coinbase
+    #[diesel(sql_type = Integer)]
+    pub pool_id: i32,
+    #[diesel(sql_type = Text)]
+    pub destination_type: String,
+    #[diesel(sql_type = BigInt)]
+    pub spends: i64,
+    /// blocks between the coinbase and the spending block
+    #[diesel(sql_type = Double)]
+    pub avg_delay: f64,
+    #[diesel(sql_type = BigInt)]
+    pub min_delay: i64,
+    #[diesel(sql_type = BigInt)]
+    pub max_delay: i64,
+    #[diesel(sql_type = BigInt)]
+    pub amount: i64,
+    #[diesel(sql_type = Double)]
+    pub avg_outputs: f64,
+}
+
+/// The tracked coinbase spends by the pool that mined the coinbase and the
+/// destination script type of the spending transaction.
+pub fn get_pools_coinbase_spends(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PoolCoinbaseSpends>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            bs.pool_id,
+            cs.destination_type,
+            COUNT(*) AS spends,
+            AVG(cs.spend_height - cs.coinbase_height) AS avg_delay,
+            MIN(cs.spend_height - cs.coinbase_height) AS min_delay,
+            MAX(cs.spend_height - cs.coinbase_height) AS max_delay,
+            SUM(cs.value) AS amount,
+            AVG(cs.outputs) AS avg_outputs
+        FROM coinbase_spends cs
+        JOIN block_stats bs ON bs.height = cs.coinbase_height
+        GROUP BY bs.pool_id, cs.destination_type
+        ORDER BY bs.pool_id, spends DESC;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct TxVsizeClasses {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_0_140: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_141_250: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_251_500: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_501_1000: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_1001_10000: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_10001_100000: i64,
+    #[diesel(sql_type = BigInt)]
+    pub vsize_100001_plus: i64,
+}
+
+pub fn tx_vsize_classes_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<TxVsizeClasses>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(tx_vsize_0_140) AS vsize_0_140,
+            SUM(tx_vsize_141_250) AS vsize_141_250,
+            SUM(tx_vsize_251_500) AS vsize_251_500,
+            SUM(tx_vsize_501_1000) AS vsize_501_1000,
+            SUM(tx_vsize_1001_10000) AS vsize_1001_10000,
+            SUM(tx_vsize_10001_100000) AS vsize_10001_100000,
+            SUM(tx_vsize_100001_plus) AS vsize_100001_plus
+        FROM tx_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct TrafficComposition {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub payment_like: i64,
+    #[diesel(sql_type = BigInt)]
+    pub data_embedding: i64,
+    #[diesel(sql_type = BigInt)]
+    pub inscription: i64,
+    #[diesel(sql_type = BigInt)]
+    pub opreturn: i64,
+}
+
+pub fn traffic_composition_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<TrafficComposition>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(tx_payment_like) AS payment_like,
+            SUM(tx_data_embedding) AS data_embedding,
+            SUM(tx_data_embedding_inscription) AS inscription,
+            SUM(tx_data_embedding_opreturn) AS opreturn
+        FROM tx_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct UtxoGrowthByProtocol {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub inscription: i64,
+    #[diesel(sql_type = BigInt)]
+    pub runestone: i64,
+    #[diesel(sql_type = BigInt)]
+    pub counterparty: i64,
+    #[diesel(sql_type = BigInt)]
+    pub other_data: i64,
+    #[diesel(sql_type = BigInt)]
+    pub ordinary: i64,
+}
+
+pub fn utxo_growth_by_protocol_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<UtxoGrowthByProtocol>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(outputs_utxo_net_inscription) AS inscription,
+            SUM(outputs_utxo_net_runestone) AS runestone,
+            SUM(outputs_utxo_net_counterparty) AS counterparty,
+            SUM(outputs_utxo_net_other_data) AS other_data,
+            SUM(outputs_utxo_net_ordinary) AS ordinary
+        FROM output_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct MalleabilityHygiene {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub sigs_ecdsa: i64,
+    #[diesel(sql_type = BigInt)]
+    pub sigs_ecdsa_low_s: i64,
+    #[diesel(sql_type = BigInt)]
+    pub sigs_ecdsa_strict_der: i64,
+    #[diesel(sql_type = BigInt)]
+    pub multisig_dummy_null: i64,
+    #[diesel(sql_type = BigInt)]
+    pub multisig_dummy_not_null: i64,
+}
+
+pub fn malleability_hygiene_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<MalleabilityHygiene>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(sigs_ecdsa) AS sigs_ecdsa,
+            SUM(sigs_ecdsa_low_s) AS sigs_ecdsa_low_s,
+            SUM(sigs_ecdsa_strict_der) AS sigs_ecdsa_strict_der,
+            SUM(multisig_dummy_null) AS multisig_dummy_null,
+            SUM(multisig_dummy_not_null) AS multisig_dummy_not_null
+        FROM script_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct SigAggregation {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub keypath_inputs: i64,
+    #[diesel(sql_type = BigInt)]
+    pub aggregatable_sigs: i64,
+}
+
+pub fn sig_aggregation_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<SigAggregation>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(tx_multiple_p2tr_keypath_inputs) AS transactions,
+            SUM(inputs_p2tr_keypath_in_multi_keypath_tx) AS keypath_inputs,
+            SUM(sigs_schnorr_aggregatable) AS aggregatable_sigs
+        FROM script_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct MultisigVsSinglesig {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub legacy_multisig: i64,
+    #[diesel(sql_type = BigInt)]
+    pub legacy_multisig_amount: i64,
+    #[diesel(sql_type = BigInt)]
+    pub legacy_singlesig: i64,
+    #[diesel(sql_type = BigInt)]
+    pub legacy_singlesig_amount: i64,
+    #[diesel(sql_type = BigInt)]
+    pub segwit_multisig: i64,
+    #[diesel(sql_type = BigInt)]
+    pub segwit_multisig_amount: i64,
+    #[diesel(sql_type = BigInt)]
+    pub segwit_singlesig: i64,
+    #[diesel(sql_type = BigInt)]
+    pub segwit_singlesig_amount: i64,
+    #[diesel(sql_type = BigInt)]
+    pub taproot_keypath: i64,
+    #[diesel(sql_type = BigInt)]
+    pub taproot_keypath_amount: i64,
+}
+
+pub fn multisig_vs_singlesig_by_date(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<MultisigVsSinglesig>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(inputs_spending_p2ms_multisig + inputs_spending_p2sh_multisig) AS legacy_multisig,
+            SUM(inputs_spending_p2ms_multisig_amount + inputs_spending_p2sh_multisig_amount) AS legacy_multisig_amount,
+            SUM(inputs_p2pk + inputs_p2pkh) AS legacy_singlesig,
+            SUM(inputs_p2pk_amount + inputs_p2pkh_amount) AS legacy_singlesig_amount,
+            SUM(inputs_spending_nested_p2wsh_multisig + inputs_spending_p2wsh_multisig) AS segwit_multisig,
+            SUM(inputs_spending_nested_p2wsh_multisig_amount + inputs_spending_p2wsh_multisig_amount) AS segwit_multisig_amount,
+            SUM(inputs_nested_p2wpkh + inputs_p2wpkh) AS segwit_singlesig,
+            SUM(inputs_nested_p2wpkh_amount + inputs_p2wpkh_amount) AS segwit_singlesig_amount,
+            SUM(inputs_p2tr_keypath) AS taproot_keypath,
+            SUM(inputs_p2tr_keypath_amount) AS taproot_keypath_amount
+        FROM input_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct Burned {
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = BigInt)]
+    pub amount: i64,
+}
+
+pub fn burned_by_date(conn: &mut SqliteConnection) -> Result<Vec<Burned>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            date,
+            SUM(outputs_burned_amount) AS amount
+        FROM output_stats
+        GROUP BY date
+        ORDER BY date;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct BitsChange {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Integer)]
+    pub previous_bits: i32,
+    #[diesel(sql_type = Integer)]
+    pub bits: i32,
+}
+
+/// The blocks where the bits differ from the bits of the previous block even
+/// though the height isn't a multiple of the retarget interval. Only heights
+/// with stats for the previous block are checked.
+pub fn unexpected_bits_changes(
+    conn: &mut SqliteConnection,
+    retarget_interval: i64,
+) -> Result<Vec<BitsChange>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT height, previous_bits, bits
+        FROM (
+            SELECT
+                height,
+                bits,
+                LAG(height) OVER (ORDER BY height) AS previous_height,
+                LAG(bits) OVER (ORDER BY height) AS previous_bits
+            FROM block_stats
+        )
+        WHERE previous_height = height - 1
+            AND height % ? != 0
+            AND bits != previous_bits
+        ORDER BY height;
+        "#,
+    )
+    .bind::<BigInt, _>(retarget_interval)
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct DifficultyAdjustment {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    #[diesel(sql_type = Text)]
+    pub date: String,
+    #[diesel(sql_type = Integer)]
+    pub bits: i32,
+    #[diesel(sql_type = Text)]
+    pub difficulty_exact: String,
+}
+
+/// The first block and the blocks where the bits differ from the bits of the
+/// previous block in the database, i.e. the difficulty adjustments.
+pub fn difficulty_adjustments(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<DifficultyAdjustment>, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT height, date, bits, difficulty_exact
+        FROM (
+            SELECT
+                height,
+                date,
+                bits,
+                difficulty_exact,
+                LAG(bits) OVER (ORDER BY height) AS previous_bits
+            FROM block_stats
+        )
+        WHERE previous_bits IS NULL OR bits != previous_bits
+        ORDER BY height;
+        "#,
+    )
+    .get_results(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct PoolFeerateFloor {
+    #[diesel(sql_type = Integer)]
+    pub pool_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+    /// average of the per-block minimum non-zero feerate
+    #[diesel(sql_type = Double)]
+    pub avg_feerate_min_nonzero: f64,
+    /// lowest per-block minimum non-zero feerate
+    #[diesel(sql_type = Double)]
+    pub min_feerate_min_nonzero: f64,
+    /// non-coinbase transactions
+    #[diesel(sql_type = BigInt)]
+    pub transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub zero_fee_tx: i64,
+    #[diesel(sql_type = BigInt)]
+    pub below_1_sat_vbyte: i64,
+    /// blocks including at least one transaction below 1 sat/vB
+    #[diesel(sql_type = BigInt)]
+    pub blocks_below_1_sat_vbyte: i64,
+}
+
+/// The feerate floor of the pools in the recent blocks. Blocks without
+/// fee-paying transactions don't count towards the feerate floor averages.
+pub fn get_pools_feerate_floor(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PoolFeerateFloor>, diesel::result::Error> {
+    sql_query(
+        r#"
+        WITH recent_blocks AS (
+            SELECT
+                bs.pool_id,
+                bs.transactions - 1 AS transactions,
+                bs.feerate_min_nonzero,
+                fs.zero_fee_tx,
+                fs.below_1_sat_vbyte
+            FROM block_stats bs
+            JOIN feerate_stats fs ON bs.height = fs.height
+            ORDER BY bs.height DESC
+            LIMIT 2016*2
+        )
+        SELECT
+            pool_id,
+            COUNT(*) AS blocks,
+            COALESCE(AVG(CASE WHEN feerate_min_nonzero > 0 THEN feerate_min_nonzero END), 0) AS avg_feerate_min_nonzero,
+            COALESCE(MIN(CASE WHEN feerate_min_nonzero > 0 THEN feerate_min_nonzero END), 0) AS min_feerate_min_nonzero,
+            SUM(transactions) AS transactions,
+            SUM(zero_fee_tx) AS zero_fee_tx,
+            SUM(below_1_sat_vbyte) AS below_1_sat_vbyte,
+            SUM(CASE WHEN below_1_sat_vbyte > 0 THEN 1 ELSE 0 END) AS blocks_below_1_sat_vbyte
+        FROM recent_blocks
+        GROUP BY pool_id
+        ORDER BY blocks DESC;
+        "#,
+    )
+    .get_results(conn)
+}
+
+pub fn insert_prices(
+    conn: &mut SqliteConnection,
+    prices: &[Price],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::prices;
+    debug!("Inserting {} prices", prices.len());
+
+    diesel::replace_into(prices::table)
+        .values(prices)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Writes the stats of the tables contained in the partial stats and records
+/// the version of the written stats in the stats_versions table. Tables not
+/// contained in the partial stats of a block are left untouched.
+pub fn insert_stats(
+    conn: &mut SqliteConnection,
+    stats: &[PartialStats],
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        for table in StatsTable::ALL {
+            let table_stats: Vec<&PartialStats> = stats
+                .iter()
+                .filter(|s| s.tables().contains(&table))
+                .collect();
+            if table_stats.is_empty() {
+                continue;
+            }
+            match table {
+                StatsTable::Block => insert_block_stats(
+                    conn,
+                    &table_stats.iter().filter_map(|s| s.block.clone()).collect(),
+                )?,
+                StatsTable::Tx => insert_tx_stats(
+                    conn,
+                    &table_stats.iter().filter_map(|s| s.tx.clone()).collect(),
+                )?,
+                StatsTable::Input => insert_input_stats(
+                    conn,
+                    &table_stats.iter().filter_map(|s| s.input.clone()).collect(),
+                )?,
+                StatsTable::Output => insert_output_stats(
+                    conn,
+                    &table_stats
+                        .iter()
+                        .filter_map(|s| s.output.clone())
+                        .collect(),
+                )?,
+                StatsTable::Script => insert_script_stats(
+                    conn,
+                    &table_stats
+                        .iter()
+                        .filter_map(|s| s.script.clone())
+                        .collect(),
+                )?,
+                StatsTable::Feerate => insert_feerate_stats(
+                    conn,
+                    &table_stats
+                        .iter()
+                        .filter_map(|s| s.feerate.clone())
+                        .collect(),
+                )?,
+                StatsTable::Truc => insert_truc_stats(
+                    conn,
+                    &table_stats.iter().filter_map(|s| s.truc.clone()).collect(),
+                )?,
+                StatsTable::InputSize => insert_input_size_stats(
+                    conn,
+                    &table_stats
+                        .iter()
+                        .filter_map(|s| s.input_size.clone())
+                        .collect(),
+                )?,
+                StatsTable::DormantSpends => insert_dormant_spends(
+                    conn,
+                    &table_stats
+                        .iter()
+                        .filter_map(|s| s.dormant.clone())
+                        .collect(),
+                )?,
+            }
+            update_stats_versions(conn, table, &table_stats.iter().map(|s| s.height).collect())?;
+        }
+        let coinbases: Vec<Coinbase> = stats.iter().filter_map(|s| s.coinbase.clone()).collect();
+        if !coinbases.is_empty() {
+            insert_coinbases(conn, &coinbases)?;
+        }
+        let coinbase_spends: Vec<CoinbaseSpend> = stats
+            .iter()
+            .filter_map(|s| s.coinbase_spends.clone())
+            .flatten()
+            .collect();
+        if !coinbase_spends.is_empty() {
+            insert_coinbase_spends(conn, &coinbase_spends)?;
+        }
+        let contributors: Vec<StatContributor> = stats
+            .iter()
+            .filter_map(|s| s.contributors.clone())
+            .flatten()
+            .collect();
+        if !contributors.is_empty() {
+            insert_stat_contributors(conn, &contributors)?;
+        }
+        let opcodes: Vec<OpcodeCount> = stats
+            .iter()
+            .filter_map(|s| s.opcodes.clone())
+            .flatten()
+            .collect();
+        if !opcodes.is_empty() {
+            insert_opcode_stats(conn, &opcodes)?;
+        }
+        let transitions: Vec<ScriptTypeTransition> = stats
+            .iter()
+            .filter_map(|s| s.transitions.clone())
+            .flatten()
+            .collect();
+        if !transitions.is_empty() {
+            insert_script_type_transitions(conn, &transitions)?;
+        }
+        let novel_scripts: Vec<NovelScript> = stats
+            .iter()
+            .filter_map(|s| s.novel_scripts.clone())
+            .flatten()
+            .collect();
+        if !novel_scripts.is_empty() {
+            insert_novel_scripts(conn, &novel_scripts)?;
+        }
+        let feerate_percentiles: Vec<FeeratePercentile> = stats
+            .iter()
+            .filter_map(|s| s.feerate_percentiles.clone())
+            .flatten()
+            .collect();
+        if !feerate_percentiles.is_empty() {
+            insert_feerate_percentiles(conn, &feerate_percentiles)?;
+        }
+        Ok(())
+    })
+}
+
+fn insert_coinbases(
+    conn: &mut SqliteConnection,
+    coinbases: &[Coinbase],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::coinbases;
+    debug!("Inserting a batch of {} coinbases", coinbases.len());
+
+    replace_chunked::<Coinbase, _>(conn, coinbases::table, coinbases)
+}
+
+fn insert_stat_contributors(
+    conn: &mut SqliteConnection,
+    contributors: &[StatContributor],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::stat_contributors;
+    debug!(
+        "Inserting a batch of {} stat contributors",
+        contributors.len()
+    );
+
+    replace_chunked::<StatContributor, _>(conn, stat_contributors::table, contributors)
+}
+
+/// Inserts the scripts of templates not seen before. For a template already in
+/// the table, the script is replaced if it's from a lower height, as blocks
+/// aren't necessarily processed in order.
+fn insert_novel_scripts(
+    conn: &mut SqliteConnection,
+    scripts: &[NovelScript],
+) -> Result<(), diesel::result::Error> {
+    debug!("Inserting a batch of {} novel scripts", scripts.len());
+
+    for script in scripts {
+        sql_query(
+            "INSERT INTO novel_scripts (template, height, txid, vout, script) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(template) DO UPDATE SET
+                height = excluded.height, txid = excluded.txid, vout = excluded.vout, script = excluded.script
+            WHERE excluded.height < novel_scripts.height",
+        )
+        .bind::<Text, _>(&script.template)
+        .bind::<BigInt, _>(script.height)
+        .bind::<Text, _>(&script.txid)
+        .bind::<Integer, _>(script.vout)
+        .bind::<Text, _>(&script.script)
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn insert_script_type_transitions(
+    conn: &mut SqliteConnection,
+    transitions: &[ScriptTypeTransition],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::script_type_transitions;
+    debug!(
+        "Inserting a batch of {} script type transitions",
+        transitions.len()
+    );
+
+    replace_chunked::<ScriptTypeTransition, _>(conn, script_type_transitions::table, transitions)
+}
+
+fn insert_feerate_percentiles(
+    conn: &mut SqliteConnection,
+    percentiles: &[FeeratePercentile],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::feerate_percentiles;
+    debug!(
+        "Inserting a batch of {} feerate percentiles",
+        percentiles.len()
+    );
+
+    replace_chunked::<FeeratePercentile, _>(conn, feerate_percentiles::table, percentiles)
+}
+
+fn insert_opcode_stats(
+    conn: &mut SqliteConnection,
+    opcodes: &[OpcodeCount],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::opcode_stats;
+    debug!("Inserting a batch of {} opcode counts", opcodes.len());
+
+    replace_chunked::<OpcodeCount, _>(conn, opcode_stats::table, opcodes)
+}
+
+fn insert_coinbase_spends(
+    conn: &mut SqliteConnection,
+    spends: &[CoinbaseSpend],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::coinbase_spends;
+    debug!("Inserting a batch of {} coinbase spends", spends.len());
+
+    replace_chunked::<CoinbaseSpend, _>(conn, coinbase_spends::table, spends)
+}
+
+fn update_stats_versions(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    heights: &Vec<i64>,
+) -> Result<(), diesel::result::Error> {
+    debug!(
+        "Setting the {} version of {} heights to {}",
+        table.table_name(),
+        heights.len(),
+        table.version()
+    );
+    for height in heights {
+        sql_query(format!(
+            "INSERT INTO stats_versions (height, {column}) VALUES (?, ?)
+            ON CONFLICT(height) DO UPDATE SET {column} = excluded.{column}",
+            column = table.table_name()
+        ))
+        .bind::<BigInt, _>(height)
+        .bind::<Integer, _>(table.version())
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn insert_block_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<BlockStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::block_stats;
+    debug!("Inserting a batch of {} block stats", stats.len());
+
+    diesel::replace_into(block_stats::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert_tx_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<TxStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::tx_stats;
+    debug!("Inserting a batch of {} tx stats", stats.len());
+
+    diesel::replace_into(tx_stats::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert_input_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<InputStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::input_stats;
+    debug!("Inserting a batch of {} input stats", stats.len());
+
+    diesel::replace_into(input_stats::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert_output_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<OutputStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::output_stats;
+    debug!("Inserting a batch of {} output stats", stats.len());
+
+    diesel::replace_into(output_stats::table)
+        .values(stats)
         .execute(conn)?;
     Ok(())
 }
@@ -565,7 +2277,7 @@ fn insert_script_stats(
     conn: &mut SqliteConnection,
     stats: &Vec<ScriptStats>,
 ) -> Result<(), diesel::result::Error> {
-    use crate::schema::script_stats;
+    use crate::stats::script_stats;
     debug!("Inserting a batch of {} script stats", stats.len());
 
     diesel::replace_into(script_stats::table)
@@ -578,7 +2290,7 @@ fn insert_feerate_stats(
     conn: &mut SqliteConnection,
     stats: &Vec<FeerateStats>,
 ) -> Result<(), diesel::result::Error> {
-    use crate::schema::feerate_stats;
+    use crate::stats::feerate_stats;
     debug!("Inserting a batch of {} feerate stats", stats.len());
 
     diesel::replace_into(feerate_stats::table)
@@ -586,3 +2298,824 @@ fn insert_feerate_stats(
         .execute(conn)?;
     Ok(())
 }
+
+fn insert_truc_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<TrucStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::truc_stats;
+    debug!("Inserting a batch of {} truc stats", stats.len());
+
+    diesel::replace_into(truc_stats::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert_dormant_spends(
+    conn: &mut SqliteConnection,
+    stats: &Vec<DormantSpends>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::dormant_spends;
+    debug!("Inserting a batch of {} dormant spends", stats.len());
+
+    diesel::replace_into(dormant_spends::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert_input_size_stats(
+    conn: &mut SqliteConnection,
+    stats: &Vec<InputSizeStats>,
+) -> Result<(), diesel::result::Error> {
+    use crate::stats::input_size_stats;
+    debug!("Inserting a batch of {} input size stats", stats.len());
+
+    diesel::replace_into(input_size_stats::table)
+        .values(stats)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// How long processing a block took in the stats collection.
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = crate::schema::processing_metrics)]
+pub struct ProcessingMetrics {
+    pub height: i64,
+    /// fetching (and with --parse-in-fetcher parsing) the block
+    pub fetch_ms: i64,
+    /// calculating the stats of the block
+    pub stats_ms: i64,
+    /// the block's share of inserting its batch into the database
+    pub insert_ms: i64,
+}
+
+pub fn insert_processing_metrics(
+    conn: &mut SqliteConnection,
+    metrics: &[ProcessingMetrics],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::processing_metrics;
+    debug!("Inserting a batch of {} processing metrics", metrics.len());
+
+    replace_chunked::<ProcessingMetrics, _>(conn, processing_metrics::table, metrics)
+}
+
+/// The relay and mempool policy the node advertised at the start of a sync
+/// run. The feerates are in sat/vB.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::node_policy_snapshots)]
+pub struct NodePolicySnapshot {
+    pub sync_run_id: i32,
+    pub recorded_at: String,
+    pub max_mempool_bytes: i64,
+    pub mempool_min_fee: f64,
+    pub min_relay_tx_fee: f64,
+    pub incremental_relay_fee: Option<f64>,
+    pub full_rbf: Option<bool>,
+    pub max_data_carrier_size: Option<i64>,
+    pub permit_bare_multisig: Option<bool>,
+}
+
+impl NodePolicySnapshot {
+    pub fn new(sync_run_id: i32, recorded_at: String, info: &MempoolInfo) -> NodePolicySnapshot {
+        // BTC/kvB to sat/vB
+        let sat_per_vbyte = |btc_per_kvb: f64| btc_per_kvb * 100_000.0;
+        NodePolicySnapshot {
+            sync_run_id,
+            recorded_at,
+            max_mempool_bytes: info.maxmempool as i64,
+            mempool_min_fee: sat_per_vbyte(info.mempoolminfee),
+            min_relay_tx_fee: sat_per_vbyte(info.minrelaytxfee),
+            incremental_relay_fee: info.incrementalrelayfee.map(sat_per_vbyte),
+            full_rbf: info.fullrbf,
+            max_data_carrier_size: info.maxdatacarriersize.map(|size| size as i64),
+            permit_bare_multisig: info.permitbaremultisig,
+        }
+    }
+}
+
+pub fn insert_node_policy_snapshot(
+    conn: &mut SqliteConnection,
+    snapshot: &NodePolicySnapshot,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::node_policy_snapshots;
+    debug!(
+        "Inserting node policy snapshot of sync run {}",
+        snapshot.sync_run_id
+    );
+
+    diesel::insert_into(node_policy_snapshots::table)
+        .values(snapshot)
+        .execute(conn)?;
+    Ok(())
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::sync_runs)]
+pub struct NewSyncRun {
+    pub started_at: String,
+    pub software_version: String,
+    /// JSON object of the per-table stats versions wanted in this run
+    pub stats_versions: String,
+    pub node_implementation: String,
+}
+
+/// The outcome of a sync run. Written once the run finished (or failed).
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = crate::schema::sync_runs)]
+#[diesel(treat_none_as_null = true)]
+pub struct SyncRunResult {
+    pub finished_at: Option<String>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub blocks_processed: i64,
+    pub error: Option<String>,
+    pub requests: i64,
+    pub failed_requests: i64,
+    pub bytes_downloaded: i64,
+    pub latency_sum_ms: i64,
+    pub latency_0_10ms: i64,
+    pub latency_10_50ms: i64,
+    pub latency_50_100ms: i64,
+    pub latency_100_250ms: i64,
+    pub latency_250_500ms: i64,
+    pub latency_500_1000ms: i64,
+    pub latency_1000_5000ms: i64,
+    pub latency_5000ms_plus: i64,
+    pub blocks_failed_validation: i64,
+    pub unexpected_bits_changes: Option<i64>,
+    pub pruned_min_height: Option<i64>,
+    pub pruned_max_height: Option<i64>,
+}
+
+impl SyncRunResult {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        finished_at: String,
+        heights: Option<(i64, i64)>,
+        blocks_processed: u64,
+        error: Option<String>,
+        fetch_stats: &FetchStatsSnapshot,
+        blocks_failed_validation: u64,
+        unexpected_bits_changes: Option<u64>,
+        pruned_heights: Option<(i64, i64)>,
+    ) -> Self {
+        let histogram = fetch_stats.latency_histogram;
+        SyncRunResult {
+            finished_at: Some(finished_at),
+            min_height: heights.map(|(min, _)| min),
+            max_height: heights.map(|(_, max)| max),
+            blocks_processed: blocks_processed as i64,
+            error,
+            requests: fetch_stats.requests as i64,
+            failed_requests: fetch_stats.failed_requests as i64,
+            bytes_downloaded: fetch_stats.bytes_downloaded as i64,
+            latency_sum_ms: fetch_stats.latency_sum_ms as i64,
+            latency_0_10ms: histogram[0] as i64,
+            latency_10_50ms: histogram[1] as i64,
+            latency_50_100ms: histogram[2] as i64,
+            latency_100_250ms: histogram[3] as i64,
+            latency_250_500ms: histogram[4] as i64,
+            latency_500_1000ms: histogram[5] as i64,
+            latency_1000_5000ms: histogram[6] as i64,
+            latency_5000ms_plus: histogram[7] as i64,
+            blocks_failed_validation: blocks_failed_validation as i64,
+            unexpected_bits_changes: unexpected_bits_changes.map(|c| c as i64),
+            pruned_min_height: pruned_heights.map(|(min, _)| min),
+            pruned_max_height: pruned_heights.map(|(_, max)| max),
+        }
+    }
+}
+
+#[derive(Debug, QueryableByName)]
+struct LastInsertRowId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[derive(Debug, QueryableByName)]
+struct BlocksPerSecond {
+    #[diesel(sql_type = Nullable<Double>)]
+    blocks_per_second: Option<f64>,
+}
+
+/// The block processing throughput of the recent successful sync runs. None if
+/// there are no such runs.
+pub fn recent_blocks_per_second(
+    conn: &mut SqliteConnection,
+) -> Result<Option<f64>, diesel::result::Error> {
+    let row: BlocksPerSecond = sql_query(
+        r#"
+        SELECT
+            SUM(blocks_processed) / SUM((julianday(finished_at) - julianday(started_at)) * 86400.0)
+                AS blocks_per_second
+        FROM (
+            SELECT started_at, finished_at, blocks_processed
+            FROM sync_runs
+            WHERE finished_at IS NOT NULL AND error IS NULL AND blocks_processed > 0
+            ORDER BY id DESC
+            LIMIT 10
+        );
+        "#,
+    )
+    .get_result(conn)?;
+    Ok(row.blocks_per_second.filter(|rate| *rate > 0.0))
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct TableCoverage {
+    #[diesel(sql_type = BigInt)]
+    pub rows: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub min_height: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub max_height: Option<i64>,
+}
+
+/// The number of rows and the heights covered by a stats table.
+pub fn table_coverage(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+) -> Result<TableCoverage, diesel::result::Error> {
+    sql_query(format!(
+        "SELECT COUNT(*) AS rows, MIN(height) AS min_height, MAX(height) AS max_height FROM {};",
+        table.table_name()
+    ))
+    .get_result(conn)
+}
+
+/// The values of a column of a stats table for the heights in `heights`,
+/// ordered by height, as rows of `T` with the column selected as `value`.
+pub fn column_values<T>(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    column: &str,
+    heights: std::ops::Range<i64>,
+) -> Result<Vec<T>, diesel::result::Error>
+where
+    T: QueryableByName<diesel::sqlite::Sqlite> + 'static,
+{
+    sql_query(format!(
+        "SELECT {} AS value FROM {} WHERE height >= ? AND height < ? ORDER BY height",
+        column,
+        table.table_name()
+    ))
+    .bind::<BigInt, _>(heights.start)
+    .bind::<BigInt, _>(heights.end)
+    .load(conn)
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct VersionCount {
+    #[diesel(sql_type = Integer)]
+    pub version: i32,
+    #[diesel(sql_type = BigInt)]
+    pub heights: i64,
+}
+
+/// The number of heights per recorded version of a stats table.
+pub fn stats_version_counts(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+) -> Result<Vec<VersionCount>, diesel::result::Error> {
+    sql_query(format!(
+        "SELECT {column} AS version, COUNT(*) AS heights FROM stats_versions GROUP BY {column} ORDER BY {column};",
+        column = table.table_name()
+    ))
+    .load(conn)
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::sync_runs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SyncRun {
+    pub id: i32,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub software_version: Option<String>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub blocks_processed: i64,
+    pub error: Option<String>,
+    pub blocks_failed_validation: i64,
+    pub pruned_min_height: Option<i64>,
+    pub pruned_max_height: Option<i64>,
+    pub node_implementation: Option<String>,
+}
+
+/// The most recently started sync run, if any.
+pub fn last_sync_run(
+    conn: &mut SqliteConnection,
+) -> Result<Option<SyncRun>, diesel::result::Error> {
+    use crate::schema::sync_runs;
+    sync_runs::table
+        .order(sync_runs::id.desc())
+        .select(SyncRun::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Inserts a new sync run and returns its id.
+pub fn start_sync_run(
+    conn: &mut SqliteConnection,
+    sync_run: &NewSyncRun,
+) -> Result<i32, diesel::result::Error> {
+    use crate::schema::sync_runs;
+    debug!("Inserting sync run started at {}", sync_run.started_at);
+
+    diesel::insert_into(sync_runs::table)
+        .values(sync_run)
+        .execute(conn)?;
+    let row: LastInsertRowId = sql_query("SELECT last_insert_rowid() AS id").get_result(conn)?;
+    Ok(row.id)
+}
+
+pub fn finish_sync_run(
+    conn: &mut SqliteConnection,
+    sync_run_id: i32,
+    result: &SyncRunResult,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::sync_runs::dsl::*;
+    debug!("Finishing sync run {}", sync_run_id);
+
+    diesel::update(sync_runs.find(sync_run_id))
+        .set(result)
+        .execute(conn)?;
+    Ok(())
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct InvariantViolation {
+    #[diesel(sql_type = BigInt)]
+    pub height: i64,
+    /// all tables the invariant involves are of the current version
+    #[diesel(sql_type = Bool)]
+    pub current: bool,
+}
+
+/// Joins the rows of the given stats tables and their versions on the height.
+/// The tables are available under their table names.
+fn invariant_join(tables: &[StatsTable]) -> String {
+    let first = tables[0].table_name();
+    let mut join = format!(
+        "{first} JOIN stats_versions sv ON sv.height = {first}.height",
+        first = first
+    );
+    for table in &tables[1..] {
+        join.push_str(&format!(
+            " JOIN {table} ON {table}.height = {first}.height",
+            table = table.table_name(),
+            first = first
+        ));
+    }
+    join
+}
+
+/// The number of heights all given stats tables have rows for.
+pub fn invariant_checked_heights(
+    conn: &mut SqliteConnection,
+    tables: &[StatsTable],
+) -> Result<i64, diesel::result::Error> {
+    let row: MergeCount = sql_query(format!(
+        "SELECT COUNT(*) AS count FROM {}",
+        invariant_join(tables)
+    ))
+    .get_result(conn)?;
+    Ok(row.count)
+}
+
+/// The heights where the `holds` SQL condition over the given stats tables is
+/// false, ordered by height.
+pub fn invariant_violations(
+    conn: &mut SqliteConnection,
+    tables: &[StatsTable],
+    holds: &str,
+) -> Result<Vec<InvariantViolation>, diesel::result::Error> {
+    let current = tables
+        .iter()
+        .map(|table| format!("sv.{} = {}", table.table_name(), table.version()))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    sql_query(format!(
+        "SELECT {first}.height AS height, ({current}) AS current FROM {join}
+        WHERE NOT ({holds}) ORDER BY {first}.height",
+        first = tables[0].table_name(),
+        current = current,
+        join = invariant_join(tables),
+        holds = holds,
+    ))
+    .load(conn)
+}
+
+/// Schema name of the database attached by [attach_merge_source].
+const MERGE_SOURCE: &str = "merge_source";
+
+/// Attaches another database, with the same schema, to merge rows from.
+pub fn attach_merge_source(
+    conn: &mut SqliteConnection,
+    path: &Path,
+) -> Result<(), diesel::result::Error> {
+    sql_query(format!("ATTACH DATABASE ? AS {}", MERGE_SOURCE))
+        .bind::<Text, _>(path.to_string_lossy())
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn detach_merge_source(conn: &mut SqliteConnection) -> Result<(), diesel::result::Error> {
+    sql_query(format!("DETACH DATABASE {}", MERGE_SOURCE)).execute(conn)?;
+    Ok(())
+}
+
+#[derive(Debug, QueryableByName)]
+struct MergeCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(Debug, QueryableByName)]
+struct MergeHeight {
+    #[diesel(sql_type = BigInt)]
+    height: i64,
+}
+
+/// Joins the rows of a stats table in both databases whose stats are of the
+/// current version.
+fn merge_overlap_join(table: StatsTable) -> String {
+    format!(
+        "main.{table} m
+        JOIN {source}.{table} o ON o.height = m.height
+        JOIN main.stats_versions mv ON mv.height = m.height AND mv.{table} = {version}
+        JOIN {source}.stats_versions ov ON ov.height = m.height AND ov.{table} = {version}",
+        table = table.table_name(),
+        source = MERGE_SOURCE,
+        version = table.version(),
+    )
+}
+
+/// The number of heights of a stats table with current stats in both the
+/// database and the merge source.
+pub fn merge_overlapping_heights(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+) -> Result<i64, diesel::result::Error> {
+    let row: MergeCount = sql_query(format!(
+        "SELECT COUNT(*) AS count FROM {}",
+        merge_overlap_join(table)
+    ))
+    .get_result(conn)?;
+    Ok(row.count)
+}
+
+/// The overlapping heights of a stats table where the column differs between
+/// the database and the merge source.
+pub fn merge_differing_heights(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    column: &str,
+) -> Result<Vec<i64>, diesel::result::Error> {
+    let rows: Vec<MergeHeight> = sql_query(format!(
+        "SELECT m.height AS height FROM {} WHERE m.{column} IS NOT o.{column} ORDER BY m.height",
+        merge_overlap_join(table),
+        column = column
+    ))
+    .load(conn)?;
+    Ok(rows.into_iter().map(|row| row.height).collect())
+}
+
+/// The number of rows of a stats table in the merge source that aren't of the
+/// current version and are therefore not merged.
+pub fn merge_source_outdated_rows(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+) -> Result<i64, diesel::result::Error> {
+    let row: MergeCount = sql_query(format!(
+        "SELECT COUNT(*) AS count FROM {source}.{table} o
+        LEFT JOIN {source}.stats_versions ov ON ov.height = o.height
+        WHERE ov.{table} IS NOT {version}",
+        table = table.table_name(),
+        source = MERGE_SOURCE,
+        version = table.version(),
+    ))
+    .get_result(conn)?;
+    Ok(row.count)
+}
+
+/// Copies the rows of a stats table with current stats from the merge source
+/// for the heights the database has no or only outdated stats for, and
+/// records their version. Heights below `pruned_below` were pruned and aren't
+/// copied, as their daily aggregates are already in daily_metrics. Returns the
+/// number of copied rows.
+pub fn merge_fill_gaps(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    pruned_below: i64,
+) -> Result<usize, diesel::result::Error> {
+    let columns: Vec<&str> = table.columns().iter().map(|c| c.name).collect();
+    conn.transaction(|conn| {
+        sql_query(format!(
+            "CREATE TEMP TABLE merge_heights AS
+            SELECT o.height AS height FROM {source}.{table} o
+            JOIN {source}.stats_versions ov ON ov.height = o.height AND ov.{table} = {version}
+            LEFT JOIN main.stats_versions mv ON mv.height = o.height
+            WHERE (mv.{table} IS NULL OR mv.{table} < {version}) AND o.height >= {pruned_below}",
+            table = table.table_name(),
+            source = MERGE_SOURCE,
+            version = table.version(),
+            pruned_below = pruned_below,
+        ))
+        .execute(conn)?;
+        let copied = sql_query(format!(
+            "INSERT OR REPLACE INTO main.{table} ({columns})
+            SELECT {columns} FROM {source}.{table} WHERE height IN (SELECT height FROM merge_heights)",
+            table = table.table_name(),
+            source = MERGE_SOURCE,
+            columns = columns.join(", "),
+        ))
+        .execute(conn)?;
+        // the WHERE clause is needed to parse the upsert after a SELECT
+        sql_query(format!(
+            "INSERT INTO main.stats_versions (height, {table})
+            SELECT height, {version} FROM merge_heights WHERE true
+            ON CONFLICT(height) DO UPDATE SET {table} = excluded.{table}",
+            table = table.table_name(),
+            version = table.version(),
+        ))
+        .execute(conn)?;
+        sql_query("DROP TABLE merge_heights").execute(conn)?;
+        Ok(copied)
+    })
+}
+
+/// Copies the rows of an auxiliary table (e.g. opcode_stats) from the merge
+/// source that the database doesn't have yet. With a `height_column`, rows
+/// below `pruned_below` aren't copied. Returns the number of copied rows.
+pub fn merge_missing_rows(
+    conn: &mut SqliteConnection,
+    table: &str,
+    height_column: Option<&str>,
+    pruned_below: i64,
+) -> Result<usize, diesel::result::Error> {
+    let filter = match height_column {
+        Some(column) => format!(" WHERE {} >= {}", column, pruned_below),
+        None => String::new(),
+    };
+    sql_query(format!(
+        "INSERT OR IGNORE INTO main.{table} SELECT * FROM {source}.{table}{filter}",
+        table = table,
+        source = MERGE_SOURCE,
+        filter = filter,
+    ))
+    .execute(conn)
+}
+
+/// The UTC offset in seconds the dates of the pruned daily aggregates are in,
+/// if blocks were pruned. The dates of the pruned blocks can't be
+/// recalculated in another timezone.
+pub fn pruned_aggregation_timezone(
+    conn: &mut SqliteConnection,
+) -> Result<Option<i32>, diesel::result::Error> {
+    schema::daily_metrics::table
+        .select(schema::daily_metrics::utc_offset_seconds)
+        .first(conn)
+        .optional()
+}
+
+/// The per-block rows below this height were pruned, if any.
+pub fn pruned_below_height(
+    conn: &mut SqliteConnection,
+) -> Result<Option<i64>, diesel::result::Error> {
+    schema::pruned_height::table
+        .select(schema::pruned_height::below_height)
+        .first(conn)
+        .optional()
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct PruneRange {
+    /// the first height on or after the cutoff date
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub below_height: Option<i64>,
+    /// the number of blocks below it
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+}
+
+/// The blocks dated before `cutoff_date` (YYYY-MM-DD) that would be pruned.
+/// As the block timestamps aren't monotonic, all blocks below the first
+/// block on or after the cutoff date are pruned.
+pub fn prune_range(
+    conn: &mut SqliteConnection,
+    cutoff_date: &str,
+) -> Result<PruneRange, diesel::result::Error> {
+    sql_query(
+        r#"
+        SELECT
+            t.below_height,
+            (SELECT COUNT(*) FROM block_stats WHERE height < t.below_height) AS blocks
+        FROM (
+            SELECT COALESCE(
+                (SELECT MIN(height) FROM block_stats WHERE date >= ?),
+                (SELECT MAX(height) + 1 FROM block_stats)
+            ) AS below_height
+        ) t;
+        "#,
+    )
+    .bind::<Text, _>(cutoff_date)
+    .get_result(conn)
+}
+
+/// Stores the daily aggregates of the given metric columns of the blocks
+/// below `below_height` in daily_metrics, with the aggregation timezone their
+/// dates are in, and deletes the per-block rows of the stats tables. Heights
+/// below `below_height` aren't synced again. Returns the number of deleted
+/// rows per table.
+pub fn prune_below(
+    conn: &mut SqliteConnection,
+    below_height: i64,
+    metrics: &[(StatsTable, Vec<&str>)],
+) -> Result<BTreeMap<&'static str, usize>, diesel::result::Error> {
+    conn.transaction(|conn| {
+        let seconds = get_aggregation_timezone(conn)?;
+        for (table, columns) in metrics {
+            for column in columns {
+                // the WHERE is needed for SQLite to parse the upsert
+                sql_query(format!(
+                    "INSERT INTO daily_metrics (table_name, column_name, date, avg, sum, blocks, utc_offset_seconds)
+                    SELECT ?, ?, date, AVG({column}), SUM({column}), COUNT(*), ? FROM {table}
+                    WHERE height < ? GROUP BY date
+                    ON CONFLICT (table_name, column_name, date) DO UPDATE SET
+                        avg = (avg * blocks + excluded.avg * excluded.blocks) / (blocks + excluded.blocks),
+                        sum = sum + excluded.sum,
+                        blocks = blocks + excluded.blocks",
+                    column = column,
+                    table = table.table_name(),
+                ))
+                .bind::<Text, _>(table.table_name())
+                .bind::<Text, _>(column)
+                .bind::<Integer, _>(seconds)
+                .bind::<BigInt, _>(below_height)
+                .execute(conn)?;
+            }
+        }
+        let mut deleted = BTreeMap::new();
+        for table in StatsTable::ALL {
+            let rows = sql_query(format!("DELETE FROM {} WHERE height < ?", table.table_name()))
+                .bind::<BigInt, _>(below_height)
+                .execute(conn)?;
+            deleted.insert(table.table_name(), rows);
+        }
+        sql_query("DELETE FROM stats_versions WHERE height < ?")
+            .bind::<BigInt, _>(below_height)
+            .execute(conn)?;
+        let below_height = match pruned_below_height(conn)? {
+            Some(previous) => previous.max(below_height),
+            None => below_height,
+        };
+        diesel::delete(schema::pruned_height::table).execute(conn)?;
+        diesel::insert_into(schema::pruned_height::table)
+            .values(schema::pruned_height::below_height.eq(below_height))
+            .execute(conn)?;
+        Ok(deleted)
+    })
+}
+
+/// Rebuilds the database file to release the space of deleted rows.
+pub fn vacuum(conn: &mut SqliteConnection) -> Result<(), diesel::result::Error> {
+    sql_query("VACUUM").execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use super::{insert_stats, open_db_and_run_migrations, replace_pools};
+    use crate::rest::Block;
+    use crate::stats::{PartialStats, StatsTable};
+    use diesel::SqliteConnection;
+    use std::collections::BTreeSet;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::{Path, PathBuf};
+
+    /// The testdata blocks from 2015, 2022 and 2025.
+    pub(crate) const TEST_HEIGHTS: [u64; 3] = [361582, 739990, 888395];
+
+    /// A temporary directory that is deleted when dropped, also when a test
+    /// panics.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Creates a migrated database in a new temporary directory and, like a
+    /// sync, stores the pool list and the stats of all tables for the
+    /// testdata blocks at the given heights.
+    pub(crate) fn stats_db(name: &str, heights: &[u64]) -> (TempDir, SqliteConnection) {
+        let dir = TempDir::new(name);
+        let mut conn = open_db_and_run_migrations(&dir.path().join("stats.db")).unwrap();
+        let tables: BTreeSet<StatsTable> = StatsTable::ALL.into_iter().collect();
+        let stats: Vec<PartialStats> = heights
+            .iter()
+            .map(|height| {
+                let file = File::open(format!("./testdata/{}.json", height)).unwrap();
+                let block: Block = serde_json::from_reader(BufReader::new(file)).unwrap();
+                PartialStats::from_block(block, &tables).unwrap()
+            })
+            .collect();
+        replace_pools(&mut conn, crate::pools::pools()).unwrap();
+        insert_stats(&mut conn, &stats).unwrap();
+        (dir, conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::stats_db;
+    use super::{audit_indexes, NodePolicySnapshot};
+    use crate::gen_csv::COLUMN_NAMES_THAT_ARENT_METRICS;
+    use crate::stats::StatsTable;
+    use diesel::sql_types::{BigInt, Double};
+    use diesel::{sql_query, QueryableByName, RunQueryDsl};
+
+    #[derive(QueryableByName)]
+    struct Row {
+        #[diesel(sql_type = BigInt)]
+        metrics: i64,
+        #[diesel(sql_type = Double)]
+        transactions: f64,
+    }
+
+    #[test]
+    fn test_audit_indexes() {
+        let (_dir, mut conn) = stats_db("index-audit-test", &[]);
+        assert!(audit_indexes(&mut conn).unwrap().is_empty());
+        sql_query("DROP INDEX tx_stats_date")
+            .execute(&mut conn)
+            .unwrap();
+        assert_eq!(audit_indexes(&mut conn).unwrap(), vec!["tx_stats_date"]);
+        assert!(audit_indexes(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_metrics_long_view() {
+        let (_dir, mut conn) = stats_db("metrics-long-test", &[739990]);
+
+        let row: Row = sql_query(
+            "SELECT COUNT(*) AS metrics, SUM(CASE metric_name WHEN 'transactions' THEN value END) AS transactions
+            FROM metrics_long WHERE height = 739990",
+        )
+        .get_result(&mut conn)
+        .unwrap();
+
+        let numeric_columns = StatsTable::ALL
+            .iter()
+            .flat_map(|table| table.columns())
+            .filter(|c| matches!(c.sql_type, "Integer" | "BigInt" | "Float" | "Double"))
+            .filter(|c| !COLUMN_NAMES_THAT_ARENT_METRICS.contains(&c.name))
+            .count();
+        assert_eq!(row.metrics, numeric_columns as i64);
+        assert_eq!(row.transactions, 645.0);
+    }
+
+    #[test]
+    fn test_node_policy_snapshot() {
+        let info = serde_json::from_str(
+            r#"{"loaded": true, "size": 1, "bytes": 200, "usage": 1000, "total_fee": 0.0001,
+            "maxmempool": 300000000, "mempoolminfee": 0.00001000, "minrelaytxfee": 0.00000100,
+            "incrementalrelayfee": 0.00001000, "unbroadcastcount": 0, "fullrbf": true}"#,
+        )
+        .unwrap();
+        let snapshot = NodePolicySnapshot::new(1, "2026-10-18T00:00:00Z".to_string(), &info);
+        assert_eq!(snapshot.max_mempool_bytes, 300_000_000);
+        assert!((snapshot.mempool_min_fee - 1.0).abs() < 1e-9);
+        assert!((snapshot.min_relay_tx_fee - 0.1).abs() < 1e-9);
+        assert_eq!(snapshot.full_rbf, Some(true));
+        assert_eq!(snapshot.max_data_carrier_size, None);
+
+        let info = serde_json::from_str(
+            r#"{"loaded": true, "size": 1, "bytes": 200, "usage": 1000, "total_fee": 0.0001,
+            "maxmempool": 300000000, "mempoolminfee": 0.00000100, "minrelaytxfee": 0.00000100,
+            "incrementalrelayfee": 0.00000100, "unbroadcastcount": 0, "fullrbf": true,
+            "permitbaremultisig": false, "maxdatacarriersize": 83}"#,
+        )
+        .unwrap();
+        let snapshot = NodePolicySnapshot::new(1, "2026-10-18T00:00:00Z".to_string(), &info);
+        assert_eq!(snapshot.max_data_carrier_size, Some(83));
+        assert_eq!(snapshot.permit_bare_multisig, Some(false));
+    }
+}
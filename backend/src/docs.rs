@@ -0,0 +1,85 @@
+use crate::db;
+use crate::stats::StatsTable;
+use crate::MainError;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+/// Generates a data dictionary of the stats tables from the column doc
+/// comments: the name, type and meaning of each column and the migration that
+/// added it.
+pub fn data_dictionary(format: DocsFormat) -> Result<String, MainError> {
+    let table_names: Vec<&str> = StatsTable::ALL.iter().map(|t| t.table_name()).collect();
+    let added = db::column_migrations(&table_names)?;
+
+    let mut out = String::new();
+    match format {
+        DocsFormat::Markdown => out.push_str("# Data dictionary\n"),
+        DocsFormat::Html => out.push_str("<h1>Data dictionary</h1>\n"),
+    }
+    for table in StatsTable::ALL {
+        let name = table.table_name();
+        match format {
+            DocsFormat::Markdown => out.push_str(&format!(
+                "\n## {} (version {})\n\n| Column | Type | Description | Added in migration |\n| --- | --- | --- | --- |\n",
+                name,
+                table.version()
+            )),
+            DocsFormat::Html => out.push_str(&format!(
+                "<h2>{} (version {})</h2>\n<table>\n<tr><th>Column</th><th>Type</th><th>Description</th><th>Added in migration</th></tr>\n",
+                name,
+                table.version()
+            )),
+        }
+        for column in table.columns() {
+            let migration = added
+                .get(&(name.to_string(), column.name.to_string()))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            match format {
+                DocsFormat::Markdown => out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    column.name,
+                    column.sql_type,
+                    column.description().replace('|', "\\|"),
+                    migration
+                )),
+                DocsFormat::Html => out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    column.name,
+                    column.sql_type,
+                    escape_html(&column.description()),
+                    migration
+                )),
+            }
+        }
+        if format == DocsFormat::Html {
+            out.push_str("</table>\n");
+        }
+    }
+    Ok(out)
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{data_dictionary, DocsFormat};
+
+    #[test]
+    fn test_data_dictionary_up_to_date() {
+        let generated = data_dictionary(DocsFormat::Markdown).unwrap();
+        assert!(
+            generated == include_str!("../DATA_DICTIONARY.md"),
+            "DATA_DICTIONARY.md is outdated, regenerate it with `cargo run -- export schema > DATA_DICTIONARY.md`"
+        );
+    }
+}
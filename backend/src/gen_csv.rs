@@ -1,21 +1,59 @@
-use crate::{db, db::TableInfo, MainError};
-use bitcoin::Network;
+use crate::stats::{StatsTable, LARGE_COINBASE_WEIGHT};
+use crate::{db, MainError};
+use clap::ValueEnum;
 use diesel::SqliteConnection;
 use log::info;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
-const METRIC_TABLES: [&str; 6] = [
-    "block_stats",
-    "tx_stats",
-    "script_stats",
-    "input_stats",
-    "output_stats",
-    "feerate_stats",
+const METRIC_TABLES: [StatsTable; 9] = [
+    StatsTable::Block,
+    StatsTable::Tx,
+    StatsTable::Script,
+    StatsTable::Input,
+    StatsTable::Output,
+    StatsTable::Feerate,
+    StatsTable::Truc,
+    StatsTable::InputSize,
+    StatsTable::DormantSpends,
+];
+pub const COLUMN_NAMES_THAT_ARENT_METRICS: [&str; 8] = [
+    "height",
+    "date",
+    "version",
+    "nonce",
+    "bits",
+    "pool_id",
+    "difficulty_exact",
+    "version_rolling_bits",
+];
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+// The subdirectory of the CSV directory the downsampled metrics are written to.
+const DOWNSAMPLED_DIR: &str = "downsampled";
+// The subdirectory of the CSV directory the partitioned metrics are written to.
+const PARTITIONS_DIR: &str = "partitions";
+const BLOCKS_PER_PARTITION: i64 = 100_000;
+pub const HALVING_INTERVAL: i64 = 210_000;
+
+const OUTLIER_BLOCKS_PER_MONTH: i64 = 10;
+// The superlatives in the outlier block report: name, table, column and the
+// number of decimals the value is written with.
+const OUTLIER_BLOCK_METRICS: [(&str, &str, &str, usize); 5] = [
+    ("most_inputs", "block_stats", "inputs", 0),
+    ("most_outputs", "block_stats", "outputs", 0),
+    (
+        "largest_opreturn_bytes",
+        "output_stats",
+        "outputs_opreturn_bytes",
+        0,
+    ),
+    ("highest_avg_feerate", "feerate_stats", "feerate_avg", 2),
+    ("most_p2a_outputs", "output_stats", "outputs_p2a", 0),
 ];
-const COLUMN_NAMES_THAT_ARENT_METRICS: [&str; 6] =
-    ["height", "date", "version", "nonce", "bits", "pool_id"];
 
 // An array with pool IDs based on https://github.com/bitcoin-data/mining-pools/blob/generated/pool-list.json
 // representing the "AntPool & Friends" proxy pool group.
@@ -37,78 +75,261 @@ pub const PROXY_POOL_GROUP_ANTPOOL: [u64; 11] = [
          // - frontend/content/charts/mining-pools-centralization-index-with-proxy-pools.md
 ];
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CsvPartition {
+    /// One partition per calendar year
+    Year,
+    /// One partition per 100k blocks, by the first block of each day
+    Blocks100k,
+}
+
+/// A date range the metrics are additionally written for, e.g. a year.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// Suffix of the partitioned file names, e.g. `2015` or `300000-399999`.
+    pub name: String,
+    pub first_date: String,
+    pub last_date: String,
+    pub min_height: i64,
+    pub max_height: i64,
+}
+
+// Splits the dates of the database into partitions. The days are assigned to
+// the partition of their first block, so a day is never split.
+pub fn partitions(
+    connection: Arc<Mutex<SqliteConnection>>,
+    scheme: CsvPartition,
+) -> Result<Vec<Partition>, MainError> {
+    let mut conn = connection.lock().unwrap();
+    Ok(split_into_partitions(
+        &db::heights_by_date(&mut conn)?,
+        scheme,
+    ))
+}
+
+fn split_into_partitions(dates: &[db::DateHeights], scheme: CsvPartition) -> Vec<Partition> {
+    let mut partitions: Vec<Partition> = vec![];
+    for date in dates {
+        let name = match scheme {
+            CsvPartition::Year => date.date[..4].to_string(),
+            CsvPartition::Blocks100k => {
+                let start = date.min_height / BLOCKS_PER_PARTITION * BLOCKS_PER_PARTITION;
+                format!("{}-{}", start, start + BLOCKS_PER_PARTITION - 1)
+            }
+        };
+        match partitions.last_mut() {
+            Some(partition) if partition.name == name => {
+                partition.last_date = date.date.clone();
+                partition.max_height = date.max_height;
+            }
+            _ => partitions.push(Partition {
+                name,
+                first_date: date.date.clone(),
+                last_date: date.date.clone(),
+                min_height: date.min_height,
+                max_height: date.max_height,
+            }),
+        }
+    }
+    partitions
+}
+
+/// The columns of each table metric CSV files are written for, i.e. without
+/// the columns that aren't metrics.
+pub fn metric_columns() -> Vec<(StatsTable, Vec<&'static str>)> {
+    METRIC_TABLES
+        .iter()
+        .map(|table| {
+            let columns = table
+                .columns()
+                .iter()
+                .filter(|col| !COLUMN_NAMES_THAT_ARENT_METRICS.contains(&col.name))
+                .map(|col| col.name)
+                .collect();
+            (*table, columns)
+        })
+        .collect()
+}
+
 // Generates a date.csv file with a single column with the date.
 // To be used together with other metric CSV files.
-pub fn date_csv(csv_path: &str, connection: Arc<Mutex<SqliteConnection>>) -> Result<(), MainError> {
+pub fn date_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating date.csv file...");
     let date_column = db::date_column(&mut conn);
-    let mut date_file = std::fs::File::create(format!("{}/date.csv", csv_path))?;
+    let mut date_file = std::fs::File::create(csv_path.join("date.csv"))?;
     let date_content: String = date_column
         .iter()
         .map(|row| format!("{}\n", row.date))
         .collect();
     date_file.write_all("date\n".as_bytes())?;
     date_file.write_all(date_content.as_bytes())?;
-    Ok(())
+    Ok(vec!["date.csv".to_string()])
 }
 
 // Generates multiple metric csv files where each metrics has its own file.
 // A metric csv file can be used together with the date.csv file and other metric csv files.
+// With `downsample_points`, each metric is also written downsampled to that
+// many points with its dates to the downsampled/ directory. Each metric is also
+// written with its dates for each of the `partitions` to the partitions/
+// directory, e.g. as partitions/fee_avg_2015.csv.
 pub fn metrics_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+    downsample_points: Option<usize>,
+    partitions: &[Partition],
+) -> Result<Vec<String>, MainError> {
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
+    let mut files = Vec::new();
+    if downsample_points.is_some() {
+        std::fs::create_dir_all(csv_path.join(DOWNSAMPLED_DIR))?;
+    }
+    if !partitions.is_empty() {
+        std::fs::create_dir_all(csv_path.join(PARTITIONS_DIR))?;
+    }
 
-    for table in METRIC_TABLES.iter() {
-        let columns = db::list_column_names(&mut conn, table)?;
-
-        // filter out columns that aren't metrics and we don't want to create csv files for
-        let columns_filtered: Vec<&TableInfo> = columns
-            .iter()
-            .filter(|col| !COLUMN_NAMES_THAT_ARENT_METRICS.contains(&&col.name[..]))
-            .collect();
+    for (table, columns) in metric_columns() {
+        let table_name = table.table_name();
 
-        for column in columns_filtered.iter().map(|col| col.name.clone()) {
-            info!("Generating metrics for '{}' in table '{}'.", column, table);
-            let avg_and_sum = db::column_sum_and_avg_by_date(&mut conn, &column, table);
+        for column in columns {
+            info!(
+                "Generating metrics for '{}' in table '{}'.",
+                column, table_name
+            );
+            let avg_and_sum = crate::timed_export(column, || {
+                Ok(db::column_sum_and_avg_by_date(
+                    &mut conn, column, table_name,
+                ))
+            })?;
 
-            let mut avg_file = std::fs::File::create(format!("{}/{}_avg.csv", csv_path, column))?;
+            let avg_filename = format!("{}_avg.csv", column);
+            let mut avg_file = std::fs::File::create(csv_path.join(&avg_filename))?;
             let avg_content: String = avg_and_sum
                 .iter()
                 .map(|aas| format!("{:.4}\n", aas.avg))
                 .collect();
             avg_file.write_all(format!("{}_avg\n", column).as_bytes())?;
             avg_file.write_all(avg_content.as_bytes())?;
+            files.push(avg_filename);
 
-            let mut sum_file = std::fs::File::create(format!("{}/{}_sum.csv", csv_path, column))?;
+            let sum_filename = format!("{}_sum.csv", column);
+            let mut sum_file = std::fs::File::create(csv_path.join(&sum_filename))?;
             let sum_content: String = avg_and_sum
                 .iter()
                 .map(|aas| format!("{}\n", aas.sum))
                 .collect();
             sum_file.write_all(format!("{}_sum\n", column).as_bytes())?;
             sum_file.write_all(sum_content.as_bytes())?;
+            files.push(sum_filename);
+
+            if let Some(points) = downsample_points {
+                let avgs: Vec<f64> = avg_and_sum.iter().map(|aas| aas.avg as f64).collect();
+                let sums: Vec<f64> = avg_and_sum.iter().map(|aas| aas.sum as f64).collect();
+                for (suffix, values) in [("avg", avgs), ("sum", sums)] {
+                    let filename = format!("{}/{}_{}.csv", DOWNSAMPLED_DIR, column, suffix);
+                    let mut file = std::fs::File::create(csv_path.join(&filename))?;
+                    file.write_all(format!("date,{}_{}\n", column, suffix).as_bytes())?;
+                    let content: String = lttb(&values, points)
+                        .into_iter()
+                        .map(|i| match suffix {
+                            "avg" => format!("{},{:.4}\n", avg_and_sum[i].date, avg_and_sum[i].avg),
+                            _ => format!("{},{}\n", avg_and_sum[i].date, avg_and_sum[i].sum),
+                        })
+                        .collect();
+                    file.write_all(content.as_bytes())?;
+                    files.push(filename);
+                }
+            }
+
+            for partition in partitions {
+                let rows: Vec<&db::AvgAndSum> = avg_and_sum
+                    .iter()
+                    .filter(|aas| {
+                        aas.date >= partition.first_date && aas.date <= partition.last_date
+                    })
+                    .collect();
+                for suffix in ["avg", "sum"] {
+                    let filename = format!(
+                        "{}/{}_{}_{}.csv",
+                        PARTITIONS_DIR, column, suffix, partition.name
+                    );
+                    let mut file = std::fs::File::create(csv_path.join(&filename))?;
+                    file.write_all(format!("date,{}_{}\n", column, suffix).as_bytes())?;
+                    let content: String = rows
+                        .iter()
+                        .map(|aas| match suffix {
+                            "avg" => format!("{},{:.4}\n", aas.date, aas.avg),
+                            _ => format!("{},{}\n", aas.date, aas.sum),
+                        })
+                        .collect();
+                    file.write_all(content.as_bytes())?;
+                    files.push(filename);
+                }
+            }
         }
     }
-    Ok(())
+    Ok(files)
+}
+
+// Selects the indices of at most `threshold` points of the series with the
+// Largest-Triangle-Three-Buckets algorithm: the first and last point and,
+// for each bucket in between, the point forming the largest triangle with the
+// point selected in the previous bucket and the average of the next bucket.
+// The x values are the indices, i.e. the days.
+fn lttb(values: &[f64], threshold: usize) -> Vec<usize> {
+    if threshold >= values.len() || threshold < 3 {
+        return (0..values.len()).collect();
+    }
+    let bucket_size = (values.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket_start = |bucket: usize| (bucket as f64 * bucket_size) as usize + 1;
+
+    let mut selected = Vec::with_capacity(threshold);
+    selected.push(0);
+    let mut previous = 0;
+    for bucket in 0..threshold - 2 {
+        let (start, end) = (bucket_start(bucket), bucket_start(bucket + 1));
+        // the average of the next bucket, or the last point for the last bucket
+        let (next_start, next_end) = (end, bucket_start(bucket + 2).min(values.len()));
+        let next_end = next_end.max(next_start + 1).min(values.len());
+        let next_len = (next_end - next_start) as f64;
+        let next_x = (next_start..next_end).sum::<usize>() as f64 / next_len;
+        let next_y = values[next_start..next_end].iter().sum::<f64>() / next_len;
+
+        let (prev_x, prev_y) = (previous as f64, values[previous]);
+        previous = (start..end)
+            .max_by(|a, b| {
+                let area = |i: usize| {
+                    ((prev_x - next_x) * (values[i] - prev_y)
+                        - (prev_x - i as f64) * (next_y - prev_y))
+                        .abs()
+                };
+                area(*a).total_cmp(&area(*b))
+            })
+            .unwrap_or(start);
+        selected.push(previous);
+    }
+    selected.push(values.len() - 1);
+    selected
 }
 
 // Generates a top5_miningpools.csv file with the current top5 pools and their blocks
 // per day along with the total daily blocks.
 pub fn top5_miningpools_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "top5pools";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let pool_data = bitcoin_pool_identification::default_data(Network::Bitcoin);
+    let pool_data = db::pool_names(&mut conn)?;
 
     let top_pools = db::current_top_mining_pools(&mut conn)?;
     let mut pool_ids: [Vec<i32>; 5] = [vec![-1], vec![-1], vec![-1], vec![-1], vec![-1]];
@@ -118,15 +339,12 @@ pub fn top5_miningpools_csv(
             break;
         }
         pool_ids[i] = vec![top_pool.pool_id];
-        for pool in pool_data.iter().rev() {
-            if top_pool.pool_id == pool.id as i32 {
-                pool_names[i] = &pool.name;
-                break;
-            }
+        if let Some(name) = pool_data.get(&top_pool.pool_id) {
+            pool_names[i] = name;
         }
     }
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all(
         format!(
             "{},{},{},{},{},{},{}\n",
@@ -157,22 +375,22 @@ pub fn top5_miningpools_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a miningpools-antpool-and-friends.csv file with the current top5
 // pool groups and including "AntPool and Friends".
 pub fn antpool_and_friends_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-antpool-and-friends";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let pool_data = bitcoin_pool_identification::default_data(Network::Bitcoin);
+    let pool_data = db::pool_names(&mut conn)?;
 
     let top_pools = db::current_top_mining_pools(&mut conn)?;
     let mut pool_ids: [Vec<i32>; 5] = [
@@ -195,17 +413,14 @@ pub fn antpool_and_friends_csv(
             continue;
         } else {
             pool_ids[pools_added] = vec![top_pool.pool_id];
-            for pool in pool_data.iter().rev() {
-                if top_pool.pool_id == pool.id as i32 {
-                    pool_names[pools_added] = &pool.name;
-                    break;
-                }
+            if let Some(name) = pool_data.get(&top_pool.pool_id) {
+                pool_names[pools_added] = name;
             }
             pools_added += 1;
         }
     }
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all(
         format!(
             "{},{},{},{},{},{},{}\n",
@@ -236,21 +451,21 @@ pub fn antpool_and_friends_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a miningpools-centralization-index.csv file.
 pub fn mining_centralization_index_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-centralization-index";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all(
         "date,top1,top2,top3,top4,top5,top6,total\n"
             .to_string()
@@ -274,63 +489,63 @@ pub fn mining_centralization_index_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a pools-mining-ephemeral-dust.csv file.
 pub fn pools_mining_ephemeral_dust_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-mining-ephemeral-dust";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
-    file.write_all("pool,height,date,total\n".to_string().as_bytes())?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("pool,height,date,total,unspent\n".to_string().as_bytes())?;
 
-    let pool_data = bitcoin_pool_identification::default_data(Network::Bitcoin);
-    let pool_names: BTreeMap<u64, String> =
-        pool_data.iter().map(|p| (p.id, p.name.clone())).collect();
+    let pool_names = db::pool_names(&mut conn)?;
 
     let rows = db::get_pools_mining_ephemeral_dust(&mut conn)?;
     let content: String = rows
         .iter()
         .map(|row| {
+            // Pools that only mined unspent ephemeral dust have no first height and date.
             format!(
-                "{},{},{},{}\n",
+                "{},{},{},{},{}\n",
                 pool_names
-                    .get(&(row.pool_id as u64))
+                    .get(&(row.pool_id as i32))
                     .unwrap_or(&row.pool_id.to_string()),
-                row.first_ephemeral_dust_height,
-                row.first_ephemeral_dust_date,
+                row.first_ephemeral_dust_height
+                    .map(|h| h.to_string())
+                    .unwrap_or_default(),
+                row.first_ephemeral_dust_date.clone().unwrap_or_default(),
                 row.count,
+                row.unspent,
             )
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a pools-mining-bip54-coinbase.csv file.
 pub fn pools_mining_bip54_coinbase_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-mining-bip54-coinbase";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all("pool,height,date,total\n".to_string().as_bytes())?;
 
-    let pool_data = bitcoin_pool_identification::default_data(Network::Bitcoin);
-    let pool_names: BTreeMap<u64, String> =
-        pool_data.iter().map(|p| (p.id, p.name.clone())).collect();
+    let pool_names = db::pool_names(&mut conn)?;
 
     let rows = db::get_pools_mining_bip54_coinbase(&mut conn)?;
     let content: String = rows
@@ -339,7 +554,7 @@ pub fn pools_mining_bip54_coinbase_csv(
             format!(
                 "{},{},{},{}\n",
                 pool_names
-                    .get(&(row.pool_id as u64))
+                    .get(&(row.pool_id as i32))
                     .unwrap_or(&row.pool_id.to_string()),
                 row.first_bip54_coibnase_height,
                 row.first_bip54_coibnase_date,
@@ -348,30 +563,28 @@ pub fn pools_mining_bip54_coinbase_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a pools-mining-p2a.csv file.
 pub fn pools_mining_p2a_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-mining-p2a";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all(
         "pool,first spend,first creation,total inputs, total outputs\n"
             .to_string()
             .as_bytes(),
     )?;
 
-    let pool_data = bitcoin_pool_identification::default_data(Network::Bitcoin);
-    let pool_names: BTreeMap<u64, String> =
-        pool_data.iter().map(|p| (p.id, p.name.clone())).collect();
+    let pool_names = db::pool_names(&mut conn)?;
 
     let rows = db::get_pools_mining_p2a(&mut conn)?;
     let content: String = rows
@@ -398,7 +611,7 @@ pub fn pools_mining_p2a_csv(
             format!(
                 "{},{},{},{},{}\n",
                 pool_names
-                    .get(&(row.pool_id as u64))
+                    .get(&(row.pool_id as i32))
                     .unwrap_or(&row.pool_id.to_string()),
                 input_date_string,
                 output_date_string,
@@ -408,21 +621,21 @@ pub fn pools_mining_p2a_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates a miningpools-centralization-index-with-proxy-pools.csv file.
 pub fn mining_centralization_index_with_proxy_pools_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     const FILENAME: &str = "miningpools-centralization-index-with-proxy-pools";
 
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
     info!("Generating {} file...", FILENAME);
 
-    let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, FILENAME))?;
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
     file.write_all(
         "date,top1,top2,top3,top4,top5,top6,total\n"
             .to_string()
@@ -446,14 +659,14 @@ pub fn mining_centralization_index_with_proxy_pools_csv(
         })
         .collect();
     file.write_all(content.as_bytes())?;
-    Ok(())
+    Ok(vec![format!("{}.csv", FILENAME)])
 }
 
 // Generates miningpools-poolid-*.csv files with the number of blocks for this pool id per day.
 pub fn mining_pool_blocks_per_day_csv(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
-) -> Result<(), MainError> {
+) -> Result<Vec<String>, MainError> {
     let connection = Arc::clone(&connection);
     let mut conn = connection.lock().unwrap();
 
@@ -470,10 +683,11 @@ pub fn mining_pool_blocks_per_day_csv(
     pool_ids.insert(140); // MaraPool
     pool_ids.insert(145); // Ocean
 
+    let mut files = Vec::new();
     for id in pool_ids.iter() {
         let filename = format!("miningpools-poolid-{}", id);
         info!("Generating {} file...", filename);
-        let mut file = std::fs::File::create(format!("{}/{}.csv", csv_path, filename))?;
+        let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", filename)))?;
 
         file.write_all("date,count,total\n".to_string().as_bytes())?;
         let rows = db::get_blocks_per_day_per_pool(&mut conn, *id)?;
@@ -482,7 +696,1089 @@ pub fn mining_pool_blocks_per_day_csv(
             .map(|row| format!("{},{},{}\n", row.date, row.count, row.total))
             .collect();
         file.write_all(content.as_bytes())?;
+        files.push(format!("{}.csv", filename));
+    }
+
+    Ok(files)
+}
+
+// Generates a fee-revenue-and-output-volume.csv file with the daily fee revenue
+// and output volume in BTC and, for days with a known price, in USD.
+pub fn fee_revenue_and_output_volume_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "fee-revenue-and-output-volume";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,price_usd,fee_revenue_btc,fee_revenue_usd,output_volume_btc,output_volume_usd\n"
+            .as_bytes(),
+    )?;
+
+    let rows = db::fee_revenue_and_output_volume(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let fee_revenue_btc = row.fee_revenue as f64 / SATS_PER_BTC;
+            let output_volume_btc = row.output_volume as f64 / SATS_PER_BTC;
+            // Days without a known price have empty USD columns.
+            let (price, fee_revenue_usd, output_volume_usd) = match row.price_usd {
+                Some(price) => (
+                    format!("{:.2}", price),
+                    format!("{:.2}", fee_revenue_btc * price),
+                    format!("{:.2}", output_volume_btc * price),
+                ),
+                None => (String::new(), String::new(), String::new()),
+            };
+            format!(
+                "{},{},{:.8},{},{:.8},{}\n",
+                row.date,
+                price,
+                fee_revenue_btc,
+                fee_revenue_usd,
+                output_volume_btc,
+                output_volume_usd,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a block-reward-and-inflation.csv file with the daily issued subsidy,
+// the cumulative supply and the annualized inflation rate in percent. The issued
+// subsidy is the coinbase output amount minus the fees, so subsidy not claimed by
+// miners isn't counted. Days with a halving are annotated and the epoch column
+// contains the halving epoch of the last block of the day.
+pub fn block_reward_and_inflation_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "block-reward-and-inflation";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,epoch,halving,issued_btc,supply_btc,inflation_rate\n".as_bytes())?;
+
+    let rows = db::miner_revenue_by_date(&mut conn)?;
+    let mut supply: i64 = 0;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            // The inflation rate is relative to the supply at the start of the day.
+            let inflation_rate = if supply > 0 {
+                row.subsidy as f64 / supply as f64 * 365.0 * 100.0
+            } else {
+                0.0
+            };
+            supply += row.subsidy;
+            let next_halving = (row.min_height + HALVING_INTERVAL - 1) / HALVING_INTERVAL;
+            let halving = next_halving > 0 && next_halving * HALVING_INTERVAL <= row.max_height;
+            format!(
+                "{},{},{},{:.8},{:.8},{:.4}\n",
+                row.date,
+                row.max_height / HALVING_INTERVAL,
+                halving as u8,
+                row.subsidy as f64 / SATS_PER_BTC,
+                supply as f64 / SATS_PER_BTC,
+                inflation_rate,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a difficulty-adjustments.csv file with the heights the bits changed
+// at and the exact difficulty. The difficulty metric is truncated to an integer.
+pub fn difficulty_adjustments_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "difficulty-adjustments";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("height,date,bits,difficulty\n".as_bytes())?;
+
+    let rows = db::difficulty_adjustments(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{:08x},{}\n",
+                row.height, row.date, row.bits as u32, row.difficulty_exact
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a security-budget.csv file with the daily fees and subsidy, the
+// fee-to-subsidy ratio and the share of fees in the miner revenue in percent.
+pub fn security_budget_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "security-budget";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,fees_btc,subsidy_btc,fee_to_subsidy_ratio,fee_share\n".as_bytes())?;
+
+    let rows = db::miner_revenue_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let revenue = row.fees + row.subsidy;
+            let ratio = if row.subsidy != 0 {
+                format!("{:.6}", row.fees as f64 / row.subsidy as f64)
+            } else {
+                String::new()
+            };
+            let fee_share = if revenue != 0 {
+                row.fees as f64 / revenue as f64 * 100.0
+            } else {
+                0.0
+            };
+            format!(
+                "{},{:.8},{:.8},{},{:.4}\n",
+                row.date,
+                row.fees as f64 / SATS_PER_BTC,
+                row.subsidy as f64 / SATS_PER_BTC,
+                ratio,
+                fee_share,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates an input-output-volume.csv file with the daily amounts spent by
+// inputs and created by the outputs of the non-coinbase transactions, the
+// fees implied by their difference and the number of inputs per output as a
+// simple on-chain volume and consolidation pressure indicator.
+pub fn input_output_volume_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "input-output-volume";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,input_btc,output_btc,implied_fees_btc,inputs,outputs,inputs_per_output\n".as_bytes(),
+    )?;
+
+    let rows = db::input_output_volume_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let inputs_per_output = if row.outputs > 0 {
+                row.inputs as f64 / row.outputs as f64
+            } else {
+                0.0
+            };
+            format!(
+                "{},{:.8},{:.8},{:.8},{},{},{:.4}\n",
+                row.date,
+                row.input_amount as f64 / SATS_PER_BTC,
+                row.output_amount as f64 / SATS_PER_BTC,
+                (row.input_amount - row.output_amount) as f64 / SATS_PER_BTC,
+                row.inputs,
+                row.outputs,
+                inputs_per_output,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates an outlier-blocks.csv report with the top blocks per month by
+// various superlatives, e.g. for a "records" section.
+pub fn outlier_blocks_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "outlier-blocks";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("month,metric,rank,height,date,value\n".as_bytes())?;
+
+    for (metric, table, column, decimals) in OUTLIER_BLOCK_METRICS {
+        let rows = db::top_blocks_per_month(&mut conn, table, column, OUTLIER_BLOCKS_PER_MONTH)?;
+        let content: String = rows
+            .iter()
+            .map(|row| {
+                format!(
+                    "{},{},{},{},{},{:.*}\n",
+                    row.month, metric, row.rank, row.height, row.date, decimals, row.value,
+                )
+            })
+            .collect();
+        file.write_all(content.as_bytes())?;
+    }
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a miningpools-coinbase-weight.csv file with the average coinbase
+// weight and share of the block weight per pool, the blocks with a large
+// coinbase and the blocks where a large coinbase displaced fee-paying
+// transactions.
+pub fn pools_coinbase_weight_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "miningpools-coinbase-weight";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "pool,blocks,avg_coinbase_weight,avg_coinbase_weight_share,blocks_large_coinbase,blocks_large_coinbase_displacing\n"
+            .as_bytes(),
+    )?;
+
+    let pool_names = db::pool_names(&mut conn)?;
+
+    let rows = db::pools_coinbase_weight(&mut conn, LARGE_COINBASE_WEIGHT as i64)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{:.2},{:.6},{},{}\n",
+                pool_names
+                    .get(&(row.pool_id as i32))
+                    .unwrap_or(&row.pool_id.to_string()),
+                row.blocks,
+                row.avg_coinbase_weight,
+                row.avg_coinbase_weight_share,
+                row.blocks_large_coinbase,
+                row.blocks_large_coinbase_displacing,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a nonce-distribution.csv file with the monthly number of blocks
+// per nonce range, the nonce space split into 16 ranges by the upper 4 bits,
+// and the average coinbase extra size, i.e. the space for the extranonce. The
+// ranges show the nonce rolling patterns of the ASICs.
+pub fn nonce_distribution_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "nonce-distribution";
+    const BUCKETS: usize = 16;
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    let buckets: Vec<String> = (0..BUCKETS).map(|b| format!("nonce_{:x}", b)).collect();
+    file.write_all(
+        format!(
+            "month,blocks,{},avg_coinbase_extra_size\n",
+            buckets.join(",")
+        )
+        .as_bytes(),
+    )?;
+
+    // month -> blocks per bucket and the summed coinbase extra size
+    let mut months: BTreeMap<String, ([i64; BUCKETS], i64)> = BTreeMap::new();
+    for row in db::nonce_buckets_by_month(&mut conn)? {
+        let month = months.entry(row.month).or_default();
+        month.0[row.bucket as usize] += row.blocks;
+        month.1 += row.coinbase_extra_size;
     }
+    let content: String = months
+        .iter()
+        .map(|(month, (blocks_per_bucket, extra_size))| {
+            let blocks: i64 = blocks_per_bucket.iter().sum();
+            format!(
+                "{},{},{},{:.2}\n",
+                month,
+                blocks,
+                blocks_per_bucket
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                *extra_size as f64 / blocks as f64,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a miningpools-version-rolling.csv file with the blocks per pool,
+// the share of them with BIP320 version-rolling bits set (overt AsicBoost) in
+// percent and the first block with version-rolling bits of the pool.
+pub fn pools_version_rolling_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "miningpools-version-rolling";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "pool,blocks,blocks_version_rolling,version_rolling_percentage,first_height,first_date\n"
+            .as_bytes(),
+    )?;
+
+    let pool_names = db::pool_names(&mut conn)?;
+
+    let rows = db::pools_version_rolling(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{:.2},{},{}\n",
+                pool_names
+                    .get(&(row.pool_id as i32))
+                    .unwrap_or(&row.pool_id.to_string()),
+                row.blocks,
+                row.blocks_version_rolling,
+                row.blocks_version_rolling as f64 * 100.0 / row.blocks as f64,
+                row.first_version_rolling_height
+                    .map(|h| h.to_string())
+                    .unwrap_or_default(),
+                row.first_version_rolling_date
+                    .as_deref()
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates an ecdsa-grinding.csv file estimating the bytes saved per day
+// by wallets producing low-R and low-S ECDSA signatures compared to naive
+// signing. With naive signing, R and S each have the high bit set half of
+// the time, which costs an extra byte. Without grinding, as many low-R as
+// high-R signatures are expected: the excess low-R signatures are attributed
+// to grinding, saving half a byte each on average. The same holds for the
+// excess low-S signatures. The bytes are witness or scriptSig bytes, the
+// vsize saved depends on the input type.
+pub fn ecdsa_grinding_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "ecdsa-grinding";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,sigs_ecdsa,sigs_71byte_or_less_share,sigs_low_r_ground,sigs_low_s_normalized,bytes_saved\n"
+            .as_bytes(),
+    )?;
 
-    Ok(())
+    let rows = db::ecdsa_signature_sizes_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let low_r_ground = (row.low_r - row.high_r).max(0);
+            let low_s_normalized = (row.low_s - row.high_s).max(0);
+            let share_71byte_or_less = if row.sigs_ecdsa > 0 {
+                row.sigs_71byte_or_less as f64 / row.sigs_ecdsa as f64 * 100.0
+            } else {
+                0.0
+            };
+            format!(
+                "{},{},{:.4},{},{},{:.1}\n",
+                row.date,
+                row.sigs_ecdsa,
+                share_71byte_or_less,
+                low_r_ground,
+                low_s_normalized,
+                (low_r_ground + low_s_normalized) as f64 / 2.0,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a truc-adoption.csv file with the daily number of v3 (TRUC)
+// transactions, their share of all transactions, the v3 transactions with an
+// in-block parent and the share of v3 transactions creating a P2A anchor, both
+// in percent.
+pub fn truc_adoption_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "truc-adoption";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,tx_v3,tx_v3_share,tx_v3_with_parent_in_block,tx_v3_creating_p2a_share\n".as_bytes(),
+    )?;
+
+    let rows = db::truc_adoption_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let share = |part: i64, total: i64| {
+                if total > 0 {
+                    part as f64 / total as f64 * 100.0
+                } else {
+                    0.0
+                }
+            };
+            format!(
+                "{},{},{:.4},{},{:.4}\n",
+                row.date,
+                row.tx_v3,
+                share(row.tx_v3, row.transactions),
+                row.tx_v3_with_parent_in_block,
+                share(row.tx_v3_creating_p2a, row.tx_v3),
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a coinbase-payout-types.csv file with the daily share of the
+// coinbase payout outputs by script type in percent. This shows the miners
+// modernizing their own addresses. OP_RETURN outputs aren't payouts and are
+// left out.
+pub fn coinbase_payout_types_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "coinbase-payout-types";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,payouts,p2pk,p2pkh,p2sh,p2wpkh,p2wsh,p2tr,other\n".as_bytes())?;
+
+    let rows = db::coinbase_payout_types_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let counts = [
+                row.p2pk, row.p2pkh, row.p2sh, row.p2wpkh, row.p2wsh, row.p2tr, row.other,
+            ];
+            let payouts: i64 = counts.iter().sum();
+            let shares: Vec<String> = counts
+                .iter()
+                .map(|count| {
+                    let share = if payouts > 0 {
+                        *count as f64 / payouts as f64 * 100.0
+                    } else {
+                        0.0
+                    };
+                    format!("{:.4}", share)
+                })
+                .collect();
+            format!("{},{},{}\n", row.date, payouts, shares.join(","))
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a p2a-spenders.csv file with the daily number of transactions
+// spending P2A outputs by the kind of spender: Lightning anchor CPFPs, TRUC
+// children and others.
+pub fn p2a_spenders_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "p2a-spenders";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,ln_anchor,truc_child,other\n".as_bytes())?;
+
+    let rows = db::p2a_spenders_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{}\n",
+                row.date, row.ln_anchor, row.truc_child, row.other
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a tx-vsize-classes.csv file with the daily number of non-coinbase
+// transactions by vsize class.
+pub fn tx_vsize_classes_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "tx-vsize-classes";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,0_140,141_250,251_500,501_1000,1001_10000,10001_100000,100001_plus\n".as_bytes(),
+    )?;
+
+    let rows = db::tx_vsize_classes_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{},{},{},{},{}\n",
+                row.date,
+                row.vsize_0_140,
+                row.vsize_141_250,
+                row.vsize_251_500,
+                row.vsize_501_1000,
+                row.vsize_1001_10000,
+                row.vsize_10001_100000,
+                row.vsize_100001_plus,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a traffic-composition.csv file with the daily number of payment-like
+// and data embedding non-coinbase transactions, and the data embedding
+// transactions revealing an inscription or with an OP_RETURN output. A
+// transaction can be both.
+pub fn traffic_composition_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "traffic-composition";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,payment_like,data_embedding,inscription,opreturn\n".as_bytes())?;
+
+    let rows = db::traffic_composition_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{},{}\n",
+                row.date, row.payment_like, row.data_embedding, row.inscription, row.opreturn,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a utxo-growth-by-protocol.csv file with the daily net change of the
+// UTXO set size attributed to the data embedding protocol of the transactions
+// and to ordinary transactions. Negative values mean the UTXO set shrank.
+pub fn utxo_growth_by_protocol_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "utxo-growth-by-protocol";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,inscription,runestone,counterparty,other_data,ordinary\n".as_bytes())?;
+
+    let rows = db::utxo_growth_by_protocol_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{},{},{}\n",
+                row.date,
+                row.inscription,
+                row.runestone,
+                row.counterparty,
+                row.other_data,
+                row.ordinary,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a malleability-hygiene.csv file with the daily share of ECDSA
+// signatures with a low S value and with strict DER encoding, and the share of
+// multisig inputs with a NULLDUMMY compliant dummy element. The shares are in
+// percent and 0 on days without such signatures or inputs. Shows the effect of
+// BIP66 and BIP146/BIP147.
+pub fn malleability_hygiene_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "malleability-hygiene";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,sigs_ecdsa,low_s_share,strict_der_share,multisig_inputs,nulldummy_share\n".as_bytes(),
+    )?;
+
+    let share = |part: i64, total: i64| match total {
+        0 => 0.0,
+        _ => part as f64 / total as f64 * 100.0,
+    };
+    let rows = db::malleability_hygiene_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let multisig_inputs = row.multisig_dummy_null + row.multisig_dummy_not_null;
+            format!(
+                "{},{},{:.4},{:.4},{},{:.4}\n",
+                row.date,
+                row.sigs_ecdsa,
+                share(row.sigs_ecdsa_low_s, row.sigs_ecdsa),
+                share(row.sigs_ecdsa_strict_der, row.sigs_ecdsa),
+                multisig_inputs,
+                share(row.multisig_dummy_null, multisig_inputs),
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a multisig-vs-singlesig.csv file with the daily count and value (in
+// sat) of multisig and single-sig inputs per era: legacy (P2MS and P2SH
+// multisig vs. P2PK and P2PKH), SegWit (nested and native P2WSH multisig vs.
+// nested and native P2WPKH) and Taproot. Taproot key-path spends are listed
+// separately as aggregated multisigs (e.g. MuSig2) look like single-sig spends.
+// Value-weighted numbers are only available for blocks processed with
+// input_stats version 4 or later.
+pub fn multisig_vs_singlesig_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "multisig-vs-singlesig";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,legacy_multisig,legacy_multisig_amount,legacy_singlesig,legacy_singlesig_amount,segwit_multisig,segwit_multisig_amount,segwit_singlesig,segwit_singlesig_amount,taproot_keypath,taproot_keypath_amount\n"
+            .as_bytes(),
+    )?;
+
+    let rows = db::multisig_vs_singlesig_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{},{},{},{},{},{},{},{}\n",
+                row.date,
+                row.legacy_multisig,
+                row.legacy_multisig_amount,
+                row.legacy_singlesig,
+                row.legacy_singlesig_amount,
+                row.segwit_multisig,
+                row.segwit_multisig_amount,
+                row.segwit_singlesig,
+                row.segwit_singlesig_amount,
+                row.taproot_keypath,
+                row.taproot_keypath_amount,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a sig-aggregation-savings.csv file estimating the daily savings of
+// hypothetical cross-input signature aggregation over the transactions with
+// two or more P2TR key-path inputs. With full aggregation, all but one 64 byte
+// signature per transaction could be dropped, saving 16 vbytes each.
+pub fn sig_aggregation_savings_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "sig-aggregation-savings";
+    const VBYTES_PER_SIGNATURE: i64 = 64 / 4;
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "date,transactions,keypath_inputs,aggregatable_sigs,potential_savings_vbytes\n".as_bytes(),
+    )?;
+
+    let rows = db::sig_aggregation_by_date(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{},{}\n",
+                row.date,
+                row.transactions,
+                row.keypath_inputs,
+                row.aggregatable_sigs,
+                row.aggregatable_sigs * VBYTES_PER_SIGNATURE,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a burned-supply.csv file with the daily value sent to provably
+// unspendable outputs and burn addresses, and the cumulative burned supply.
+pub fn burned_supply_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "burned-supply";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("date,burned_btc,cumulative_burned_btc\n".as_bytes())?;
+
+    let rows = db::burned_by_date(&mut conn)?;
+    let mut cumulative: i64 = 0;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            cumulative += row.amount;
+            format!(
+                "{},{:.8},{:.8}\n",
+                row.date,
+                row.amount as f64 / SATS_PER_BTC,
+                cumulative as f64 / SATS_PER_BTC,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a miningpools.csv file with the metadata of the pools in the pools
+// table, to look up the pool names and links by the pool ids in other files.
+pub fn pools_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "miningpools";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("id,name,link,tags\n".as_bytes())?;
+
+    let rows = db::get_pools(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{}\n",
+                row.id,
+                csv_escape(&row.name),
+                csv_escape(&row.link),
+                csv_escape(&row.tags)
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Quotes a CSV field containing a comma, a quote or a line break.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Generates a fee-cliff.csv file with how steep the feerate gradient in each
+// block is: the differences between the 50th and 5th package feerate
+// percentiles and between the median and the minimum package feerate.
+pub fn fee_cliff_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "fee-cliff";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all("height,date,p50_minus_p5,median_minus_min\n".as_bytes())?;
+
+    let rows = db::fee_cliff_by_block(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{:.3},{:.3}\n",
+                row.height, row.date, row.p50_p5, row.median_min
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a miningpools-coinbase-spends.csv file with the first hop of the
+// miner payouts: how long the pools wait before spending their coinbase
+// outputs and which script types they pay to. Only the coinbase spends
+// tracked with --track-coinbase-spends are included.
+pub fn pools_coinbase_spends_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "miningpools-coinbase-spends";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "pool,destination_type,spends,avg_delay_blocks,min_delay_blocks,max_delay_blocks,amount_btc,avg_outputs\n"
+            .as_bytes(),
+    )?;
+
+    let pool_names = db::pool_names(&mut conn)?;
+
+    let rows = db::get_pools_coinbase_spends(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{},{},{},{:.1},{},{},{:.8},{:.1}\n",
+                pool_names
+                    .get(&row.pool_id)
+                    .unwrap_or(&row.pool_id.to_string()),
+                row.destination_type,
+                row.spends,
+                row.avg_delay,
+                row.min_delay,
+                row.max_delay,
+                row.amount as f64 / SATS_PER_BTC,
+                row.avg_outputs,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+// Generates a miningpools-feerate-floor.csv file comparing the inclusion
+// policies of the pools over the recent blocks. Pools including many
+// transactions below 1 sat/vB or with a zero fee likely accept out-of-band
+// payments or run a lower minimum relay feerate than the default.
+pub fn pools_feerate_floor_csv(
+    csv_path: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Vec<String>, MainError> {
+    const FILENAME: &str = "miningpools-feerate-floor";
+
+    let connection = Arc::clone(&connection);
+    let mut conn = connection.lock().unwrap();
+    info!("Generating {} file...", FILENAME);
+
+    let mut file = std::fs::File::create(csv_path.join(format!("{}.csv", FILENAME)))?;
+    file.write_all(
+        "pool,blocks,avg_min_nonzero_feerate,lowest_min_nonzero_feerate,transactions,zero_fee_tx,below_1_sat_vbyte_tx,below_1_sat_vbyte_share,blocks_with_below_1_sat_vbyte\n"
+            .as_bytes(),
+    )?;
+
+    let pool_names = db::pool_names(&mut conn)?;
+
+    let rows = db::get_pools_feerate_floor(&mut conn)?;
+    let content: String = rows
+        .iter()
+        .map(|row| {
+            let below_1_sat_vbyte_share = match row.transactions {
+                0 => 0.0,
+                _ => row.below_1_sat_vbyte as f64 / row.transactions as f64 * 100.0,
+            };
+            format!(
+                "{},{},{:.3},{:.3},{},{},{},{:.4},{}\n",
+                pool_names
+                    .get(&row.pool_id)
+                    .unwrap_or(&row.pool_id.to_string()),
+                row.blocks,
+                row.avg_feerate_min_nonzero,
+                row.min_feerate_min_nonzero,
+                row.transactions,
+                row.zero_fee_tx,
+                row.below_1_sat_vbyte,
+                below_1_sat_vbyte_share,
+                row.blocks_below_1_sat_vbyte,
+            )
+        })
+        .collect();
+    file.write_all(content.as_bytes())?;
+    Ok(vec![format!("{}.csv", FILENAME)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lttb;
+
+    // The header row of each CSV file, as `<file>: <header>`.
+    const CSV_HEADERS_GOLDEN_FILE: &str = "./testdata/csv-headers.txt";
+
+    #[test]
+    fn test_csv_headers() {
+        use crate::db::test_utils::{stats_db, TEST_HEIGHTS};
+        use crate::write_csv_files;
+        use std::sync::{Arc, Mutex};
+
+        let (dir, conn) = stats_db("csv-headers-test", &TEST_HEIGHTS);
+        let csv_path = dir.path().join("csv");
+        write_csv_files(&csv_path, Arc::new(Mutex::new(conn)), None, None).unwrap();
+
+        let manifest = crate::manifest::read_manifest(&csv_path).unwrap();
+        let headers: String = manifest
+            .files
+            .iter()
+            .filter(|file| file.name.ends_with(".csv"))
+            .map(|file| {
+                let content = std::fs::read_to_string(csv_path.join(&file.name)).unwrap();
+                format!("{}: {}\n", file.name, content.lines().next().unwrap_or(""))
+            })
+            .collect();
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(CSV_HEADERS_GOLDEN_FILE, &headers).unwrap();
+        }
+        let golden = std::fs::read_to_string(CSV_HEADERS_GOLDEN_FILE).unwrap();
+        for (line, golden_line) in headers.lines().zip(golden.lines()) {
+            assert_eq!(
+                line, golden_line,
+                "a CSV header changed, which breaks the frontend. If intended, update {} with `UPDATE_GOLDEN=1 cargo test test_csv_headers`",
+                CSV_HEADERS_GOLDEN_FILE
+            );
+        }
+        assert_eq!(
+            headers.lines().count(),
+            golden.lines().count(),
+            "CSV files were added or removed. If intended, update {} with `UPDATE_GOLDEN=1 cargo test test_csv_headers`",
+            CSV_HEADERS_GOLDEN_FILE
+        );
+    }
+
+    #[test]
+    fn test_split_into_partitions() {
+        use super::{split_into_partitions, CsvPartition};
+        use crate::db::DateHeights;
+
+        let date = |date: &str, min_height: i64, max_height: i64| DateHeights {
+            date: date.to_string(),
+            min_height,
+            max_height,
+        };
+        let dates = vec![
+            date("2015-12-31", 99_900, 99_999),
+            date("2016-01-01", 100_000, 100_100),
+            date("2016-01-02", 100_101, 100_200),
+        ];
+
+        let years = split_into_partitions(&dates, CsvPartition::Year);
+        assert_eq!(years.len(), 2);
+        assert_eq!(years[0].name, "2015");
+        assert_eq!(years[1].name, "2016");
+        assert_eq!(years[1].first_date, "2016-01-01");
+        assert_eq!(years[1].last_date, "2016-01-02");
+        assert_eq!(years[1].max_height, 100_200);
+
+        let blocks = split_into_partitions(&dates, CsvPartition::Blocks100k);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].name, "0-99999");
+        assert_eq!(blocks[1].name, "100000-199999");
+        assert_eq!(blocks[1].min_height, 100_000);
+    }
+
+    #[test]
+    fn test_lttb() {
+        let values: Vec<f64> = (0..100).map(|i| (i % 10) as f64).collect();
+        assert_eq!(lttb(&values, 200), (0..100).collect::<Vec<usize>>());
+        assert_eq!(lttb(&values, 100).len(), 100);
+
+        let selected = lttb(&values, 10);
+        assert_eq!(selected.len(), 10);
+        assert_eq!(selected.first(), Some(&0));
+        assert_eq!(selected.last(), Some(&99));
+        assert!(selected.windows(2).all(|w| w[0] < w[1]));
+
+        // a single spike is kept
+        let mut flat = vec![1.0; 1000];
+        flat[500] = 100.0;
+        assert!(lttb(&flat, 20).contains(&500));
+    }
 }
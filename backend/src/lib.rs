@@ -1,19 +1,75 @@
+//! Collects protocol-level statistics about Bitcoin blocks from a Bitcoin Core
+//! REST endpoint, stores them in a SQLite database and exports them as CSV.
+//!
+//! Besides the full pipeline in [collect_statistics], the per-block stats can
+//! be computed on their own. A [Block] is deserialized from the JSON returned
+//! by Bitcoin Core's `/rest/block/<hash>.json` endpoint, or fetched with a
+//! [RestClient]:
+//!
+//! ```no_run
+//! use mainnet_observer_backend::{RestClient, Stats};
+//!
+//! let client = RestClient::new("localhost", 8332, None);
+//! let block = client.block_at_height(840_000).unwrap();
+//! let stats = Stats::from_block(block).unwrap();
+//! println!("{} inputs", stats.block.inputs);
+//! ```
+
+mod alerts;
+mod annotations;
+mod audit;
 pub mod db;
+mod docs;
 mod gen_csv;
-mod rest;
+mod manifest;
+mod memory;
+mod merge;
+mod pools;
+mod prices;
+mod prune;
+mod publish;
+pub mod rest;
+mod rules;
 mod schema;
-mod stats;
+pub mod stats;
+mod status;
+mod telemetry;
+mod upload;
+mod validation;
+mod verify;
 
-use clap::Parser;
+pub use alerts::{check_sync_lag, send_dormant_alerts, DormantThreshold};
+pub use audit::block_audit;
+use bitcoin::hex::DisplayHex;
+use bitcoin::{Network, ScriptBuf};
+use chrono::{FixedOffset, SecondsFormat, Utc};
+use clap::{Parser, Subcommand};
 use diesel::SqliteConnection;
+pub use docs::{data_dictionary, DocsFormat};
+pub use gen_csv::CsvPartition;
 use log::{debug, error, info, warn};
+use memory::{estimated_block_memory, MemoryBudget};
+pub use merge::merge;
+use opentelemetry::KeyValue;
+pub use pools::load_pool_data;
+pub use prices::import_prices;
+pub use prune::prune;
+pub use publish::publish;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
-use stats::Stats;
-use std::collections::BTreeSet;
+pub use rest::{Block, BlockSource, NodeImplementation, RestClient, RestError};
+pub use stats::{set_aggregation_timezone, PartialStats, Stats, StatsError, StatsTable};
+pub use status::status;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
-use std::{error, fmt, io, thread};
+use std::time::{Duration, Instant};
+use std::{env, error, fmt, io, thread};
+pub use telemetry::init_tracing;
+pub use upload::upload_export;
+pub use verify::verify;
 
 const DATABASE_BATCH_SIZE: usize = 100;
 
@@ -29,7 +85,23 @@ pub enum MainError {
     REST(rest::RestError),
     Stats(stats::StatsError),
     IBDNotDone,
+    WrongNetwork(Network, String),
+    /// the challenge stored in the database and the challenge of the node
+    WrongSignetChallenge(String, String),
+    /// the first needed height and the prune height of the node
+    BlocksPruned(u64, u64),
+    DatabaseLocked(String),
+    SchemaOutdated(String),
+    /// the database migrated by a different version of the backend
+    SchemaMismatch(String),
+    /// the UTC offsets in seconds of the pruned daily aggregates and of
+    /// --aggregation-timezone
+    PrunedTimezoneMismatch(i32, i32),
     IOError(io::Error),
+    JSON(serde_json::Error),
+    Upload(upload::UploadError),
+    Prices(prices::PricesError),
+    Parquet(parquet::errors::ParquetError),
 }
 
 impl fmt::Display for MainError {
@@ -39,9 +111,49 @@ impl fmt::Display for MainError {
             MainError::DBConnection(e) => write!(f, "Database Connection Error: {}", e),
             MainError::DBMigration(e) => write!(f, "Database Migration Error: {}", e),
             MainError::IBDNotDone => write!(f, "Node is still in IBD"),
+            MainError::WrongNetwork(network, chain) => write!(
+                f,
+                "Expected a node on {} but the node is on chain '{}'",
+                network, chain
+            ),
+            MainError::WrongSignetChallenge(stored, node) => write!(
+                f,
+                "The database has stats for the signet with challenge {} but the node is on the signet with challenge {}",
+                stored, node
+            ),
+            MainError::BlocksPruned(height, prune_height) => write!(
+                f,
+                "The block at height {} is needed, but the node is pruned below height {}",
+                height, prune_height
+            ),
             MainError::REST(e) => write!(f, "REST error: {}", e),
             MainError::Stats(e) => write!(f, "Stats generation error: {}", e),
+            MainError::DatabaseLocked(path) => write!(
+                f,
+                "The database is in use by another instance ({} is locked). Use --wait-for-lock to wait for it.",
+                path
+            ),
+            MainError::SchemaOutdated(path) => write!(
+                f,
+                "The database {} doesn't exist or has an outdated schema. Run `sync` first.",
+                path
+            ),
+            MainError::SchemaMismatch(path) => write!(
+                f,
+                "The database {} was migrated by a different version of the backend",
+                path
+            ),
+            MainError::PrunedTimezoneMismatch(pruned, offset) => write!(
+                f,
+                "The pruned blocks are aggregated in UTC{} and can't be recalculated in UTC{}. Keep the --aggregation-timezone of the pruning.",
+                FixedOffset::east_opt(*pruned).map(|o| o.to_string()).unwrap_or_default(),
+                FixedOffset::east_opt(*offset).map(|o| o.to_string()).unwrap_or_default(),
+            ),
             MainError::IOError(e) => write!(f, "IO error: {}", e),
+            MainError::JSON(e) => write!(f, "JSON error: {}", e),
+            MainError::Upload(e) => write!(f, "Upload error: {}", e),
+            MainError::Prices(e) => write!(f, "Prices error: {}", e),
+            MainError::Parquet(e) => write!(f, "Parquet error: {}", e),
         }
     }
 }
@@ -55,7 +167,18 @@ impl error::Error for MainError {
             MainError::REST(ref e) => Some(e),
             MainError::Stats(ref e) => Some(e),
             MainError::IBDNotDone => None,
+            MainError::WrongNetwork(_, _) => None,
+            MainError::WrongSignetChallenge(_, _) => None,
+            MainError::BlocksPruned(_, _) => None,
+            MainError::DatabaseLocked(_) => None,
+            MainError::SchemaOutdated(_) => None,
+            MainError::SchemaMismatch(_) => None,
+            MainError::PrunedTimezoneMismatch(_, _) => None,
             MainError::IOError(ref e) => Some(e),
+            MainError::JSON(ref e) => Some(e),
+            MainError::Upload(ref e) => Some(e),
+            MainError::Prices(ref e) => Some(e),
+            MainError::Parquet(ref e) => Some(e),
         }
     }
 }
@@ -96,6 +219,36 @@ impl From<io::Error> for MainError {
     }
 }
 
+impl From<serde_json::Error> for MainError {
+    fn from(e: serde_json::Error) -> Self {
+        MainError::JSON(e)
+    }
+}
+
+impl From<upload::UploadError> for MainError {
+    fn from(e: upload::UploadError) -> Self {
+        MainError::Upload(e)
+    }
+}
+
+impl From<parquet::errors::ParquetError> for MainError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        MainError::Parquet(e)
+    }
+}
+
+impl From<arrow_schema::ArrowError> for MainError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        MainError::Parquet(e.into())
+    }
+}
+
+impl From<prices::PricesError> for MainError {
+    fn from(e: prices::PricesError) -> Self {
+        MainError::Prices(e)
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
@@ -107,18 +260,37 @@ pub struct Args {
     #[arg(long, default_value_t = 8332)]
     pub rest_port: u16,
 
+    /// The node implementation serving the REST API. Recorded with each sync
+    /// run, as Bitcoin Knots has different default policies than Bitcoin Core.
+    #[arg(long, value_enum, default_value_t = NodeImplementation::BitcoinCore)]
+    pub node_implementation: NodeImplementation,
+
     /// Path to the SQLite database file where the stats are stored
-    #[arg(long, default_value = "./db.sqlite")]
-    pub database_path: String,
+    #[arg(long, default_value = "./db.sqlite", value_parser = parse_path)]
+    pub database_path: PathBuf,
 
     /// Path where the CSV files should be written to
-    #[arg(long, default_value = "./csv")]
-    pub csv_path: String,
+    #[arg(long, default_value = "./csv", value_parser = parse_path)]
+    pub csv_path: PathBuf,
 
     /// Flag to disable CSV file writing
     #[arg(long, default_value_t = false)]
     pub no_csv: bool,
 
+    /// Also write each metric downsampled to at most this many points with
+    /// Largest-Triangle-Three-Buckets (LTTB) to the `downsampled` subdirectory
+    /// of the CSV directory, for fast chart rendering. The full resolution
+    /// CSV files are written as well.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(3..))]
+    pub downsample_points: Option<u64>,
+
+    /// Also write each metric split by year or by 100k blocks to the
+    /// `partitions` subdirectory of the CSV directory (e.g.
+    /// `partitions/fee_avg_2015.csv`), so that downloads and frontends can
+    /// load smaller files. The partitions are listed in the manifest.
+    #[arg(long, value_enum)]
+    pub csv_partition: Option<CsvPartition>,
+
     /// Flag to disable stat generation
     #[arg(long, default_value_t = false)]
     pub no_stats: bool,
@@ -128,28 +300,525 @@ pub struct Args {
     /// By default, we use 14 of these and leave 2 threads to service other requests.
     #[arg(long, default_value_t = 14)]
     pub num_threads: usize,
+
+    /// Limit the number of requests per second made to the Bitcoin Core REST
+    /// API, e.g. when running against a node shared with other RPC consumers.
+    /// By default, requests aren't limited.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_requests_per_second: Option<u32>,
+
+    /// Upload the CSV files and the manifest after writing them. Either an
+    /// S3 bucket as `s3://bucket/prefix` (credentials are read from the AWS_*
+    /// environment variables) or a HTTP(S) URL the files are PUT to.
+    #[arg(long)]
+    pub upload_url: Option<String>,
+
+    /// Import daily BTC prices in USD before collecting the stats. Either a
+    /// path to a CSV file or a HTTP(S) URL returning a CSV file with a header
+    /// and `YYYY-MM-DD,price` rows. Used for the USD denominated exports.
+    /// Imported by `sync` and runs without a subcommand, which hold the
+    /// database lock, not by `export csv`.
+    #[arg(long)]
+    pub prices: Option<String>,
+
+    /// Limit the estimated memory used by fetched blocks waiting for and
+    /// being processed. Block fetching is throttled once the limit is reached.
+    /// By default, the memory use is only bounded by the channel sizes.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_memory_mb: Option<u64>,
+
+    /// Deserialize the transactions of a block once in the block fetching
+    /// threads and pass the parsed transactions on instead of the raw bytes.
+    /// Reduces the memory used by blocks waiting to be processed.
+    #[arg(long, default_value_t = false)]
+    pub parse_in_fetcher: bool,
+
+    /// The network the Bitcoin Core node is on. The stats of each network
+    /// are kept in their own database file and CSV directory: for networks
+    /// other than mainnet, the network name is added to the database file
+    /// name and the CSV files are written to a subdirectory.
+    #[arg(long, default_value_t = Network::Bitcoin)]
+    pub network: Network,
+
+    /// Wait for another instance using the same database to finish instead of
+    /// exiting with an error.
+    #[arg(long, default_value_t = false)]
+    pub wait_for_lock: bool,
+
+    /// Report the blocks that would be fetched, the outdated stats tables, an
+    /// estimated duration and the CSV files that would be rewritten, then exit
+    /// without doing any of it.
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Webhook URL a JSON alert is POSTed to for each newly processed block
+    /// that spends long dormant outputs. No alerts are sent for the initial
+    /// sync of an empty database.
+    #[arg(long)]
+    pub dormant_alert_url: Option<String>,
+
+    /// Minimum age of the spent outputs to send a dormant spend alert for
+    #[arg(long, value_enum, default_value_t = DormantThreshold::TenYears)]
+    pub dormant_alert_threshold: DormantThreshold,
+
+    /// Record the spends of coinbase outputs in the coinbase_spends table to
+    /// follow the first hop of the miner payouts. Only the blocks processed
+    /// while this is enabled are tracked.
+    #[arg(long, default_value_t = false)]
+    pub track_coinbase_spends: bool,
+
+    /// Debug mode: record the txids of the transactions contributing to the
+    /// given tx_stats counter (e.g. tx_spending_ephemeral_dust) in the
+    /// stat_contributors table. Only the blocks processed in this run are
+    /// captured.
+    #[arg(long, value_parser = parse_capturable_stat)]
+    pub capture_contributors: Option<String>,
+
+    /// Count the opcodes of the redeem scripts, witness scripts and tapscripts
+    /// revealed in each block in the opcode_stats table. Only the blocks
+    /// processed while this is enabled are tracked.
+    #[arg(long, default_value_t = false)]
+    pub track_opcodes: bool,
+
+    /// Count the script types spent and created together in the
+    /// script_type_transitions table, e.g. P2PKH inputs moving to P2TR
+    /// outputs. Only the blocks processed while this is enabled are tracked.
+    #[arg(long, default_value_t = false)]
+    pub track_script_transitions: bool,
+
+    /// Record the first output of each non-standard script template (the
+    /// script with its data pushes replaced by their length) in the
+    /// novel_scripts table to spot new protocols early. Only the blocks
+    /// processed while this is enabled are tracked.
+    #[arg(long, default_value_t = false)]
+    pub track_novel_scripts: bool,
+
+    /// Comma separated feerate percentiles (0 to 100) to record per block in
+    /// the feerate_percentiles table, e.g. `1,99`. Percentiles can be added
+    /// without a schema change, unlike the fixed percentile columns of
+    /// feerate_stats. Only the blocks processed while this is set are recorded.
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub feerate_percentiles: Vec<u8>,
+
+    /// Comma separated stats tables to skip in this run, e.g. `script,feerate`
+    /// for a first fast pass. The tables are named like in the database, the
+    /// `_stats` suffix is optional. Skipped tables stay outdated and are
+    /// calculated by a later run without them. block_stats can't be skipped.
+    #[arg(long, value_delimiter = ',', value_parser = parse_skippable_table)]
+    pub skip: Vec<StatsTable>,
+
+    /// On a pruned node, skip the blocks below the prune height instead of
+    /// exiting with an error. The skipped heights are recorded in the
+    /// sync_runs table.
+    #[arg(long, default_value_t = false)]
+    pub skip_pruned: bool,
+
+    /// The timezone the stats are bucketed into days in, as a fixed UTC
+    /// offset like `+02:00` or `-05:00`. By default, the days are in UTC. When
+    /// the timezone changes, the dates of the existing stats are recalculated
+    /// after the next successful stats collection.
+    #[arg(long, default_value = "UTC", value_parser = parse_utc_offset)]
+    pub aggregation_timezone: FixedOffset,
+
+    /// Fetch the mining pool list from the upstream bitcoin-data/mining-pools
+    /// repository and refetch it once the cached copy (stored next to the
+    /// database) is older than this many hours. Newly added pools are then
+    /// identified without recompiling. By default, or if the list can't be
+    /// fetched and there's no cached copy, the list vendored at build time is
+    /// used. Pools are only identified on mainnet.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub pool_data_refresh_hours: Option<u64>,
+
+    /// Warn when the database lags more than this many blocks behind the
+    /// node's tip after the stats collection, e.g. because the runs keep
+    /// failing. The most recent blocks are always skipped as a reorg safety
+    /// margin, so this should be larger than that margin.
+    #[arg(long)]
+    pub max_lag: Option<u64>,
+
+    /// Webhook URL a JSON alert is POSTed to when the lag exceeds --max-lag
+    #[arg(long, requires = "max_lag")]
+    pub lag_alert_url: Option<String>,
+
+    /// Export OpenTelemetry spans of the block fetching, stats calculation
+    /// and database inserts to an OTLP/HTTP collector, e.g.
+    /// `http://localhost:4318/v1/traces`, to profile long syncs in Jaeger or
+    /// Tempo.
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Only collect the statistics, without writing the CSV files. Meant for a
+    /// long-running syncer next to a separate `export csv` process.
+    Sync,
+    /// Print a JSON overview of the database: the heights and stats versions
+    /// of each stats table, the last sync run, the CSV export and the lag
+    /// behind the node's tip
+    Status,
+    /// Export data without collecting statistics
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Merge the stats of another observer's database, e.g. to combine
+    /// backfills of different height ranges. Fills the heights this database
+    /// has no or outdated stats for and prints a JSON report of the rows that
+    /// differ at the heights both databases have.
+    Merge {
+        /// Path to the SQLite database to merge from. It needs the same schema.
+        #[arg(long, value_parser = parse_path)]
+        from: PathBuf,
+    },
+    /// Cross-check related counters of the stats tables, e.g. that the input
+    /// type counts add up to the inputs of the block, and print a JSON report
+    /// of the heights where they don't. Exits with 1 if an invariant fails.
+    /// Doesn't take the database lock.
+    Verify,
+    /// Drop the per-block rows of the stats tables of blocks older than the
+    /// given number of years, keeping their daily metric aggregates, to
+    /// shrink the database. The pruned blocks aren't synced again, only the
+    /// metric CSV files cover them and the aggregation timezone is fixed.
+    /// Only reports what would be pruned unless --confirm is set.
+    Prune {
+        /// Keep the blocks of this many years
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        older_than_years: u32,
+        /// Actually prune the blocks. This can't be undone.
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Write a versioned release bundle for a public data repository: the CSV
+    /// export, the stats tables as Parquet files, a catalog of the stats
+    /// tables, a license, a manifest with the schema and stats versions and a
+    /// SHA256SUMS file. Doesn't take the database lock.
+    Publish {
+        /// Name of the release, e.g. 2026-q3. Defaults to the current date.
+        #[arg(long)]
+        release: Option<String>,
+        /// Directory the release directory is created in
+        #[arg(long, default_value = "releases", value_parser = parse_path)]
+        output: PathBuf,
+        /// SPDX identifier of the license the data is released under
+        #[arg(long, default_value = "CC-BY-4.0")]
+        license: String,
+        /// File with the license text. Without it, the LICENSE file only
+        /// names the license.
+        #[arg(long, value_parser = parse_path)]
+        license_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportCommand {
+    /// Print a detailed JSON report of the stats of a single block and how
+    /// each of its transactions, inputs and outputs was classified. Useful to
+    /// debug why the numbers of a block look odd.
+    Block {
+        /// Height of the block
+        #[arg(long)]
+        height: u64,
+    },
+    /// Print a data dictionary of the stats tables generated from the column
+    /// documentation
+    Schema {
+        #[arg(long, value_enum, default_value_t = DocsFormat::Markdown)]
+        format: DocsFormat,
+    },
+    /// Write the CSV files and upload them from the database. Only reads the
+    /// database, so it doesn't take the database lock and can run while a
+    /// `sync` process writes to the database.
+    Csv,
+}
+
+fn parse_capturable_stat(stat: &str) -> Result<String, String> {
+    if stats::capturable_stats().any(|s| s == stat) {
+        Ok(stat.to_string())
+    } else {
+        Err(format!(
+            "not a tx_stats counter, expected one of: {}",
+            stats::capturable_stats().collect::<Vec<_>>().join(", ")
+        ))
+    }
+}
+
+fn parse_utc_offset(offset: &str) -> Result<FixedOffset, String> {
+    match offset {
+        "UTC" | "Z" => Ok(FixedOffset::east_opt(0).expect("zero offset is valid")),
+        _ => offset
+            .parse()
+            .map_err(|_| "expected UTC or an offset like +02:00 or -05:30".to_string()),
+    }
+}
+
+fn parse_skippable_table(name: &str) -> Result<StatsTable, String> {
+    let table = StatsTable::ALL
+        .into_iter()
+        .find(|table| {
+            table.table_name() == name || table.table_name().strip_suffix("_stats") == Some(name)
+        })
+        .ok_or_else(|| {
+            format!(
+                "not a stats table, expected one of: {}",
+                StatsTable::ALL.map(|table| table.table_name()).join(", ")
+            )
+        })?;
+    if table == StatsTable::Block {
+        return Err("block_stats can't be skipped".to_string());
+    }
+    Ok(table)
+}
+
+/// The CSV directory for the network. Mainnet uses the `csv_path` as is, the
+/// other networks use a subdirectory named after the network.
+pub fn network_csv_path(csv_path: &Path, network: Network) -> PathBuf {
+    match network {
+        Network::Bitcoin => csv_path.to_path_buf(),
+        _ => csv_path.join(network.to_string()),
+    }
+}
+
+/// Parses a path argument, expanding a leading `~` to the home directory.
+fn parse_path(path: &str) -> Result<PathBuf, String> {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return Ok(PathBuf::from(path)),
+    };
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .ok_or("can't expand ~, neither HOME nor USERPROFILE is set")?;
+    Ok(PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])))
+}
+
+/// The options of a stats collection run, see the [Args] they're built from.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub rest_host: String,
+    pub rest_port: u16,
+    pub node_implementation: NodeImplementation,
+    pub network: Network,
+    pub num_threads: usize,
+    pub max_requests_per_second: Option<u32>,
+    pub max_memory_mb: Option<u64>,
+    pub parse_in_fetcher: bool,
+    pub track_coinbase_spends: bool,
+    pub capture_contributors: Option<String>,
+    pub track_opcodes: bool,
+    pub track_script_transitions: bool,
+    pub track_novel_scripts: bool,
+    pub feerate_percentiles: Vec<u8>,
+    pub skip: Vec<StatsTable>,
+    pub skip_pruned: bool,
+}
+
+impl From<&Args> for SyncOptions {
+    fn from(args: &Args) -> SyncOptions {
+        SyncOptions {
+            rest_host: args.rest_host.clone(),
+            rest_port: args.rest_port,
+            node_implementation: args.node_implementation,
+            network: args.network,
+            num_threads: args.num_threads,
+            max_requests_per_second: args.max_requests_per_second,
+            max_memory_mb: args.max_memory_mb,
+            parse_in_fetcher: args.parse_in_fetcher,
+            track_coinbase_spends: args.track_coinbase_spends,
+            capture_contributors: args.capture_contributors.clone(),
+            track_opcodes: args.track_opcodes,
+            track_script_transitions: args.track_script_transitions,
+            track_novel_scripts: args.track_novel_scripts,
+            feerate_percentiles: args.feerate_percentiles.clone(),
+            skip: args.skip.clone(),
+            skip_pruned: args.skip_pruned,
+        }
+    }
 }
 
+/// Progress of a stats collection run as recorded in the sync_runs table.
+#[derive(Default)]
+struct SyncProgress {
+    /// min and max height of the blocks that are (re)processed in this run
+    heights: Option<(i64, i64)>,
+    blocks_processed: Arc<AtomicU64>,
+    /// blocks that failed the soft header validation
+    blocks_failed_validation: Arc<AtomicU64>,
+    /// None if the retarget schedule wasn't checked
+    unexpected_bits_changes: Option<u64>,
+    /// min and max height of the blocks skipped because the node pruned them
+    pruned_heights: Option<(i64, i64)>,
+}
+
+/// The blocks a stats collection run fetches.
+struct SyncPlan {
+    /// the heights to fetch and, for each height, the stats tables to write
+    heights: Vec<(i64, BTreeSet<StatsTable>)>,
+    /// the node's challenge, only set on signet
+    signet_challenge: Option<ScriptBuf>,
+    /// min and max height of the blocks skipped because the node pruned them
+    pruned_heights: Option<(i64, i64)>,
+}
+
+/// Collects the statistics for all blocks that aren't in the database yet or
+/// have outdated stats. Every run is recorded in the sync_runs table.
 pub fn collect_statistics(
-    rest_host: &str,
-    rest_port: u16,
+    options: &SyncOptions,
     connection: Arc<Mutex<SqliteConnection>>,
-    num_threads: usize,
 ) -> Result<(), MainError> {
-    let connection = Arc::clone(&connection);
+    let client = rest::block_source(
+        options.node_implementation,
+        &options.rest_host,
+        options.rest_port,
+        options.max_requests_per_second,
+    );
+    let sync_run_id = {
+        let mut conn = connection.lock().unwrap();
+        db::start_sync_run(
+            &mut conn,
+            &db::NewSyncRun {
+                started_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                software_version: env!("CARGO_PKG_VERSION").to_string(),
+                stats_versions: serde_json::to_string(&StatsTable::versions())?,
+                node_implementation: client.implementation().name().to_string(),
+            },
+        )?
+    };
 
-    let client = rest::RestClient::new(rest_host, rest_port);
+    // The policy is only context for the stats, a node without it (e.g. with
+    // the mempool disabled) doesn't fail the run.
+    match client.mempool_info() {
+        Ok(info) => {
+            let snapshot = db::NodePolicySnapshot::new(
+                sync_run_id,
+                Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                &info,
+            );
+            let mut conn = connection.lock().unwrap();
+            if let Err(e) = db::insert_node_policy_snapshot(&mut conn, &snapshot) {
+                warn!("Could not record the node policy: {}", e);
+            }
+        }
+        Err(e) => warn!("Could not get the node policy: {}", e),
+    }
+    let mut progress = SyncProgress::default();
+    let result = sync_blocks(
+        Arc::clone(&client),
+        Arc::clone(&connection),
+        options,
+        &mut progress,
+    );
+
+    let fetch_stats = client.fetch_stats();
+    info!("collect-statistics: {}", fetch_stats);
+    let mut conn = connection.lock().unwrap();
+    // The difficulty only changes every retarget interval on mainnet. The test
+    // networks allow minimum difficulty blocks in between.
+    if result.is_ok() && options.network == Network::Bitcoin {
+        match db::unexpected_bits_changes(&mut conn, validation::RETARGET_INTERVAL) {
+            Ok(changes) => {
+                for change in changes.iter() {
+                    warn!(
+                        "Unexpected difficulty change at height {}: bits {:08x} -> {:08x}",
+                        change.height, change.previous_bits, change.bits
+                    );
+                }
+                progress.unexpected_bits_changes = Some(changes.len() as u64);
+            }
+            Err(e) => warn!("Could not check the difficulty retarget schedule: {}", e),
+        }
+    }
+    // Done after the sync, so that the dates of the blocks with an outdated
+    // block_stats row are recalculated from their new timestamp too.
+    if result.is_ok() {
+        let offset = stats::aggregation_timezone();
+        match db::rebucket_dates(&mut conn, offset) {
+            Ok(true) => info!("Recalculated the dates of the stats in UTC{}", offset),
+            Ok(false) => (),
+            Err(e) => warn!("Could not recalculate the dates of the stats: {}", e),
+        }
+    }
+    db::finish_sync_run(
+        &mut conn,
+        sync_run_id,
+        &db::SyncRunResult::new(
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            progress.heights,
+            progress.blocks_processed.load(Ordering::Relaxed),
+            result.as_ref().err().map(|e| e.to_string()),
+            &fetch_stats,
+            progress.blocks_failed_validation.load(Ordering::Relaxed),
+            progress.unexpected_bits_changes,
+            progress.pruned_heights,
+        ),
+    )?;
+    result
+}
+
+/// Determines the blocks to fetch and, for each block, the stats tables to
+/// write to (or override in) the database. The `skip` tables aren't written
+/// and stay outdated. Blocks pruned by the node are an error, unless
+/// `skip_pruned` is set.
+fn plan_sync(
+    client: &dyn BlockSource,
+    connection: &Arc<Mutex<SqliteConnection>>,
+    options: &SyncOptions,
+) -> Result<SyncPlan, MainError> {
+    let SyncOptions {
+        network,
+        ref skip,
+        skip_pruned,
+        ..
+    } = *options;
     let chain_info = match client.chain_info() {
         Ok(chain_info) => chain_info,
         Err(e) => {
             error!(
-                "Could load chain information from Bitcoin Core at {}:{}: {}",
-                rest_host, rest_port, e
+                "Could load chain information from Bitcoin Core at {}: {}",
+                client.endpoint(),
+                e
             );
             return Err(MainError::REST(e));
         }
     };
 
+    if chain_info.chain != network.to_core_arg() {
+        error!(
+            "The Bitcoin Core node is on chain '{}', but the stats are collected for {}. Use --network to select the node's network.",
+            chain_info.chain, network
+        );
+        return Err(MainError::WrongNetwork(network, chain_info.chain));
+    }
+
+    // Custom signets share the chain name, but have a different challenge.
+    if let Some(challenge) = &chain_info.signet_challenge {
+        let stored = {
+            let mut conn = connection.lock().unwrap();
+            db::get_signet_challenge(&mut conn)?
+        };
+        if let Some(stored) = stored.filter(|stored| stored != challenge.as_bytes()) {
+            let (stored, node) = (stored.to_lower_hex_string(), challenge.to_hex_string());
+            error!(
+                "The database has stats for the signet with challenge {}, but the node is on the signet with challenge {}. Use a different --database-path for each signet.",
+                stored, node
+            );
+            return Err(MainError::WrongSignetChallenge(stored, node));
+        }
+    }
+
+    // The dates of pruned blocks can't be recalculated in a new timezone.
+    let pruned_timezone = {
+        let mut conn = connection.lock().unwrap();
+        db::pruned_aggregation_timezone(&mut conn)?
+    };
+    let offset = stats::aggregation_timezone().local_minus_utc();
+    if let Some(pruned) = pruned_timezone.filter(|pruned| *pruned != offset) {
+        error!(
+            "The pruned blocks are aggregated in a different timezone than --aggregation-timezone. Keep the timezone of the pruning."
+        );
+        return Err(MainError::PrunedTimezoneMismatch(pruned, offset));
+    }
+
     if chain_info.initialblockdownload {
         error!("The Bitcoin Core node is in initial block download (progress: {:.2}%). Please try again once the IBD is done.", chain_info.verificationprogress*100.0);
         return Err(MainError::IBDNotDone);
@@ -160,27 +829,206 @@ pub fn collect_statistics(
     let rest_height = chain_info.blocks;
     // 2. Substract an reorg margin.
     let fetch_height = std::cmp::max(0, rest_height - REORG_SAFETY_MARGIN);
-    // 3. Get a list of block heights where our block_stats stats_version is up-to-date
-    //    (i.e. stats are already at the newest version)
-    let uptodate_heights: BTreeSet<i64> = {
+    // 3. Get the stats tables with an outdated version for each block height
+    //    we already have stats for, and the height the blocks were pruned below.
+    let (outdated_tables, pruned_below): (BTreeMap<i64, BTreeSet<StatsTable>>, i64) = {
         let mut conn = connection.lock().unwrap();
-        db::block_heights_greater_equals_version(&mut conn, stats::STATS_VERSION)?
-            .iter()
-            .copied()
-            .collect()
+        (
+            db::outdated_stats_tables(&mut conn)?,
+            db::pruned_below_height(&mut conn)?.unwrap_or(0),
+        )
     };
-    // 4. Filter out heights that are already up-to-date from all possible heights
-    //    we could fetch.
-    let heights_to_fetch: Vec<i64> = (0..fetch_height as i64)
-        .filter(|h| !uptodate_heights.contains(h))
+    for table in StatsTable::ALL {
+        let outdated = outdated_tables
+            .values()
+            .filter(|tables| tables.contains(&table))
+            .count();
+        if outdated > 0 {
+            info!(
+                "{} blocks have {} older than version {}",
+                outdated,
+                table.table_name(),
+                table.version()
+            );
+        }
+    }
+    // 4. Fetch the blocks we don't have stats for yet and the blocks with
+    //    outdated stats. For the latter, only the outdated tables are written.
+    //    Skipped tables are left out, a block with only skipped tables
+    //    outdated isn't fetched. Pruned blocks aren't fetched again.
+    if !skip.is_empty() {
+        info!(
+            "Skipping {}, these tables stay outdated until a run without --skip",
+            skip.iter()
+                .map(|table| table.table_name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let mut heights: Vec<(i64, BTreeSet<StatsTable>)> = (pruned_below..fetch_height as i64)
+        .filter_map(|h| {
+            let tables: BTreeSet<StatsTable> = match outdated_tables.get(&h) {
+                Some(tables) => tables.clone(),
+                None => StatsTable::ALL.into_iter().collect(),
+            };
+            let tables: BTreeSet<StatsTable> = tables
+                .into_iter()
+                .filter(|table| !skip.contains(table))
+                .collect();
+            (!tables.is_empty()).then_some((h, tables))
+        })
         .collect();
 
+    // 5. A pruned node only has the blocks from the prune height on.
+    let mut pruned_heights = None;
+    if let Some(prune_height) = chain_info.pruneheight.filter(|_| chain_info.pruned) {
+        let pruned: Vec<i64> = heights
+            .iter()
+            .map(|(h, _)| *h)
+            .filter(|h| *h < prune_height as i64)
+            .collect();
+        if let (Some(&min), Some(&max)) = (pruned.first(), pruned.last()) {
+            if !skip_pruned {
+                error!(
+                    "The Bitcoin Core node is pruned below height {}, but the blocks from height {} on are needed. Use an unpruned node or --skip-pruned to skip them.",
+                    prune_height, min
+                );
+                return Err(MainError::BlocksPruned(min as u64, prune_height));
+            }
+            warn!(
+                "Skipping {} blocks (heights min={}, max={}) pruned by the node",
+                pruned.len(),
+                min,
+                max
+            );
+            heights.retain(|(h, _)| *h >= prune_height as i64);
+            pruned_heights = Some((min, max));
+        }
+    }
+
+    Ok(SyncPlan {
+        heights,
+        signet_challenge: chain_info.signet_challenge,
+        pruned_heights,
+    })
+}
+
+/// Reports the work a stats collection run would do without fetching any
+/// blocks or writing to the database.
+pub fn dry_run(
+    options: &SyncOptions,
+    connection: Arc<Mutex<SqliteConnection>>,
+    csv_path: Option<&Path>,
+) -> Result<(), MainError> {
+    let client = rest::block_source(
+        options.node_implementation,
+        &options.rest_host,
+        options.rest_port,
+        None,
+    );
+    let SyncPlan {
+        heights: heights_to_fetch,
+        ..
+    } = plan_sync(client.as_ref(), &connection, options)?;
+
+    info!(
+        "dry-run: would fetch {} blocks (heights min={}, max={})",
+        heights_to_fetch.len(),
+        heights_to_fetch.first().map(|(h, _)| *h).unwrap_or(0),
+        heights_to_fetch.last().map(|(h, _)| *h).unwrap_or(0),
+    );
+    for table in StatsTable::ALL {
+        let blocks = heights_to_fetch
+            .iter()
+            .filter(|(_, tables)| tables.contains(&table))
+            .count();
+        if blocks > 0 {
+            info!(
+                "dry-run: would write {} for {} blocks",
+                table.table_name(),
+                blocks
+            );
+        }
+    }
+
+    let blocks_per_second = {
+        let mut conn = connection.lock().unwrap();
+        db::recent_blocks_per_second(&mut conn)?
+    };
+    match blocks_per_second {
+        Some(rate) if !heights_to_fetch.is_empty() => info!(
+            "dry-run: estimated duration {} minutes at the recent {:.1} blocks per second",
+            (heights_to_fetch.len() as f64 / rate / 60.0).ceil(),
+            rate
+        ),
+        Some(_) => (),
+        None => info!("dry-run: no recent sync runs to estimate the duration from"),
+    }
+
+    // The export is always regenerated in full.
+    match csv_path {
+        Some(csv_path) => {
+            let previous = manifest::read_manifest(csv_path)
+                .map(|manifest| manifest.files.len().to_string())
+                .unwrap_or_else(|_| "no".to_string());
+            info!(
+                "dry-run: would rewrite all CSV files in {} ({} files in the previous export)",
+                csv_path.display(),
+                previous
+            );
+        }
+        None => info!("dry-run: CSV file writing is disabled"),
+    }
+    Ok(())
+}
+
+fn sync_blocks(
+    client: Arc<dyn BlockSource>,
+    connection: Arc<Mutex<SqliteConnection>>,
+    options: &SyncOptions,
+    progress: &mut SyncProgress,
+) -> Result<(), MainError> {
+    let SyncOptions {
+        network,
+        num_threads,
+        parse_in_fetcher,
+        track_coinbase_spends,
+        track_opcodes,
+        track_script_transitions,
+        track_novel_scripts,
+        ..
+    } = *options;
+    let capture_contributors = options.capture_contributors.clone();
+    let feerate_percentiles = options.feerate_percentiles.clone();
+    let memory_budget = MemoryBudget::new(options.max_memory_mb);
+    let SyncPlan {
+        heights: heights_to_fetch,
+        signet_challenge,
+        pruned_heights,
+    } = plan_sync(client.as_ref(), &connection, options)?;
+    progress.pruned_heights = pruned_heights;
+    if let Some(challenge) = &signet_challenge {
+        let mut conn = connection.lock().unwrap();
+        db::set_signet_challenge(&mut conn, challenge.as_bytes())?;
+    }
+    // Pool identification uses the mainnet pool list.
+    if network != Network::Bitcoin {
+        pools::disable_pool_identification();
+    }
+    // written here, as `export csv` only reads the database
+    db::replace_pools(&mut connection.lock().unwrap(), pools::pools())?;
+
     let blocks_to_fetch = heights_to_fetch.len();
+    let min_height = heights_to_fetch.first().map(|(h, _)| *h);
+    let max_height = heights_to_fetch.last().map(|(h, _)| *h);
+    if let (Some(min), Some(max)) = (min_height, max_height) {
+        progress.heights = Some((min, max));
+    }
     info!(
         "Fetching {} blocks (heights min={}, max={})",
         blocks_to_fetch,
-        heights_to_fetch.first().unwrap_or(&0),
-        heights_to_fetch.last().unwrap_or(&0),
+        min_height.unwrap_or(0),
+        max_height.unwrap_or(0),
     );
 
     // TODO: Shuffel the heights around, so each rayon thread gets different heights.
@@ -190,6 +1038,19 @@ pub fn collect_statistics(
     let (block_sender, block_receiver) = mpsc::sync_channel(10);
     let (stat_sender, stat_receiver) = mpsc::sync_channel(100);
 
+    let fetch_client = Arc::clone(&client);
+    let blocks_failed_validation = Arc::clone(&progress.blocks_failed_validation);
+
+    // the parent span of the per block and per batch spans
+    let sync_span = telemetry::span(
+        "sync",
+        &opentelemetry::Context::new(),
+        vec![KeyValue::new("blocks", blocks_to_fetch as i64)],
+    );
+    let fetch_span = sync_span.clone();
+    let stats_span = sync_span.clone();
+    let insert_span = sync_span.clone();
+
     // get-blocks task
     // gets blocks from the Bitcoin Core REST interface and sends them onwards
     // to the `calc-stats` task
@@ -200,16 +1061,45 @@ pub fn collect_statistics(
             .unwrap();
         pool.install(|| {
             heights_to_fetch.par_iter()
-                .map(|&height| {
+                .map(|(height, tables)| {
+                    let height = *height;
                     debug!("get-blocks: getting block at height {}", height);
-                    let block = match client.block_at_height(height as u64) {
+                    let span = telemetry::span("get-block", &fetch_span, vec![KeyValue::new("height", height)]);
+                    let fetch_start = Instant::now();
+                    // the binary blocks don't include the prevout heights
+                    let prevout_heights =
+                        stats::needs_prevout_heights(tables, track_coinbase_spends);
+                    let mut block = match fetch_client.block_at_height(height as u64, prevout_heights) {
                         Ok(block) => block,
                         Err(e) => {
                             error!("Could not get block at height {}: {}", height, e);
                             return Err(MainError::REST(e));
                         }
                     };
-                    if block_sender.send((height, block)).is_err() {
+                    // A soft check: the stats are still calculated, but the
+                    // failure is logged and recorded for the sync run.
+                    if let Err(e) = validation::validate_header(&block) {
+                        warn!("Block at height {} failed validation: {}", height, e);
+                        blocks_failed_validation.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // the genesis block has no signet solution
+                    if let Some(challenge) = signet_challenge.as_ref().filter(|_| height > 0) {
+                        if let Err(e) = validation::validate_signet_solution(&block, challenge) {
+                            warn!("Block at height {} failed signet validation: {}", height, e);
+                            blocks_failed_validation.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    if parse_in_fetcher {
+                        if let Err(e) = block.parse_transactions() {
+                            error!("Could not parse the transactions of block at height {}: {}", height, e);
+                            return Err(MainError::Stats(e.into()));
+                        }
+                    }
+                    let fetch_ms = fetch_start.elapsed().as_millis() as i64;
+                    // don't include the time waiting for the calc-stats task
+                    drop(span);
+                    let reservation = memory_budget.reserve(estimated_block_memory(block.size));
+                    if block_sender.send((height, block, tables.clone(), reservation, fetch_ms)).is_err() {
                         warn!(
                             "during sending block at height {} to stats generator: block receiver dropped",
                             height
@@ -229,11 +1119,43 @@ pub fn collect_statistics(
     // calculates the per block stats and sends them onwards to the batch-insert
     // task
     let calc_stats_task = thread::spawn(move || -> Result<(), MainError> {
-        while let Ok((height, block)) = block_receiver.recv() {
+        while let Ok((height, block, tables, reservation, fetch_ms)) = block_receiver.recv() {
             debug!("calc-stats: processing block at height {}..", height);
             let stat_sender_clone = stat_sender.clone();
+            let capture = capture_contributors.clone();
+            let feerate_percentiles = feerate_percentiles.clone();
+            let stats_span = stats_span.clone();
             rayon::spawn(move || {
-                let stats_result = Stats::from_block(block);
+                let span = telemetry::span(
+                    "calc-stats",
+                    &stats_span,
+                    vec![KeyValue::new("height", height)],
+                );
+                let stats_start = Instant::now();
+                let coinbase_spends = track_coinbase_spends
+                    .then(|| stats::CoinbaseSpend::from_block(&block))
+                    .transpose();
+                let opcodes = track_opcodes.then(|| stats::OpcodeCount::from_block(&block));
+                let transitions = track_script_transitions
+                    .then(|| stats::ScriptTypeTransition::from_block(&block));
+                let novel_scripts =
+                    track_novel_scripts.then(|| stats::NovelScript::from_block(&block));
+                let percentiles = (!feerate_percentiles.is_empty())
+                    .then(|| stats::FeeratePercentile::from_block(&block, &feerate_percentiles));
+                let stats_result =
+                    PartialStats::from_block_capturing(block, &tables, capture.as_deref())
+                        .and_then(|mut stats| {
+                            stats.coinbase_spends = coinbase_spends?;
+                            stats.opcodes = opcodes;
+                            stats.transitions = transitions;
+                            stats.novel_scripts = novel_scripts;
+                            stats.feerate_percentiles = percentiles;
+                            Ok(stats)
+                        });
+                let stats_ms = stats_start.elapsed().as_millis() as i64;
+                drop(span);
+                // the block has been dropped by now
+                drop(reservation);
                 if let Err(e) = stats_result {
                     error!(
                         "Could not calculate stats for block at height {}: {}",
@@ -247,7 +1169,13 @@ pub fn collect_statistics(
                         MainError::Stats(e)
                     );
                 };
-                if let Err(e) = stat_sender_clone.send(stats_result) {
+                let metrics = db::ProcessingMetrics {
+                    height,
+                    fetch_ms,
+                    stats_ms,
+                    insert_ms: 0,
+                };
+                if let Err(e) = stat_sender_clone.send(stats_result.map(|stats| (stats, metrics))) {
                     // We can't continue here..
                     panic!(
                         "during sending stats at height {} to db writer: stats receiver dropped: {}",
@@ -266,18 +1194,20 @@ pub fn collect_statistics(
 
     // batch-insert task
     // inserts the block stats in batches
+    let blocks_processed = Arc::clone(&progress.blocks_processed);
     let batch_insert_task = thread::spawn(move || -> Result<(), MainError> {
         let connection = Arc::clone(&connection);
         let mut conn = connection.lock().unwrap();
         db::performance_tune(&mut conn)?;
         let mut stat_buffer = Vec::with_capacity(DATABASE_BATCH_SIZE);
+        let mut metrics_buffer = Vec::with_capacity(DATABASE_BATCH_SIZE);
         let mut written = 0;
 
         loop {
             let stat_recv_result = stat_receiver.recv();
-            let stat = match stat_recv_result {
+            let (stat, metrics) = match stat_recv_result {
                 Ok(stat_result) => match stat_result {
-                    Ok(stat) => stat,
+                    Ok(stat_and_metrics) => stat_and_metrics,
                     Err(e) => {
                         error!("Could write stat: {}", e);
                         return Err(MainError::Stats(e));
@@ -290,9 +1220,11 @@ pub fn collect_statistics(
             };
 
             stat_buffer.push(stat);
+            metrics_buffer.push(metrics);
             if stat_buffer.len() >= DATABASE_BATCH_SIZE {
-                db::insert_stats(&mut conn, &stat_buffer)?;
+                insert_batch(&mut conn, &stat_buffer, &mut metrics_buffer, &insert_span)?;
                 written += stat_buffer.len();
+                blocks_processed.fetch_add(stat_buffer.len() as u64, Ordering::Relaxed);
                 info!(
                     "written {} out of {} block stats to database ({:0.2}%)",
                     written,
@@ -300,6 +1232,7 @@ pub fn collect_statistics(
                     (written as f32 / blocks_to_fetch as f32) * 100.0,
                 );
                 stat_buffer.clear();
+                metrics_buffer.clear();
             }
         }
 
@@ -310,7 +1243,8 @@ pub fn collect_statistics(
                 "collect-statistics: writing the final batch of {} block-stats to database",
                 stat_buffer.len()
             );
-            db::insert_stats(&mut conn, &stat_buffer)?;
+            insert_batch(&mut conn, &stat_buffer, &mut metrics_buffer, &insert_span)?;
+            blocks_processed.fetch_add(stat_buffer.len() as u64, Ordering::Relaxed);
         } else {
             info!("collect-statistics: no new blocks to insert.");
         }
@@ -330,19 +1264,179 @@ pub fn collect_statistics(
     Ok(())
 }
 
+/// Inserts a batch of block stats and their processing metrics. The insert
+/// duration of the batch is split evenly between its blocks.
+fn insert_batch(
+    conn: &mut SqliteConnection,
+    stats: &[PartialStats],
+    metrics: &mut [db::ProcessingMetrics],
+    parent_span: &opentelemetry::Context,
+) -> Result<(), MainError> {
+    let _span = telemetry::span(
+        "batch-insert",
+        parent_span,
+        vec![KeyValue::new("blocks", stats.len() as i64)],
+    );
+    let insert_start = Instant::now();
+    db::insert_stats(conn, stats)?;
+    let insert_ms = insert_start.elapsed().as_millis() as i64 / stats.len().max(1) as i64;
+    for m in metrics.iter_mut() {
+        m.insert_ms = insert_ms;
+    }
+    db::insert_processing_metrics(conn, metrics)?;
+    Ok(())
+}
+
+/// Exports taking longer than this are logged with a warning, as they likely
+/// lack an index.
+const SLOW_EXPORT_WARNING: Duration = Duration::from_secs(30);
+
+/// Runs an export step and warns if it's slow.
+fn timed_export<T>(
+    name: &str,
+    export: impl FnOnce() -> Result<T, MainError>,
+) -> Result<T, MainError> {
+    let start = Instant::now();
+    let result = export();
+    let elapsed = start.elapsed();
+    if elapsed > SLOW_EXPORT_WARNING {
+        warn!(
+            "Export {} took {:.1}s, more than {}s. Check the indexes of the queried tables.",
+            name,
+            elapsed.as_secs_f64(),
+            SLOW_EXPORT_WARNING.as_secs()
+        );
+    }
+    result
+}
+
+/// Writes all CSV files and the manifest. Creates the `csv_path` directory if
+/// it doesn't exist yet.
 pub fn write_csv_files(
-    csv_path: &str,
+    csv_path: &Path,
     connection: Arc<Mutex<SqliteConnection>>,
+    downsample_points: Option<u64>,
+    partition: Option<CsvPartition>,
 ) -> Result<(), MainError> {
-    gen_csv::date_csv(csv_path, connection.clone())?;
-    gen_csv::metrics_csv(csv_path, connection.clone())?;
-    gen_csv::top5_miningpools_csv(csv_path, connection.clone())?;
-    gen_csv::antpool_and_friends_csv(csv_path, connection.clone())?;
-    gen_csv::mining_centralization_index_csv(csv_path, connection.clone())?;
-    gen_csv::mining_centralization_index_with_proxy_pools_csv(csv_path, connection.clone())?;
-    gen_csv::mining_pool_blocks_per_day_csv(csv_path, connection.clone())?;
-    gen_csv::pools_mining_ephemeral_dust_csv(csv_path, connection.clone())?;
-    gen_csv::pools_mining_p2a_csv(csv_path, connection.clone())?;
-    gen_csv::pools_mining_bip54_coinbase_csv(csv_path, connection.clone())?;
+    std::fs::create_dir_all(csv_path)?;
+    let partitions = match partition {
+        Some(scheme) => gen_csv::partitions(connection.clone(), scheme)?,
+        None => vec![],
+    };
+    let mut files: Vec<String> = Vec::new();
+    files.extend(timed_export("date_csv", || {
+        gen_csv::date_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_csv", || {
+        gen_csv::pools_csv(csv_path, connection.clone())
+    })?);
+    // timed per metric, as writing all metrics is slow
+    files.extend(gen_csv::metrics_csv(
+        csv_path,
+        connection.clone(),
+        downsample_points.map(|points| points as usize),
+        &partitions,
+    )?);
+    files.extend(timed_export("top5_miningpools_csv", || {
+        gen_csv::top5_miningpools_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("antpool_and_friends_csv", || {
+        gen_csv::antpool_and_friends_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("mining_centralization_index_csv", || {
+        gen_csv::mining_centralization_index_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export(
+        "mining_centralization_index_with_proxy_pools_csv",
+        || gen_csv::mining_centralization_index_with_proxy_pools_csv(csv_path, connection.clone()),
+    )?);
+    files.extend(timed_export("mining_pool_blocks_per_day_csv", || {
+        gen_csv::mining_pool_blocks_per_day_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_mining_ephemeral_dust_csv", || {
+        gen_csv::pools_mining_ephemeral_dust_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_mining_p2a_csv", || {
+        gen_csv::pools_mining_p2a_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_mining_bip54_coinbase_csv", || {
+        gen_csv::pools_mining_bip54_coinbase_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("fee_revenue_and_output_volume_csv", || {
+        gen_csv::fee_revenue_and_output_volume_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("block_reward_and_inflation_csv", || {
+        gen_csv::block_reward_and_inflation_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("difficulty_adjustments_csv", || {
+        gen_csv::difficulty_adjustments_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("security_budget_csv", || {
+        gen_csv::security_budget_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("input_output_volume_csv", || {
+        gen_csv::input_output_volume_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("outlier_blocks_csv", || {
+        gen_csv::outlier_blocks_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_version_rolling_csv", || {
+        gen_csv::pools_version_rolling_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_coinbase_weight_csv", || {
+        gen_csv::pools_coinbase_weight_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("nonce_distribution_csv", || {
+        gen_csv::nonce_distribution_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("ecdsa_grinding_csv", || {
+        gen_csv::ecdsa_grinding_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("truc_adoption_csv", || {
+        gen_csv::truc_adoption_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("coinbase_payout_types_csv", || {
+        gen_csv::coinbase_payout_types_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("p2a_spenders_csv", || {
+        gen_csv::p2a_spenders_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("fee_cliff_csv", || {
+        gen_csv::fee_cliff_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("tx_vsize_classes_csv", || {
+        gen_csv::tx_vsize_classes_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("traffic_composition_csv", || {
+        gen_csv::traffic_composition_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("utxo_growth_by_protocol_csv", || {
+        gen_csv::utxo_growth_by_protocol_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("sig_aggregation_savings_csv", || {
+        gen_csv::sig_aggregation_savings_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("malleability_hygiene_csv", || {
+        gen_csv::malleability_hygiene_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("multisig_vs_singlesig_csv", || {
+        gen_csv::multisig_vs_singlesig_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_coinbase_spends_csv", || {
+        gen_csv::pools_coinbase_spends_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("burned_supply_csv", || {
+        gen_csv::burned_supply_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("pools_feerate_floor_csv", || {
+        gen_csv::pools_feerate_floor_csv(csv_path, connection.clone())
+    })?);
+    files.extend(timed_export("write_annotations", || {
+        annotations::write_annotations(csv_path, connection.clone())
+    })?);
+    // The manifest is written last. If it's missing or doesn't match the
+    // files, the export is partial. The last_updated.json summarizes it.
+    let manifest = manifest::write_manifest(csv_path, &files, &partitions, connection)?;
+    manifest::write_last_updated(csv_path, &manifest)?;
     Ok(())
 }
@@ -1,7 +1,16 @@
+use bitcoin::Network;
+use chrono::Utc;
 use clap::Parser;
+use diesel::SqliteConnection;
 use env_logger::Env;
-use log::{error, info};
-use mainnet_observer_backend::{collect_statistics, db, write_csv_files, Args};
+use log::{error, info, warn};
+use mainnet_observer_backend::{
+    block_audit, check_sync_lag, collect_statistics, data_dictionary, db, dry_run, import_prices,
+    init_tracing, load_pool_data, merge, network_csv_path, prune, publish, send_dormant_alerts,
+    set_aggregation_timezone, status, upload_export, verify, write_csv_files, Args, Command,
+    ExportCommand, RestClient, SyncOptions,
+};
+use std::path::Path;
 use std::process::exit;
 use std::sync::{Arc, Mutex};
 
@@ -12,7 +21,165 @@ fn main() {
 
     let args = Args::parse();
 
-    let conn = match db::open_db_and_run_migrations(&args.database_path) {
+    match args.command {
+        Some(Command::Export(ExportCommand::Block { height })) => {
+            match block_audit(&args.rest_host, args.rest_port, height) {
+                Ok(report) => println!("{:#}", report),
+                Err(e) => {
+                    error!("Could not export block {}: {}", height, e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Export(ExportCommand::Schema { format })) => {
+            match data_dictionary(format) {
+                Ok(dictionary) => print!("{}", dictionary),
+                Err(e) => {
+                    error!("Could not generate the data dictionary: {}", e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Export(ExportCommand::Csv)) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            let conn = match db::open_db_for_export(&database_path) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!("Could not open database: {}", e);
+                    exit(1);
+                }
+            };
+            let csv_path = network_csv_path(&args.csv_path, args.network);
+            export(&args, &csv_path, Arc::new(Mutex::new(conn)));
+            return;
+        }
+        Some(Command::Status) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            let csv_path = network_csv_path(&args.csv_path, args.network);
+            let client = RestClient::new(&args.rest_host, args.rest_port, None);
+            let result = db::open_db_for_export(&database_path)
+                .and_then(|mut conn| status(&mut conn, &client, &csv_path));
+            match result {
+                Ok(report) => println!("{:#}", report),
+                Err(e) => {
+                    error!("Could not get the status: {}", e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Publish {
+            ref release,
+            ref output,
+            ref license,
+            ref license_file,
+        }) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            let conn = match db::open_db_for_export(&database_path) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!("Could not open database: {}", e);
+                    exit(1);
+                }
+            };
+            let release = release
+                .clone()
+                .unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+            match publish(
+                output,
+                &release,
+                args.network,
+                license,
+                license_file.as_deref(),
+                Arc::new(Mutex::new(conn)),
+            ) {
+                Ok(path) => info!("Published release {} to {}", release, path.display()),
+                Err(e) => {
+                    error!("Could not publish release {}: {}", release, e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Verify) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            match db::open_db_for_export(&database_path).and_then(|mut conn| verify(&mut conn)) {
+                Ok((all_hold, report)) => {
+                    println!("{:#}", report);
+                    if !all_hold {
+                        exit(1);
+                    }
+                }
+                Err(e) => {
+                    error!("Could not verify the database: {}", e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Merge { ref from }) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            let _lock = match db::lock_database(&database_path, args.wait_for_lock) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    error!("Could not lock database: {}", e);
+                    exit(1);
+                }
+            };
+            let result = db::open_db_and_run_migrations(&database_path)
+                .and_then(|mut conn| merge(&mut conn, from));
+            match result {
+                Ok(report) => println!("{:#}", report),
+                Err(e) => {
+                    error!("Could not merge {}: {}", from.display(), e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Prune {
+            older_than_years,
+            confirm,
+        }) => {
+            let database_path = db::network_database_path(&args.database_path, args.network);
+            let _lock = match db::lock_database(&database_path, args.wait_for_lock) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    error!("Could not lock database: {}", e);
+                    exit(1);
+                }
+            };
+            let result = db::open_db_and_run_migrations(&database_path)
+                .and_then(|mut conn| prune(&mut conn, older_than_years, confirm));
+            match result {
+                Ok(report) => println!("{:#}", report),
+                Err(e) => {
+                    error!("Could not prune the database: {}", e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Sync) | None => (),
+    }
+    // `sync` leaves the CSV files to a separate `export csv` process
+    let write_csv = !args.no_csv && args.command.is_none();
+
+    let database_path = db::network_database_path(&args.database_path, args.network);
+    let csv_path = network_csv_path(&args.csv_path, args.network);
+
+    // held until the process exits
+    let _lock = match db::lock_database(&database_path, args.wait_for_lock) {
+        Ok(lock) => lock,
+        Err(e) => {
+            error!("Could not lock database: {}", e);
+            exit(1);
+        }
+    };
+
+    let conn = match db::open_db_and_run_migrations(&database_path) {
         Ok(conn) => conn,
         Err(e) => {
             error!("Could not open database: {}", e);
@@ -26,22 +193,99 @@ fn main() {
         args.num_threads
     );
 
+    if args.dry_run {
+        let csv_path = write_csv.then_some(csv_path.as_path());
+        if let Err(e) = dry_run(&SyncOptions::from(&args), conn, csv_path) {
+            error!("Could not plan the stats collection: {}", e);
+            exit(1);
+        };
+        return;
+    }
+
+    // imported while holding the lock, as `export csv` only reads
+    if let Some(prices) = &args.prices {
+        if let Err(e) = import_prices(prices, Arc::clone(&conn)) {
+            error!("Could not import prices: {}", e);
+            exit(1);
+        };
+    }
+
     if !args.no_stats {
-        if let Err(e) = collect_statistics(
-            &args.rest_host,
-            args.rest_port,
-            Arc::clone(&conn),
-            args.num_threads,
-        ) {
+        set_aggregation_timezone(args.aggregation_timezone);
+        // pools are only identified on mainnet
+        if let (Some(refresh_hours), Network::Bitcoin) =
+            (args.pool_data_refresh_hours, args.network)
+        {
+            load_pool_data(&database_path, refresh_hours);
+        }
+
+        // the height before this run, alerts are only sent for new blocks
+        let previous_height = match db::get_db_block_height(&mut conn.lock().unwrap()) {
+            Ok(height) => height,
+            Err(e) => {
+                error!("Could not read the database block height: {}", e);
+                exit(1);
+            }
+        };
+        let tracer_provider = args.otlp_endpoint.as_ref().and_then(|endpoint| {
+            init_tracing(endpoint)
+                .inspect_err(|e| warn!("Could not set up the OTLP exporter: {}", e))
+                .ok()
+        });
+        let result = collect_statistics(&SyncOptions::from(&args), Arc::clone(&conn));
+        if let Some(provider) = tracer_provider {
+            if let Err(e) = provider.shutdown() {
+                warn!("Could not export the remaining spans: {}", e);
+            }
+        }
+
+        // checked even if the collection failed, as failing runs are what
+        // makes the database fall behind
+        if let Some(max_lag) = args.max_lag {
+            let client = RestClient::new(&args.rest_host, args.rest_port, None);
+            if let Err(e) = check_sync_lag(
+                &client,
+                Arc::clone(&conn),
+                max_lag,
+                args.lag_alert_url.as_deref(),
+            ) {
+                warn!("Could not check the sync lag: {}", e);
+            }
+        }
+
+        if let Err(e) = result {
             error!("Could not collect statistics: {}", e);
             exit(1);
         };
+
+        if let (Some(url), Some(height)) = (&args.dormant_alert_url, previous_height) {
+            if let Err(e) =
+                send_dormant_alerts(url, args.dormant_alert_threshold, Arc::clone(&conn), height)
+            {
+                warn!("Could not send dormant spend alerts: {}", e);
+            }
+        }
+    }
+
+    if args.command.is_none() {
+        export(&args, &csv_path, conn);
     }
+}
 
+/// Writes the CSV files and uploads them. Exits on errors.
+fn export(args: &Args, csv_path: &Path, conn: Arc<Mutex<SqliteConnection>>) {
     if !args.no_csv {
-        if let Err(e) = write_csv_files(&args.csv_path, conn) {
+        if let Err(e) = write_csv_files(csv_path, conn, args.downsample_points, args.csv_partition)
+        {
             error!("Could not write CSV files to disk: {}", e);
             exit(1);
         };
     }
+
+    if let Some(upload_url) = &args.upload_url {
+        if let Err(e) = upload_export(upload_url, csv_path) {
+            error!("Could not upload the CSV files: {}", e);
+            exit(1);
+        };
+    }
 }
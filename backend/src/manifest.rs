@@ -0,0 +1,117 @@
+use crate::gen_csv::Partition;
+use crate::{db, stats::StatsTable, MainError};
+use bitcoin::hashes::{sha256, Hash};
+use chrono::{SecondsFormat, Utc};
+use diesel::SqliteConnection;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+pub const LAST_UPDATED_FILENAME: &str = "last_updated.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ManifestFile {
+    pub name: String,
+    pub sha256: String,
+    /// Number of rows in the file, excluding the header row.
+    pub rows: usize,
+}
+
+/// Describes a complete export. Consumers can use it to detect partial or
+/// stale exports by comparing the checksums and the covered range.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    /// Stats version of each table by table name.
+    pub stats_versions: BTreeMap<String, i32>,
+    /// RFC 3339 timestamp of when the export was generated.
+    pub generated_at: String,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    pub files: Vec<ManifestFile>,
+    /// The date ranges the metrics are additionally written for, if the
+    /// export is partitioned.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<Partition>,
+}
+
+/// A small summary of the manifest, so that the website can show how recent
+/// the data is without fetching the manifest or a CSV file.
+#[derive(Serialize, Debug)]
+pub struct LastUpdated {
+    pub max_height: Option<i64>,
+    pub max_date: Option<String>,
+    /// Stats version of each table by table name.
+    pub stats_versions: BTreeMap<String, i32>,
+    /// RFC 3339 timestamp of when the export was generated.
+    pub generated_at: String,
+}
+
+fn manifest_file(csv_path: &Path, name: &str) -> Result<ManifestFile, MainError> {
+    let content = std::fs::read(csv_path.join(name))?;
+    let lines = content.iter().filter(|&&b| b == b'\n').count();
+    Ok(ManifestFile {
+        name: name.to_string(),
+        sha256: sha256::Hash::hash(&content).to_string(),
+        rows: lines.saturating_sub(1),
+    })
+}
+
+// Generates a manifest.json file listing the given files with their checksums
+// and row counts. This should be called after all other files are written.
+pub fn write_manifest(
+    csv_path: &Path,
+    files: &[String],
+    partitions: &[Partition],
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<Manifest, MainError> {
+    info!("Generating {} file...", MANIFEST_FILENAME);
+    let range = {
+        let mut conn = connection.lock().unwrap();
+        db::height_and_date_range(&mut conn)?
+    };
+
+    let manifest = Manifest {
+        stats_versions: StatsTable::versions(),
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        min_height: range.min_height,
+        max_height: range.max_height,
+        first_date: range.first_date,
+        last_date: range.last_date,
+        files: files
+            .iter()
+            .map(|name| manifest_file(csv_path, name))
+            .collect::<Result<Vec<ManifestFile>, MainError>>()?,
+        partitions: partitions.to_vec(),
+    };
+
+    let file = std::fs::File::create(csv_path.join(MANIFEST_FILENAME))?;
+    serde_json::to_writer_pretty(file, &manifest)?;
+    Ok(manifest)
+}
+
+// Generates a last_updated.json file with the max height and date, the stats
+// versions and the generation timestamp of the export described by the
+// manifest.
+pub fn write_last_updated(csv_path: &Path, manifest: &Manifest) -> Result<(), MainError> {
+    info!("Generating {} file...", LAST_UPDATED_FILENAME);
+    let last_updated = LastUpdated {
+        max_height: manifest.max_height,
+        max_date: manifest.last_date.clone(),
+        stats_versions: manifest.stats_versions.clone(),
+        generated_at: manifest.generated_at.clone(),
+    };
+    let file = std::fs::File::create(csv_path.join(LAST_UPDATED_FILENAME))?;
+    serde_json::to_writer_pretty(file, &last_updated)?;
+    Ok(())
+}
+
+/// Reads the manifest of a previous export.
+pub fn read_manifest(csv_path: &Path) -> Result<Manifest, MainError> {
+    let file = std::fs::File::open(csv_path.join(MANIFEST_FILENAME))?;
+    Ok(serde_json::from_reader(file)?)
+}
@@ -0,0 +1,109 @@
+use log::debug;
+use std::sync::{Arc, Condvar, Mutex};
+
+// A deserialized block takes up a multiple of its serialized size: besides the
+// raw transactions, the REST response includes the prevouts of all inputs and
+// the scripts and witnesses are kept separately. Processing the block parses
+// the transactions once more.
+const BLOCK_MEMORY_FACTOR: usize = 6;
+
+/// Estimates the memory needed to hold and process a block of the given
+/// serialized size.
+pub fn estimated_block_memory(block_size: i64) -> usize {
+    (block_size.max(0) as usize).saturating_mul(BLOCK_MEMORY_FACTOR)
+}
+
+/// Limits the (estimated) memory used by the blocks in flight between the
+/// get-blocks and calc-stats tasks. Without a limit, reservations never block.
+pub struct MemoryBudget {
+    limit: Option<usize>,
+    used: Mutex<usize>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(max_memory_mb: Option<u64>) -> Arc<MemoryBudget> {
+        Arc::new(MemoryBudget {
+            limit: max_memory_mb.map(|mb| (mb as usize).saturating_mul(1024 * 1024)),
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        })
+    }
+
+    /// Blocks until `bytes` fit into the budget and reserves them until the
+    /// returned [Reservation] is dropped. A reservation larger than the whole
+    /// budget is granted once nothing else is reserved, so a single huge
+    /// block can't stall the pipeline.
+    pub fn reserve(self: &Arc<Self>, bytes: usize) -> Reservation {
+        let mut used = self.used.lock().unwrap();
+        if let Some(limit) = self.limit {
+            if *used > 0 && *used + bytes > limit {
+                debug!(
+                    "memory-budget: waiting for {} bytes ({} of {} bytes in use)",
+                    bytes, *used, limit
+                );
+            }
+            while *used > 0 && *used + bytes > limit {
+                used = self.released.wait(used).unwrap();
+            }
+        }
+        *used += bytes;
+        Reservation {
+            budget: Arc::clone(self),
+            bytes,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn used(&self) -> usize {
+        *self.used.lock().unwrap()
+    }
+}
+
+/// Reserved memory of a [MemoryBudget]. Released on drop.
+pub struct Reservation {
+    budget: Arc<MemoryBudget>,
+    bytes: usize,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut used = self.budget.used.lock().unwrap();
+        *used -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryBudget;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_memory_budget() {
+        let budget = MemoryBudget::new(Some(1));
+        let mb = 1024 * 1024;
+
+        // larger than the budget, but nothing else is reserved
+        let huge = budget.reserve(3 * mb);
+        assert_eq!(budget.used(), 3 * mb);
+
+        let waiting = {
+            let budget = budget.clone();
+            thread::spawn(move || budget.reserve(mb / 2))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+
+        drop(huge);
+        let half = waiting.join().unwrap();
+        assert_eq!(budget.used(), mb / 2);
+        drop(half);
+        assert_eq!(budget.used(), 0);
+
+        let unlimited = MemoryBudget::new(None);
+        let _a = unlimited.reserve(usize::MAX / 2);
+        let _b = unlimited.reserve(usize::MAX / 2);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::stats::StatsTable;
+use crate::{db, MainError};
+use diesel::SqliteConnection;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Tables without stats versions whose missing rows are copied as they are,
+/// with their height column, if any. novel_scripts isn't merged, as its rows
+/// record the first height a script template was seen by this instance.
+const AUXILIARY_TABLES: [(&str, Option<&str>); 7] = [
+    ("coinbases", Some("height")),
+    ("coinbase_spends", Some("coinbase_height")),
+    ("feerate_percentiles", Some("height")),
+    ("opcode_stats", Some("height")),
+    ("script_type_transitions", Some("height")),
+    ("stat_contributors", Some("height")),
+    ("prices", None),
+];
+
+/// Number of differing heights listed per column in the report.
+const MAX_LISTED_HEIGHTS: usize = 10;
+
+/// Merges the stats of another observer's database at `source_path`, e.g.
+/// from someone who processed a different height range. Rows with current
+/// stats fill the heights this database has no or only outdated stats for.
+/// Heights with current stats in both databases are compared column by column
+/// and differences are reported, but this database's rows are kept. Both
+/// databases need the same schema. Heights this database pruned aren't
+/// merged, as their daily aggregates are kept separately. Returns a JSON
+/// report per table.
+pub fn merge(conn: &mut SqliteConnection, source_path: &Path) -> Result<Value, MainError> {
+    let source_version = {
+        let mut source = db::open_db_for_export(source_path)?;
+        db::schema_version(&mut source)?
+    };
+    if source_version != db::schema_version(conn)? {
+        return Err(MainError::SchemaMismatch(source_path.display().to_string()));
+    }
+
+    db::attach_merge_source(conn, source_path)?;
+    let result = merge_attached(conn);
+    db::detach_merge_source(conn)?;
+    result
+}
+
+fn merge_attached(conn: &mut SqliteConnection) -> Result<Value, MainError> {
+    let pruned_below = db::pruned_below_height(conn)?.unwrap_or(0);
+    if pruned_below > 0 {
+        info!(
+            "Not merging the heights below {}, they were pruned",
+            pruned_below
+        );
+    }
+    let mut tables = serde_json::Map::new();
+    let mut filled_rows = 0;
+    for table in StatsTable::ALL {
+        let name = table.table_name();
+        let compared = db::merge_overlapping_heights(conn, table)?;
+        let mut differences = serde_json::Map::new();
+        // the date depends on the aggregation timezone and is recalculated
+        for column in table.columns().iter().filter(|c| c.name != "date") {
+            let heights = db::merge_differing_heights(conn, table, column.name)?;
+            if heights.is_empty() {
+                continue;
+            }
+            warn!(
+                "{}.{} differs at {} heights, e.g. at height {}",
+                name,
+                column.name,
+                heights.len(),
+                heights[0]
+            );
+            differences.insert(
+                column.name.to_string(),
+                json!({
+                    "heights": heights.len(),
+                    "first_heights": heights.iter().take(MAX_LISTED_HEIGHTS).collect::<Vec<_>>(),
+                }),
+            );
+        }
+        let outdated = db::merge_source_outdated_rows(conn, table)?;
+        let filled = db::merge_fill_gaps(conn, table, pruned_below)?;
+        info!(
+            "Merged {} rows into {}, compared {} heights",
+            filled, name, compared
+        );
+        filled_rows += filled;
+        tables.insert(
+            name.to_string(),
+            json!({
+                "filled": filled,
+                "compared": compared,
+                "skipped_outdated": outdated,
+                "differences": differences,
+            }),
+        );
+    }
+
+    let mut auxiliary = serde_json::Map::new();
+    for (table, height_column) in AUXILIARY_TABLES {
+        let copied = db::merge_missing_rows(conn, table, height_column, pruned_below)?;
+        auxiliary.insert(table.to_string(), json!(copied));
+    }
+
+    // the merged rows might be bucketed in a different timezone
+    if filled_rows > 0 {
+        let seconds = db::get_aggregation_timezone(conn)?;
+        db::recalculate_dates(conn, seconds)?;
+    }
+
+    Ok(json!({
+        "tables": tables,
+        "auxiliary_tables": auxiliary,
+        "pruned_below_height": (pruned_below > 0).then_some(pruned_below),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge;
+    use crate::db::test_utils::{stats_db, TEST_HEIGHTS};
+    use crate::stats::StatsTable;
+    use crate::{db, gen_csv};
+    use diesel::{sql_query, RunQueryDsl};
+
+    #[test]
+    fn test_merge() {
+        let (_ours_dir, mut ours) = stats_db("merge-ours-test", &[361582, 739990]);
+        let (theirs_dir, mut theirs) = stats_db("merge-theirs-test", &[739990, 888395]);
+        sql_query("UPDATE tx_stats SET tx_version_1 = 0 WHERE height = 739990")
+            .execute(&mut theirs)
+            .unwrap();
+        drop(theirs);
+
+        let report = merge(&mut ours, &theirs_dir.path().join("stats.db")).unwrap();
+
+        for table in StatsTable::ALL {
+            assert_eq!(report["tables"][table.table_name()]["filled"], 1);
+            assert_eq!(report["tables"][table.table_name()]["compared"], 1);
+        }
+        let differences = &report["tables"]["tx_stats"]["differences"];
+        assert_eq!(differences.as_object().unwrap().len(), 1);
+        assert_eq!(differences["tx_version_1"]["first_heights"][0], 739990);
+        assert_eq!(db::get_db_block_height(&mut ours).unwrap(), Some(888395));
+    }
+
+    #[test]
+    fn test_merge_after_prune() {
+        let (_ours_dir, mut ours) = stats_db("merge-prune-ours-test", &TEST_HEIGHTS);
+        let before = db::column_sum_and_avg_by_date(&mut ours, "transactions", "block_stats");
+        db::prune_below(&mut ours, 888395, &gen_csv::metric_columns()).unwrap();
+        let (theirs_dir, theirs) = stats_db("merge-prune-theirs-test", &[361582, 739990]);
+        drop(theirs);
+
+        let report = merge(&mut ours, &theirs_dir.path().join("stats.db")).unwrap();
+        let after = db::column_sum_and_avg_by_date(&mut ours, "transactions", "block_stats");
+
+        assert_eq!(report["pruned_below_height"], 888395);
+        for table in StatsTable::ALL {
+            assert_eq!(report["tables"][table.table_name()]["filled"], 0);
+        }
+        assert_eq!(before.len(), after.len());
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert_eq!(before.date, after.date);
+            assert_eq!(before.sum, after.sum);
+            assert_eq!(before.avg, after.avg);
+        }
+    }
+}
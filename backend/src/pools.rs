@@ -0,0 +1,149 @@
+use bitcoin::Network;
+use bitcoin_pool_identification::{default_data, parse_json, Pool};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::{error, fmt, fs, io};
+
+/// The upstream mining pool dataset. The copy vendored at build time is used
+/// unless --pool-data-refresh-hours is set.
+const POOL_LIST_URL: &str =
+    "https://raw.githubusercontent.com/bitcoin-data/mining-pools/generated/pool-list.json";
+
+static POOLS: OnceLock<Vec<Pool>> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum PoolsError {
+    MinReq(minreq::Error),
+    Http(i32, String),
+    Json(serde_json::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for PoolsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolsError::MinReq(e) => write!(f, "MinReq HTTP GET request error: {:?}", e),
+            PoolsError::Http(code, msg) => write!(f, "HTTP error: {} {}", code, msg),
+            PoolsError::Json(e) => write!(f, "Invalid pool list: {}", e),
+            PoolsError::Io(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+impl error::Error for PoolsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PoolsError::MinReq(ref e) => Some(e),
+            PoolsError::Http(_, _) => None,
+            PoolsError::Json(ref e) => Some(e),
+            PoolsError::Io(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<minreq::Error> for PoolsError {
+    fn from(e: minreq::Error) -> Self {
+        PoolsError::MinReq(e)
+    }
+}
+
+impl From<serde_json::Error> for PoolsError {
+    fn from(e: serde_json::Error) -> Self {
+        PoolsError::Json(e)
+    }
+}
+
+impl From<io::Error> for PoolsError {
+    fn from(e: io::Error) -> Self {
+        PoolsError::Io(e)
+    }
+}
+
+/// The pool data used for pool identification: the data loaded with
+/// [load_pool_data] or, if none was loaded, the vendored copy.
+pub fn pools() -> &'static [Pool] {
+    POOLS.get_or_init(|| default_data(Network::Bitcoin))
+}
+
+/// Disables the pool identification for the rest of the process, e.g. on test
+/// networks where the mainnet pool list doesn't apply. All blocks get the
+/// pool id 0.
+pub fn disable_pool_identification() {
+    if POOLS.set(vec![]).is_err() && !pools().is_empty() {
+        warn!("Pool data was already in use, pool identification stays enabled");
+    }
+}
+
+/// The pool list is cached next to the database as `<database_path>.pools.json`.
+fn cache_path(database_path: &Path) -> PathBuf {
+    let mut path = database_path.as_os_str().to_owned();
+    path.push(".pools.json");
+    PathBuf::from(path)
+}
+
+fn fetch_pool_list() -> Result<String, PoolsError> {
+    let response = minreq::get(POOL_LIST_URL).with_timeout(30).send()?;
+    if response.status_code != 200 {
+        return Err(PoolsError::Http(
+            response.status_code,
+            response.reason_phrase,
+        ));
+    }
+    Ok(response.as_str()?.to_string())
+}
+
+/// Reads the cached pool list if it's younger than `max_age`.
+fn read_cache(path: &Path, max_age: Duration) -> Option<Vec<Pool>> {
+    let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > max_age {
+        return None;
+    }
+    parse_json(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn refresh_cache(path: &Path) -> Result<Vec<Pool>, PoolsError> {
+    let json = fetch_pool_list()?;
+    // only cache a list that can be parsed
+    let pools = parse_json(&json)?;
+    fs::write(path, json)?;
+    Ok(pools)
+}
+
+/// Loads the upstream pool list, so that newly added pools are identified
+/// without recompiling, and uses it for the rest of the process. The list is
+/// fetched again once the cached copy is older than `refresh_hours`. If the
+/// list can't be fetched, a stale cached copy or the vendored copy is used.
+pub fn load_pool_data(database_path: &Path, refresh_hours: u64) {
+    let path = cache_path(database_path);
+    let pools = match read_cache(&path, Duration::from_secs(refresh_hours * 60 * 60)) {
+        Some(pools) => pools,
+        None => match refresh_cache(&path) {
+            Ok(pools) => {
+                info!("Fetched {} pools from {}", pools.len(), POOL_LIST_URL);
+                pools
+            }
+            Err(e) => match read_cache(&path, Duration::MAX) {
+                Some(pools) => {
+                    warn!(
+                        "Could not refresh the pool list, using the stale copy in {}: {}",
+                        path.display(),
+                        e
+                    );
+                    pools
+                }
+                None => {
+                    warn!(
+                        "Could not fetch the pool list, using the vendored copy: {}",
+                        e
+                    );
+                    default_data(Network::Bitcoin)
+                }
+            },
+        },
+    };
+    if POOLS.set(pools).is_err() {
+        warn!("Pool data was already in use, keeping the previous copy");
+    }
+}
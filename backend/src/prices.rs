@@ -0,0 +1,140 @@
+use crate::{db, MainError};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use log::info;
+use std::sync::{Arc, Mutex};
+use std::{error, fmt};
+
+#[derive(Debug)]
+pub enum PricesError {
+    MinReq(minreq::Error),
+    Http(i32, String),
+    /// line number (starting at 1) and the invalid line
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for PricesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PricesError::MinReq(e) => write!(f, "MinReq HTTP GET request error: {:?}", e),
+            PricesError::Http(code, msg) => write!(f, "HTTP error: {} {}", code, msg),
+            PricesError::InvalidLine(number, line) => {
+                write!(f, "Invalid price on line {}: '{}'", number, line)
+            }
+        }
+    }
+}
+
+impl error::Error for PricesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PricesError::MinReq(ref e) => Some(e),
+            PricesError::Http(_, _) => None,
+            PricesError::InvalidLine(_, _) => None,
+        }
+    }
+}
+
+impl From<minreq::Error> for PricesError {
+    fn from(e: minreq::Error) -> Self {
+        PricesError::MinReq(e)
+    }
+}
+
+/// The daily BTC price in USD.
+#[derive(Insertable, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::prices)]
+pub struct Price {
+    /// YYYY-MM-DD, as used in the date columns of the stats tables
+    pub date: String,
+    pub usd: f64,
+}
+
+/// Parses `date,usd` rows with the date as `YYYY-MM-DD`. The first line is
+/// a header and skipped. Empty lines are ignored.
+fn parse_prices_csv(content: &str) -> Result<Vec<Price>, PricesError> {
+    let mut prices = Vec::new();
+    for (index, line) in content.lines().enumerate().skip(1) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || PricesError::InvalidLine(index + 1, line.to_string());
+        let (date, usd) = line.split_once(',').ok_or_else(invalid)?;
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| invalid())?;
+        let usd: f64 = usd.trim().parse().map_err(|_| invalid())?;
+        if !usd.is_finite() || usd < 0.0 {
+            return Err(invalid());
+        }
+        prices.push(Price {
+            date: date.format("%Y-%m-%d").to_string(),
+            usd,
+        });
+    }
+    Ok(prices)
+}
+
+fn fetch_prices_csv(url: &str) -> Result<String, PricesError> {
+    let response = minreq::get(url).send()?;
+    if response.status_code != 200 {
+        return Err(PricesError::Http(
+            response.status_code,
+            response.reason_phrase,
+        ));
+    }
+    Ok(response.as_str()?.to_string())
+}
+
+/// Imports daily prices into the prices table. The source is either a path
+/// to a CSV file or a HTTP(S) URL returning the CSV. Existing prices for the
+/// same dates are replaced.
+pub fn import_prices(
+    source: &str,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<(), MainError> {
+    let content = if source.starts_with("http://") || source.starts_with("https://") {
+        fetch_prices_csv(source)?
+    } else {
+        std::fs::read_to_string(source)?
+    };
+    let prices = parse_prices_csv(&content)?;
+
+    let mut conn = connection.lock().unwrap();
+    db::insert_prices(&mut conn, &prices)?;
+    info!("Imported {} daily prices from {}", prices.len(), source);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_prices_csv, Price, PricesError};
+
+    #[test]
+    fn test_parse_prices_csv() {
+        let prices = parse_prices_csv("date,usd\n2024-04-20,64994.44\n\n2024-04-21, 64926.64\n")
+            .expect("valid prices csv");
+        assert_eq!(
+            prices,
+            vec![
+                Price {
+                    date: "2024-04-20".to_string(),
+                    usd: 64994.44,
+                },
+                Price {
+                    date: "2024-04-21".to_string(),
+                    usd: 64926.64,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            parse_prices_csv("date,usd\n2024-04-20,64994.44\n20.04.2024,1\n"),
+            Err(PricesError::InvalidLine(3, _))
+        ));
+        assert!(matches!(
+            parse_prices_csv("date,usd\n2024-04-20\n"),
+            Err(PricesError::InvalidLine(2, _))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{db, gen_csv, MainError};
+use chrono::{Months, NaiveDate, Utc};
+use diesel::SqliteConnection;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+/// The date `years` years before `today`, the first date that is kept.
+fn cutoff_date(today: NaiveDate, years: u32) -> NaiveDate {
+    today
+        .checked_sub_months(Months::new(years * 12))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+/// Drops the per-block rows of the stats tables of blocks older than `years`
+/// years, for deployments that only need recent per-block data. The daily
+/// aggregates of the metrics are kept, so the metric CSV files still cover
+/// the pruned dates, but the other exports only cover the remaining blocks.
+/// The pruned heights aren't synced again and, as the dates of the pruned
+/// blocks can't be recalculated, the aggregation timezone can't be changed
+/// anymore. Only reports what would be pruned unless `confirm` is set.
+/// Returns a JSON report.
+pub fn prune(conn: &mut SqliteConnection, years: u32, confirm: bool) -> Result<Value, MainError> {
+    prune_before(conn, cutoff_date(Utc::now().date_naive(), years), confirm)
+}
+
+fn prune_before(
+    conn: &mut SqliteConnection,
+    cutoff: NaiveDate,
+    confirm: bool,
+) -> Result<Value, MainError> {
+    let cutoff = cutoff.format("%Y-%m-%d").to_string();
+    let range = db::prune_range(conn, &cutoff)?;
+    let mut report = json!({
+        "cutoff_date": cutoff,
+        "below_height": range.below_height,
+        "blocks": range.blocks,
+        "pruned": false,
+    });
+    let below_height = match range.below_height {
+        Some(below_height) if range.blocks > 0 => below_height,
+        _ => {
+            info!("No blocks before {} to prune", cutoff);
+            return Ok(report);
+        }
+    };
+    if !confirm {
+        warn!(
+            "Would prune {} blocks below height {} (before {}). Run with --confirm to prune them.",
+            range.blocks, below_height, cutoff
+        );
+        return Ok(report);
+    }
+
+    info!(
+        "Pruning {} blocks below height {} (before {})",
+        range.blocks, below_height, cutoff
+    );
+    let deleted = db::prune_below(conn, below_height, &gen_csv::metric_columns())?;
+    db::vacuum(conn)?;
+    report["pruned"] = json!(true);
+    report["deleted_rows"] = json!(deleted);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cutoff_date, prune_before};
+    use crate::db;
+    use crate::db::test_utils::{stats_db, TEST_HEIGHTS};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_prune() {
+        let today = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(
+            cutoff_date(today, 1),
+            NaiveDate::from_ymd_opt(2023, 2, 28).unwrap()
+        );
+
+        let (_dir, mut conn) = stats_db("prune-test", &TEST_HEIGHTS);
+        let before = db::column_sum_and_avg_by_date(&mut conn, "transactions", "block_stats");
+
+        // the blocks are from 2015, 2022 and 2025
+        let cutoff = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let report = prune_before(&mut conn, NaiveDate::MIN, true).unwrap();
+        assert_eq!(report["blocks"], 0);
+        let report = prune_before(&mut conn, cutoff, false).unwrap();
+        assert_eq!(report["below_height"], 888395);
+        assert_eq!(report["blocks"], 2);
+        assert_eq!(report["pruned"], false);
+        let report = prune_before(&mut conn, cutoff, true).unwrap();
+        assert_eq!(report["pruned"], true);
+        assert_eq!(report["deleted_rows"]["tx_stats"], 2);
+
+        let after = db::column_sum_and_avg_by_date(&mut conn, "transactions", "block_stats");
+        let dates = db::date_column(&mut conn);
+        let pruned_below = db::pruned_below_height(&mut conn).unwrap();
+        let pruned_timezone = db::pruned_aggregation_timezone(&mut conn).unwrap();
+
+        assert_eq!(pruned_below, Some(888395));
+        assert_eq!(pruned_timezone, Some(0));
+        assert_eq!(dates.len(), 3);
+        assert_eq!(before.len(), after.len());
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert_eq!(before.date, after.date);
+            assert_eq!(before.sum, after.sum);
+            assert_eq!(before.avg, after.avg);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+use crate::manifest::MANIFEST_FILENAME;
+use crate::stats::{StatsColumn, StatsTable};
+use crate::{db, write_csv_files, MainError};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float32Array, Int32Array, Int64Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Network;
+use chrono::{SecondsFormat, Utc};
+use diesel::sql_types::{BigInt, Bool, Float, Integer, Text};
+use diesel::{QueryableByName, SqliteConnection};
+use log::info;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const DATA_DIR: &str = "data";
+// The subdirectory of the data directory the stats tables are written to as
+// Parquet files.
+const PARQUET_DIR: &str = "parquet";
+// Number of heights per Parquet row group. The rows of a row group are read
+// from the database at once.
+const ROW_GROUP_HEIGHTS: i64 = 100_000;
+const CATALOG_FILENAME: &str = "catalog.json";
+const CHECKSUMS_FILENAME: &str = "SHA256SUMS";
+const LICENSE_FILENAME: &str = "LICENSE";
+
+#[derive(Serialize)]
+struct CatalogColumn {
+    name: &'static str,
+    sql_type: &'static str,
+    description: String,
+}
+
+#[derive(Serialize)]
+struct CatalogTable {
+    name: &'static str,
+    version: i32,
+    columns: Vec<CatalogColumn>,
+}
+
+/// Describes the stats tables the files of a release are generated from.
+#[derive(Serialize)]
+struct Catalog {
+    tables: Vec<CatalogTable>,
+}
+
+#[derive(Serialize)]
+struct ReleaseFile {
+    /// Path relative to the release directory.
+    path: String,
+    bytes: u64,
+    sha256: String,
+}
+
+/// Describes a release bundle. The schema version is the last database
+/// migration, the stats versions tell how the rows were calculated.
+#[derive(Serialize)]
+struct ReleaseManifest {
+    release: String,
+    network: String,
+    software_version: &'static str,
+    schema_version: Option<String>,
+    stats_versions: BTreeMap<String, i32>,
+    /// RFC 3339 timestamp of when the release was generated.
+    generated_at: String,
+    min_height: Option<i64>,
+    max_height: Option<i64>,
+    first_date: Option<String>,
+    last_date: Option<String>,
+    /// SPDX identifier of the data license.
+    license: String,
+    files: Vec<ReleaseFile>,
+}
+
+fn catalog() -> Catalog {
+    Catalog {
+        tables: StatsTable::ALL
+            .iter()
+            .map(|table| CatalogTable {
+                name: table.table_name(),
+                version: table.version(),
+                columns: table
+                    .columns()
+                    .iter()
+                    .map(|column| CatalogColumn {
+                        name: column.name,
+                        sql_type: column.sql_type,
+                        description: column.description(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[derive(QueryableByName)]
+struct IntegerValue {
+    #[diesel(sql_type = Integer)]
+    value: i32,
+}
+
+#[derive(QueryableByName)]
+struct BigIntValue {
+    #[diesel(sql_type = BigInt)]
+    value: i64,
+}
+
+#[derive(QueryableByName)]
+struct FloatValue {
+    #[diesel(sql_type = Float)]
+    value: f32,
+}
+
+#[derive(QueryableByName)]
+struct BoolValue {
+    #[diesel(sql_type = Bool)]
+    value: bool,
+}
+
+#[derive(QueryableByName)]
+struct TextValue {
+    #[diesel(sql_type = Text)]
+    value: String,
+}
+
+fn arrow_type(column: &StatsColumn) -> DataType {
+    match column.sql_type {
+        "Integer" => DataType::Int32,
+        "BigInt" => DataType::Int64,
+        "Float" => DataType::Float32,
+        "Bool" => DataType::Boolean,
+        _ => DataType::Utf8,
+    }
+}
+
+fn arrow_array(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    column: &StatsColumn,
+    heights: std::ops::Range<i64>,
+) -> Result<ArrayRef, MainError> {
+    let name = column.name;
+    Ok(match column.sql_type {
+        "Integer" => Arc::new(Int32Array::from_iter_values(
+            db::column_values::<IntegerValue>(conn, table, name, heights)?
+                .into_iter()
+                .map(|v| v.value),
+        )),
+        "BigInt" => Arc::new(Int64Array::from_iter_values(
+            db::column_values::<BigIntValue>(conn, table, name, heights)?
+                .into_iter()
+                .map(|v| v.value),
+        )),
+        "Float" => Arc::new(Float32Array::from_iter_values(
+            db::column_values::<FloatValue>(conn, table, name, heights)?
+                .into_iter()
+                .map(|v| v.value),
+        )),
+        "Bool" => Arc::new(BooleanArray::from_iter(
+            db::column_values::<BoolValue>(conn, table, name, heights)?
+                .into_iter()
+                .map(|v| Some(v.value)),
+        )),
+        _ => Arc::new(StringArray::from_iter_values(
+            db::column_values::<TextValue>(conn, table, name, heights)?
+                .into_iter()
+                .map(|v| v.value),
+        )),
+    })
+}
+
+/// Writes the rows of a stats table, ordered by height, to a Snappy
+/// compressed Parquet file with one row group per 100k heights. Returns the
+/// number of rows.
+fn write_parquet(
+    conn: &mut SqliteConnection,
+    table: StatsTable,
+    path: &Path,
+) -> Result<i64, MainError> {
+    let schema = Arc::new(Schema::new(
+        table
+            .columns()
+            .iter()
+            .map(|column| Field::new(column.name, arrow_type(column), false))
+            .collect::<Vec<Field>>(),
+    ));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer =
+        ArrowWriter::try_new(fs::File::create(path)?, schema.clone(), Some(properties))?;
+
+    let coverage = db::table_coverage(conn, table)?;
+    if let (Some(min_height), Some(max_height)) = (coverage.min_height, coverage.max_height) {
+        let mut start = min_height;
+        while start <= max_height {
+            let heights = start..start + ROW_GROUP_HEIGHTS;
+            let columns = table
+                .columns()
+                .iter()
+                .map(|column| arrow_array(conn, table, column, heights.clone()))
+                .collect::<Result<Vec<ArrayRef>, MainError>>()?;
+            let batch = RecordBatch::try_new(schema.clone(), columns)?;
+            if batch.num_rows() > 0 {
+                writer.write(&batch)?;
+                writer.flush()?;
+            }
+            start = heights.end;
+        }
+    }
+    writer.close()?;
+    Ok(coverage.rows)
+}
+
+/// Writes each stats table to `<data_dir>/parquet/<table>.parquet`.
+fn write_parquet_files(
+    data_dir: &Path,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<(), MainError> {
+    let dir = data_dir.join(PARQUET_DIR);
+    fs::create_dir_all(&dir)?;
+    let mut conn = connection.lock().unwrap();
+    for table in StatsTable::ALL {
+        let path = dir.join(format!("{}.parquet", table.table_name()));
+        let rows = write_parquet(&mut conn, table, &path)?;
+        info!(
+            "Wrote {} rows of {} to {}",
+            rows,
+            table.table_name(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// All files below `dir` as paths relative to `dir` with `/` as separator,
+/// sorted.
+fn relative_files(dir: &Path) -> Result<Vec<String>, MainError> {
+    let mut files = vec![];
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative) = dirs.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                let components: Vec<String> = path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                files.push(components.join("/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The content of a `sha256sum` compatible checksum file, which can be verified with
+/// `sha256sum -c SHA256SUMS`.
+fn checksums(files: &[ReleaseFile]) -> String {
+    files
+        .iter()
+        .map(|file| format!("{}  {}\n", file.sha256, file.path))
+        .collect()
+}
+
+fn license_text(license: &str) -> String {
+    format!(
+        "The data in this release is licensed under {}.\nSee https://spdx.org/licenses/{}.html for the license text.\n",
+        license, license
+    )
+}
+
+/// Writes a versioned release bundle of the database to
+/// `<output>/mainnet-observer-<network>-<release>/`, meant to be uploaded to a
+/// data repository: the CSV export and the stats tables as Parquet files in
+/// `data/`, a catalog of the stats tables,
+/// the license, a manifest with the schema and stats versions and a checksum
+/// file covering all files. The data license is given as an SPDX identifier,
+/// with `license_file` the license text is copied from the file. Fails if the
+/// release already exists. Returns the path of the release.
+pub fn publish(
+    output: &Path,
+    release: &str,
+    network: Network,
+    license: &str,
+    license_file: Option<&Path>,
+    connection: Arc<Mutex<SqliteConnection>>,
+) -> Result<PathBuf, MainError> {
+    let release_path = output.join(format!("mainnet-observer-{}-{}", network, release));
+    fs::create_dir_all(output)?;
+    fs::create_dir(&release_path)?;
+    info!(
+        "Publishing release {} to {}",
+        release,
+        release_path.display()
+    );
+
+    write_csv_files(&release_path.join(DATA_DIR), connection.clone(), None, None)?;
+    write_parquet_files(&release_path.join(DATA_DIR), connection.clone())?;
+
+    let file = fs::File::create(release_path.join(CATALOG_FILENAME))?;
+    serde_json::to_writer_pretty(file, &catalog())?;
+
+    let license_text = match license_file {
+        Some(path) => fs::read_to_string(path)?,
+        None => license_text(license),
+    };
+    fs::write(release_path.join(LICENSE_FILENAME), license_text)?;
+
+    let mut files = vec![];
+    for path in relative_files(&release_path)? {
+        let content = fs::read(release_path.join(&path))?;
+        files.push(ReleaseFile {
+            path,
+            bytes: content.len() as u64,
+            sha256: sha256::Hash::hash(&content).to_string(),
+        });
+    }
+    fs::write(release_path.join(CHECKSUMS_FILENAME), checksums(&files))?;
+
+    let (range, schema_version) = {
+        let mut conn = connection.lock().unwrap();
+        (
+            db::height_and_date_range(&mut conn)?,
+            db::schema_version(&mut conn)?,
+        )
+    };
+    let manifest = ReleaseManifest {
+        release: release.to_string(),
+        network: network.to_string(),
+        software_version: env!("CARGO_PKG_VERSION"),
+        schema_version,
+        stats_versions: StatsTable::versions(),
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        min_height: range.min_height,
+        max_height: range.max_height,
+        first_date: range.first_date,
+        last_date: range.last_date,
+        license: license.to_string(),
+        files,
+    };
+    // the manifest isn't covered by the checksums, as it contains them
+    let file = fs::File::create(release_path.join(MANIFEST_FILENAME))?;
+    serde_json::to_writer_pretty(file, &manifest)?;
+
+    Ok(release_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{catalog, checksums, relative_files, write_parquet, ReleaseFile};
+    use crate::db::test_utils::{stats_db, TempDir, TEST_HEIGHTS};
+    use crate::stats::StatsTable;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int64Type;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+
+    #[test]
+    fn test_catalog_and_checksums() {
+        let catalog = catalog();
+        assert_eq!(catalog.tables.len(), StatsTable::ALL.len());
+        for (table, catalog_table) in StatsTable::ALL.iter().zip(catalog.tables.iter()) {
+            assert_eq!(table.columns().len(), catalog_table.columns.len());
+        }
+
+        let dir = TempDir::new("publish-test");
+        std::fs::create_dir_all(dir.path().join("data/downsampled")).unwrap();
+        std::fs::write(dir.path().join("LICENSE"), "").unwrap();
+        std::fs::write(dir.path().join("data/a.csv"), "").unwrap();
+        std::fs::write(dir.path().join("data/downsampled/b.csv"), "").unwrap();
+        let files = relative_files(dir.path()).unwrap();
+        assert_eq!(
+            files,
+            vec!["LICENSE", "data/a.csv", "data/downsampled/b.csv"]
+        );
+
+        let files = vec![ReleaseFile {
+            path: "data/a.csv".to_string(),
+            bytes: 0,
+            sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string(),
+        }];
+        assert_eq!(
+            checksums(&files),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  data/a.csv\n"
+        );
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let (dir, mut conn) = stats_db("parquet-test", &TEST_HEIGHTS);
+
+        let path = dir.path().join("block_stats.parquet");
+        let rows = write_parquet(&mut conn, StatsTable::Block, &path).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        let row_groups = builder.metadata().num_row_groups();
+        let batches: Vec<_> = builder.build().unwrap().map(|b| b.unwrap()).collect();
+
+        assert_eq!(rows, 3);
+        // the heights are more than 100k apart, one row group each
+        assert_eq!(row_groups, 3);
+        let heights: Vec<i64> = batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        assert_eq!(heights, TEST_HEIGHTS.map(|height| height as i64));
+        assert_eq!(batches[0].num_columns(), StatsTable::Block.columns().len());
+    }
+}
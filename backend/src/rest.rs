@@ -1,20 +1,241 @@
+use bitcoin::consensus::encode::{self, Decodable, VarInt};
+use bitcoin::opcodes::all::{OP_CHECKMULTISIG, OP_CHECKSIG, OP_PUSHNUM_16, OP_RETURN};
+use bitcoin::opcodes::{Class, ClassifyContext};
+use bitcoin::script::Instruction;
 use bitcoin::{
     self, absolute::LockTime, address::NetworkUnchecked, block, Address, Amount, BlockHash,
     ScriptBuf, Sequence, TxMerkleNode, Weight, Witness,
 };
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{error, fmt};
 
+/// The node implementation serving the REST interface. Bitcoin Knots has the
+/// same REST interface as Bitcoin Core, but different default policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NodeImplementation {
+    BitcoinCore,
+    BitcoinKnots,
+}
+
+impl NodeImplementation {
+    /// The name as recorded in the sync_runs table.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeImplementation::BitcoinCore => "bitcoin-core",
+            NodeImplementation::BitcoinKnots => "bitcoin-knots",
+        }
+    }
+}
+
+/// A node the blocks, with the prevouts of their inputs, are fetched from
+/// during a sync. The implementation it reports is recorded with the sync run.
+pub trait BlockSource: Send + Sync {
+    fn implementation(&self) -> NodeImplementation;
+
+    /// The host and port of the node, e.g. for log messages.
+    fn endpoint(&self) -> String;
+
+    fn chain_info(&self) -> Result<ChainInfo, RestError>;
+
+    fn mempool_info(&self) -> Result<MempoolInfo, RestError>;
+
+    /// Fetches the block at the given height of the node's active chain. The
+    /// heights of the prevouts and whether they were generated are only set
+    /// with `prevout_heights`, as not all sources can provide them cheaply.
+    fn block_at_height(&self, height: u64, prevout_heights: bool) -> Result<Block, RestError>;
+
+    /// Statistics about the requests made to the node so far.
+    fn fetch_stats(&self) -> FetchStatsSnapshot;
+}
+
+/// Connects to the REST interface of the node the blocks are fetched from.
+pub fn block_source(
+    implementation: NodeImplementation,
+    host: &str,
+    port: u16,
+    max_requests_per_second: Option<u32>,
+) -> Arc<dyn BlockSource> {
+    Arc::new(
+        RestClient::new(host, port, max_requests_per_second).with_implementation(implementation),
+    )
+}
+
+/// A client for the Bitcoin Core REST interface. Requests can optionally be
+/// rate limited and are counted in [FetchStatsSnapshot]s.
 pub struct RestClient {
     host: String,
     port: u16,
+    implementation: NodeImplementation,
+    rate_limiter: Option<RateLimiter>,
+    fetch_stats: FetchStats,
+    /// set once the node returned a 404 for the spent outputs of a block,
+    /// which nodes before v30 don't serve
+    no_spent_txouts: AtomicBool,
+}
+
+/// Upper bounds (exclusive) of the request latency histogram buckets in
+/// milliseconds. Requests taking longer end up in an additional last bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 7] = [10, 50, 100, 250, 500, 1000, 5000];
+
+/// Statistics about the requests made by a RestClient. Updated concurrently by
+/// all threads using the client.
+#[derive(Default)]
+struct FetchStats {
+    requests: AtomicU64,
+    failed_requests: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    latency_sum_ms: AtomicU64,
+    latency_histogram: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl FetchStats {
+    /// Records a request that downloaded `bytes` and took `latency` until the
+    /// whole response was read.
+    fn record(&self, latency: Duration, bytes: u64, failed: bool) {
+        let latency_ms = latency.as_millis() as u64;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_ms.fetch_add(latency_ms, Ordering::Relaxed);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&upper| latency_ms < upper)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency_histogram[bucket].fetch_add(1, Ordering::Relaxed);
+        self.bytes_downloaded.fetch_add(bytes, Ordering::Relaxed);
+        if failed {
+            self.failed_requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self) -> FetchStatsSnapshot {
+        FetchStatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            failed_requests: self.failed_requests.load(Ordering::Relaxed),
+            bytes_downloaded: self.bytes_downloaded.load(Ordering::Relaxed),
+            latency_sum_ms: self.latency_sum_ms.load(Ordering::Relaxed),
+            latency_histogram: std::array::from_fn(|i| {
+                self.latency_histogram[i].load(Ordering::Relaxed)
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FetchStatsSnapshot {
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub bytes_downloaded: u64,
+    pub latency_sum_ms: u64,
+    /// Number of requests per LATENCY_BUCKETS_MS bucket.
+    pub latency_histogram: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl fmt::Display for FetchStatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} requests ({} failed), {:.2} MiB downloaded, average latency {} ms, latency histogram:",
+            self.requests,
+            self.failed_requests,
+            self.bytes_downloaded as f64 / (1024.0 * 1024.0),
+            self.latency_sum_ms.checked_div(self.requests).unwrap_or(0),
+        )?;
+        let mut lower = 0;
+        for (upper, count) in LATENCY_BUCKETS_MS.iter().zip(self.latency_histogram.iter()) {
+            write!(f, " {}-{}ms: {},", lower, upper, count)?;
+            lower = *upper;
+        }
+        write!(
+            f,
+            " {}ms+: {}",
+            lower,
+            self.latency_histogram[LATENCY_BUCKETS_MS.len()]
+        )
+    }
+}
+
+/// A token bucket limiting the number of requests per second. It's shared
+/// between all threads using the RestClient. Up to one second worth of
+/// requests can be made in a burst.
+struct RateLimiter {
+    requests_per_second: f64,
+    // available tokens and the time they were last refilled
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: u32) -> RateLimiter {
+        let requests_per_second = requests_per_second as f64;
+        RateLimiter {
+            requests_per_second,
+            bucket: Mutex::new((requests_per_second, Instant::now())),
+        }
+    }
+
+    /// Blocks until a request can be made.
+    fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let (tokens, last_refill) = *bucket;
+                let now = Instant::now();
+                let tokens = (tokens
+                    + now.duration_since(last_refill).as_secs_f64() * self.requests_per_second)
+                    .min(self.requests_per_second.max(1.0));
+                if tokens >= 1.0 {
+                    *bucket = (tokens - 1.0, now);
+                    return;
+                }
+                *bucket = (tokens, now);
+                (1.0 - tokens) / self.requests_per_second
+            };
+            thread::sleep(Duration::from_secs_f64(wait));
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct ChainInfo {
+    pub chain: String,
     pub initialblockdownload: bool,
     pub verificationprogress: f32,
     pub blocks: u64,
+    #[serde(default)]
+    pub pruned: bool,
+    /// the lowest height with a stored block, only set on pruned nodes
+    #[serde(default)]
+    pub pruneheight: Option<u64>,
+    /// the challenge blocks have to satisfy, only set on signet
+    #[serde(default)]
+    pub signet_challenge: Option<bitcoin::ScriptBuf>,
+}
+
+/// The mempool and relay policy of the node as returned by the
+/// `/rest/mempool/info.json` endpoint. The feerates are in BTC/kvB.
+#[derive(Deserialize)]
+pub struct MempoolInfo {
+    pub maxmempool: u64,
+    pub mempoolminfee: f64,
+    pub minrelaytxfee: f64,
+    /// not returned by nodes before v25
+    #[serde(default)]
+    pub incrementalrelayfee: Option<f64>,
+    /// not returned by nodes before v24
+    #[serde(default)]
+    pub fullrbf: Option<bool>,
+    /// not returned by nodes before v29
+    #[serde(default)]
+    pub maxdatacarriersize: Option<u64>,
+    /// not returned by nodes before v29
+    #[serde(default)]
+    pub permitbaremultisig: Option<bool>,
 }
 
 pub mod serde_hex {
@@ -34,7 +255,7 @@ pub struct ScriptSig {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Deserialize)]
+#[derive(Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ScriptPubkeyType {
     Nonstandard,
@@ -50,6 +271,119 @@ pub enum ScriptPubkeyType {
     Anchor,
 }
 
+impl ScriptPubkeyType {
+    /// The type name as used by Bitcoin Core.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptPubkeyType::Nonstandard => "nonstandard",
+            ScriptPubkeyType::Pubkey => "pubkey",
+            ScriptPubkeyType::PubkeyHash => "pubkeyhash",
+            ScriptPubkeyType::ScriptHash => "scripthash",
+            ScriptPubkeyType::MultiSig => "multisig",
+            ScriptPubkeyType::NullData => "nulldata",
+            ScriptPubkeyType::Witness_v0_KeyHash => "witness_v0_keyhash",
+            ScriptPubkeyType::Witness_v0_ScriptHash => "witness_v0_scripthash",
+            ScriptPubkeyType::Witness_v1_Taproot => "witness_v1_taproot",
+            ScriptPubkeyType::Witness_Unknown => "witness_unknown",
+            ScriptPubkeyType::Anchor => "anchor",
+        }
+    }
+
+    /// Classifies the script like Bitcoin Core's `Solver`, for blocks that
+    /// aren't fetched as JSON.
+    pub fn of(script: &bitcoin::Script) -> ScriptPubkeyType {
+        let bytes = script.as_bytes();
+        if script.is_p2sh() {
+            return ScriptPubkeyType::ScriptHash;
+        }
+        if let Some(version) = script.witness_version() {
+            let program = &bytes[2..];
+            return match (version.to_num(), program.len()) {
+                (0, 20) => ScriptPubkeyType::Witness_v0_KeyHash,
+                (0, 32) => ScriptPubkeyType::Witness_v0_ScriptHash,
+                (0, _) => ScriptPubkeyType::Nonstandard,
+                (1, 32) => ScriptPubkeyType::Witness_v1_Taproot,
+                (1, _) if program == [0x4e, 0x73] => ScriptPubkeyType::Anchor,
+                _ => ScriptPubkeyType::Witness_Unknown,
+            };
+        }
+        if bytes.first() == Some(&OP_RETURN.to_u8())
+            && bitcoin::Script::from_bytes(&bytes[1..])
+                .instructions()
+                .all(|i| match i {
+                    Ok(Instruction::PushBytes(_)) => true,
+                    Ok(Instruction::Op(op)) => op.to_u8() <= OP_PUSHNUM_16.to_u8(),
+                    Err(_) => false,
+                })
+        {
+            return ScriptPubkeyType::NullData;
+        }
+        if let [len, pubkey @ .., last] = bytes {
+            if *last == OP_CHECKSIG.to_u8()
+                && *len as usize == pubkey.len()
+                && is_valid_pubkey_size(pubkey)
+            {
+                return ScriptPubkeyType::Pubkey;
+            }
+        }
+        if script.is_p2pkh() {
+            return ScriptPubkeyType::PubkeyHash;
+        }
+        if is_multisig(script) {
+            return ScriptPubkeyType::MultiSig;
+        }
+        ScriptPubkeyType::Nonstandard
+    }
+}
+
+/// Like Bitcoin Core's `CPubKey::ValidSize`: the length matches the one the
+/// first byte indicates.
+fn is_valid_pubkey_size(pubkey: &[u8]) -> bool {
+    match pubkey.first() {
+        Some(2 | 3) => pubkey.len() == 33,
+        Some(4 | 6 | 7) => pubkey.len() == 65,
+        _ => false,
+    }
+}
+
+/// Like Bitcoin Core's `MatchMultisig`: `<m> <pubkey>... <n> OP_CHECKMULTISIG`
+/// with 1 <= m <= n <= 20.
+fn is_multisig(script: &bitcoin::Script) -> bool {
+    let number = |instruction: Option<Result<Instruction, _>>| match instruction {
+        Some(Ok(Instruction::Op(op))) => match op.classify(ClassifyContext::Legacy) {
+            Class::PushNum(n) if n >= 1 => Some(n as usize),
+            _ => None,
+        },
+        Some(Ok(Instruction::PushBytes(bytes))) if bytes.len() == 1 => {
+            Some(bytes[0] as usize).filter(|n| *n > 16)
+        }
+        _ => None,
+    };
+    let mut instructions = script.instructions().peekable();
+    let Some(required) = number(instructions.next()) else {
+        return false;
+    };
+    let mut keys = 0;
+    while let Some(Ok(Instruction::PushBytes(bytes))) = instructions.peek() {
+        if !is_valid_pubkey_size(bytes.as_bytes()) {
+            break;
+        }
+        keys += 1;
+        instructions.next();
+    }
+    let Some(total) = number(instructions.next()) else {
+        return false;
+    };
+    required <= total
+        && total <= 20
+        && keys == total
+        && matches!(
+            instructions.next(),
+            Some(Ok(Instruction::Op(OP_CHECKMULTISIG)))
+        )
+        && instructions.next().is_none()
+}
+
 #[derive(Deserialize)]
 pub struct ScriptPubKey {
     #[serde(rename = "hex")]
@@ -61,10 +395,24 @@ pub struct ScriptPubKey {
     pub address: Option<Address<NetworkUnchecked>>,
 }
 
+impl ScriptPubKey {
+    /// The script without the descriptor and address, which need the network.
+    fn from_script(script: ScriptBuf) -> ScriptPubKey {
+        ScriptPubKey {
+            type_: ScriptPubkeyType::of(&script),
+            script,
+            descriptor: None,
+            address: None,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Prevout {
+    /// false if the block was fetched without prevout heights
     pub generated: bool,
+    /// 0 if the block was fetched without prevout heights
     pub height: i64,
     #[serde(with = "bitcoin::amount::serde::as_btc")]
     pub value: Amount,
@@ -120,14 +468,29 @@ pub struct Transaction {
     pub input: Vec<Input>,
     #[serde(rename = "vout")]
     pub output: Vec<Output>,
+    /// the deserialized transaction, set by [Block::parse_transactions]
+    #[serde(skip)]
+    pub parsed: Option<bitcoin::Transaction>,
 }
 
 impl Transaction {
     pub fn is_lock_time_enabled(&self) -> bool {
         self.input.iter().any(|i| i.sequence != Sequence::MAX)
     }
+
+    /// Returns the deserialized transaction. Uses the pre-parsed transaction
+    /// if available and deserializes the raw bytes otherwise.
+    pub fn transaction(&self) -> Result<Cow<'_, bitcoin::Transaction>, encode::Error> {
+        match &self.parsed {
+            Some(tx) => Ok(Cow::Borrowed(tx)),
+            None => Ok(Cow::Owned(bitcoin::consensus::deserialize(&self.raw)?)),
+        }
+    }
 }
 
+/// A block as returned by Bitcoin Core's `/rest/block/<hash>.json` endpoint.
+/// The inputs include their prevouts, which are needed for the input and
+/// feerate stats.
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Block {
@@ -156,6 +519,135 @@ pub struct Block {
     pub previous_block_hash: Option<BlockHash>,
     #[serde(rename = "nextblockhash")]
     pub next_block_hash: Option<BlockHash>,
+    /// set for blocks built by [Block::from_binary], whose prevouts have no
+    /// height and aren't marked as generated
+    #[serde(skip)]
+    pub without_prevout_heights: bool,
+}
+
+impl Block {
+    /// Deserializes all transactions once and drops their raw bytes. Stats
+    /// calculated for the block afterwards don't need to deserialize the
+    /// transactions again and the block takes up less memory.
+    pub fn parse_transactions(&mut self) -> Result<(), encode::Error> {
+        for tx in self.txdata.iter_mut() {
+            if tx.parsed.is_none() {
+                tx.parsed = Some(bitcoin::consensus::deserialize(&tx.raw)?);
+                tx.raw = Vec::new();
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the block from the `.bin` block and the outputs spent by each of
+    /// its transactions. The confirmations, median time, chain work and next
+    /// block aren't part of the binary block and are left empty, as are the
+    /// heights of the prevouts and whether they were generated. The stats
+    /// needing them refuse such a block, see [crate::stats::needs_prevout_heights].
+    pub fn from_binary(
+        height: i64,
+        block: bitcoin::Block,
+        spent: Vec<Vec<bitcoin::TxOut>>,
+    ) -> Result<Block, RestError> {
+        if spent.len() != block.txdata.len() {
+            return Err(encode::Error::ParseFailed("spent outputs don't match the block").into());
+        }
+        let size = block.total_size() as i64;
+        let weight = block.weight();
+        let header = block.header;
+        let n_tx = block.txdata.len() as u32;
+        let mut txdata = Vec::with_capacity(block.txdata.len());
+        for (tx, spent) in block.txdata.into_iter().zip(spent) {
+            let is_coinbase = tx.is_coinbase();
+            if !is_coinbase && spent.len() != tx.input.len() {
+                return Err(
+                    encode::Error::ParseFailed("spent outputs don't match the inputs").into(),
+                );
+            }
+            let fee = (!is_coinbase).then(|| {
+                let spent_value: Amount = spent.iter().map(|o| o.value).sum();
+                let output_value: Amount = tx.output.iter().map(|o| o.value).sum();
+                spent_value - output_value
+            });
+            let input = if is_coinbase {
+                tx.input
+                    .iter()
+                    .map(|i| Input {
+                        sequence: i.sequence,
+                        witness: (!i.witness.is_empty()).then(|| i.witness.clone()),
+                        data: InputData::Coinbase(i.script_sig.to_bytes()),
+                    })
+                    .collect()
+            } else {
+                tx.input
+                    .iter()
+                    .zip(spent)
+                    .map(|(i, prevout)| Input {
+                        sequence: i.sequence,
+                        witness: (!i.witness.is_empty()).then(|| i.witness.clone()),
+                        data: InputData::NonCoinbase {
+                            txid: i.previous_output.txid,
+                            vout: i.previous_output.vout,
+                            script_sig: ScriptSig {
+                                script: i.script_sig.clone(),
+                            },
+                            prevout: Prevout {
+                                generated: false,
+                                height: 0,
+                                value: prevout.value,
+                                script_pub_key: ScriptPubKey::from_script(prevout.script_pubkey),
+                            },
+                        },
+                    })
+                    .collect()
+            };
+            let output = tx
+                .output
+                .iter()
+                .enumerate()
+                .map(|(n, o)| Output {
+                    value: o.value,
+                    n: n as u32,
+                    script_pub_key: ScriptPubKey::from_script(o.script_pubkey.clone()),
+                })
+                .collect();
+            txdata.push(Transaction {
+                raw: Vec::new(),
+                txid: tx.compute_txid(),
+                hash: tx.compute_wtxid(),
+                size: tx.total_size() as u32,
+                vsize: tx.vsize() as u32,
+                weight: tx.weight(),
+                version: tx.version.0 as u32,
+                fee,
+                lock_time: tx.lock_time,
+                input,
+                output,
+                parsed: Some(tx),
+            });
+        }
+        Ok(Block {
+            hash: header.block_hash(),
+            confirmations: 0,
+            size,
+            stripped_size: (weight.to_wu() as i64 - size) / 3,
+            weight,
+            height,
+            version: header.version,
+            merkle_root: header.merkle_root,
+            txdata,
+            time: header.time,
+            median_time: 0,
+            nonce: header.nonce,
+            bits: format!("{:08x}", header.bits.to_consensus()),
+            difficulty: header.difficulty_float(),
+            chain_work: Vec::new(),
+            n_tx,
+            previous_block_hash: (height > 0).then_some(header.prev_blockhash),
+            next_block_hash: None,
+            without_prevout_heights: true,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -198,16 +690,104 @@ impl From<bitcoin::consensus::encode::Error> for RestError {
 }
 
 impl RestClient {
-    pub fn new(host: &str, port: u16) -> RestClient {
+    pub fn new(host: &str, port: u16, max_requests_per_second: Option<u32>) -> RestClient {
         RestClient {
             host: host.to_string(),
             port,
+            implementation: NodeImplementation::BitcoinCore,
+            rate_limiter: max_requests_per_second.map(RateLimiter::new),
+            fetch_stats: FetchStats::default(),
+            no_spent_txouts: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the implementation of the node serving the REST interface, which
+    /// can't be queried over REST. Defaults to Bitcoin Core.
+    pub fn with_implementation(mut self, implementation: NodeImplementation) -> RestClient {
+        self.implementation = implementation;
+        self
+    }
+
+    /// Returns statistics about the requests made with this client so far.
+    pub fn fetch_stats(&self) -> FetchStatsSnapshot {
+        self.fetch_stats.snapshot()
+    }
+
+    fn get(&self, url: String) -> Result<minreq::Response, RestError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire();
+        }
+        let start = Instant::now();
+        let response = minreq::get(url).send();
+        let (bytes, failed) = match &response {
+            Ok(response) => (
+                response.as_bytes().len() as u64,
+                response.status_code != 200,
+            ),
+            Err(_) => (0, true),
+        };
+        self.fetch_stats.record(start.elapsed(), bytes, failed);
+        Ok(response?)
+    }
+
+    /// Like [RestClient::get], but deserializes the response with `read` while
+    /// it's being received instead of buffering the whole body first. For big
+    /// blocks, the JSON body is a multiple of the size of the deserialized
+    /// block.
+    fn get_streamed<T>(
+        &self,
+        url: String,
+        read: impl FnOnce(&mut dyn BufRead) -> Result<T, RestError>,
+    ) -> Result<T, RestError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire();
+        }
+        let start = Instant::now();
+        let response = match minreq::get(url).send_lazy() {
+            Ok(response) => response,
+            Err(e) => {
+                self.fetch_stats.record(start.elapsed(), 0, true);
+                return Err(e.into());
+            }
+        };
+        if !(response.status_code == 200 && response.reason_phrase == "OK") {
+            self.fetch_stats.record(start.elapsed(), 0, true);
+            return Err(RestError::Http(
+                response.status_code,
+                response.reason_phrase,
+            ));
         }
+        let mut reader = CountingReader {
+            inner: response,
+            bytes: 0,
+        };
+        let result = read(&mut BufReader::new(&mut reader));
+        self.fetch_stats
+            .record(start.elapsed(), reader.bytes, result.is_err());
+        result
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, url: String) -> Result<T, RestError> {
+        self.get_streamed(url, |reader| {
+            Ok(serde_json::from_reader(reader).map_err(minreq::Error::SerdeJsonError)?)
+        })
+    }
+
+    /// Like [RestClient::get_json], but for consensus encoded `.bin` responses.
+    fn get_binary<T: Decodable>(&self, url: String) -> Result<T, RestError> {
+        self.get_streamed(url, |reader| {
+            Ok(T::consensus_decode(&mut bitcoin::io::FromStd::new(reader))?)
+        })
+    }
+
+    /// The host and port of the REST endpoint, e.g. for log messages.
+    pub fn endpoint(&self) -> String {
+        format!("{}:{}", self.host, self.port)
     }
 
     pub fn chain_info(&self) -> Result<ChainInfo, RestError> {
         let url = format!("http://{}:{}/rest/chaininfo.json", self.host, self.port);
-        let response = minreq::get(url).send()?;
+        let response = self.get(url)?;
         if !(response.status_code == 200 && response.reason_phrase == "OK") {
             return Err(RestError::Http(
                 response.status_code,
@@ -218,12 +798,26 @@ impl RestClient {
         Ok(response.json::<ChainInfo>()?)
     }
 
-    pub fn block_at_height(&self, height: u64) -> Result<Block, RestError> {
+    pub fn mempool_info(&self) -> Result<MempoolInfo, RestError> {
+        let url = format!("http://{}:{}/rest/mempool/info.json", self.host, self.port);
+        let response = self.get(url)?;
+        if !(response.status_code == 200 && response.reason_phrase == "OK") {
+            return Err(RestError::Http(
+                response.status_code,
+                response.reason_phrase,
+            ));
+        }
+
+        Ok(response.json::<MempoolInfo>()?)
+    }
+
+    /// The hash of the block at the given height of the node's active chain.
+    fn block_hash_at_height(&self, height: u64) -> Result<String, RestError> {
         let url = format!(
             "http://{}:{}/rest/blockhashbyheight/{}.hex",
             self.host, self.port, height
         );
-        let response_hash = minreq::get(url).send()?;
+        let response_hash = self.get(url)?;
         if !(response_hash.status_code == 200 && response_hash.reason_phrase == "OK") {
             return Err(RestError::Http(
                 response_hash.status_code,
@@ -231,20 +825,290 @@ impl RestClient {
             ));
         }
 
-        let hash = response_hash.as_str()?.trim();
+        Ok(response_hash.as_str()?.trim().to_string())
+    }
+
+    /// Fetches up to `count` consecutive headers of the node's active chain,
+    /// starting at the given height, with a single request instead of one
+    /// block request per header. Bitcoin Core returns at most 2000 headers per
+    /// request and fewer if the chain ends earlier.
+    pub fn headers_at_height_range(
+        &self,
+        start_height: u64,
+        count: u32,
+    ) -> Result<Vec<block::Header>, RestError> {
+        let hash = self.block_hash_at_height(start_height)?;
 
         let url = format!(
-            "http://{}:{}/rest/block/{}.json",
-            self.host, self.port, hash
+            "http://{}:{}/rest/headers/{}.bin?count={}",
+            self.host, self.port, hash, count
         );
-        let response_block = minreq::get(url).send()?;
-        if !(response_block.status_code == 200 && response_block.reason_phrase == "OK") {
+        let response = self.get(url)?;
+        if !(response.status_code == 200 && response.reason_phrase == "OK") {
             return Err(RestError::Http(
-                response_block.status_code,
-                response_block.reason_phrase,
+                response.status_code,
+                response.reason_phrase,
             ));
         }
 
-        Ok(response_block.json()?)
+        // the headers are serialized back to back, 80 bytes each
+        Ok(response
+            .as_bytes()
+            .chunks(80)
+            .map(encode::deserialize)
+            .collect::<Result<Vec<block::Header>, encode::Error>>()?)
+    }
+
+    /// Fetches the block at the given height of the node's active chain.
+    pub fn block_at_height(&self, height: u64) -> Result<Block, RestError> {
+        let hash = self.block_hash_at_height(height)?;
+
+        let url = format!(
+            "http://{}:{}/rest/block/{}.json",
+            self.host, self.port, hash
+        );
+        self.get_json(url)
+    }
+
+    /// Fetches the block at the given height of the node's active chain as
+    /// `.bin` together with the outputs its inputs spend, which is a fraction
+    /// of the JSON response to download and parse. The spent outputs don't
+    /// include their height and whether they were generated, see
+    /// [Block::from_binary]. Falls back to [RestClient::block_at_height] on
+    /// nodes before v30, which don't serve the spent outputs.
+    pub fn block_at_height_binary(&self, height: u64) -> Result<Block, RestError> {
+        if self.no_spent_txouts.load(Ordering::Relaxed) {
+            return self.block_at_height(height);
+        }
+        let hash = self.block_hash_at_height(height)?;
+
+        let url = format!(
+            "http://{}:{}/rest/spenttxouts/{}.bin",
+            self.host, self.port, hash
+        );
+        let spent = match self.get_binary::<SpentOutputs>(url) {
+            Ok(spent) => spent,
+            Err(RestError::Http(404, _)) => {
+                self.no_spent_txouts.store(true, Ordering::Relaxed);
+                return self.block_at_height(height);
+            }
+            Err(e) => return Err(e),
+        };
+        let url = format!("http://{}:{}/rest/block/{}.bin", self.host, self.port, hash);
+        let block: bitcoin::Block = self.get_binary(url)?;
+        Block::from_binary(height as i64, block, spent.0)
+    }
+}
+
+/// The outputs spent by each transaction of a block as returned by the
+/// `/rest/spenttxouts/<hash>.bin` endpoint. The coinbase spends no outputs.
+struct SpentOutputs(Vec<Vec<bitcoin::TxOut>>);
+
+impl Decodable for SpentOutputs {
+    fn consensus_decode<R: bitcoin::io::Read + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let count = VarInt::consensus_decode(r)?.0;
+        let mut txs = Vec::new();
+        for _ in 0..count {
+            txs.push(Vec::<bitcoin::TxOut>::consensus_decode(r)?);
+        }
+        Ok(SpentOutputs(txs))
+    }
+}
+
+impl BlockSource for RestClient {
+    fn implementation(&self) -> NodeImplementation {
+        self.implementation
+    }
+
+    fn endpoint(&self) -> String {
+        RestClient::endpoint(self)
+    }
+
+    fn chain_info(&self) -> Result<ChainInfo, RestError> {
+        RestClient::chain_info(self)
+    }
+
+    fn mempool_info(&self) -> Result<MempoolInfo, RestError> {
+        RestClient::mempool_info(self)
+    }
+
+    fn block_at_height(&self, height: u64, prevout_heights: bool) -> Result<Block, RestError> {
+        if prevout_heights {
+            RestClient::block_at_height(self, height)
+        } else {
+            self.block_at_height_binary(height)
+        }
+    }
+
+    fn fetch_stats(&self) -> FetchStatsSnapshot {
+        RestClient::fetch_stats(self)
+    }
+}
+
+/// Counts the bytes read from a response.
+struct CountingReader<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Block, InputData, ScriptPubkeyType, SpentOutputs};
+    use crate::stats::Stats;
+    use bitcoin::block::Header;
+    use bitcoin::consensus::encode::{self, Encodable, VarInt};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, CompactTarget, ScriptBuf, TxOut};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn load_block(height: u64) -> Block {
+        let buffer = BufReader::new(File::open(format!("./testdata/{}.json", height)).unwrap());
+        serde_json::from_reader(buffer).expect("test block json to be valid")
+    }
+
+    /// The `.bin` block and spent outputs the REST interface returns for the
+    /// JSON block.
+    fn binary(block: &Block) -> (bitcoin::Block, Vec<u8>) {
+        let header = Header {
+            version: block.version,
+            prev_blockhash: block.previous_block_hash.unwrap_or(BlockHash::all_zeros()),
+            merkle_root: block.merkle_root,
+            time: block.time,
+            bits: CompactTarget::from_unprefixed_hex(&block.bits).unwrap(),
+            nonce: block.nonce,
+        };
+        let txdata = block
+            .txdata
+            .iter()
+            .map(|tx| encode::deserialize(&tx.raw).unwrap())
+            .collect();
+        let mut spent = Vec::new();
+        VarInt(block.txdata.len() as u64)
+            .consensus_encode(&mut spent)
+            .unwrap();
+        for tx in block.txdata.iter() {
+            let outputs: Vec<TxOut> = tx
+                .input
+                .iter()
+                .filter_map(|input| match &input.data {
+                    InputData::Coinbase(_) => None,
+                    InputData::NonCoinbase { prevout, .. } => Some(TxOut {
+                        value: prevout.value,
+                        script_pubkey: prevout.script_pub_key.script.clone(),
+                    }),
+                })
+                .collect();
+            outputs.consensus_encode(&mut spent).unwrap();
+        }
+        (bitcoin::Block { header, txdata }, spent)
+    }
+
+    #[test]
+    fn test_block_from_binary() {
+        for height in [
+            215049, 227154, 361582, 367843, 739990, 888395, 913612, 920533, 925262,
+        ] {
+            let json = load_block(height);
+            let (block, spent) = binary(&json);
+            let spent: SpentOutputs = encode::deserialize(&spent).unwrap();
+            let mut from_binary = Block::from_binary(json.height, block, spent.0).unwrap();
+            assert_eq!(from_binary.hash, json.hash);
+            assert_eq!(from_binary.size, json.size);
+            assert_eq!(from_binary.stripped_size, json.stripped_size);
+            assert_eq!(from_binary.bits, json.bits);
+
+            for (tx, json_tx) in from_binary.txdata.iter_mut().zip(json.txdata.iter()) {
+                assert_eq!(tx.fee, json_tx.fee);
+                for (output, json_output) in tx.output.iter().zip(json_tx.output.iter()) {
+                    assert!(
+                        output.script_pub_key.type_ == json_output.script_pub_key.type_,
+                        "type of {} in block {}",
+                        json_output.script_pub_key.script,
+                        height
+                    );
+                }
+                // the binary block doesn't include the prevout heights
+                for (input, json_input) in tx.input.iter_mut().zip(json_tx.input.iter()) {
+                    if let (
+                        InputData::NonCoinbase { prevout, .. },
+                        InputData::NonCoinbase {
+                            prevout: json_prevout,
+                            ..
+                        },
+                    ) = (&mut input.data, &json_input.data)
+                    {
+                        assert!(prevout.script_pub_key.type_ == json_prevout.script_pub_key.type_);
+                        prevout.height = json_prevout.height;
+                        prevout.generated = json_prevout.generated;
+                    }
+                }
+            }
+            from_binary.without_prevout_heights = false;
+            assert_eq!(
+                Stats::from_block(from_binary).unwrap(),
+                Stats::from_block(load_block(height)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_binary_block_not_used_for_prevout_heights() {
+        use crate::stats::{needs_prevout_heights, CoinbaseSpend, PartialStats, StatsTable};
+        use std::collections::BTreeSet;
+
+        // the sync only fetches binary blocks when no stats need the heights
+        let all: BTreeSet<StatsTable> = StatsTable::ALL.into_iter().collect();
+        let mut without_dormant = all.clone();
+        without_dormant.remove(&StatsTable::DormantSpends);
+        assert!(needs_prevout_heights(&all, false));
+        assert!(needs_prevout_heights(&without_dormant, true));
+        assert!(!needs_prevout_heights(&without_dormant, false));
+
+        // and the stats that need them refuse a binary block
+        let from_binary = || {
+            let json = load_block(925262);
+            let (block, spent) = binary(&json);
+            let spent: SpentOutputs = encode::deserialize(&spent).unwrap();
+            Block::from_binary(json.height, block, spent.0).unwrap()
+        };
+        assert!(CoinbaseSpend::from_block(&from_binary()).is_err());
+        assert!(PartialStats::from_block(from_binary(), &all).is_err());
+        let stats = PartialStats::from_block(from_binary(), &without_dormant).unwrap();
+        assert!(stats.dormant.is_none());
+    }
+
+    #[test]
+    fn test_script_pubkey_type() {
+        let of = |hex: &str| ScriptPubkeyType::of(&ScriptBuf::from_hex(hex).unwrap());
+        assert!(of("51024e73") == ScriptPubkeyType::Anchor);
+        assert!(of("5120") == ScriptPubkeyType::Nonstandard);
+        // a v0 witness program of neither 20 nor 32 bytes
+        assert!(
+            of("0015000000000000000000000000000000000000000000") == ScriptPubkeyType::Nonstandard
+        );
+        assert!(of("6a0401020304") == ScriptPubkeyType::NullData);
+        assert!(of("6aac") == ScriptPubkeyType::Nonstandard);
+        // a pubkey with an invalid first byte
+        assert!(
+            of("21050000000000000000000000000000000000000000000000000000000000000000ac")
+                == ScriptPubkeyType::Nonstandard
+        );
+        assert!(
+            of("512102000000000000000000000000000000000000000000000000000000000000000051ae")
+                == ScriptPubkeyType::MultiSig
+        );
+        assert!(
+            of("522102000000000000000000000000000000000000000000000000000000000000000051ae")
+                == ScriptPubkeyType::Nonstandard
+        );
     }
 }
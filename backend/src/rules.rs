@@ -0,0 +1,134 @@
+use bitcoin::{Amount, FeeRate, Script};
+
+/// The default standardness (policy) rules of Bitcoin Core relevant to the
+/// stats, in force from the release date of a version on. Nodes upgrade
+/// gradually, so blocks mined shortly after a release might still follow the
+/// previous rules.
+#[derive(Debug, PartialEq)]
+pub struct StandardnessRules {
+    /// Bitcoin Core version introducing these rules.
+    pub version: &'static str,
+    /// Release date (YYYY-MM-DD) of the version.
+    pub since: &'static str,
+    /// Whether OP_RETURN (data carrier) outputs are relayed at all.
+    pub datacarrier: bool,
+    /// Maximum summed size of the OP_RETURN scriptPubKeys of a transaction.
+    pub max_datacarrier_bytes: usize,
+    /// Whether a transaction may have more than one OP_RETURN output.
+    pub multiple_datacarrier_outputs: bool,
+    /// Fee rate used to calculate the dust threshold of an output. None if
+    /// there was no dust limit.
+    pub dust_relay_fee: Option<FeeRate>,
+}
+
+/// History of the standardness rules, sorted by date.
+pub const STANDARDNESS_RULES: [StandardnessRules; 5] = [
+    StandardnessRules {
+        version: "0.1.0",
+        since: "2009-01-03",
+        datacarrier: false,
+        max_datacarrier_bytes: 0,
+        multiple_datacarrier_outputs: false,
+        dust_relay_fee: None,
+    },
+    // dust limit of 3x the min relay fee of 10 sat/vbyte
+    StandardnessRules {
+        version: "0.8.2",
+        since: "2013-05-29",
+        datacarrier: false,
+        max_datacarrier_bytes: 0,
+        multiple_datacarrier_outputs: false,
+        dust_relay_fee: Some(FeeRate::from_sat_per_vb_unchecked(30)),
+    },
+    // OP_RETURN with up to 40 bytes of data and a min relay fee of 1 sat/vbyte
+    StandardnessRules {
+        version: "0.9.0",
+        since: "2014-03-19",
+        datacarrier: true,
+        max_datacarrier_bytes: 42,
+        multiple_datacarrier_outputs: false,
+        dust_relay_fee: Some(FeeRate::from_sat_per_vb_unchecked(3)),
+    },
+    // OP_RETURN with up to 80 bytes of data
+    StandardnessRules {
+        version: "0.11.0",
+        since: "2015-07-12",
+        datacarrier: true,
+        max_datacarrier_bytes: 83,
+        multiple_datacarrier_outputs: false,
+        dust_relay_fee: Some(FeeRate::from_sat_per_vb_unchecked(3)),
+    },
+    // multiple OP_RETURN outputs and a data carrier size limited by the
+    // transaction size
+    StandardnessRules {
+        version: "30.0",
+        since: "2025-10-10",
+        datacarrier: true,
+        max_datacarrier_bytes: 100_000,
+        multiple_datacarrier_outputs: true,
+        dust_relay_fee: Some(FeeRate::from_sat_per_vb_unchecked(3)),
+    },
+];
+
+/// Returns the standardness rules in force on the given date (YYYY-MM-DD).
+pub fn rules_at(date: &str) -> &'static StandardnessRules {
+    STANDARDNESS_RULES
+        .iter()
+        .rev()
+        .find(|rules| rules.since <= date)
+        .unwrap_or(&STANDARDNESS_RULES[0])
+}
+
+impl StandardnessRules {
+    /// Whether an output with the script and value was dust under these rules.
+    /// OP_RETURN outputs are never dust.
+    pub fn is_dust(&self, script: &Script, value: Amount) -> bool {
+        match self.dust_relay_fee {
+            Some(fee_rate) => value < script.minimal_non_dust_custom(fee_rate),
+            None => false,
+        }
+    }
+
+    /// Whether the OP_RETURN outputs of a transaction, given as scriptPubKeys,
+    /// were standard under these rules.
+    pub fn is_datacarrier_standard(&self, opreturn_scripts: &[&Script]) -> bool {
+        if opreturn_scripts.is_empty() {
+            return true;
+        }
+        self.datacarrier
+            && (opreturn_scripts.len() == 1 || self.multiple_datacarrier_outputs)
+            && opreturn_scripts.iter().map(|s| s.len()).sum::<usize>() <= self.max_datacarrier_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rules_at;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{script::PushBytesBuf, Amount, ScriptBuf};
+
+    #[test]
+    fn test_rules_at() {
+        assert_eq!(rules_at("2009-01-03").version, "0.1.0");
+        assert_eq!(rules_at("2014-03-19").version, "0.9.0");
+        assert_eq!(rules_at("2020-01-01").version, "0.11.0");
+        assert_eq!(rules_at("2026-01-01").version, "30.0");
+
+        let p2pkh = ScriptBuf::new_p2pkh(&bitcoin::PubkeyHash::from_byte_array([0; 20]));
+        assert!(!rules_at("2012-01-01").is_dust(&p2pkh, Amount::from_sat(1)));
+        assert!(rules_at("2013-06-01").is_dust(&p2pkh, Amount::from_sat(5459)));
+        assert!(!rules_at("2013-06-01").is_dust(&p2pkh, Amount::from_sat(5460)));
+        assert!(rules_at("2020-01-01").is_dust(&p2pkh, Amount::from_sat(545)));
+        assert!(!rules_at("2020-01-01").is_dust(&p2pkh, Amount::from_sat(546)));
+
+        let opreturn =
+            |len: usize| ScriptBuf::new_op_return(PushBytesBuf::try_from(vec![0u8; len]).unwrap());
+        let (small, large) = (opreturn(40), opreturn(80));
+        assert!(!rules_at("2013-06-01").is_datacarrier_standard(&[&small]));
+        assert!(rules_at("2014-06-01").is_datacarrier_standard(&[&small]));
+        assert!(!rules_at("2014-06-01").is_datacarrier_standard(&[&large]));
+        assert!(rules_at("2020-01-01").is_datacarrier_standard(&[&large]));
+        assert!(!rules_at("2020-01-01").is_datacarrier_standard(&[&small, &small]));
+        assert!(rules_at("2026-01-01").is_datacarrier_standard(&[&large, &large]));
+    }
+}
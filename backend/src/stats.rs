@@ -1,33 +1,332 @@
 use bitcoin::{
     absolute::LockTime, error::UnprefixedHexError, script::Instruction, Amount, CompactTarget,
-    Network, Target, Transaction, Txid,
+    Network, Target, Txid, Weight,
 };
-use bitcoin_pool_identification::{default_data, Pool, PoolIdentification};
-use chrono::DateTime;
+use bitcoin_pool_identification::{Pool, PoolIdentification};
+use chrono::{DateTime, FixedOffset};
 use diesel::prelude::*;
-use log::{debug, error};
+use log::{debug, error, warn};
 use rawtx_rs::{
     input::InputType, output::OpReturnFlavor, output::OutputType, script::DEREncoding,
     script::SignatureType, tx::TxInfo,
 };
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use statrs::statistics::Data;
 use statrs::statistics::OrderStatistics;
-use std::{collections::HashSet, error, fmt, num::ParseIntError};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error, fmt,
+    num::ParseIntError,
+    sync::OnceLock,
+};
 
-use crate::rest::{Block, InputData, ScriptPubkeyType};
+use crate::pools;
+use crate::rest::{self, Block, InputData, ScriptPubkeyType};
+use crate::rules::rules_at;
 
 const UNKNOWN_POOL_ID: i32 = 0;
 const P2A_DUST_THRESHOLD: u64 = 240;
+/// Blocks per year at the 10 minute target block interval.
+pub const BLOCKS_PER_YEAR: i64 = 144 * 365;
+/// Block 100000 was mined on 2010-12-29. Outputs created before it are
+/// counted as pre-2011 coins.
+pub const PRE_2011_HEIGHT: i64 = 100_000;
+
+/// A column of a stats table. Generated from the fields of the stats structs
+/// by `stats_table!`, so the column list is only maintained in one place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsColumn {
+    pub name: &'static str,
+    /// The Diesel SQL type of the column.
+    pub sql_type: &'static str,
+    /// The doc comment of the field. Empty if the field isn't documented.
+    pub doc: &'static str,
+}
+
+impl StatsColumn {
+    /// The doc comment as a single line description.
+    pub fn description(&self) -> String {
+        self.doc
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+/// A field of a stats struct that can be summed up over partial stats, see the
+/// `add_counts` method `stats_table!` generates.
+trait StatsCount {
+    fn add_count(&mut self, other: &Self);
+}
+
+impl StatsCount for i32 {
+    fn add_count(&mut self, other: &i32) {
+        *self += other;
+    }
+}
+
+impl StatsCount for i64 {
+    fn add_count(&mut self, other: &i64) {
+        // the amount sums saturate, see add_sats()
+        *self = self.saturating_add(*other);
+    }
+}
+
+impl StatsCount for f32 {
+    fn add_count(&mut self, other: &f32) {
+        *self += other;
+    }
+}
+
+impl StatsCount for bool {
+    fn add_count(&mut self, other: &bool) {
+        *self |= other;
+    }
+}
+
+impl StatsCount for String {
+    fn add_count(&mut self, _other: &String) {}
+}
+
+macro_rules! stats_sql_type {
+    ($ty:ident, $sql_type:ident) => {
+        stringify!($sql_type)
+    };
+    (i32) => {
+        "Integer"
+    };
+    (i64) => {
+        "BigInt"
+    };
+    (f32) => {
+        "Float"
+    };
+    (bool) => {
+        "Bool"
+    };
+    (String) => {
+        "Text"
+    };
+}
+
+/// Emits the `diesel::table!` of a stats table. The SQL types are resolved
+/// one field at a time, as `diesel::table!` only accepts type paths.
+macro_rules! stats_schema {
+    ($table:ident { $($columns:tt)* }) => {
+        diesel::table! {
+            $table (height) {
+                $($columns)*
+            }
+        }
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: $ty:ident as $sql_type:ident, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::$sql_type, } $($rest)*);
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: i32, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::Integer, } $($rest)*);
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: i64, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::BigInt, } $($rest)*);
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: f32, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::Float, } $($rest)*);
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: bool, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::Bool, } $($rest)*);
+    };
+    ($table:ident { $($columns:tt)* } $field:ident: String, $($rest:tt)*) => {
+        stats_schema!($table { $($columns)* $field -> diesel::sql_types::Text, } $($rest)*);
+    };
+}
+
+/// Defines a stats struct and the Diesel schema of its table with the height
+/// as primary key. Adds the Diesel derives and attributes the stats tables
+/// share and a `COLUMNS` list with the name, SQL type and doc comment of each
+/// field. The SQL type follows from the Rust type, unless the field has a
+/// `#[sql_type = ...]` attribute. The stats tables are excluded from the
+/// schema.rs the Diesel CLI generates (see diesel.toml), so the column list is
+/// only maintained here and in the migrations.
+macro_rules! stats_table {
+    (
+        table = $table:ident;
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[sql_type = $sql_type:ident])?
+                $field_vis:vis $field:ident: $ty:ident,
+            )*
+        }
+    ) => {
+        stats_schema!($table {} $($field: $ty $(as $sql_type)?,)*);
+
+        #[derive(Queryable, Selectable, Insertable, AsChangeset, serde::Serialize)]
+        #[diesel(table_name = $table)]
+        #[diesel(primary_key(height))]
+        #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[doc = $doc])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// Adds the counters and sums of `other`, e.g. to merge the stats
+            /// of a block calculated in parallel for parts of its
+            /// transactions. Only meaningful for counters and sums, the
+            /// height, date and other fields of partial stats are expected
+            /// to be left at their defaults.
+            #[allow(dead_code)]
+            fn add_counts(&mut self, other: &Self) {
+                $(StatsCount::add_count(&mut self.$field, &other.$field);)*
+            }
+
+            pub const COLUMNS: &'static [StatsColumn] = &[
+                $(
+                    StatsColumn {
+                        name: stringify!($field),
+                        sql_type: stats_sql_type!($ty $(, $sql_type)?),
+                        doc: concat!($($doc, "\n",)* ""),
+                    },
+                )*
+            ];
+        }
+    };
+}
+
+/// The tables we generate stats for. Each table has its own version, so
+/// adding a stat to one table only requires recomputing that table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StatsTable {
+    Block,
+    Tx,
+    Input,
+    Output,
+    Script,
+    Feerate,
+    Truc,
+    InputSize,
+    DormantSpends,
+}
+
+impl StatsTable {
+    pub const ALL: [StatsTable; 9] = [
+        StatsTable::Block,
+        StatsTable::Tx,
+        StatsTable::Input,
+        StatsTable::Output,
+        StatsTable::Script,
+        StatsTable::Feerate,
+        StatsTable::Truc,
+        StatsTable::InputSize,
+        StatsTable::DormantSpends,
+    ];
+
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            StatsTable::Block => "block_stats",
+            StatsTable::Tx => "tx_stats",
+            StatsTable::Input => "input_stats",
+            StatsTable::Output => "output_stats",
+            StatsTable::Script => "script_stats",
+            StatsTable::Feerate => "feerate_stats",
+            StatsTable::Truc => "truc_stats",
+            StatsTable::InputSize => "input_size_stats",
+            StatsTable::DormantSpends => "dormant_spends",
+        }
+    }
+
+    // The version we want the stats in the table to be and, at the same
+    // time also the version we record when generating and writing stats
+    // to the table.
+    // Up to version 3, all tables shared a single version:
+    // version 0: default db version
+    // version 1: initial version
+    // version 2: add coinbase locktime stats
+    // version 3: add coinbase output stats
+    // Later versions are per table:
+    // block_stats version 4: add feerate_min_nonzero
+    // block_stats version 5: add vsize-weighted feerate percentiles
+    // block_stats version 6: add the block header timestamp
+    // block_stats version 7: add the BIP34 coinbase height
+    // block_stats version 8: add the exact difficulty
+    // block_stats version 9: add the coinbase scriptSig size after the height
+    // block_stats version 10: add the version-rolling (BIP320) bits
+    // block_stats version 11: add the coinbase weight share and large coinbases
+    // block_stats version 12: add the input and output amounts
+    // tx_stats version 4: add unspent ephemeral dust
+    // tx_stats version 5: add P2A spender classification
+    // tx_stats version 6: add transaction counts by vsize class
+    // tx_stats version 7: add value-weighted adoption amounts and multisig spending
+    // tx_stats version 8: add vsize-weighted segwit, taproot and legacy spending
+    // tx_stats version 9: add txids with leading zero bytes
+    // tx_stats version 10: add silent payments eligible transactions
+    // tx_stats version 11: add transactions with malleable scriptSigs
+    // tx_stats version 12: add payment-like and data embedding transactions
+    // input_stats version 4: add multisig and single-sig input amounts
+    // output_stats version 4: add burned outputs
+    // script_stats version 4: add CHECKMULTISIG dummy element stats
+    // script_stats version 5: add Schnorr signature lengths
+    // script_stats version 6: add cross-input signature aggregation stats
+    // script_stats version 7: add proposed opcode and covenant template usage
+    // script_stats version 8: add witness bytes by signatures, keys, scripts and data
+    // script_stats version 9: add taproot key-path and script-path sighash usage
+    // script_stats version 10: add unexpected script nesting
+    // output_stats version 5: add data-embedding protocols
+    // output_stats version 6: add outputs non-standard under the rules at the time
+    // output_stats version 7: add outputs with fake pubkeys and pubkey hashes
+    // output_stats version 8: add the net UTXO set change by data embedding protocol
+    // truc_stats version 1: initial version
+    // input_size_stats version 1: initial version
+    // dormant_spends version 1: initial version
+    pub fn version(&self) -> i32 {
+        match self {
+            StatsTable::Block => 12,
+            StatsTable::Tx => 12,
+            StatsTable::Input => 4,
+            StatsTable::Output => 8,
+            StatsTable::Script => 10,
+            StatsTable::Feerate => 3,
+            StatsTable::Truc => 1,
+            StatsTable::InputSize => 1,
+            StatsTable::DormantSpends => 1,
+        }
+    }
+
+    pub fn columns(&self) -> &'static [StatsColumn] {
+        match self {
+            StatsTable::Block => BlockStats::COLUMNS,
+            StatsTable::Tx => TxStats::COLUMNS,
+            StatsTable::Input => InputStats::COLUMNS,
+            StatsTable::Output => OutputStats::COLUMNS,
+            StatsTable::Script => ScriptStats::COLUMNS,
+            StatsTable::Feerate => FeerateStats::COLUMNS,
+            StatsTable::Truc => TrucStats::COLUMNS,
+            StatsTable::InputSize => InputSizeStats::COLUMNS,
+            StatsTable::DormantSpends => DormantSpends::COLUMNS,
+        }
+    }
+
+    /// The versions we want for each table by table name.
+    pub fn versions() -> BTreeMap<String, i32> {
+        StatsTable::ALL
+            .iter()
+            .map(|table| (table.table_name().to_string(), table.version()))
+            .collect()
+    }
+}
 
-// The version we want the stats in the database to be and, at
-// the same time also the stats_version we set when generating
-// and writing stats to the database.
-// History:
-// version 0: default db version
-// version 1: initial version
-// version 2: add coinbase locktime stats
-// version 3: add coinbase output stats
-pub const STATS_VERSION: i32 = 3;
+/// Whether the prevout heights, and whether the prevouts were generated, are
+/// needed for the given tables and the coinbase spend tracking. Only the
+/// dormant spends and the coinbase spends use them. Blocks fetched without
+/// them, see [crate::rest::Block::from_binary], are refused by those stats.
+pub fn needs_prevout_heights(tables: &BTreeSet<StatsTable>, track_coinbase_spends: bool) -> bool {
+    tables.contains(&StatsTable::DormantSpends) || track_coinbase_spends
+}
 
 #[derive(Debug)]
 pub enum StatsError {
@@ -35,6 +334,8 @@ pub enum StatsError {
     BitcoinEncode(bitcoin::consensus::encode::Error),
     ParseInt(ParseIntError),
     UnprefixedHex(UnprefixedHexError),
+    /// the stats need the prevout heights, which the block was fetched without
+    MissingPrevoutHeights(i64),
 }
 
 impl fmt::Display for StatsError {
@@ -44,6 +345,11 @@ impl fmt::Display for StatsError {
             StatsError::BitcoinEncode(e) => write!(f, "Bitcoin Encode Error: {:?}", e),
             StatsError::ParseInt(e) => write!(f, "Parse Int Error: {:?}", e),
             StatsError::UnprefixedHex(e) => write!(f, "Unprefixed Hex Error: {:?}", e),
+            StatsError::MissingPrevoutHeights(height) => write!(
+                f,
+                "Block {} was fetched without the prevout heights the stats need",
+                height
+            ),
         }
     }
 }
@@ -55,6 +361,7 @@ impl error::Error for StatsError {
             StatsError::BitcoinEncode(ref e) => Some(e),
             StatsError::ParseInt(ref e) => Some(e),
             StatsError::UnprefixedHex(ref e) => Some(e),
+            StatsError::MissingPrevoutHeights(_) => None,
         }
     }
 }
@@ -83,7 +390,9 @@ impl From<UnprefixedHexError> for StatsError {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Stats for all tables of a block. The sync only calculates the outdated
+/// tables with [PartialStats].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub struct Stats {
     pub block: BlockStats,
     pub tx: TxStats,
@@ -91,108 +400,426 @@ pub struct Stats {
     pub output: OutputStats,
     pub feerate: FeerateStats,
     pub script: ScriptStats,
+    pub truc: TrucStats,
+    pub input_size: InputSizeStats,
+    pub dormant: DormantSpends,
 }
 
 impl Stats {
+    /// Calculates the stats of all tables for a block. The stats are the same
+    /// as the ones written to the database by the sync.
     pub fn from_block(block: Block) -> Result<Stats, StatsError> {
+        let stats = PartialStats::from_block(block, &StatsTable::ALL.into_iter().collect())?;
+        Ok(Stats {
+            block: stats.block.expect("block stats should be calculated"),
+            tx: stats.tx.expect("tx stats should be calculated"),
+            input: stats.input.expect("input stats should be calculated"),
+            output: stats.output.expect("output stats should be calculated"),
+            feerate: stats.feerate.expect("feerate stats should be calculated"),
+            script: stats.script.expect("script stats should be calculated"),
+            truc: stats.truc.expect("truc stats should be calculated"),
+            input_size: stats
+                .input_size
+                .expect("input size stats should be calculated"),
+            dormant: stats.dormant.expect("dormant spends should be calculated"),
+        })
+    }
+}
+
+static AGGREGATION_TIMEZONE: OnceLock<FixedOffset> = OnceLock::new();
+
+/// Sets the UTC offset the `date` columns of the stats are calculated in for
+/// the rest of the process. Defaults to UTC.
+pub fn set_aggregation_timezone(offset: FixedOffset) {
+    if AGGREGATION_TIMEZONE.set(offset).is_err() && aggregation_timezone() != offset {
+        warn!("Aggregation timezone was already in use, keeping the previous one");
+    }
+}
+
+/// The UTC offset the `date` columns of the stats are calculated in.
+pub fn aggregation_timezone() -> FixedOffset {
+    *AGGREGATION_TIMEZONE.get_or_init(|| FixedOffset::east_opt(0).expect("zero offset is valid"))
+}
+
+/// Number of fractional digits of the exact difficulty.
+const DIFFICULTY_DECIMALS: usize = 16;
+
+/// The difficulty of the compact target as a decimal with
+/// [DIFFICULTY_DECIMALS] fractional digits, calculated with integers instead
+/// of floats. Like [Target::difficulty_float], it's relative to the mainnet
+/// maximum target `0xffff * 2^208` on all networks. With the target being
+/// `mantissa * 256^(exponent - 3)`, the difficulty is
+/// `0xffff * 2^(232 - 8 * exponent) / mantissa`. None for negative or zero
+/// targets and exponents too far from the ones in use to fit into a u128.
+fn exact_difficulty(bits: CompactTarget) -> Option<String> {
+    let bits = bits.to_consensus();
+    let exponent = (bits >> 24) as i32;
+    let mantissa = (bits & 0x007f_ffff) as u128;
+    if mantissa == 0 || bits & 0x0080_0000 != 0 {
+        return None;
+    }
+    let shift = 232 - 8 * exponent;
+    let (numerator, denominator) = match shift {
+        0..=112 => (0xffff_u128 << shift, mantissa),
+        -96..=-1 => (0xffff, mantissa << -shift),
+        _ => return None,
+    };
+    let mut decimal = format!("{}.", numerator / denominator);
+    let mut remainder = numerator % denominator;
+    for _ in 0..DIFFICULTY_DECIMALS {
+        remainder *= 10;
+        decimal.push(char::from(b'0' + (remainder / denominator) as u8));
+        remainder %= denominator;
+    }
+    Some(decimal)
+}
+
+/// The amount in sat as stored in the database. Amounts in valid blocks are
+/// at most 21M BTC, larger ones saturate at i64::MAX instead of wrapping to a
+/// negative value.
+fn sats(amount: Amount) -> i64 {
+    saturating_i64(amount.to_sat())
+}
+
+/// Adds an amount in sat to a per-block sum, saturating at i64::MAX. A single
+/// amount is bounded by the supply, but a sum isn't: outputs created and spent
+/// in the same block are counted again with each spend.
+fn add_sats(sum: &mut i64, amount: Amount) {
+    *sum = sum.saturating_add(sats(amount));
+}
+
+/// The sum of the amounts in sat, saturating at i64::MAX.
+fn sum_sats(amounts: impl Iterator<Item = Amount>) -> i64 {
+    amounts.fold(0, |sum, amount| sum.saturating_add(sats(amount)))
+}
+
+/// Converts a sum of unsigned values (e.g. fees or sizes) to i64, saturating
+/// at i64::MAX.
+fn saturating_i64(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// Stats for only some of the tables of a block. Used when only some tables
+/// of a block are outdated. The other tables are left as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialStats {
+    pub height: i64,
+    pub block: Option<BlockStats>,
+    pub tx: Option<TxStats>,
+    pub input: Option<InputStats>,
+    pub output: Option<OutputStats>,
+    pub feerate: Option<FeerateStats>,
+    pub script: Option<ScriptStats>,
+    pub truc: Option<TrucStats>,
+    pub input_size: Option<InputSizeStats>,
+    pub dormant: Option<DormantSpends>,
+    /// the raw coinbase transaction of the block
+    pub coinbase: Option<Coinbase>,
+    /// the coinbase outputs spent in the block, only set when the coinbase
+    /// spend tracking is enabled
+    pub coinbase_spends: Option<Vec<CoinbaseSpend>>,
+    /// the transactions contributing to the captured tx_stats counter, only
+    /// set when capturing contributors
+    pub contributors: Option<Vec<StatContributor>>,
+    /// the opcodes of the scripts revealed in the block, only set when the
+    /// opcode tracking is enabled
+    pub opcodes: Option<Vec<OpcodeCount>>,
+    /// the spent to created script type transitions in the block, only set
+    /// when the transition tracking is enabled
+    pub transitions: Option<Vec<ScriptTypeTransition>>,
+    /// the first output of each non-standard script template in the block,
+    /// only set when the novel script tracking is enabled
+    pub novel_scripts: Option<Vec<NovelScript>>,
+    /// the configured feerate percentiles of the block, only set when
+    /// percentiles are configured
+    pub feerate_percentiles: Option<Vec<FeeratePercentile>>,
+}
+
+impl PartialStats {
+    pub fn from_block(
+        block: Block,
+        tables: &BTreeSet<StatsTable>,
+    ) -> Result<PartialStats, StatsError> {
+        Self::from_block_capturing(block, tables, None)
+    }
+
+    /// Like [PartialStats::from_block], but also records the transactions
+    /// contributing to the tx_stats counter in `capture`, if set.
+    pub fn from_block_capturing(
+        block: Block,
+        tables: &BTreeSet<StatsTable>,
+        capture: Option<&str>,
+    ) -> Result<PartialStats, StatsError> {
+        if block.without_prevout_heights && needs_prevout_heights(tables, false) {
+            return Err(StatsError::MissingPrevoutHeights(block.height));
+        }
         let timestamp =
             DateTime::from_timestamp(block.time as i64, 0).expect("invalid block header timestamp");
-        let date = timestamp.format("%Y-%m-%d").to_string();
-        let mut tx_infos: Vec<TxInfo> = Vec::with_capacity(block.txdata.len());
-        for tx in block.txdata.iter() {
-            let tx: Transaction = bitcoin::consensus::deserialize(&tx.raw)?;
-            match TxInfo::new(&tx) {
-                Ok(txinfo) => tx_infos.push(txinfo),
-                Err(e) => {
+        let date = timestamp
+            .with_timezone(&aggregation_timezone())
+            .format("%Y-%m-%d")
+            .to_string();
+        // Huge blocks dominate the sync time, so the transactions of a block
+        // are analyzed in parallel and the tables are calculated concurrently.
+        // This runs on the rayon pool the blocks are processed on.
+        let tx_infos: Vec<TxInfo> = block
+            .txdata
+            .par_iter()
+            .map(|tx| {
+                let tx = tx.transaction()?;
+                TxInfo::new(&tx).map_err(|e| {
                     error!(
                         "Could not create TxInfo for {} in block {}: {}",
                         tx.compute_txid(),
                         block.height,
                         e
                     );
-                    return Err(StatsError::TxInfo(e));
-                }
+                    StatsError::TxInfo(e)
+                })
+            })
+            .collect::<Result<_, StatsError>>()?;
+
+        let (tx_stats, contributors) = match capture {
+            Some(column) => {
+                let (tx_stats, txids) =
+                    TxStats::from_block_with_contributors(&block, date.clone(), &tx_infos, column);
+                let contributors = txids
+                    .iter()
+                    .map(|txid| StatContributor {
+                        height: block.height,
+                        stat: column.to_string(),
+                        txid: txid.to_string(),
+                    })
+                    .collect();
+                (Some(tx_stats), Some(contributors))
             }
-        }
+            None => (None, None),
+        };
 
-        // TODO: if we ever wanted to generate stats on a network other than
-        // mainnet and do pool identification, we'd need to be able to change
-        // the network here.
-        let pools = default_data(Network::Bitcoin);
+        let (block, tx_infos, date) = (&block, &tx_infos, &date);
+        let mut stats = PartialStats {
+            height: block.height,
+            block: None,
+            tx: None,
+            input: None,
+            output: None,
+            feerate: None,
+            script: None,
+            truc: None,
+            input_size: None,
+            dormant: None,
+            coinbase: None,
+            coinbase_spends: None,
+            contributors,
+            opcodes: None,
+            transitions: None,
+            novel_scripts: None,
+            feerate_percentiles: None,
+        };
+        let mut block_stats = None;
+        let mut coinbase = None;
+        rayon::scope(|s| {
+            if tables.contains(&StatsTable::Block) {
+                // The pool list only covers mainnet. On other networks, the
+                // pool identification is disabled and the list is empty.
+                s.spawn(|_| {
+                    block_stats = Some(BlockStats::from_block(
+                        block,
+                        date.clone(),
+                        tx_infos,
+                        pools::pools(),
+                    ))
+                });
+            }
+            if tables.contains(&StatsTable::Tx) {
+                let tx = &mut stats.tx;
+                s.spawn(move |_| {
+                    *tx = Some(
+                        tx_stats
+                            .unwrap_or_else(|| TxStats::from_block(block, date.clone(), tx_infos)),
+                    )
+                });
+            }
+            if tables.contains(&StatsTable::Input) {
+                let input = &mut stats.input;
+                s.spawn(move |_| {
+                    *input = Some(InputStats::from_block(block, date.clone(), tx_infos))
+                });
+            }
+            if tables.contains(&StatsTable::Output) {
+                let output = &mut stats.output;
+                s.spawn(move |_| {
+                    *output = Some(OutputStats::from_block(block, date.clone(), tx_infos))
+                });
+            }
+            if tables.contains(&StatsTable::Script) {
+                let script = &mut stats.script;
+                s.spawn(move |_| {
+                    *script = Some(ScriptStats::from_block(block, date.clone(), tx_infos))
+                });
+            }
+            if tables.contains(&StatsTable::Feerate) {
+                let feerate = &mut stats.feerate;
+                s.spawn(move |_| {
+                    *feerate = Some(FeerateStats::from_block(block, date.clone(), tx_infos))
+                });
+            }
+            if tables.contains(&StatsTable::Truc) {
+                let truc = &mut stats.truc;
+                s.spawn(move |_| *truc = Some(TrucStats::from_block(block, date.clone())));
+            }
+            if tables.contains(&StatsTable::InputSize) {
+                let input_size = &mut stats.input_size;
+                s.spawn(move |_| {
+                    *input_size = Some(InputSizeStats::from_block(block, date.clone(), tx_infos))
+                });
+            }
+            if tables.contains(&StatsTable::DormantSpends) {
+                let dormant = &mut stats.dormant;
+                s.spawn(move |_| *dormant = Some(DormantSpends::from_block(block, date.clone())));
+            }
+            s.spawn(|_| coinbase = Some(Coinbase::from_block(block)));
+        });
+        stats.block = block_stats.transpose()?;
+        stats.coinbase = coinbase.transpose()?;
+        Ok(stats)
+    }
 
-        Ok(Stats {
-            block: BlockStats::from_block(&block, date.clone(), &tx_infos, &pools)?,
-            tx: TxStats::from_block(&block, date.clone(), &tx_infos),
-            input: InputStats::from_block(&block, date.clone(), &tx_infos),
-            output: OutputStats::from_block(&block, date.clone(), &tx_infos),
-            script: ScriptStats::from_block(&block, date.clone(), &tx_infos),
-            feerate: FeerateStats::from_block(&block, date.clone(), &tx_infos),
-        })
+    /// The tables these stats contain.
+    pub fn tables(&self) -> BTreeSet<StatsTable> {
+        StatsTable::ALL
+            .into_iter()
+            .filter(|table| match table {
+                StatsTable::Block => self.block.is_some(),
+                StatsTable::Tx => self.tx.is_some(),
+                StatsTable::Input => self.input.is_some(),
+                StatsTable::Output => self.output.is_some(),
+                StatsTable::Script => self.script.is_some(),
+                StatsTable::Feerate => self.feerate.is_some(),
+                StatsTable::Truc => self.truc.is_some(),
+                StatsTable::InputSize => self.input_size.is_some(),
+                StatsTable::DormantSpends => self.dormant.is_some(),
+            })
+            .collect()
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Debug, PartialEq)]
-#[diesel(table_name = crate::schema::block_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct BlockStats {
-    /// The version of stats we have for this block. If the stats version is too
-    /// old, we need to update the stats.
-    pub stats_version: i32,
-
-    pub height: i64,
-    pub date: String,
-
-    pub version: i32,
-    pub nonce: i32,
-    pub bits: i32,
-    /// Low-presision block difficulty. Stored as i64 as SQLite doesn't support
-    /// f64 nor u128.
-    pub difficulty: i64,
-    /// Low-presision log2(work) for this block. Not to be confused with Bitcoin Core's cumulative log2_work
-    /// for a block at a given height. This one is not cumulative.
-    pub log2_work: f32,
-
-    /// the size of the block in bytes
-    pub size: i64,
-    /// the size of the block excluding the witness data.
-    pub stripped_size: i64,
-    /// the virtual size of the block in bytes (ceil(weight / 4.0))
-    pub vsize: i64,
-    /// the size of the block in bytes
-    pub weight: i64,
-    /// the block is empty (no tx besides the coinbase tx)
-    pub empty: bool,
-
-    /// Coinbase output amounts (sum)
-    pub coinbase_output_amount: i64,
-    /// Coinbase transactoin weight
-    pub coinbase_weight: i64,
-    /// the coinbase locktime has a (non zero) value set. This locktime might not be enforced.
-    pub coinbase_locktime_set: bool,
-    /// The coinbase locktime as a bip54 value set:
-    /// from https://github.com/bitcoin/bips/blob/master/bip-0054.md:
-    /// > The coinbase transaction's nLockTime field must be set to the height of the block minus 1 and its nSequence field must not be equal to 0xffffffff.
-    pub coinbase_locktime_set_bip54: bool,
-
-    /// number of transactions in the block
-    pub transactions: i32,
-    /// number of payments in the block
-    pub payments: i32,
-    /// count of payments made by SegWit spending transactions
-    pub payments_segwit_spending_tx: i32,
-    /// count of payments made by Taproot spending transactions
-    pub payments_taproot_spending_tx: i32,
-    /// count of payments where the transaction signals RBF
-    pub payments_signaling_explicit_rbf: i32,
-
-    /// number of inputs spent in this block
-    pub inputs: i32,
-    /// number of outputs created in this block
-    pub outputs: i32,
-    /// the pool id, if the pool could be identified. If the pool is unknown,
-    /// the id will be 0. See the IDs in https://github.com/bitcoin-data/mining-pools/blob/generated/pool-list.json
-    pub pool_id: i32,
+stats_table! {
+    table = block_stats;
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BlockStats {
+        pub height: i64,
+        #[sql_type = Date]
+        pub date: String,
+
+        pub version: i32,
+        pub nonce: i32,
+        pub bits: i32,
+        /// Low-presision block difficulty. Stored as i64 as SQLite doesn't support
+        /// f64 nor u128.
+        pub difficulty: i64,
+        /// the difficulty calculated exactly from the bits, as a decimal with
+        /// 16 fractional digits (truncated)
+        pub difficulty_exact: String,
+        /// Low-presision log2(work) for this block. Not to be confused with Bitcoin Core's cumulative log2_work
+        /// for a block at a given height. This one is not cumulative.
+        pub log2_work: f32,
+
+        /// the size of the block in bytes
+        pub size: i64,
+        /// the size of the block excluding the witness data.
+        pub stripped_size: i64,
+        /// the virtual size of the block in bytes (ceil(weight / 4.0))
+        pub vsize: i64,
+        /// the size of the block in bytes
+        pub weight: i64,
+        /// the block is empty (no tx besides the coinbase tx)
+        pub empty: bool,
+
+        /// Coinbase output amounts (sum)
+        pub coinbase_output_amount: i64,
+        /// Coinbase transactoin weight
+        pub coinbase_weight: i64,
+        /// the coinbase locktime has a (non zero) value set. This locktime might not be enforced.
+        pub coinbase_locktime_set: bool,
+        /// The coinbase locktime as a bip54 value set:
+        /// from https://github.com/bitcoin/bips/blob/master/bip-0054.md:
+        /// > The coinbase transaction's nLockTime field must be set to the height of the block minus 1 and its nSequence field must not be equal to 0xffffffff.
+        pub coinbase_locktime_set_bip54: bool,
+
+        /// number of transactions in the block
+        pub transactions: i32,
+        /// number of payments in the block
+        pub payments: i32,
+        /// count of payments made by SegWit spending transactions
+        pub payments_segwit_spending_tx: i32,
+        /// count of payments made by Taproot spending transactions
+        pub payments_taproot_spending_tx: i32,
+        /// count of payments where the transaction signals RBF
+        pub payments_signaling_explicit_rbf: i32,
+
+        /// number of inputs spent in this block
+        pub inputs: i32,
+        /// number of outputs created in this block
+        pub outputs: i32,
+        /// the pool id, if the pool could be identified. If the pool is unknown,
+        /// the id will be 0. See the IDs in https://github.com/bitcoin-data/mining-pools/blob/generated/pool-list.json
+        pub pool_id: i32,
+        /// always 1. The sum is the number of blocks per day.
+        pub block_count: i32,
+
+        /// the lowest feerate (sat/vB) of the transactions paying a non-zero fee.
+        /// The feerate floor the pool included. 0 if there are no such transactions.
+        pub feerate_min_nonzero: f32,
+
+        /// the feerate (sat/vB) at which the given share of the non-coinbase
+        /// block space was bought: transactions are weighted by their vsize
+        /// instead of counted once. 0 for blocks with only a coinbase.
+        pub feerate_vsize_weighted_5th_percentile: f32,
+        pub feerate_vsize_weighted_10th_percentile: f32,
+        pub feerate_vsize_weighted_25th_percentile: f32,
+        pub feerate_vsize_weighted_50th_percentile: f32,
+        pub feerate_vsize_weighted_75th_percentile: f32,
+        pub feerate_vsize_weighted_90th_percentile: f32,
+        pub feerate_vsize_weighted_95th_percentile: f32,
+
+        /// the block header timestamp (unix time). Used to recalculate the
+        /// dates when the aggregation timezone changes.
+        pub time: i64,
+
+        /// the number in the first push of the coinbase scriptSig, which
+        /// BIP34 requires to be the block height. Before BIP34, the coinbase
+        /// often starts with other data (e.g. the bits), so this isn't the
+        /// height. -1 if there's no minimally encoded number of up to 4 bytes.
+        pub coinbase_script_height: i64,
+        /// the coinbase scriptSig starts with the block height as required by
+        /// BIP34, which is enforced since height 227931. false after the
+        /// activation is a violation.
+        pub coinbase_bip34_height: bool,
+        /// the size of the coinbase scriptSig after the first push, the
+        /// BIP34 height: the space pools use for the extranonce and their tags
+        pub coinbase_extra_size: i32,
+        /// the header version bits reserved for version-rolling by BIP320
+        /// (bits 13 to 28) that are set, i.e. the version masked with
+        /// 0x1fffe000. Set by miners using overt AsicBoost.
+        pub version_rolling_bits: i32,
+        /// any of the BIP320 version-rolling bits is set
+        pub version_rolling: bool,
+        /// the share of the block weight used by the coinbase transaction
+        pub coinbase_weight_share: f32,
+        /// the coinbase is larger than 5000 WU, e.g. due to many payout
+        /// outputs, and the block is too full to fit transactions of the
+        /// coinbase's weight: the coinbase displaced fee-paying transactions
+        pub coinbase_large_displacing: bool,
+        /// the sum of the amounts spent by the inputs (sats)
+        pub input_amount: i64,
+        /// the sum of the output amounts of the non-coinbase transactions
+        /// (sats). The difference to the input amount are the fees.
+        pub output_amount: i64,
+        /// the number of inputs per output of the non-coinbase transactions.
+        /// Above 1 when more outputs are consolidated than created.
+        pub inputs_per_output: f32,
+    }
 }
 
 impl BlockStats {
@@ -203,13 +830,11 @@ impl BlockStats {
         pools: &[Pool],
     ) -> Result<BlockStats, StatsError> {
         let height = block.height;
-        let coinbase_tx: Transaction = bitcoin::consensus::deserialize(
-            &block
-                .txdata
-                .first()
-                .expect("block should have a coinbase tx")
-                .raw,
-        )?;
+        let coinbase_tx = block
+            .txdata
+            .first()
+            .expect("block should have a coinbase tx")
+            .transaction()?;
         let pool_id: i32 = match coinbase_tx.identify_pool(Network::Bitcoin, pools) {
             Some(result) => {
                 debug!(
@@ -224,18 +849,21 @@ impl BlockStats {
             }
         };
 
-        let target = Target::from_compact(CompactTarget::from_unprefixed_hex(&block.bits)?);
+        let bits = CompactTarget::from_unprefixed_hex(&block.bits)?;
+        let target = Target::from_compact(bits);
+        let weighted_feerates = VsizeWeightedFeerates::from_block(block);
 
         Ok(BlockStats {
-            stats_version: STATS_VERSION,
             height,
             date: date.to_string(),
             version: block.version.to_consensus(),
             nonce: block.nonce as i32,
             bits: i32::from_str_radix(&block.bits, 16)?,
             difficulty: target.difficulty_float() as i64,
+            difficulty_exact: exact_difficulty(bits).unwrap_or_default(),
             log2_work: target.to_work().log2() as f32,
             pool_id,
+            block_count: 1,
 
             size: block.size,
             stripped_size: block.stripped_size,
@@ -243,11 +871,7 @@ impl BlockStats {
             weight: block.weight.to_wu() as i64,
             empty: block.txdata.len() == 1,
 
-            coinbase_output_amount: coinbase_tx
-                .output
-                .iter()
-                .map(|o| o.value.to_sat())
-                .sum::<u64>() as i64,
+            coinbase_output_amount: sum_sats(coinbase_tx.output.iter().map(|o| o.value)),
             coinbase_weight: coinbase_tx.weight().to_wu() as i64,
 
             coinbase_locktime_set: coinbase_tx.lock_time != LockTime::ZERO,
@@ -281,66 +905,492 @@ impl BlockStats {
 
             inputs: block.txdata.iter().map(|tx| tx.input.len()).sum::<usize>() as i32,
             outputs: block.txdata.iter().map(|tx| tx.output.len()).sum::<usize>() as i32,
+
+            feerate_min_nonzero: block
+                .txdata
+                .iter()
+                .skip(1)
+                .filter_map(|tx| {
+                    tx.fee
+                        .filter(|fee| fee.to_sat() > 0)
+                        .map(|fee| (fee, tx.vsize))
+                })
+                .map(|(fee, vsize)| fee.to_sat() as f64 / vsize as f64)
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap_or(0.0) as f32,
+
+            feerate_vsize_weighted_5th_percentile: weighted_feerates.percentile(5),
+            feerate_vsize_weighted_10th_percentile: weighted_feerates.percentile(10),
+            feerate_vsize_weighted_25th_percentile: weighted_feerates.percentile(25),
+            feerate_vsize_weighted_50th_percentile: weighted_feerates.percentile(50),
+            feerate_vsize_weighted_75th_percentile: weighted_feerates.percentile(75),
+            feerate_vsize_weighted_90th_percentile: weighted_feerates.percentile(90),
+            feerate_vsize_weighted_95th_percentile: weighted_feerates.percentile(95),
+
+            time: block.time as i64,
+
+            coinbase_script_height: coinbase_script_height(&coinbase_tx.input[0].script_sig)
+                .unwrap_or(-1),
+            coinbase_bip34_height: coinbase_tx.input[0].script_sig.as_bytes().starts_with(
+                bitcoin::script::Builder::new()
+                    .push_int(height)
+                    .into_script()
+                    .as_bytes(),
+            ),
+            coinbase_extra_size: coinbase_extra_size(&coinbase_tx.input[0].script_sig),
+            version_rolling_bits: block.version.to_consensus() & VERSION_ROLLING_MASK,
+            version_rolling: block.version.to_consensus() & VERSION_ROLLING_MASK != 0,
+            coinbase_weight_share: coinbase_tx.weight().to_wu() as f32
+                / block.weight.to_wu() as f32,
+            coinbase_large_displacing: coinbase_tx.weight().to_wu() > LARGE_COINBASE_WEIGHT
+                && Weight::MAX_BLOCK
+                    .to_wu()
+                    .saturating_sub(block.weight.to_wu())
+                    < coinbase_tx.weight().to_wu(),
+            input_amount: sum_sats(block.txdata.iter().flat_map(|tx| {
+                tx.input.iter().filter_map(|input| match &input.data {
+                    InputData::NonCoinbase { prevout, .. } => Some(prevout.value),
+                    InputData::Coinbase(_) => None,
+                })
+            })),
+            output_amount: sum_sats(
+                block
+                    .txdata
+                    .iter()
+                    .skip(1)
+                    .flat_map(|tx| tx.output.iter().map(|o| o.value)),
+            ),
+            inputs_per_output: match block
+                .txdata
+                .iter()
+                .skip(1)
+                .map(|tx| tx.output.len())
+                .sum::<usize>()
+            {
+                0 => 0.0,
+                outputs => {
+                    block
+                        .txdata
+                        .iter()
+                        .skip(1)
+                        .map(|tx| tx.input.len())
+                        .sum::<usize>() as f32
+                        / outputs as f32
+                }
+            },
         })
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Default, Debug, PartialEq)]
-#[diesel(table_name = crate::schema::tx_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct TxStats {
-    pub height: i64,
-    pub date: String,
-
-    // number of version 1 transactions
-    pub tx_version_1: i32,
-    // number of version 2 transactions
-    pub tx_version_2: i32,
-    // number of version 3 transactions
-    pub tx_version_3: i32,
-    // number of transactions with an unknown version (might change once there are proposals to use e.g. version=4)
-    pub tx_version_unknown: i32,
-
-    pub tx_output_amount: i64,
-
-    pub tx_spending_segwit: i32,
-    pub tx_spending_only_segwit: i32,
-    pub tx_spending_only_legacy: i32,
-    pub tx_spending_only_taproot: i32,
-    pub tx_spending_segwit_and_legacy: i32,
-    pub tx_spending_nested_segwit: i32,
-    pub tx_spending_native_segwit: i32,
-    pub tx_spending_taproot: i32,
-
-    pub tx_bip69_compliant: i32,
-    pub tx_signaling_explicit_rbf: i32,
-
-    pub tx_1_input: i32,
-    pub tx_1_output: i32,
-    pub tx_1_input_1_output: i32,
-    pub tx_1_input_2_output: i32,
-    pub tx_spending_newly_created_utxos: i32,
-    pub tx_spending_ephemeral_dust: i32,
-
-    pub tx_timelock_height: i32,
-    pub tx_timelock_timestamp: i32,
-    pub tx_timelock_not_enforced: i32,
-    pub tx_timelock_too_high: i32,
+/// The header version bits BIP320 reserves for version-rolling. They are
+/// outside of the bits used by BIP9 deployments so far.
+const VERSION_ROLLING_MASK: i32 = 0x1fffe000;
+
+/// Coinbase transactions heavier than this are considered large. Bitcoin
+/// Core reserves 4000 WU for the coinbase when building a block template.
+pub const LARGE_COINBASE_WEIGHT: u64 = 5000;
+
+/// The size of a coinbase scriptSig after its first push, the BIP34 height.
+/// Where the extranonce ends and the pool's tag starts isn't standardized,
+/// so this is the space for both.
+fn coinbase_extra_size(script_sig: &bitcoin::Script) -> i32 {
+    let bytes = script_sig.as_bytes();
+    let first_push = match script_sig.instructions().next() {
+        Some(Ok(Instruction::PushBytes(push))) => {
+            let prefix = match bytes[0] {
+                0x4c => 2, // OP_PUSHDATA1
+                0x4d => 3, // OP_PUSHDATA2
+                0x4e => 5, // OP_PUSHDATA4
+                _ => 1,
+            };
+            prefix + push.len()
+        }
+        Some(Ok(Instruction::Op(_))) => 1,
+        _ => 0,
+    };
+    (bytes.len() - first_push) as i32
+}
+
+/// The number in the first push of a coinbase scriptSig, i.e. the height for
+/// blocks following BIP34. None if the scriptSig doesn't start with a
+/// minimally encoded number of up to 4 bytes.
+fn coinbase_script_height(script_sig: &bitcoin::Script) -> Option<i64> {
+    match script_sig.instructions().next()?.ok()? {
+        Instruction::PushBytes(push) => bitcoin::script::read_scriptint(push.as_bytes()).ok(),
+        Instruction::Op(op) => match op.classify(bitcoin::opcodes::ClassifyContext::Legacy) {
+            bitcoin::opcodes::Class::PushNum(n) => Some(n as i64),
+            _ => None,
+        },
+    }
+}
+
+/// The ordinals inscription envelope marker, pushed after OP_FALSE OP_IF.
+const INSCRIPTION_MARKER: &[u8] = b"ord";
+
+/// How a transaction embeds data, detected with the output and input
+/// classifiers.
+#[derive(Debug, Default, PartialEq)]
+struct DataEmbedding {
+    inscription: bool,
+    opreturn: bool,
+    /// a bare multisig output used by Counterparty or with a pubkey that
+    /// isn't a valid curve point
+    multisig: bool,
+}
+
+impl DataEmbedding {
+    fn of_tx(tx: &rest::Transaction) -> DataEmbedding {
+        let mut embedding = DataEmbedding {
+            inscription: tx.input.iter().any(reveals_inscription),
+            ..Default::default()
+        };
+        let mut counterparty = None;
+        for output in tx.output.iter() {
+            match output.script_pub_key.type_ {
+                ScriptPubkeyType::NullData => embedding.opreturn = true,
+                ScriptPubkeyType::MultiSig => {
+                    let key = counterparty.get_or_insert_with(|| counterparty_key(tx));
+                    let pubkeys = multisig_pubkeys(&output.script_pub_key.script);
+                    embedding.multisig |= is_counterparty_multisig(&pubkeys, key.as_ref())
+                        || pubkeys.iter().any(|pubkey| {
+                            bitcoin::secp256k1::PublicKey::from_slice(pubkey).is_err()
+                        });
+                }
+                _ => (),
+            }
+        }
+        embedding
+    }
+
+    fn any(&self) -> bool {
+        self.inscription || self.opreturn || self.multisig
+    }
+}
+
+/// Whether a P2TR script path spend reveals a tapscript with an inscription
+/// envelope (OP_FALSE OP_IF "ord" ...).
+fn reveals_inscription(input: &rest::Input) -> bool {
+    let InputData::NonCoinbase { prevout, .. } = &input.data else {
+        return false;
+    };
+    if prevout.script_pub_key.type_ != ScriptPubkeyType::Witness_v1_Taproot {
+        return false;
+    }
+    let Some(tapscript) = input.witness.as_ref().and_then(|w| w.tapscript()) else {
+        return false;
+    };
+    let instructions: Vec<Instruction> = tapscript.instructions().map_while(Result::ok).collect();
+    instructions.windows(3).any(|window| {
+        matches!(window[0], Instruction::PushBytes(bytes) if bytes.is_empty())
+            && window[1] == Instruction::Op(bitcoin::opcodes::all::OP_IF)
+            && matches!(window[2], Instruction::PushBytes(bytes) if bytes.as_bytes() == INSCRIPTION_MARKER)
+    })
+}
+
+/// Whether a scriptSig contains a non-push opcode and whether it contains a
+/// non-minimal push. Both can be changed by a third party without
+/// invalidating the signatures, as the signatures don't commit to the
+/// scriptSig. Invalid scripts count as non-push.
+fn scriptsig_malleability(script_sig: &bitcoin::Script) -> (bool, bool) {
+    let non_push = !script_sig.is_push_only();
+    let non_minimal_push = script_sig
+        .instructions_minimal()
+        .any(|i| matches!(i, Err(bitcoin::script::Error::NonMinimalPush)));
+    (non_push, non_minimal_push)
+}
+
+/// The feerates of the non-coinbase transactions of a block in ascending
+/// order, each with the vsize of the transaction.
+struct VsizeWeightedFeerates {
+    feerates: Vec<(f64, u64)>,
+    total_vsize: u64,
+}
+
+impl VsizeWeightedFeerates {
+    fn from_block(block: &Block) -> VsizeWeightedFeerates {
+        let mut feerates: Vec<(f64, u64)> = block
+            .txdata
+            .iter()
+            .skip(1)
+            .map(|tx| {
+                let vsize = tx.vsize as u64;
+                (
+                    tx.fee.unwrap_or_default().to_sat() as f64 / vsize as f64,
+                    vsize,
+                )
+            })
+            .collect();
+        feerates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total_vsize = feerates.iter().map(|(_, vsize)| vsize).sum();
+        VsizeWeightedFeerates {
+            feerates,
+            total_vsize,
+        }
+    }
+
+    /// The lowest feerate at which at least `percentile` percent of the
+    /// vsize was bought.
+    fn percentile(&self, percentile: u8) -> f32 {
+        let threshold = self.total_vsize as f64 * percentile as f64 / 100.0;
+        let mut cumulative_vsize = 0;
+        for (feerate, vsize) in self.feerates.iter() {
+            cumulative_vsize += vsize;
+            if cumulative_vsize as f64 >= threshold {
+                return *feerate as f32;
+            }
+        }
+        0.0
+    }
+}
+
+stats_table! {
+    table = tx_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct TxStats {
+        pub height: i64,
+        #[sql_type = Date]
+        pub date: String,
+
+        /// number of version 1 transactions
+        pub tx_version_1: i32,
+        /// number of version 2 transactions
+        pub tx_version_2: i32,
+        /// number of version 3 transactions
+        pub tx_version_3: i32,
+        /// number of transactions with an unknown version (might change once there are proposals to use e.g. version=4)
+        pub tx_version_unknown: i32,
+
+        pub tx_output_amount: i64,
+
+        pub tx_spending_segwit: i32,
+        pub tx_spending_only_segwit: i32,
+        pub tx_spending_only_legacy: i32,
+        pub tx_spending_only_taproot: i32,
+        pub tx_spending_segwit_and_legacy: i32,
+        pub tx_spending_nested_segwit: i32,
+        pub tx_spending_native_segwit: i32,
+        pub tx_spending_taproot: i32,
+        /// number of transactions spending at least one multisig input
+        pub tx_spending_multisig: i32,
+
+        /// output amount of the segwit spending transactions in sat
+        pub tx_spending_segwit_amount: i64,
+        /// output amount of the taproot spending transactions in sat
+        pub tx_spending_taproot_amount: i64,
+        /// output amount of the multisig spending transactions in sat
+        pub tx_spending_multisig_amount: i64,
+
+        /// vsize of the segwit spending transactions in vbyte
+        pub tx_spending_segwit_vsize: i32,
+        /// vsize of the taproot spending transactions in vbyte
+        pub tx_spending_taproot_vsize: i32,
+        /// vsize of the transactions only spending legacy inputs in vbyte
+        pub tx_spending_only_legacy_vsize: i32,
+
+        pub tx_bip69_compliant: i32,
+        pub tx_signaling_explicit_rbf: i32,
+        /// output amount of the transactions explicitly signaling RBF in sat
+        pub tx_signaling_explicit_rbf_amount: i64,
+
+        pub tx_1_input: i32,
+        pub tx_1_output: i32,
+        pub tx_1_input_1_output: i32,
+        pub tx_1_input_2_output: i32,
+        pub tx_spending_newly_created_utxos: i32,
+        pub tx_spending_ephemeral_dust: i32,
+        /// number of transactions with an ephemeral dust output that isn't spent
+        /// in the same block. These violate the ephemeral dust policy.
+        pub tx_ephemeral_dust_unspent: i32,
+
+        /// number of transactions spending a P2A output of a Lightning commitment
+        /// transaction in this block, i.e. a CPFP of a force close
+        pub tx_spending_p2a_ln_anchor: i32,
+        /// number of v3 (TRUC) transactions spending a P2A output not belonging
+        /// to a Lightning commitment transaction
+        pub tx_spending_p2a_truc_child: i32,
+        /// number of other transactions spending a P2A output
+        pub tx_spending_p2a_other: i32,
+
+        /// number of non-coinbase transactions with a vsize of at most 140 vbyte
+        pub tx_vsize_0_140: i32,
+        /// number of non-coinbase transactions with a vsize of 141 to 250 vbyte
+        pub tx_vsize_141_250: i32,
+        /// number of non-coinbase transactions with a vsize of 251 to 500 vbyte
+        pub tx_vsize_251_500: i32,
+        /// number of non-coinbase transactions with a vsize of 501 to 1000 vbyte
+        pub tx_vsize_501_1000: i32,
+        /// number of non-coinbase transactions with a vsize of 1001 to 10000 vbyte
+        pub tx_vsize_1001_10000: i32,
+        /// number of non-coinbase transactions with a vsize of 10001 to 100000 vbyte
+        pub tx_vsize_10001_100000: i32,
+        /// number of non-coinbase transactions with a vsize above 100000 vbyte
+        pub tx_vsize_100001_plus: i32,
+
+        pub tx_timelock_height: i32,
+        pub tx_timelock_timestamp: i32,
+        pub tx_timelock_not_enforced: i32,
+        pub tx_timelock_too_high: i32,
+
+        /// number of transactions with a txid (in RPC byte order) starting with
+        /// at least two zero bytes. By chance, one in 65536 txids does.
+        pub tx_txid_leading_zero_bytes_2: i32,
+        /// number of transactions with a txid starting with at least three zero
+        /// bytes. One in 16.7 million by chance, likely a ground txid.
+        pub tx_txid_leading_zero_bytes_3: i32,
+        /// number of transactions with a txid starting with at least four zero
+        /// bytes, almost certainly a ground txid
+        pub tx_txid_leading_zero_bytes_4: i32,
+
+        /// number of transactions a BIP352 silent payments wallet has to scan:
+        /// transactions with a P2TR output and an input eligible for the shared
+        /// secret derivation, but no input spending a SegWit version 2 or
+        /// higher output
+        pub tx_silent_payments_eligible: i32,
+        /// number of P2TR outputs in the silent payments eligible transactions
+        pub tx_silent_payments_eligible_outputs_p2tr: i32,
+        /// number of eligible P2TR inputs (key path or script path spends with
+        /// an internal key other than the NUMS point) in the silent payments
+        /// eligible transactions
+        pub tx_silent_payments_eligible_inputs_p2tr: i32,
+        /// number of eligible P2WPKH inputs (with a compressed public key) in
+        /// the silent payments eligible transactions
+        pub tx_silent_payments_eligible_inputs_p2wpkh: i32,
+        /// number of eligible P2SH-P2WPKH inputs (with a compressed public key)
+        /// in the silent payments eligible transactions
+        pub tx_silent_payments_eligible_inputs_p2sh_p2wpkh: i32,
+        /// number of eligible P2PKH inputs (with a compressed public key) in the
+        /// silent payments eligible transactions
+        pub tx_silent_payments_eligible_inputs_p2pkh: i32,
+        /// transactions with an input whose scriptSig contains an opcode other
+        /// than a data push. A third party can change such a scriptSig, and
+        /// with it the txid, without invalidating the signatures
+        pub tx_scriptsig_non_push: i32,
+        /// transactions with an input whose scriptSig pushes data with a
+        /// larger than necessary push opcode. Re-encoding the push minimally
+        /// changes the txid without invalidating the signatures
+        pub tx_scriptsig_non_minimal_push: i32,
+        /// non-coinbase transactions that embed no data as detected by the
+        /// tx_data_embedding classifiers, i.e. payment-like traffic
+        pub tx_payment_like: i32,
+        /// non-coinbase transactions with an OP_RETURN output, a bare
+        /// multisig output storing data (Counterparty or pubkeys that aren't
+        /// valid curve points) or an input revealing an inscription
+        pub tx_data_embedding: i32,
+        /// non-coinbase transactions with an input revealing an ordinals
+        /// inscription envelope
+        pub tx_data_embedding_inscription: i32,
+        /// non-coinbase transactions with an OP_RETURN output, e.g. runestones
+        pub tx_data_embedding_opreturn: i32,
+    }
+}
+
+/// Who spends a P2A (pay-to-anchor) output, based on heuristics.
+#[derive(Debug, PartialEq)]
+enum P2aSpender {
+    /// The spent P2A output belongs to a Lightning commitment transaction.
+    LightningAnchor,
+    /// A v3 (TRUC) child.
+    TrucChild,
+    Other,
+}
+
+impl P2aSpender {
+    /// Classifies a transaction spending one or more P2A outputs. Returns None
+    /// if the transaction doesn't spend a P2A output. Lightning commitment
+    /// transactions can only be detected if they are in the same block.
+    fn classify(
+        tx: &rest::Transaction,
+        txs_in_this_block: &HashMap<&Txid, &rest::Transaction>,
+    ) -> Option<P2aSpender> {
+        let mut p2a_parents = tx.input.iter().filter_map(|input| match &input.data {
+            InputData::NonCoinbase { txid, prevout, .. }
+                if matches!(prevout.script_pub_key.type_, ScriptPubkeyType::Anchor) =>
+            {
+                Some(txid)
+            }
+            _ => None,
+        });
+        let first_parent = p2a_parents.next()?;
+
+        let spends_ln_anchor = std::iter::once(first_parent)
+            .chain(p2a_parents)
+            .filter_map(|txid| txs_in_this_block.get(txid))
+            .any(|parent| is_ln_commitment(parent));
+        if spends_ln_anchor {
+            Some(P2aSpender::LightningAnchor)
+        } else if tx.version == 3 {
+            Some(P2aSpender::TrucChild)
+        } else {
+            Some(P2aSpender::Other)
+        }
+    }
+}
+
+/// Heuristic for Lightning commitment transactions: BOLT 3 encodes the obscured
+/// commitment number in the lower 24 bits of the locktime and the sequence of
+/// the single input. The upper 8 bits are set to 0x20 and 0x80 respectively.
+fn is_ln_commitment(tx: &rest::Transaction) -> bool {
+    tx.input.len() == 1
+        && tx.lock_time.to_consensus_u32() >> 24 == 0x20
+        && tx.input[0].sequence.0 >> 24 == 0x80
 }
 
 impl TxStats {
     pub fn from_block(block: &Block, date: String, tx_infos: &[TxInfo]) -> TxStats {
+        Self::calculate(block, date, tx_infos, None)
+    }
+
+    /// Like [TxStats::from_block], but also returns the txids of the
+    /// transactions that contributed to the counter in `column`.
+    pub fn from_block_with_contributors(
+        block: &Block,
+        date: String,
+        tx_infos: &[TxInfo],
+        column: &str,
+    ) -> (TxStats, Vec<Txid>) {
+        let mut contributors = vec![];
+        let s = Self::calculate(block, date, tx_infos, Some((column, &mut contributors)));
+        (s, contributors)
+    }
+
+    /// The value of the counter in `column`. Only used when capturing the
+    /// contributors of a counter, as it's slow.
+    fn counter(&self, column: &str) -> Option<i64> {
+        serde_json::to_value(self).ok()?.get(column)?.as_i64()
+    }
+
+    fn calculate(
+        block: &Block,
+        date: String,
+        tx_infos: &[TxInfo],
+        mut capture: Option<(&str, &mut Vec<Txid>)>,
+    ) -> TxStats {
         let height = block.height;
         let mut s = TxStats::default();
 
-        let mut txids_in_this_block: HashSet<&Txid> = HashSet::with_capacity(block.txdata.len());
+        let mut txs_in_this_block: HashMap<&Txid, &rest::Transaction> =
+            HashMap::with_capacity(block.txdata.len());
         let mut ephemeral_dust_outpoints_in_this_block: HashSet<(&Txid, u32)> = HashSet::new();
 
         s.height = height;
         s.date = date;
 
-        for (tx, tx_info) in block.txdata.iter().zip(tx_infos.iter()) {
+        for (i, (tx, tx_info)) in block.txdata.iter().zip(tx_infos.iter()).enumerate() {
+            let counter_before = capture.as_ref().and_then(|(column, _)| s.counter(column));
+
+            // the coinbase transaction isn't counted in the vsize classes
+            if i > 0 {
+                match tx.vsize {
+                    0..=140 => s.tx_vsize_0_140 += 1,
+                    141..=250 => s.tx_vsize_141_250 += 1,
+                    251..=500 => s.tx_vsize_251_500 += 1,
+                    501..=1_000 => s.tx_vsize_501_1000 += 1,
+                    1_001..=10_000 => s.tx_vsize_1001_10000 += 1,
+                    10_001..=100_000 => s.tx_vsize_10001_100000 += 1,
+                    _ => s.tx_vsize_100001_plus += 1,
+                }
+            }
+
             match tx.version {
                 1 => s.tx_version_1 += 1,
                 2 => s.tx_version_2 += 1,
@@ -348,10 +1398,13 @@ impl TxStats {
                 _ => s.tx_version_unknown += 1,
             }
 
-            s.tx_output_amount += tx_info.output_value_sum().to_sat() as i64;
+            let output_amount = tx_info.output_value_sum();
+            add_sats(&mut s.tx_output_amount, output_amount);
 
             if tx_info.is_spending_segwit() {
                 s.tx_spending_segwit += 1;
+                add_sats(&mut s.tx_spending_segwit_amount, output_amount);
+                s.tx_spending_segwit_vsize += tx.vsize as i32;
                 if tx_info.is_spending_native_segwit() {
                     s.tx_spending_native_segwit += 1;
                 }
@@ -360,15 +1413,23 @@ impl TxStats {
                 }
                 if tx_info.is_spending_taproot() {
                     s.tx_spending_taproot += 1;
+                    add_sats(&mut s.tx_spending_taproot_amount, output_amount);
+                    s.tx_spending_taproot_vsize += tx.vsize as i32;
                 }
             }
 
+            if tx_info.is_spending_multisig() {
+                s.tx_spending_multisig += 1;
+                add_sats(&mut s.tx_spending_multisig_amount, output_amount);
+            }
+
             if tx_info.is_spending_segwit_and_legacy() {
                 s.tx_spending_segwit_and_legacy += 1;
             }
 
             if tx_info.is_only_spending_legacy() {
                 s.tx_spending_only_legacy += 1;
+                s.tx_spending_only_legacy_vsize += tx.vsize as i32;
             } else if tx_info.is_only_spending_segwit() {
                 s.tx_spending_only_segwit += 1;
                 if tx_info.is_only_spending_taproot() {
@@ -382,6 +1443,7 @@ impl TxStats {
 
             if tx_info.is_signaling_explicit_rbf_replicability() {
                 s.tx_signaling_explicit_rbf += 1;
+                add_sats(&mut s.tx_signaling_explicit_rbf_amount, output_amount);
             }
 
             if tx.input.len() == 1 {
@@ -405,7 +1467,7 @@ impl TxStats {
                     None
                 }
             }) {
-                tx_spending_newly_created_utxos |= txids_in_this_block.contains(txid);
+                tx_spending_newly_created_utxos |= txs_in_this_block.contains_key(txid);
                 tx_spending_ephemeral_dust |= ephemeral_dust_outpoints_in_this_block
                     .take(&(txid, *vout))
                     .is_some()
@@ -414,7 +1476,9 @@ impl TxStats {
                     && tx.fee.unwrap() != Amount::ZERO
                     && tx.vsize <= 1_000;
 
-                if (tx_spending_ephemeral_dust || ephemeral_dust_outpoints_in_this_block.is_empty())
+                // Don't stop early on spent ephemeral dust, as we need to remove all spent
+                // ephemeral dust outpoints to know which ones remain unspent.
+                if ephemeral_dust_outpoints_in_this_block.is_empty()
                     && tx_spending_newly_created_utxos
                 {
                     break;
@@ -423,9 +1487,16 @@ impl TxStats {
             s.tx_spending_newly_created_utxos += i32::from(tx_spending_newly_created_utxos);
             s.tx_spending_ephemeral_dust += i32::from(tx_spending_ephemeral_dust);
 
+            match P2aSpender::classify(tx, &txs_in_this_block) {
+                Some(P2aSpender::LightningAnchor) => s.tx_spending_p2a_ln_anchor += 1,
+                Some(P2aSpender::TrucChild) => s.tx_spending_p2a_truc_child += 1,
+                Some(P2aSpender::Other) => s.tx_spending_p2a_other += 1,
+                None => (),
+            }
+
             // A parent is always ordered before the child in the transaction list of a block, so we can insert the
             // parent here, and detect any children of this parent in subsequent iterations of the loop
-            txids_in_this_block.insert(&tx.txid);
+            txs_in_this_block.insert(&tx.txid, tx);
 
             // We do not include any cases of ephemeral dust on coinbase transactions (these transactions have their
             // `tx.fee` set to `None`); we are only interested in cases of ephemeral dust that could have been submitted
@@ -443,8 +1514,7 @@ impl TxStats {
                 // A transaction with more than 1 dust output was likely submitted out-of-band, so don't count them in
                 // the `tx_spending_ephemeral_dust` tally
                 if staged_ephemeral_dust_outpoints.len() == 1 {
-                    ephemeral_dust_outpoints_in_this_block
-                        .extend(staged_ephemeral_dust_outpoints.into_iter());
+                    ephemeral_dust_outpoints_in_this_block.extend(staged_ephemeral_dust_outpoints);
                 }
             }
 
@@ -461,197 +1531,671 @@ impl TxStats {
             if tx.lock_time.is_block_height() && tx.lock_time.to_consensus_u32() > height as u32 {
                 s.tx_timelock_too_high += 1;
             }
+
+            match txid_leading_zero_bytes(&tx.txid) {
+                0 | 1 => (),
+                2 => s.tx_txid_leading_zero_bytes_2 += 1,
+                3 => {
+                    s.tx_txid_leading_zero_bytes_2 += 1;
+                    s.tx_txid_leading_zero_bytes_3 += 1;
+                }
+                _ => {
+                    s.tx_txid_leading_zero_bytes_2 += 1;
+                    s.tx_txid_leading_zero_bytes_3 += 1;
+                    s.tx_txid_leading_zero_bytes_4 += 1;
+                }
+            }
+
+            let outputs_p2tr = tx
+                .output
+                .iter()
+                .filter(|output| {
+                    output.script_pub_key.type_ == ScriptPubkeyType::Witness_v1_Taproot
+                })
+                .count() as i32;
+            if outputs_p2tr > 0 && !tx.input.iter().any(SilentPaymentsInput::is_future_segwit) {
+                let eligible_inputs: Vec<SilentPaymentsInput> = tx
+                    .input
+                    .iter()
+                    .filter_map(SilentPaymentsInput::classify)
+                    .collect();
+                if !eligible_inputs.is_empty() {
+                    s.tx_silent_payments_eligible += 1;
+                    s.tx_silent_payments_eligible_outputs_p2tr += outputs_p2tr;
+                    for input in eligible_inputs.iter() {
+                        match input {
+                            SilentPaymentsInput::P2tr => {
+                                s.tx_silent_payments_eligible_inputs_p2tr += 1
+                            }
+                            SilentPaymentsInput::P2wpkh => {
+                                s.tx_silent_payments_eligible_inputs_p2wpkh += 1
+                            }
+                            SilentPaymentsInput::P2shP2wpkh => {
+                                s.tx_silent_payments_eligible_inputs_p2sh_p2wpkh += 1
+                            }
+                            SilentPaymentsInput::P2pkh => {
+                                s.tx_silent_payments_eligible_inputs_p2pkh += 1
+                            }
+                        }
+                    }
+                }
+            }
+
+            // SegWit inputs have an empty or a single push scriptSig, only the
+            // scriptSigs of legacy inputs are malleable
+            let (mut non_push, mut non_minimal_push) = (false, false);
+            for input in tx.input.iter() {
+                if let InputData::NonCoinbase { script_sig, .. } = &input.data {
+                    let (a, b) = scriptsig_malleability(&script_sig.script);
+                    non_push |= a;
+                    non_minimal_push |= b;
+                }
+            }
+            s.tx_scriptsig_non_push += i32::from(non_push);
+            s.tx_scriptsig_non_minimal_push += i32::from(non_minimal_push);
+
+            if i > 0 {
+                let embedding = DataEmbedding::of_tx(tx);
+                if embedding.any() {
+                    s.tx_data_embedding += 1;
+                    s.tx_data_embedding_inscription += i32::from(embedding.inscription);
+                    s.tx_data_embedding_opreturn += i32::from(embedding.opreturn);
+                } else {
+                    s.tx_payment_like += 1;
+                }
+            }
+
+            if let Some((column, contributors)) = capture.as_mut() {
+                if s.counter(column) > counter_before {
+                    contributors.push(tx.txid);
+                }
+            }
+        }
+
+        // Spent ephemeral dust outpoints are removed above, the remaining ones
+        // weren't spent in this block.
+        s.tx_ephemeral_dust_unspent = ephemeral_dust_outpoints_in_this_block.len() as i32;
+        if let Some(("tx_ephemeral_dust_unspent", contributors)) = capture.as_mut() {
+            contributors.extend(
+                ephemeral_dust_outpoints_in_this_block
+                    .iter()
+                    .map(|(txid, _)| **txid),
+            );
         }
 
         s
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Default, Debug, PartialEq)]
-#[diesel(table_name = crate::schema::script_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct ScriptStats {
-    height: i64,
-    date: String,
-
-    pubkeys: i32,
-    pubkeys_compressed: i32,
-    pubkeys_uncompressed: i32,
-    pubkeys_compressed_inputs: i32,
-    pubkeys_uncompressed_inputs: i32,
-    pubkeys_compressed_outputs: i32,
-    pubkeys_uncompressed_outputs: i32,
-
-    sigs_schnorr: i32,
-    sigs_ecdsa: i32,
-    sigs_ecdsa_not_strict_der: i32,
-    sigs_ecdsa_strict_der: i32,
-
-    sigs_ecdsa_length_less_70byte: i32,
-    sigs_ecdsa_length_70byte: i32,
-    sigs_ecdsa_length_71byte: i32,
-    sigs_ecdsa_length_72byte: i32,
-    sigs_ecdsa_length_73byte: i32,
-    sigs_ecdsa_length_74byte: i32,
-    sigs_ecdsa_length_75byte_or_more: i32,
-
-    sigs_ecdsa_low_r: i32,
-    sigs_ecdsa_high_r: i32,
-    sigs_ecdsa_low_s: i32,
-    sigs_ecdsa_high_s: i32,
-    sigs_ecdsa_high_rs: i32,
-    sigs_ecdsa_low_rs: i32,
-    sigs_ecdsa_low_r_high_s: i32,
-    sigs_ecdsa_high_r_low_s: i32,
-
-    sigs_sighashes: i32,
-    sigs_sighash_all: i32,
-    sigs_sighash_none: i32,
-    sigs_sighash_single: i32,
-    sigs_sighash_all_acp: i32,
-    sigs_sighash_none_acp: i32,
-    sigs_sighash_single_acp: i32,
+stats_table! {
+    table = script_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct ScriptStats {
+        height: i64,
+        #[sql_type = Date]
+        date: String,
+
+        pubkeys: i32,
+        pubkeys_compressed: i32,
+        pubkeys_uncompressed: i32,
+        pubkeys_compressed_inputs: i32,
+        pubkeys_uncompressed_inputs: i32,
+        pubkeys_compressed_outputs: i32,
+        pubkeys_uncompressed_outputs: i32,
+
+        sigs_schnorr: i32,
+        /// number of 64 byte Schnorr signatures using the default sighash
+        sigs_schnorr_64byte: i32,
+        /// number of 65 byte Schnorr signatures with an explicit sighash byte
+        sigs_schnorr_65byte: i32,
+        sigs_ecdsa: i32,
+        sigs_ecdsa_not_strict_der: i32,
+        sigs_ecdsa_strict_der: i32,
+
+        sigs_ecdsa_length_less_70byte: i32,
+        sigs_ecdsa_length_70byte: i32,
+        sigs_ecdsa_length_71byte: i32,
+        sigs_ecdsa_length_72byte: i32,
+        sigs_ecdsa_length_73byte: i32,
+        sigs_ecdsa_length_74byte: i32,
+        sigs_ecdsa_length_75byte_or_more: i32,
+
+        sigs_ecdsa_low_r: i32,
+        sigs_ecdsa_high_r: i32,
+        sigs_ecdsa_low_s: i32,
+        sigs_ecdsa_high_s: i32,
+        sigs_ecdsa_high_rs: i32,
+        sigs_ecdsa_low_rs: i32,
+        sigs_ecdsa_low_r_high_s: i32,
+        sigs_ecdsa_high_r_low_s: i32,
+
+        sigs_sighashes: i32,
+        sigs_sighash_all: i32,
+        sigs_sighash_none: i32,
+        sigs_sighash_single: i32,
+        sigs_sighash_all_acp: i32,
+        sigs_sighash_none_acp: i32,
+        sigs_sighash_single_acp: i32,
+
+        /// number of P2TR key-path signatures using SIGHASH_DEFAULT (64 byte)
+        sigs_schnorr_keypath_sighash_default: i32,
+        /// number of P2TR key-path signatures with an explicit SIGHASH_ALL byte
+        sigs_schnorr_keypath_sighash_all: i32,
+        /// number of P2TR script-path signatures using SIGHASH_DEFAULT (64 byte)
+        sigs_schnorr_scriptpath_sighash_default: i32,
+        /// number of P2TR script-path signatures with an explicit SIGHASH_ALL byte
+        sigs_schnorr_scriptpath_sighash_all: i32,
+
+        /// number of multisig inputs with an empty CHECKMULTISIG dummy
+        /// element (OP_0), as required by NULLDUMMY (BIP147)
+        multisig_dummy_null: i32,
+        /// number of multisig inputs with a non-empty CHECKMULTISIG dummy
+        /// element. Only possible before NULLDUMMY activated.
+        multisig_dummy_not_null: i32,
+
+        /// number of transactions with two or more P2TR key-path inputs
+        tx_multiple_p2tr_keypath_inputs: i32,
+        /// number of P2TR key-path inputs in transactions with two or more of them
+        inputs_p2tr_keypath_in_multi_keypath_tx: i32,
+        /// number of key-path signatures that could be dropped with full
+        /// cross-input signature aggregation: all but one per transaction
+        sigs_schnorr_aggregatable: i32,
+
+        /// number of bare CTV outputs: `<32 byte template hash> OP_NOP4`
+        outputs_bare_ctv: i32,
+        /// number of outputs with OP_NOP4 (OP_CHECKTEMPLATEVERIFY) in the
+        /// scriptPubKey, including bare CTV outputs
+        outputs_nop4: i32,
+        /// number of outputs with one of the other upgradable NOPs (OP_NOP1,
+        /// OP_NOP5 to OP_NOP10) in the scriptPubKey
+        outputs_reserved_nop: i32,
+        /// number of revealed redeem scripts, witness scripts and tapscripts
+        /// with OP_NOP4 (OP_CHECKTEMPLATEVERIFY)
+        scripts_nop4: i32,
+        /// number of revealed redeem scripts, witness scripts and tapscripts
+        /// with one of the other upgradable NOPs
+        scripts_reserved_nop: i32,
+        /// number of revealed tapscripts with an OP_SUCCESSx opcode reserved
+        /// for future soft forks
+        tapscripts_op_success: i32,
+        /// number of revealed tapscripts with OP_CAT (OP_SUCCESS126)
+        tapscripts_op_cat: i32,
+
+        /// number of P2SH redeem scripts that are witness programs other than
+        /// the standard P2SH-P2WPKH and P2SH-P2WSH wrappers, e.g. P2SH-wrapped
+        /// taproot, which is anyone-can-spend
+        redeem_scripts_nonstandard_witness_program: i32,
+        /// number of P2SH redeem scripts that are P2SH scripts themselves
+        redeem_scripts_p2sh: i32,
+        /// number of P2WSH witness scripts that are P2SH scripts or witness
+        /// programs. Their inner script is never executed.
+        witness_scripts_nested_program: i32,
+        /// number of revealed tapscripts that are P2SH scripts or witness
+        /// programs. Their inner script is never executed.
+        tapscripts_nested_program: i32,
+
+        /// size of all witness stack elements of the non-coinbase inputs.
+        /// Excludes the stack element count and length prefixes.
+        witness_bytes: i64,
+        /// witness bytes in elements that look like signatures: 64 or 65 byte
+        /// Schnorr signatures and DER-encoded ECDSA signatures
+        witness_bytes_signatures: i64,
+        /// witness bytes in elements that look like public keys: 33 byte
+        /// compressed and 65 byte uncompressed keys
+        witness_bytes_pubkeys: i64,
+        /// witness bytes in witness scripts, tapscripts and taproot control
+        /// blocks, excluding the data pushes counted as data
+        witness_bytes_scripts: i64,
+        /// witness bytes carrying a data payload: pushes of more than 75
+        /// bytes in witness scripts and tapscripts (e.g. inscription
+        /// envelopes), annexes and other stack elements of more than 75 bytes
+        witness_bytes_data: i64,
+    }
 }
 
 impl ScriptStats {
     pub fn from_block(block: &Block, date: String, tx_infos: &[TxInfo]) -> ScriptStats {
         let height = block.height;
-        let mut s = Self {
-            height,
-            date,
-            ..Default::default()
-        };
 
-        for (_, tx_info) in block.txdata.iter().zip(tx_infos.iter()) {
-            for input in tx_info.input_infos.iter() {
-                // pubkey stats
-                for pubkey in input.pubkey_stats.iter() {
-                    s.pubkeys += 1;
-                    if pubkey.compressed {
-                        s.pubkeys_compressed += 1;
-                        s.pubkeys_compressed_inputs += 1;
-                    } else {
-                        s.pubkeys_uncompressed += 1;
-                        s.pubkeys_uncompressed_inputs += 1;
-                    }
+        // the counters are summed up per rayon job and merged afterwards
+        let s = block
+            .txdata
+            .par_iter()
+            .zip(tx_infos.par_iter())
+            .fold(Self::default, |mut s, (tx, tx_info)| {
+                let keypath_inputs = tx_info
+                    .input_infos
+                    .iter()
+                    .filter(|input| input.in_type == InputType::P2trkp)
+                    .count() as i32;
+                if keypath_inputs > 1 {
+                    s.tx_multiple_p2tr_keypath_inputs += 1;
+                    s.inputs_p2tr_keypath_in_multi_keypath_tx += keypath_inputs;
+                    s.sigs_schnorr_aggregatable += keypath_inputs - 1;
                 }
 
-                // signature stats
-                for sig in input.signature_info.iter() {
-                    if matches!(sig.signature, SignatureType::Schnorr(_)) {
-                        s.sigs_schnorr += 1;
-                    } else if matches!(sig.signature, SignatureType::Ecdsa(_)) {
-                        s.sigs_ecdsa += 1;
-                        if sig.der_encoded == DEREncoding::Valid {
-                            s.sigs_ecdsa_strict_der += 1;
-                        } else {
-                            s.sigs_ecdsa_not_strict_der += 1;
+                for (input, rest_input) in tx_info.input_infos.iter().zip(tx.input.iter()) {
+                    // proposed opcode stats
+                    if let InputData::NonCoinbase {
+                        script_sig,
+                        prevout,
+                        ..
+                    } = &rest_input.data
+                    {
+                        for (script_type, script) in revealed_scripts(
+                            &prevout.script_pub_key.type_,
+                            &script_sig.script,
+                            rest_input.witness.as_ref(),
+                        ) {
+                            let opcodes = ProposedOpcodes::in_script(script);
+                            s.scripts_nop4 += i32::from(opcodes.nop4);
+                            s.scripts_reserved_nop += i32::from(opcodes.reserved_nop);
+                            if script_type == "tapscript" {
+                                s.tapscripts_op_success += i32::from(opcodes.op_success);
+                                s.tapscripts_op_cat += i32::from(opcodes.op_cat);
+                            }
+
+                            // unexpected script nesting stats
+                            let nested = is_nested_program(script);
+                            match script_type {
+                                "redeem_script" => {
+                                    s.redeem_scripts_p2sh += i32::from(script.is_p2sh())
+                                }
+                                "witness_script" => {
+                                    s.witness_scripts_nested_program += i32::from(nested)
+                                }
+                                "tapscript" => s.tapscripts_nested_program += i32::from(nested),
+                                _ => (),
+                            }
                         }
-                        match sig.length {
-                            8..=69 => s.sigs_ecdsa_length_less_70byte += 1,
-                            70 => s.sigs_ecdsa_length_70byte += 1,
-                            71 => s.sigs_ecdsa_length_71byte += 1,
-                            72 => s.sigs_ecdsa_length_72byte += 1,
-                            73 => s.sigs_ecdsa_length_73byte += 1,
-                            74 => s.sigs_ecdsa_length_74byte += 1,
-                            75.. => s.sigs_ecdsa_length_75byte_or_more += 1,
-                            _ => panic!("ECDSA signature with {} bytes..?", sig.length),
+                        if prevout.script_pub_key.type_ == ScriptPubkeyType::ScriptHash {
+                            if let Some(Ok(Instruction::PushBytes(redeem_script))) =
+                                script_sig.script.instructions().last()
+                            {
+                                s.redeem_scripts_nonstandard_witness_program +=
+                                    i32::from(is_nonstandard_wrapped_program(
+                                        bitcoin::Script::from_bytes(redeem_script.as_bytes()),
+                                    ));
+                            }
                         }
+                    }
 
-                        let is_r_low = sig.low_r();
-                        let is_s_low = sig.low_s();
+                    // witness authorization vs. data stats
+                    if let (
+                        InputData::NonCoinbase {
+                            script_sig,
+                            prevout,
+                            ..
+                        },
+                        Some(witness),
+                    ) = (&rest_input.data, rest_input.witness.as_ref())
+                    {
+                        let bytes = WitnessBytes::of_input(
+                            &prevout.script_pub_key.type_,
+                            &script_sig.script,
+                            witness,
+                        );
+                        s.witness_bytes += bytes.total as i64;
+                        s.witness_bytes_signatures += bytes.signatures as i64;
+                        s.witness_bytes_pubkeys += bytes.pubkeys as i64;
+                        s.witness_bytes_scripts += bytes.scripts as i64;
+                        s.witness_bytes_data += bytes.data as i64;
+                    }
 
-                        if is_r_low {
-                            s.sigs_ecdsa_low_r += 1;
-                        } else {
-                            s.sigs_ecdsa_high_r += 1;
+                    // CHECKMULTISIG dummy element stats
+                    if input.multisig_info.is_some() {
+                        match multisig_dummy_is_null(&input.in_type, rest_input) {
+                            Some(true) => s.multisig_dummy_null += 1,
+                            Some(false) => s.multisig_dummy_not_null += 1,
+                            None => (),
                         }
+                    }
 
-                        if is_s_low {
-                            s.sigs_ecdsa_low_s += 1;
+                    // pubkey stats
+                    for pubkey in input.pubkey_stats.iter() {
+                        s.pubkeys += 1;
+                        if pubkey.compressed {
+                            s.pubkeys_compressed += 1;
+                            s.pubkeys_compressed_inputs += 1;
                         } else {
-                            s.sigs_ecdsa_high_s += 1;
+                            s.pubkeys_uncompressed += 1;
+                            s.pubkeys_uncompressed_inputs += 1;
                         }
+                    }
+
+                    // signature stats
+                    for sig in input.signature_info.iter() {
+                        if matches!(sig.signature, SignatureType::Schnorr(_)) {
+                            s.sigs_schnorr += 1;
+                            match sig.length {
+                                64 => s.sigs_schnorr_64byte += 1,
+                                65 => s.sigs_schnorr_65byte += 1,
+                                _ => (),
+                            }
+                            let sighash_default = sig.length == 64;
+                            let sighash_all = sig.length == 65 && sig.sig_hash == 0x01;
+                            match input.in_type {
+                                InputType::P2trkp => {
+                                    s.sigs_schnorr_keypath_sighash_default +=
+                                        i32::from(sighash_default);
+                                    s.sigs_schnorr_keypath_sighash_all += i32::from(sighash_all);
+                                }
+                                InputType::P2trsp => {
+                                    s.sigs_schnorr_scriptpath_sighash_default +=
+                                        i32::from(sighash_default);
+                                    s.sigs_schnorr_scriptpath_sighash_all += i32::from(sighash_all);
+                                }
+                                _ => (),
+                            }
+                        } else if matches!(sig.signature, SignatureType::Ecdsa(_)) {
+                            s.sigs_ecdsa += 1;
+                            if sig.der_encoded == DEREncoding::Valid {
+                                s.sigs_ecdsa_strict_der += 1;
+                            } else {
+                                s.sigs_ecdsa_not_strict_der += 1;
+                            }
+                            match sig.length {
+                                8..=69 => s.sigs_ecdsa_length_less_70byte += 1,
+                                70 => s.sigs_ecdsa_length_70byte += 1,
+                                71 => s.sigs_ecdsa_length_71byte += 1,
+                                72 => s.sigs_ecdsa_length_72byte += 1,
+                                73 => s.sigs_ecdsa_length_73byte += 1,
+                                74 => s.sigs_ecdsa_length_74byte += 1,
+                                75.. => s.sigs_ecdsa_length_75byte_or_more += 1,
+                                _ => panic!("ECDSA signature with {} bytes..?", sig.length),
+                            }
 
-                        if is_r_low && is_s_low {
-                            s.sigs_ecdsa_low_rs += 1;
-                        } else if !is_r_low && !is_s_low {
-                            s.sigs_ecdsa_high_rs += 1;
-                        } else if is_r_low && !is_s_low {
-                            s.sigs_ecdsa_low_r_high_s += 1;
-                        } else if !is_r_low && is_s_low {
-                            s.sigs_ecdsa_high_r_low_s += 1;
+                            let is_r_low = sig.low_r();
+                            let is_s_low = sig.low_s();
+
+                            if is_r_low {
+                                s.sigs_ecdsa_low_r += 1;
+                            } else {
+                                s.sigs_ecdsa_high_r += 1;
+                            }
+
+                            if is_s_low {
+                                s.sigs_ecdsa_low_s += 1;
+                            } else {
+                                s.sigs_ecdsa_high_s += 1;
+                            }
+
+                            if is_r_low && is_s_low {
+                                s.sigs_ecdsa_low_rs += 1;
+                            } else if !is_r_low && !is_s_low {
+                                s.sigs_ecdsa_high_rs += 1;
+                            } else if is_r_low && !is_s_low {
+                                s.sigs_ecdsa_low_r_high_s += 1;
+                            } else if !is_r_low && is_s_low {
+                                s.sigs_ecdsa_high_r_low_s += 1;
+                            }
+
+                            s.sigs_sighashes += 1;
+                            match sig.sig_hash {
+                                0x01 => s.sigs_sighash_all += 1,
+                                0x02 => s.sigs_sighash_none += 1,
+                                0x03 => s.sigs_sighash_single += 1,
+                                0x81 => s.sigs_sighash_all_acp += 1,
+                                0x82 => s.sigs_sighash_none_acp += 1,
+                                0x83 => s.sigs_sighash_single_acp += 1,
+                                _ => (),
+                            }
                         }
+                    }
+                }
 
-                        s.sigs_sighashes += 1;
-                        match sig.sig_hash {
-                            0x01 => s.sigs_sighash_all += 1,
-                            0x02 => s.sigs_sighash_none += 1,
-                            0x03 => s.sigs_sighash_single += 1,
-                            0x81 => s.sigs_sighash_all_acp += 1,
-                            0x82 => s.sigs_sighash_none_acp += 1,
-                            0x83 => s.sigs_sighash_single_acp += 1,
-                            _ => (),
+                for output in tx_info.output_infos.iter() {
+                    // pubkey stats
+                    for pubkey in output.pubkey_stats.iter() {
+                        s.pubkeys += 1;
+                        if pubkey.compressed {
+                            s.pubkeys_compressed += 1;
+                            s.pubkeys_compressed_outputs += 1;
+                        } else {
+                            s.pubkeys_uncompressed += 1;
+                            s.pubkeys_uncompressed_outputs += 1;
                         }
                     }
                 }
-            }
 
-            for output in tx_info.output_infos.iter() {
-                // pubkey stats
-                for pubkey in output.pubkey_stats.iter() {
-                    s.pubkeys += 1;
-                    if pubkey.compressed {
-                        s.pubkeys_compressed += 1;
-                        s.pubkeys_compressed_outputs += 1;
-                    } else {
-                        s.pubkeys_uncompressed += 1;
-                        s.pubkeys_uncompressed_outputs += 1;
+                for output in tx.output.iter() {
+                    let script = &output.script_pub_key.script;
+                    if is_bare_ctv(script) {
+                        s.outputs_bare_ctv += 1;
                     }
+                    let opcodes = ProposedOpcodes::in_script(script);
+                    s.outputs_nop4 += i32::from(opcodes.nop4);
+                    s.outputs_reserved_nop += i32::from(opcodes.reserved_nop);
                 }
+                s
+            })
+            .reduce(Self::default, |mut s, other| {
+                s.add_counts(&other);
+                s
+            });
+        Self { height, date, ..s }
+    }
+}
+
+/// Opcodes reserved for or used by soft fork proposals present in a script.
+#[derive(Debug, Default, PartialEq)]
+struct ProposedOpcodes {
+    /// OP_NOP4, proposed as OP_CHECKTEMPLATEVERIFY (BIP119)
+    nop4: bool,
+    /// OP_NOP1 or OP_NOP5 to OP_NOP10
+    reserved_nop: bool,
+    /// an opcode that is OP_SUCCESSx in tapscript (BIP342)
+    op_success: bool,
+    /// OP_CAT, proposed for tapscript in BIP347
+    op_cat: bool,
+}
+
+impl ProposedOpcodes {
+    /// Scans the opcodes of a script up to the first invalid push.
+    fn in_script(script: &bitcoin::Script) -> ProposedOpcodes {
+        use bitcoin::opcodes::all::{OP_CAT, OP_NOP1, OP_NOP10, OP_NOP4, OP_NOP5};
+        use bitcoin::opcodes::{Class, ClassifyContext};
+
+        let mut opcodes = ProposedOpcodes::default();
+        for (index, _) in script.instruction_indices().map_while(Result::ok) {
+            let opcode = bitcoin::Opcode::from(script.as_bytes()[index]);
+            if opcode == OP_NOP4 {
+                opcodes.nop4 = true;
+            } else if opcode == OP_NOP1
+                || (OP_NOP5.to_u8()..=OP_NOP10.to_u8()).contains(&opcode.to_u8())
+            {
+                opcodes.reserved_nop = true;
+            }
+            if opcode.classify(ClassifyContext::TapScript) == Class::SuccessOp {
+                opcodes.op_success = true;
+            }
+            if opcode == OP_CAT {
+                opcodes.op_cat = true;
             }
         }
-        s
+        opcodes
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Default, Debug, PartialEq)]
-#[diesel(table_name = crate::schema::input_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct InputStats {
-    height: i64,
-    date: String,
-
-    inputs_spending_legacy: i32,
-    inputs_spending_segwit: i32,
-    inputs_spending_taproot: i32,
-    inputs_spending_nested_segwit: i32,
-    inputs_spending_native_segwit: i32,
-    inputs_spending_multisig: i32,
-    inputs_spending_p2ms_multisig: i32,
-    inputs_spending_p2sh_multisig: i32,
-    inputs_spending_nested_p2wsh_multisig: i32,
-    inputs_spending_p2wsh_multisig: i32,
-
-    inputs_p2pk: i32,
-    inputs_p2pkh: i32,
-    inputs_nested_p2wpkh: i32,
-    inputs_p2wpkh: i32,
-    inputs_p2ms: i32,
-    inputs_p2sh: i32,
-    inputs_nested_p2wsh: i32,
-    inputs_p2wsh: i32,
-    inputs_coinbase: i32,
-    inputs_witness_coinbase: i32,
-    inputs_p2tr_keypath: i32,
-    inputs_p2tr_scriptpath: i32,
-    inputs_p2a: i32,
-    inputs_p2a_dust: i32,
-    inputs_unknown: i32,
-
-    inputs_spend_in_same_block: i32,
+/// Whether the scriptPubKey is a bare CTV template: `<32 bytes> OP_NOP4`.
+fn is_bare_ctv(script: &bitcoin::Script) -> bool {
+    let bytes = script.as_bytes();
+    bytes.len() == 34
+        && bytes[0] == bitcoin::opcodes::all::OP_PUSHBYTES_32.to_u8()
+        && bytes[33] == bitcoin::opcodes::all::OP_NOP4.to_u8()
+}
+
+/// Size of the witness stack elements of an input split by what they likely
+/// carry. Elements that don't fit any category (e.g. hash preimages or empty
+/// elements) only count towards the total.
+#[derive(Debug, Default, PartialEq)]
+struct WitnessBytes {
+    total: usize,
+    signatures: usize,
+    pubkeys: usize,
+    scripts: usize,
+    data: usize,
+}
+
+impl WitnessBytes {
+    /// Pushes up to 75 bytes use a direct push opcode. Larger pushes are
+    /// rarely keys or signatures.
+    const MAX_NON_DATA_PUSH: usize = 75;
+
+    /// Classifies the witness elements by size, content and position: the
+    /// last element of a P2WSH spend is the witness script, the last two
+    /// elements of a P2TR script-path spend are the tapscript and the control
+    /// block.
+    fn of_input(
+        spent_type: &ScriptPubkeyType,
+        script_sig: &bitcoin::Script,
+        witness: &bitcoin::Witness,
+    ) -> WitnessBytes {
+        let mut bytes = WitnessBytes::default();
+        let mut elements: Vec<&[u8]> = witness.iter().collect();
+        bytes.total = elements.iter().map(|e| e.len()).sum();
+
+        let is_p2wsh = match spent_type {
+            ScriptPubkeyType::Witness_v0_ScriptHash => true,
+            ScriptPubkeyType::ScriptHash => matches!(
+                script_sig.instructions().last(),
+                Some(Ok(Instruction::PushBytes(redeem_script)))
+                    if bitcoin::Script::from_bytes(redeem_script.as_bytes()).is_p2wsh()
+            ),
+            _ => false,
+        };
+        if is_p2wsh {
+            if let Some(witness_script) = elements.pop() {
+                bytes.add_script(witness_script);
+            }
+        } else if *spent_type == ScriptPubkeyType::Witness_v1_Taproot {
+            if let Some(annex) = witness.taproot_annex() {
+                elements.pop();
+                bytes.data += annex.len();
+            }
+            if elements.len() >= 2 {
+                let control_block = elements.pop().unwrap_or_default();
+                bytes.scripts += control_block.len();
+                let tapscript = elements.pop().unwrap_or_default();
+                bytes.add_script(tapscript);
+            }
+        }
+
+        for element in elements {
+            if is_pubkey_like(element) {
+                bytes.pubkeys += element.len();
+            } else if is_signature_like(element) {
+                bytes.signatures += element.len();
+            } else if element.len() > Self::MAX_NON_DATA_PUSH {
+                bytes.data += element.len();
+            }
+        }
+        bytes
+    }
+
+    /// Counts large pushes in a revealed script as data and the rest of the
+    /// script as script bytes.
+    fn add_script(&mut self, script: &[u8]) {
+        let data: usize = bitcoin::Script::from_bytes(script)
+            .instructions()
+            .map_while(Result::ok)
+            .filter_map(|instruction| match instruction {
+                Instruction::PushBytes(push) if push.len() > Self::MAX_NON_DATA_PUSH => {
+                    Some(push.len())
+                }
+                _ => None,
+            })
+            .sum();
+        self.data += data;
+        self.scripts += script.len() - data;
+    }
+}
+
+fn is_pubkey_like(element: &[u8]) -> bool {
+    matches!(
+        (element.len(), element.first()),
+        (33, Some(0x02 | 0x03)) | (65, Some(0x04))
+    )
+}
+
+/// Schnorr signatures with and without sighash byte and DER-encoded ECDSA
+/// signatures with a sighash byte.
+fn is_signature_like(element: &[u8]) -> bool {
+    matches!(element.len(), 64 | 65)
+        || (matches!(element.len(), 9..=73) && element.first() == Some(&0x30))
+}
+
+/// Returns whether the dummy element consumed by OP_CHECKMULTISIG is empty for
+/// a multisig input. The dummy is the first element of the script sig for
+/// bare and P2SH multisig, and the first witness element for P2WSH multisig.
+fn multisig_dummy_is_null(in_type: &InputType, input: &rest::Input) -> Option<bool> {
+    match in_type {
+        InputType::P2ms | InputType::P2msLaxDer | InputType::P2sh => {
+            let InputData::NonCoinbase { script_sig, .. } = &input.data else {
+                return None;
+            };
+            match script_sig.script.instructions().next()? {
+                Ok(Instruction::PushBytes(push)) => Some(push.is_empty()),
+                _ => Some(false),
+            }
+        }
+        InputType::P2shP2wsh | InputType::P2wsh => Some(input.witness.as_ref()?.nth(0)?.is_empty()),
+        _ => None,
+    }
+}
+
+stats_table! {
+    table = input_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct InputStats {
+        height: i64,
+        #[sql_type = Date]
+        date: String,
+
+        inputs_spending_legacy: i32,
+        inputs_spending_segwit: i32,
+        inputs_spending_taproot: i32,
+        inputs_spending_nested_segwit: i32,
+        inputs_spending_native_segwit: i32,
+        inputs_spending_multisig: i32,
+        inputs_spending_p2ms_multisig: i32,
+        inputs_spending_p2sh_multisig: i32,
+        inputs_spending_nested_p2wsh_multisig: i32,
+        inputs_spending_p2wsh_multisig: i32,
+
+        /// value of the multisig inputs in sat
+        inputs_spending_multisig_amount: i64,
+        inputs_spending_p2ms_multisig_amount: i64,
+        inputs_spending_p2sh_multisig_amount: i64,
+        inputs_spending_nested_p2wsh_multisig_amount: i64,
+        inputs_spending_p2wsh_multisig_amount: i64,
+
+        inputs_p2pk: i32,
+        inputs_p2pkh: i32,
+        inputs_nested_p2wpkh: i32,
+        inputs_p2wpkh: i32,
+        inputs_p2ms: i32,
+        inputs_p2sh: i32,
+        inputs_nested_p2wsh: i32,
+        inputs_p2wsh: i32,
+        inputs_coinbase: i32,
+        inputs_witness_coinbase: i32,
+        inputs_p2tr_keypath: i32,
+        inputs_p2tr_scriptpath: i32,
+        inputs_p2a: i32,
+        inputs_p2a_dust: i32,
+        inputs_unknown: i32,
+
+        /// value of the single-sig inputs in sat. P2TR key-path spends might
+        /// be aggregated multisigs (e.g. MuSig2), which can't be told apart.
+        inputs_p2pk_amount: i64,
+        inputs_p2pkh_amount: i64,
+        inputs_nested_p2wpkh_amount: i64,
+        inputs_p2wpkh_amount: i64,
+        inputs_p2tr_keypath_amount: i64,
+
+        inputs_spend_in_same_block: i32,
+    }
 }
 
 impl InputStats {
@@ -659,130 +2203,313 @@ impl InputStats {
         let height = block.height;
         let txids_in_this_block: HashSet<Txid> = block.txdata.iter().map(|tx| tx.txid).collect();
 
-        let mut s = Self {
-            height,
-            date,
-            ..Default::default()
-        };
+        // the counters are summed up per rayon job and merged afterwards
+        let s = block
+            .txdata
+            .par_iter()
+            .zip(tx_infos.par_iter())
+            .fold(Self::default, |mut s, (tx, tx_info)| {
+                for (input, rest_input) in tx_info.input_infos.iter().zip(tx.input.iter()) {
+                    let amount = match &rest_input.data {
+                        InputData::NonCoinbase { prevout, .. } => prevout.value,
+                        InputData::Coinbase(_) => Amount::ZERO,
+                    };
+
+                    if input.is_spending_legacy() {
+                        s.inputs_spending_legacy += 1;
+                    }
+                    if input.is_spending_segwit() {
+                        s.inputs_spending_segwit += 1;
+                    }
+                    if input.is_spending_taproot() {
+                        s.inputs_spending_taproot += 1;
+                    }
+                    if input.is_spending_nested_segwit() {
+                        s.inputs_spending_nested_segwit += 1;
+                    }
+                    if input.is_spending_native_segwit() {
+                        s.inputs_spending_native_segwit += 1;
+                    }
+                    if input.is_spending_multisig() {
+                        s.inputs_spending_multisig += 1;
+                        add_sats(&mut s.inputs_spending_multisig_amount, amount);
+                        match input.in_type {
+                            InputType::P2ms => {
+                                s.inputs_spending_p2ms_multisig += 1;
+                                add_sats(&mut s.inputs_spending_p2ms_multisig_amount, amount);
+                            }
+                            InputType::P2shP2wsh => {
+                                s.inputs_spending_nested_p2wsh_multisig += 1;
+                                add_sats(
+                                    &mut s.inputs_spending_nested_p2wsh_multisig_amount,
+                                    amount,
+                                );
+                            }
+                            InputType::P2wsh => {
+                                s.inputs_spending_p2wsh_multisig += 1;
+                                add_sats(&mut s.inputs_spending_p2wsh_multisig_amount, amount);
+                            }
+                            InputType::P2sh => {
+                                s.inputs_spending_p2sh_multisig += 1;
+                                add_sats(&mut s.inputs_spending_p2sh_multisig_amount, amount);
+                            }
+                            _ => (),
+                        }
+                    }
 
-        for (tx, tx_info) in block.txdata.iter().zip(tx_infos.iter()) {
-            for input in tx_info.input_infos.iter() {
-                if input.is_spending_legacy() {
-                    s.inputs_spending_legacy += 1;
-                }
-                if input.is_spending_segwit() {
-                    s.inputs_spending_segwit += 1;
-                }
-                if input.is_spending_taproot() {
-                    s.inputs_spending_taproot += 1;
-                }
-                if input.is_spending_nested_segwit() {
-                    s.inputs_spending_nested_segwit += 1;
-                }
-                if input.is_spending_native_segwit() {
-                    s.inputs_spending_native_segwit += 1;
-                }
-                if input.is_spending_multisig() {
-                    s.inputs_spending_multisig += 1;
                     match input.in_type {
-                        InputType::P2ms => s.inputs_spending_p2ms_multisig += 1,
-                        InputType::P2shP2wsh => s.inputs_spending_nested_p2wsh_multisig += 1,
-                        InputType::P2wsh => s.inputs_spending_p2wsh_multisig += 1,
-                        InputType::P2sh => s.inputs_spending_p2sh_multisig += 1,
-                        _ => (),
+                        InputType::P2pk | InputType::P2pkLaxDer => {
+                            s.inputs_p2pk += 1;
+                            add_sats(&mut s.inputs_p2pk_amount, amount);
+                        }
+                        InputType::P2pkh | InputType::P2pkhLaxDer => {
+                            s.inputs_p2pkh += 1;
+                            add_sats(&mut s.inputs_p2pkh_amount, amount);
+                        }
+                        InputType::P2shP2wpkh => {
+                            s.inputs_nested_p2wpkh += 1;
+                            add_sats(&mut s.inputs_nested_p2wpkh_amount, amount);
+                        }
+                        InputType::P2wpkh => {
+                            s.inputs_p2wpkh += 1;
+                            add_sats(&mut s.inputs_p2wpkh_amount, amount);
+                        }
+                        InputType::P2ms | InputType::P2msLaxDer => s.inputs_p2ms += 1,
+                        InputType::P2sh => s.inputs_p2sh += 1,
+                        InputType::P2shP2wsh => s.inputs_nested_p2wsh += 1,
+                        InputType::P2wsh => s.inputs_p2wsh += 1,
+                        InputType::Coinbase => s.inputs_coinbase += 1,
+                        InputType::CoinbaseWitness => s.inputs_witness_coinbase += 1,
+                        InputType::P2trkp => {
+                            s.inputs_p2tr_keypath += 1;
+                            add_sats(&mut s.inputs_p2tr_keypath_amount, amount);
+                        }
+                        InputType::P2trsp => s.inputs_p2tr_scriptpath += 1,
+                        InputType::Unknown | InputType::P2a => s.inputs_unknown += 1,
                     }
                 }
+                for input in tx.input.iter() {
+                    let InputData::NonCoinbase { txid, prevout, .. } = &input.data else {
+                        continue;
+                    };
+                    if txids_in_this_block.contains(txid) {
+                        s.inputs_spend_in_same_block += 1;
+                    }
 
-                match input.in_type {
-                    InputType::P2pk | InputType::P2pkLaxDer => s.inputs_p2pk += 1,
-                    InputType::P2pkh | InputType::P2pkhLaxDer => s.inputs_p2pkh += 1,
-                    InputType::P2shP2wpkh => s.inputs_nested_p2wpkh += 1,
-                    InputType::P2wpkh => s.inputs_p2wpkh += 1,
-                    InputType::P2ms | InputType::P2msLaxDer => s.inputs_p2ms += 1,
-                    InputType::P2sh => s.inputs_p2sh += 1,
-                    InputType::P2shP2wsh => s.inputs_nested_p2wsh += 1,
-                    InputType::P2wsh => s.inputs_p2wsh += 1,
-                    InputType::Coinbase => s.inputs_coinbase += 1,
-                    InputType::CoinbaseWitness => s.inputs_witness_coinbase += 1,
-                    InputType::P2trkp => s.inputs_p2tr_keypath += 1,
-                    InputType::P2trsp => s.inputs_p2tr_scriptpath += 1,
-                    InputType::Unknown | InputType::P2a => s.inputs_unknown += 1,
-                }
-            }
-            for input in tx.input.iter() {
-                let InputData::NonCoinbase { txid, prevout, .. } = &input.data else {
-                    continue;
-                };
-                if txids_in_this_block.contains(txid) {
-                    s.inputs_spend_in_same_block += 1;
+                    if matches!(prevout.script_pub_key.type_, ScriptPubkeyType::Anchor) {
+                        s.inputs_p2a += 1;
+                        s.inputs_unknown -= 1;
+
+                        if prevout.value < bitcoin::Amount::from_sat(P2A_DUST_THRESHOLD) {
+                            s.inputs_p2a_dust += 1;
+                        }
+                    }
                 }
+                s
+            })
+            .reduce(Self::default, |mut s, other| {
+                s.add_counts(&other);
+                s
+            });
+        Self { height, date, ..s }
+    }
+}
 
-                if matches!(prevout.script_pub_key.type_, ScriptPubkeyType::Anchor) {
-                    s.inputs_p2a += 1;
-                    s.inputs_unknown -= 1;
+stats_table! {
+    table = output_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct OutputStats {
+        height: i64,
+        #[sql_type = Date]
+        date: String,
 
-                    if prevout.value < bitcoin::Amount::from_sat(P2A_DUST_THRESHOLD) {
-                        s.inputs_p2a_dust += 1;
-                    }
+        outputs_p2pk: i32,
+        outputs_p2pkh: i32,
+        outputs_p2wpkh: i32,
+        outputs_p2ms: i32,
+        outputs_p2sh: i32,
+        outputs_p2wsh: i32,
+        outputs_opreturn: i32,
+        outputs_p2tr: i32,
+        outputs_p2a: i32,
+        outputs_p2a_dust: i32,
+        outputs_unknown: i32,
+
+        outputs_p2pk_amount: i64,
+        outputs_p2pkh_amount: i64,
+        outputs_p2wpkh_amount: i64,
+        outputs_p2ms_amount: i64,
+        outputs_p2sh_amount: i64,
+        outputs_p2wsh_amount: i64,
+        outputs_p2tr_amount: i64,
+        outputs_p2a_amount: i64,
+        outputs_opreturn_amount: i64,
+        outputs_unknown_amount: i64,
+
+        outputs_opreturn_omnilayer: i32,
+        outputs_opreturn_stacks_block_commit: i32,
+        outputs_opreturn_bip47_payment_code: i32,
+        outputs_opreturn_coinbase_rsk: i32,
+        outputs_opreturn_coinbase_coredao: i32,
+        outputs_opreturn_coinbase_exsat: i32,
+        outputs_opreturn_coinbase_hathor: i32,
+        outputs_opreturn_coinbase_witness_commitment: i32,
+        outputs_opreturn_runestone: i32,
+        outputs_opreturn_bytes: i64,
+
+        outputs_coinbase: i32,
+        outputs_coinbase_p2pk: i32,
+        outputs_coinbase_p2pkh: i32,
+        outputs_coinbase_p2wpkh: i32,
+        outputs_coinbase_p2ms: i32,
+        outputs_coinbase_p2sh: i32,
+        outputs_coinbase_p2wsh: i32,
+        outputs_coinbase_p2tr: i32,
+        outputs_coinbase_opreturn: i32,
+        outputs_coinbase_unknown: i32,
+
+        /// number of outputs that are provably unspendable or pay to a known
+        /// burn address
+        outputs_burned: i32,
+        /// the value permanently destroyed by burned outputs
+        outputs_burned_amount: i64,
+
+        /// OP_RETURN outputs carrying Counterparty data
+        outputs_opreturn_counterparty: i32,
+        /// OP_RETURN outputs of timestamping services like Proof of Existence
+        outputs_opreturn_timestamping: i32,
+        /// bare multisig outputs carrying Counterparty data
+        outputs_p2ms_counterparty: i32,
+        /// bare multisig outputs with a pubkey that isn't a valid curve point,
+        /// i.e. outputs used for embedding arbitrary data
+        outputs_p2ms_data: i32,
+
+        /// non-coinbase OP_RETURN outputs in transactions violating the data
+        /// carrier rules of Bitcoin Core in force when the block was mined
+        outputs_opreturn_nonstandard_at_the_time: i32,
+        /// non-coinbase outputs below the dust threshold of Bitcoin Core in
+        /// force when the block was mined (includes ephemeral dust)
+        outputs_dust_at_the_time: i32,
+
+        /// P2PK outputs with a pubkey that isn't a valid curve point or is a
+        /// pattern like all zero bytes or ASCII text
+        outputs_p2pk_fake_pubkey: i32,
+        /// P2PKH outputs with a pubkey hash that is a pattern like all zero
+        /// bytes or ASCII text
+        outputs_p2pkh_fake_pubkey_hash: i32,
+        /// bare multisig outputs with a pubkey that isn't a valid curve point
+        /// or is a pattern like all zero bytes or ASCII text
+        outputs_p2ms_fake_pubkey: i32,
+        /// the value of the outputs with a fake pubkey or pubkey hash
+        outputs_fake_pubkey_amount: i64,
+
+        /// net change of the UTXO set size (created spendable outputs minus
+        /// spent inputs) by transactions revealing an inscription
+        outputs_utxo_net_inscription: i32,
+        /// net change of the UTXO set size by transactions with a runestone
+        /// and no inscription
+        outputs_utxo_net_runestone: i32,
+        /// net change of the UTXO set size by Counterparty transactions
+        outputs_utxo_net_counterparty: i32,
+        /// net change of the UTXO set size by other data embedding
+        /// transactions (e.g. OP_RETURN outputs or fake pubkeys)
+        outputs_utxo_net_other_data: i32,
+        /// net change of the UTXO set size by transactions embedding no data,
+        /// including the coinbase
+        outputs_utxo_net_ordinary: i32,
+    }
+}
+
+/// The data embedding protocol a transaction's UTXO set change is attributed
+/// to. A transaction matching multiple protocols is attributed to the first.
+#[derive(Debug, PartialEq)]
+enum UtxoProtocol {
+    Inscription,
+    Runestone,
+    Counterparty,
+    OtherData,
+    Ordinary,
+}
+
+impl UtxoProtocol {
+    fn classify(
+        tx: &rest::Transaction,
+        tx_info: &TxInfo,
+        counterparty_key: Option<&[u8; 32]>,
+    ) -> UtxoProtocol {
+        if tx.input.iter().any(reveals_inscription) {
+            return UtxoProtocol::Inscription;
+        }
+        if tx_info
+            .output_infos
+            .iter()
+            .any(|o| matches!(o.out_type, OutputType::OpReturn(OpReturnFlavor::Runestone)))
+        {
+            return UtxoProtocol::Runestone;
+        }
+        let is_counterparty = |output: &rest::Output| {
+            let script = &output.script_pub_key.script;
+            match output.script_pub_key.type_ {
+                ScriptPubkeyType::NullData => {
+                    is_counterparty_opreturn(&opreturn_payload(script), counterparty_key)
+                }
+                ScriptPubkeyType::MultiSig => {
+                    is_counterparty_multisig(&multisig_pubkeys(script), counterparty_key)
                 }
+                _ => false,
             }
+        };
+        if tx.output.iter().any(is_counterparty) {
+            return UtxoProtocol::Counterparty;
         }
-        s
+        if tx.output.iter().any(is_fake_key_output) || DataEmbedding::of_tx(tx).any() {
+            return UtxoProtocol::OtherData;
+        }
+        UtxoProtocol::Ordinary
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Default, Debug, PartialEq)]
-#[diesel(table_name = crate::schema::output_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct OutputStats {
-    height: i64,
-    date: String,
-
-    outputs_p2pk: i32,
-    outputs_p2pkh: i32,
-    outputs_p2wpkh: i32,
-    outputs_p2ms: i32,
-    outputs_p2sh: i32,
-    outputs_p2wsh: i32,
-    outputs_opreturn: i32,
-    outputs_p2tr: i32,
-    outputs_p2a: i32,
-    outputs_p2a_dust: i32,
-    outputs_unknown: i32,
-
-    outputs_p2pk_amount: i64,
-    outputs_p2pkh_amount: i64,
-    outputs_p2wpkh_amount: i64,
-    outputs_p2ms_amount: i64,
-    outputs_p2sh_amount: i64,
-    outputs_p2wsh_amount: i64,
-    outputs_p2tr_amount: i64,
-    outputs_p2a_amount: i64,
-    outputs_opreturn_amount: i64,
-    outputs_unknown_amount: i64,
-
-    outputs_opreturn_omnilayer: i32,
-    outputs_opreturn_stacks_block_commit: i32,
-    outputs_opreturn_bip47_payment_code: i32,
-    outputs_opreturn_coinbase_rsk: i32,
-    outputs_opreturn_coinbase_coredao: i32,
-    outputs_opreturn_coinbase_exsat: i32,
-    outputs_opreturn_coinbase_hathor: i32,
-    outputs_opreturn_coinbase_witness_commitment: i32,
-    outputs_opreturn_runestone: i32,
-    outputs_opreturn_bytes: i64,
-
-    outputs_coinbase: i32,
-    outputs_coinbase_p2pk: i32,
-    outputs_coinbase_p2pkh: i32,
-    outputs_coinbase_p2wpkh: i32,
-    outputs_coinbase_p2ms: i32,
-    outputs_coinbase_p2sh: i32,
-    outputs_coinbase_p2wsh: i32,
-    outputs_coinbase_p2tr: i32,
-    outputs_coinbase_opreturn: i32,
-    outputs_coinbase_unknown: i32,
+// Heights of the blocks with a coinbase that can never be spent: the genesis
+// block coinbase isn't part of the UTXO set, and the coinbases of blocks 91722
+// and 91812 were overwritten by duplicate coinbase transactions (see BIP 30).
+const UNSPENDABLE_COINBASE_HEIGHTS: [i64; 3] = [0, 91722, 91812];
+
+// Scripts larger than this can't be executed and are thus unspendable.
+const MAX_SCRIPT_SIZE: usize = 10_000;
+
+// Well-known burn addresses without a known private key.
+const BURN_ADDRESSES: [&str; 3] = [
+    "1BitcoinEaterAddressDontSendf59kuE",
+    "1CounterpartyXXXXXXXXXXXXXXXUWLpVr",
+    "1111111111111111111114oLvT2",
+];
+
+fn burn_script_pubkeys() -> &'static HashSet<bitcoin::ScriptBuf> {
+    static BURN_SCRIPT_PUBKEYS: OnceLock<HashSet<bitcoin::ScriptBuf>> = OnceLock::new();
+    BURN_SCRIPT_PUBKEYS.get_or_init(|| {
+        BURN_ADDRESSES
+            .iter()
+            .map(|address| {
+                address
+                    .parse::<bitcoin::Address<_>>()
+                    .expect("burn addresses should be valid")
+                    .assume_checked()
+                    .script_pubkey()
+            })
+            .collect()
+    })
+}
+
+/// Provably unspendable outputs (OP_RETURN or larger than the maximum script
+/// size) aren't added to the UTXO set.
+fn is_provably_unspendable(script: &bitcoin::Script) -> bool {
+    script.is_op_return() || script.len() > MAX_SCRIPT_SIZE
+}
+
+/// An output is burned if it's provably unspendable (OP_RETURN or larger than
+/// the maximum script size) or pays to a known burn address.
+fn is_burned(script: &bitcoin::Script) -> bool {
+    is_provably_unspendable(script) || burn_script_pubkeys().contains(script)
 }
 
 /// Returns the total size of data pushed in an OP_RETURN script.
@@ -801,211 +2528,517 @@ fn calculate_opreturn_data_size(script: &bitcoin::ScriptBuf) -> usize {
     total
 }
 
+const COUNTERPARTY_PREFIX: &[u8] = b"CNTRPRTY";
+
+// OP_RETURN payload prefixes of (mostly historical) timestamping services.
+const TIMESTAMPING_PREFIXES: [&[u8]; 4] = [
+    b"DOCPROOF",     // Proof of Existence
+    b"STAMPD##",     // Stampd
+    b"ASCRIBESPOOL", // ascribe
+    b"EW ",          // Eternity Wall
+];
+
+/// RC4 stream cipher. Counterparty obfuscates its data with it.
+fn arc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|i| i as u8);
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// The NUMS point H from BIP341. Script path spends with it as internal key
+/// have no key path and aren't eligible for silent payments.
+const TAPROOT_NUMS_POINT: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// An input type whose public key BIP352 silent payments use to derive the
+/// shared secret.
+#[derive(Debug, PartialEq)]
+enum SilentPaymentsInput {
+    P2tr,
+    P2wpkh,
+    P2shP2wpkh,
+    P2pkh,
+}
+
+impl SilentPaymentsInput {
+    /// Classifies an input under the BIP352 scanning rules. Returns None for
+    /// inputs that are ignored when deriving the shared secret.
+    fn classify(input: &rest::Input) -> Option<SilentPaymentsInput> {
+        let InputData::NonCoinbase {
+            script_sig,
+            prevout,
+            ..
+        } = &input.data
+        else {
+            return None;
+        };
+        let is_compressed_pubkey =
+            |key: &[u8]| key.len() == 33 && (key[0] == 0x02 || key[0] == 0x03);
+        let witness_last = input.witness.as_ref().and_then(|w| w.last());
+        match prevout.script_pub_key.type_ {
+            ScriptPubkeyType::Witness_v1_Taproot => {
+                let witness = input.witness.as_ref()?;
+                let mut elements: Vec<&[u8]> = witness.iter().collect();
+                // the annex is ignored
+                if elements.len() > 1 && elements.last()?.first() == Some(&0x50) {
+                    elements.pop();
+                }
+                // a script path spend ends with the control block, which
+                // contains the internal key
+                if elements.len() > 1 {
+                    let control_block = elements.last()?;
+                    if control_block.get(1..33) == Some(&TAPROOT_NUMS_POINT[..]) {
+                        return None;
+                    }
+                }
+                Some(SilentPaymentsInput::P2tr)
+            }
+            ScriptPubkeyType::Witness_v0_KeyHash => {
+                is_compressed_pubkey(witness_last?).then_some(SilentPaymentsInput::P2wpkh)
+            }
+            ScriptPubkeyType::ScriptHash => {
+                let redeem_script = script_sig.script.as_bytes();
+                // a single push of a P2WPKH witness program
+                (redeem_script.len() == 23
+                    && redeem_script[..3] == [0x16, 0x00, 0x14]
+                    && is_compressed_pubkey(witness_last?))
+                .then_some(SilentPaymentsInput::P2shP2wpkh)
+            }
+            ScriptPubkeyType::PubkeyHash => {
+                let pubkey = match script_sig.script.instructions().last() {
+                    Some(Ok(Instruction::PushBytes(pubkey))) => pubkey,
+                    _ => return None,
+                };
+                is_compressed_pubkey(pubkey.as_bytes()).then_some(SilentPaymentsInput::P2pkh)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the input spends a SegWit version 2 or higher output, which
+    /// makes the transaction ineligible for silent payments.
+    fn is_future_segwit(input: &rest::Input) -> bool {
+        match &input.data {
+            InputData::NonCoinbase { prevout, .. } => prevout
+                .script_pub_key
+                .script
+                .witness_version()
+                .is_some_and(|version| version.to_num() > 1),
+            InputData::Coinbase(_) => false,
+        }
+    }
+}
+
+/// The number of leading zero bytes of the txid in RPC byte order, i.e. the
+/// trailing zero bytes of the hash.
+fn txid_leading_zero_bytes(txid: &Txid) -> usize {
+    use bitcoin::hashes::Hash;
+    txid.to_byte_array()
+        .iter()
+        .rev()
+        .take_while(|byte| **byte == 0)
+        .count()
+}
+
+/// Counterparty uses the txid (in RPC byte order) of the first input as the
+/// ARC4 key.
+fn counterparty_key(tx: &rest::Transaction) -> Option<[u8; 32]> {
+    use bitcoin::hashes::Hash;
+    match &tx.input.first()?.data {
+        InputData::NonCoinbase { txid, .. } => {
+            let mut key = txid.to_byte_array();
+            key.reverse();
+            Some(key)
+        }
+        InputData::Coinbase(_) => None,
+    }
+}
+
+fn opreturn_payload(script: &bitcoin::Script) -> Vec<u8> {
+    script
+        .instructions()
+        .flatten()
+        .filter_map(|inst| match inst {
+            Instruction::PushBytes(bytes) => Some(bytes.as_bytes().to_vec()),
+            Instruction::Op(_) => None,
+        })
+        .flatten()
+        .collect()
+}
+
+fn is_counterparty_opreturn(payload: &[u8], key: Option<&[u8; 32]>) -> bool {
+    payload.starts_with(COUNTERPARTY_PREFIX)
+        || key.is_some_and(|key| arc4(key, payload).starts_with(COUNTERPARTY_PREFIX))
+}
+
+fn is_timestamping_opreturn(payload: &[u8]) -> bool {
+    TIMESTAMPING_PREFIXES
+        .iter()
+        .any(|prefix| payload.starts_with(prefix))
+}
+
+/// Whether key or hash bytes are a pattern rather than a key or hash: all
+/// bytes are equal (e.g. all zero) or printable ASCII. Random bytes are all
+/// printable ASCII with a probability of less than 1e-9 for 20 bytes.
+fn is_pattern_data(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| *b == bytes[0]) || bytes.iter().all(|b| (0x20..=0x7e).contains(b))
+}
+
+/// Whether a pubkey isn't a valid curve point or, ignoring the prefix byte,
+/// is a pattern.
+fn is_fake_pubkey(pubkey: &[u8]) -> bool {
+    bitcoin::secp256k1::PublicKey::from_slice(pubkey).is_err() || is_pattern_data(&pubkey[1..])
+}
+
+/// Whether a P2PK, P2PKH or bare multisig output has a fake pubkey or pubkey
+/// hash. The pubkey of a P2PK script is pushed like in a multisig script.
+fn is_fake_key_output(output: &rest::Output) -> bool {
+    let script = &output.script_pub_key.script;
+    match output.script_pub_key.type_ {
+        ScriptPubkeyType::Pubkey | ScriptPubkeyType::MultiSig => multisig_pubkeys(script)
+            .iter()
+            .any(|key| is_fake_pubkey(key)),
+        ScriptPubkeyType::PubkeyHash => script.as_bytes().get(3..23).is_some_and(is_pattern_data),
+        _ => false,
+    }
+}
+
+/// Returns the pubkeys pushed in a bare multisig script.
+fn multisig_pubkeys(script: &bitcoin::Script) -> Vec<&[u8]> {
+    script
+        .instructions()
+        .flatten()
+        .filter_map(|inst| match inst {
+            Instruction::PushBytes(bytes) if bytes.len() == 33 || bytes.len() == 65 => {
+                Some(bytes.as_bytes())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Counterparty stores its data in all but the last pubkey of a bare multisig
+/// output (the last one belongs to the sender). The first (sign) and last
+/// (nonce) byte of the data pubkeys are ignored. The data starts with a length
+/// byte followed by the prefix and is, in newer versions, ARC4 obfuscated.
+fn is_counterparty_multisig(pubkeys: &[&[u8]], key: Option<&[u8; 32]>) -> bool {
+    if pubkeys.len() < 2 {
+        return false;
+    }
+    let data: Vec<u8> = pubkeys[..pubkeys.len() - 1]
+        .iter()
+        .flat_map(|pubkey| &pubkey[1..pubkey.len() - 1])
+        .copied()
+        .collect();
+    let has_prefix =
+        |data: &[u8]| data.get(1..=COUNTERPARTY_PREFIX.len()) == Some(COUNTERPARTY_PREFIX);
+    has_prefix(&data) || key.is_some_and(|key| has_prefix(&arc4(key, &data)))
+}
+
 impl OutputStats {
     pub fn from_block(block: &Block, date: String, tx_infos: &[TxInfo]) -> OutputStats {
         let height = block.height;
-        let mut s = Self {
-            height,
-            date,
-            ..Default::default()
-        };
-
-        let mut is_coinbase = true;
-        for (tx, tx_info) in block.txdata.iter().zip(tx_infos.iter()) {
-            if is_coinbase {
-                s.outputs_coinbase += tx.output.len() as i32;
-            }
-            for (output_index, output) in tx_info.output_infos.iter().enumerate() {
-                match output.out_type {
-                    OutputType::P2pk => {
-                        s.outputs_p2pk += 1;
-                        s.outputs_p2pk_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2pk += 1;
-                        }
-                    }
-                    OutputType::P2pkh => {
-                        s.outputs_p2pkh += 1;
-                        s.outputs_p2pkh_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2pkh += 1;
-                        }
-                    }
-                    OutputType::P2wpkhV0 => {
-                        s.outputs_p2wpkh += 1;
-                        s.outputs_p2wpkh_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2wpkh += 1;
-                        }
-                    }
-                    OutputType::P2ms => {
-                        s.outputs_p2ms += 1;
-                        s.outputs_p2ms_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2ms += 1;
-                        }
+        let rules = rules_at(&date);
+
+        // the counters are summed up per rayon job and merged afterwards
+        let s = block
+            .txdata
+            .par_iter()
+            .zip(tx_infos.par_iter())
+            .enumerate()
+            .fold(Self::default, |mut s, (index, (tx, tx_info))| {
+                let is_coinbase = index == 0;
+                if is_coinbase {
+                    s.outputs_coinbase += tx.output.len() as i32;
+                } else {
+                    // coinbase transactions aren't relayed and thus not subject to standardness
+                    let opreturn_scripts: Vec<&bitcoin::Script> = tx
+                        .output
+                        .iter()
+                        .map(|output| output.script_pub_key.script.as_script())
+                        .filter(|script| script.is_op_return())
+                        .collect();
+                    if !rules.is_datacarrier_standard(&opreturn_scripts) {
+                        s.outputs_opreturn_nonstandard_at_the_time += opreturn_scripts.len() as i32;
                     }
-                    OutputType::P2sh => {
-                        s.outputs_p2sh += 1;
-                        s.outputs_p2sh_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2sh += 1;
-                        }
+                    s.outputs_dust_at_the_time += tx
+                        .output
+                        .iter()
+                        .filter(|output| rules.is_dust(&output.script_pub_key.script, output.value))
+                        .count() as i32;
+                }
+                let unspendable_coinbase =
+                    is_coinbase && UNSPENDABLE_COINBASE_HEIGHTS.contains(&height);
+                let counterparty_key = counterparty_key(tx);
+                for output in tx.output.iter() {
+                    if unspendable_coinbase || is_burned(&output.script_pub_key.script) {
+                        s.outputs_burned += 1;
+                        add_sats(&mut s.outputs_burned_amount, output.value);
                     }
-                    OutputType::P2wshV0 => {
-                        s.outputs_p2wsh += 1;
-                        s.outputs_p2wsh_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2wsh += 1;
+                    if is_fake_key_output(output) {
+                        match output.script_pub_key.type_ {
+                            ScriptPubkeyType::Pubkey => s.outputs_p2pk_fake_pubkey += 1,
+                            ScriptPubkeyType::PubkeyHash => s.outputs_p2pkh_fake_pubkey_hash += 1,
+                            ScriptPubkeyType::MultiSig => s.outputs_p2ms_fake_pubkey += 1,
+                            _ => (),
                         }
+                        add_sats(&mut s.outputs_fake_pubkey_amount, output.value);
                     }
-                    OutputType::P2tr => {
-                        s.outputs_p2tr += 1;
-                        s.outputs_p2tr_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_p2tr += 1;
+                }
+                for (output_index, output) in tx_info.output_infos.iter().enumerate() {
+                    match output.out_type {
+                        OutputType::P2pk => {
+                            s.outputs_p2pk += 1;
+                            add_sats(&mut s.outputs_p2pk_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2pk += 1;
+                            }
                         }
-                    }
-                    OutputType::P2a => {
-                        s.outputs_p2a += 1;
-                        s.outputs_p2a_amount += output.value.to_sat() as i64;
-
-                        if output.value < bitcoin::Amount::from_sat(P2A_DUST_THRESHOLD) {
-                            s.outputs_p2a_dust += 1;
+                        OutputType::P2pkh => {
+                            s.outputs_p2pkh += 1;
+                            add_sats(&mut s.outputs_p2pkh_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2pkh += 1;
+                            }
                         }
-                    }
-                    OutputType::OpReturn(flavor) => {
-                        s.outputs_opreturn += 1;
-                        s.outputs_opreturn_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_opreturn += 1;
+                        OutputType::P2wpkhV0 => {
+                            s.outputs_p2wpkh += 1;
+                            add_sats(&mut s.outputs_p2wpkh_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2wpkh += 1;
+                            }
                         }
+                        OutputType::P2ms => {
+                            s.outputs_p2ms += 1;
+                            add_sats(&mut s.outputs_p2ms_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2ms += 1;
+                            }
 
-                        // Calculate OP_RETURN payload size (only counts PushBytes data)
-                        let script = &tx.output[output_index].script_pub_key.script;
-                        let data_size = calculate_opreturn_data_size(script);
-                        s.outputs_opreturn_bytes += data_size as i64;
-
-                        match flavor {
-                            OpReturnFlavor::Runestone => s.outputs_opreturn_runestone += 1,
-                            OpReturnFlavor::Omni => s.outputs_opreturn_omnilayer += 1,
-                            OpReturnFlavor::StacksBlockCommit => {
-                                s.outputs_opreturn_stacks_block_commit += 1
+                            let script = &tx.output[output_index].script_pub_key.script;
+                            let pubkeys = multisig_pubkeys(script);
+                            if is_counterparty_multisig(&pubkeys, counterparty_key.as_ref()) {
+                                s.outputs_p2ms_counterparty += 1;
                             }
-                            OpReturnFlavor::Bip47PaymentCode => {
-                                s.outputs_opreturn_bip47_payment_code += 1
+                            if pubkeys.iter().any(|pubkey| {
+                                bitcoin::secp256k1::PublicKey::from_slice(pubkey).is_err()
+                            }) {
+                                s.outputs_p2ms_data += 1;
                             }
-                            OpReturnFlavor::RSKBlock => {
-                                s.outputs_opreturn_coinbase_rsk += if is_coinbase { 1 } else { 0 }
+                        }
+                        OutputType::P2sh => {
+                            s.outputs_p2sh += 1;
+                            add_sats(&mut s.outputs_p2sh_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2sh += 1;
                             }
-                            OpReturnFlavor::CoreDao => {
-                                s.outputs_opreturn_coinbase_coredao +=
-                                    if is_coinbase { 1 } else { 0 }
+                        }
+                        OutputType::P2wshV0 => {
+                            s.outputs_p2wsh += 1;
+                            add_sats(&mut s.outputs_p2wsh_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2wsh += 1;
                             }
-                            OpReturnFlavor::ExSat => {
-                                s.outputs_opreturn_coinbase_exsat += if is_coinbase { 1 } else { 0 }
+                        }
+                        OutputType::P2tr => {
+                            s.outputs_p2tr += 1;
+                            add_sats(&mut s.outputs_p2tr_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_p2tr += 1;
                             }
-                            OpReturnFlavor::HathorNetwork => {
-                                s.outputs_opreturn_coinbase_hathor +=
-                                    if is_coinbase { 1 } else { 0 }
+                        }
+                        OutputType::P2a => {
+                            s.outputs_p2a += 1;
+                            add_sats(&mut s.outputs_p2a_amount, output.value);
+
+                            if output.value < bitcoin::Amount::from_sat(P2A_DUST_THRESHOLD) {
+                                s.outputs_p2a_dust += 1;
+                            }
+                        }
+                        OutputType::OpReturn(flavor) => {
+                            s.outputs_opreturn += 1;
+                            add_sats(&mut s.outputs_opreturn_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_opreturn += 1;
                             }
-                            OpReturnFlavor::WitnessCommitment => {
-                                s.outputs_opreturn_coinbase_witness_commitment +=
-                                    if is_coinbase { 1 } else { 0 }
+
+                            // Calculate OP_RETURN payload size (only counts PushBytes data)
+                            let script = &tx.output[output_index].script_pub_key.script;
+                            let data_size = calculate_opreturn_data_size(script);
+                            s.outputs_opreturn_bytes += data_size as i64;
+
+                            match flavor {
+                                OpReturnFlavor::Runestone => s.outputs_opreturn_runestone += 1,
+                                OpReturnFlavor::Omni => s.outputs_opreturn_omnilayer += 1,
+                                OpReturnFlavor::StacksBlockCommit => {
+                                    s.outputs_opreturn_stacks_block_commit += 1
+                                }
+                                OpReturnFlavor::Bip47PaymentCode => {
+                                    s.outputs_opreturn_bip47_payment_code += 1
+                                }
+                                OpReturnFlavor::RSKBlock => {
+                                    s.outputs_opreturn_coinbase_rsk +=
+                                        if is_coinbase { 1 } else { 0 }
+                                }
+                                OpReturnFlavor::CoreDao => {
+                                    s.outputs_opreturn_coinbase_coredao +=
+                                        if is_coinbase { 1 } else { 0 }
+                                }
+                                OpReturnFlavor::ExSat => {
+                                    s.outputs_opreturn_coinbase_exsat +=
+                                        if is_coinbase { 1 } else { 0 }
+                                }
+                                OpReturnFlavor::HathorNetwork => {
+                                    s.outputs_opreturn_coinbase_hathor +=
+                                        if is_coinbase { 1 } else { 0 }
+                                }
+                                OpReturnFlavor::WitnessCommitment => {
+                                    s.outputs_opreturn_coinbase_witness_commitment +=
+                                        if is_coinbase { 1 } else { 0 }
+                                }
+                                OpReturnFlavor::Len1Byte => (), // TODO: not implemented yet
+                                OpReturnFlavor::Len20Byte => (), // TODO: not implemented yet
+                                OpReturnFlavor::Len80Byte => (), // TODO: not implemented yet
+                                OpReturnFlavor::Unspecified => {
+                                    let payload = opreturn_payload(script);
+                                    if is_counterparty_opreturn(&payload, counterparty_key.as_ref())
+                                    {
+                                        s.outputs_opreturn_counterparty += 1;
+                                    } else if is_timestamping_opreturn(&payload) {
+                                        s.outputs_opreturn_timestamping += 1;
+                                    }
+                                }
                             }
-                            OpReturnFlavor::Len1Byte => (), // TODO: not implemented yet
-                            OpReturnFlavor::Len20Byte => (), // TODO: not implemented yet
-                            OpReturnFlavor::Len80Byte => (), // TODO: not implemented yet
-                            OpReturnFlavor::Unspecified => (), // we don't know
                         }
-                    }
-                    OutputType::Unknown => {
-                        s.outputs_unknown += 1;
-                        s.outputs_unknown_amount += output.value.to_sat() as i64;
-                        if is_coinbase {
-                            s.outputs_coinbase_unknown += 1;
+                        OutputType::Unknown => {
+                            s.outputs_unknown += 1;
+                            add_sats(&mut s.outputs_unknown_amount, output.value);
+                            if is_coinbase {
+                                s.outputs_coinbase_unknown += 1;
+                            }
                         }
                     }
                 }
-            }
-            is_coinbase = false;
-        }
-        s
+
+                // outputs of the unspendable coinbases never entered the UTXO set
+                let created = if unspendable_coinbase {
+                    0
+                } else {
+                    tx.output
+                        .iter()
+                        .filter(|output| !is_provably_unspendable(&output.script_pub_key.script))
+                        .count() as i32
+                };
+                let spent = if is_coinbase {
+                    0
+                } else {
+                    tx.input.len() as i32
+                };
+                let net = created - spent;
+                match UtxoProtocol::classify(tx, tx_info, counterparty_key.as_ref()) {
+                    UtxoProtocol::Inscription => s.outputs_utxo_net_inscription += net,
+                    UtxoProtocol::Runestone => s.outputs_utxo_net_runestone += net,
+                    UtxoProtocol::Counterparty => s.outputs_utxo_net_counterparty += net,
+                    UtxoProtocol::OtherData => s.outputs_utxo_net_other_data += net,
+                    UtxoProtocol::Ordinary => s.outputs_utxo_net_ordinary += net,
+                }
+                s
+            })
+            .reduce(Self::default, |mut s, other| {
+                s.add_counts(&other);
+                s
+            });
+        Self { height, date, ..s }
     }
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Debug, PartialEq, Default)]
-#[diesel(table_name = crate::schema::feerate_stats)]
-#[diesel(primary_key(height))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
-pub struct FeerateStats {
-    height: i64,
-    date: String,
-
-    fee_min: i64,
-    fee_5th_percentile: i64,
-    fee_10th_percentile: i64,
-    fee_25th_percentile: i64,
-    fee_35th_percentile: i64,
-    fee_50th_percentile: i64,
-    fee_65th_percentile: i64,
-    fee_75th_percentile: i64,
-    fee_90th_percentile: i64,
-    fee_95th_percentile: i64,
-    fee_max: i64,
-    fee_sum: i64,
-    fee_avg: f32,
-    size_min: i32,
-    size_5th_percentile: i32,
-    size_10th_percentile: i32,
-    size_25th_percentile: i32,
-    size_35th_percentile: i32,
-    size_50th_percentile: i32,
-    size_65th_percentile: i32,
-    size_75th_percentile: i32,
-    size_90th_percentile: i32,
-    size_95th_percentile: i32,
-    size_max: i32,
-    size_avg: f32,
-    size_sum: i64,
-    feerate_min: f32,
-    feerate_5th_percentile: f32,
-    feerate_10th_percentile: f32,
-    feerate_25th_percentile: f32,
-    feerate_35th_percentile: f32,
-    feerate_50th_percentile: f32,
-    feerate_65th_percentile: f32,
-    feerate_75th_percentile: f32,
-    feerate_90th_percentile: f32,
-    feerate_95th_percentile: f32,
-    feerate_max: f32,
-    feerate_avg: f32,
-    feerate_package_min: f32,
-    feerate_package_5th_percentile: f32,
-    feerate_package_10th_percentile: f32,
-    feerate_package_25th_percentile: f32,
-    feerate_package_35th_percentile: f32,
-    feerate_package_50th_percentile: f32,
-    feerate_package_65th_percentile: f32,
-    feerate_package_75th_percentile: f32,
-    feerate_package_90th_percentile: f32,
-    feerate_package_95th_percentile: f32,
-    feerate_package_max: f32,
-    feerate_package_avg: f32,
-    // Added 2025-08-01:
-    zero_fee_tx: i32,
-    below_1_sat_vbyte: i32,
-    // Fee band counts, added 2025-08-11
-    feerate_1_2_sat_vbyte: i32,
-    feerate_2_5_sat_vbyte: i32,
-    feerate_5_10_sat_vbyte: i32,
-    feerate_10_25_sat_vbyte: i32,
-    feerate_25_50_sat_vbyte: i32,
-    feerate_50_100_sat_vbyte: i32,
-    feerate_100_250_sat_vbyte: i32,
-    feerate_250_500_sat_vbyte: i32,
-    feerate_500_1000_sat_vbyte: i32,
-    feerate_1000_plus_sat_vbyte: i32,
+stats_table! {
+    table = feerate_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct FeerateStats {
+        height: i64,
+        #[sql_type = Date]
+        date: String,
+
+        fee_min: i64,
+        fee_5th_percentile: i64,
+        fee_10th_percentile: i64,
+        fee_25th_percentile: i64,
+        fee_35th_percentile: i64,
+        fee_50th_percentile: i64,
+        fee_65th_percentile: i64,
+        fee_75th_percentile: i64,
+        fee_90th_percentile: i64,
+        fee_95th_percentile: i64,
+        fee_max: i64,
+        fee_sum: i64,
+        fee_avg: f32,
+        size_min: i32,
+        size_5th_percentile: i32,
+        size_10th_percentile: i32,
+        size_25th_percentile: i32,
+        size_35th_percentile: i32,
+        size_50th_percentile: i32,
+        size_65th_percentile: i32,
+        size_75th_percentile: i32,
+        size_90th_percentile: i32,
+        size_95th_percentile: i32,
+        size_max: i32,
+        size_avg: f32,
+        size_sum: i64,
+        feerate_min: f32,
+        feerate_5th_percentile: f32,
+        feerate_10th_percentile: f32,
+        feerate_25th_percentile: f32,
+        feerate_35th_percentile: f32,
+        feerate_50th_percentile: f32,
+        feerate_65th_percentile: f32,
+        feerate_75th_percentile: f32,
+        feerate_90th_percentile: f32,
+        feerate_95th_percentile: f32,
+        feerate_max: f32,
+        feerate_avg: f32,
+        feerate_package_min: f32,
+        feerate_package_5th_percentile: f32,
+        feerate_package_10th_percentile: f32,
+        feerate_package_25th_percentile: f32,
+        feerate_package_35th_percentile: f32,
+        feerate_package_50th_percentile: f32,
+        feerate_package_65th_percentile: f32,
+        feerate_package_75th_percentile: f32,
+        feerate_package_90th_percentile: f32,
+        feerate_package_95th_percentile: f32,
+        feerate_package_max: f32,
+        feerate_package_avg: f32,
+        /// Added 2025-08-01:
+        zero_fee_tx: i32,
+        below_1_sat_vbyte: i32,
+        /// Fee band counts, added 2025-08-11
+        feerate_1_2_sat_vbyte: i32,
+        feerate_2_5_sat_vbyte: i32,
+        feerate_5_10_sat_vbyte: i32,
+        feerate_10_25_sat_vbyte: i32,
+        feerate_25_50_sat_vbyte: i32,
+        feerate_50_100_sat_vbyte: i32,
+        feerate_100_250_sat_vbyte: i32,
+        feerate_250_500_sat_vbyte: i32,
+        feerate_500_1000_sat_vbyte: i32,
+        feerate_1000_plus_sat_vbyte: i32,
+    }
 }
 
 /// helper function to treat f64::NAN values as 0. If we try to insert NANs into the database,
@@ -1070,108 +3103,778 @@ impl FeerateStats {
                 }
             }
 
-            fees_sat.push(fee.to_sat());
-            sizes.push(tx.size as u64);
-            feerates.push(feerate);
+            fees_sat.push(fee.to_sat());
+            sizes.push(tx.size as u64);
+            feerates.push(feerate);
+        }
+
+        let mut fees_data: Data<Vec<f64>> = Data::new(fees_sat.iter().map(|f| *f as f64).collect());
+        let mut sizes_data: Data<Vec<f64>> = Data::new(sizes.iter().map(|f| *f as f64).collect());
+        let mut feerates_data: Data<Vec<f64>> = Data::new(feerates.clone());
+
+        let fee_sum = fees_sat
+            .iter()
+            .fold(0u64, |sum, fee| sum.saturating_add(*fee));
+        let fee_avg = match num_tx_without_coinbase {
+            0 => 0.0f32,
+            _ => fee_sum as f32 / num_tx_without_coinbase as f32,
+        };
+
+        let size_sum: u64 = sizes.iter().sum();
+        let size_avg = match num_tx_without_coinbase {
+            0 => 0.0f32,
+            _ => size_sum as f32 / num_tx_without_coinbase as f32,
+        };
+
+        let feerate_sum: f64 = feerates.iter().sum();
+        let feerate_avg = match num_tx_without_coinbase {
+            0 => 0.0f32,
+            _ => feerate_sum as f32 / num_tx_without_coinbase as f32,
+        };
+
+        FeerateStats {
+            height: block.height,
+            date,
+            fee_min: *(fees_sat.iter().min().unwrap_or(&0)) as i64,
+            fee_5th_percentile: fees_data.percentile(5) as i64,
+            fee_10th_percentile: fees_data.percentile(10) as i64,
+            fee_25th_percentile: fees_data.percentile(25) as i64,
+            fee_35th_percentile: fees_data.percentile(35) as i64,
+            fee_50th_percentile: fees_data.percentile(50) as i64,
+            fee_65th_percentile: fees_data.percentile(65) as i64,
+            fee_75th_percentile: fees_data.percentile(75) as i64,
+            fee_90th_percentile: fees_data.percentile(90) as i64,
+            fee_95th_percentile: fees_data.percentile(95) as i64,
+            fee_max: *(fees_sat.iter().max().unwrap_or(&0)) as i64,
+            fee_sum: saturating_i64(fee_sum),
+            fee_avg,
+            size_min: *(sizes.iter().min().unwrap_or(&0)) as i32,
+            size_5th_percentile: sizes_data.percentile(5) as i32,
+            size_10th_percentile: sizes_data.percentile(10) as i32,
+            size_25th_percentile: sizes_data.percentile(25) as i32,
+            size_35th_percentile: sizes_data.percentile(35) as i32,
+            size_50th_percentile: sizes_data.percentile(50) as i32,
+            size_65th_percentile: sizes_data.percentile(65) as i32,
+            size_75th_percentile: sizes_data.percentile(75) as i32,
+            size_90th_percentile: sizes_data.percentile(90) as i32,
+            size_95th_percentile: sizes_data.percentile(95) as i32,
+            size_max: *(sizes.iter().max().unwrap_or(&0)) as i32,
+            size_avg,
+            size_sum: saturating_i64(size_sum),
+            feerate_min: *(feerates
+                .iter()
+                .filter(|x| !x.is_nan())
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap_or(&0.0)) as f32,
+            feerate_5th_percentile: f64_nan_as_0(feerates_data.percentile(5)) as f32,
+            feerate_10th_percentile: f64_nan_as_0(feerates_data.percentile(10)) as f32,
+            feerate_25th_percentile: f64_nan_as_0(feerates_data.percentile(25)) as f32,
+            feerate_35th_percentile: f64_nan_as_0(feerates_data.percentile(35)) as f32,
+            feerate_50th_percentile: f64_nan_as_0(feerates_data.percentile(50)) as f32,
+            feerate_65th_percentile: f64_nan_as_0(feerates_data.percentile(65)) as f32,
+            feerate_75th_percentile: f64_nan_as_0(feerates_data.percentile(75)) as f32,
+            feerate_90th_percentile: f64_nan_as_0(feerates_data.percentile(90)) as f32,
+            feerate_95th_percentile: f64_nan_as_0(feerates_data.percentile(95)) as f32,
+            feerate_max: *(feerates
+                .iter()
+                .filter(|x| !x.is_nan())
+                .max_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap_or(&0.0)) as f32,
+            feerate_avg,
+            // TODO: Transaction package feerate stats are not yet implemented.
+            feerate_package_min: 0.0f32,
+            feerate_package_5th_percentile: 0.0f32,
+            feerate_package_10th_percentile: 0.0f32,
+            feerate_package_25th_percentile: 0.0f32,
+            feerate_package_35th_percentile: 0.0f32,
+            feerate_package_50th_percentile: 0.0f32,
+            feerate_package_65th_percentile: 0.0f32,
+            feerate_package_75th_percentile: 0.0f32,
+            feerate_package_90th_percentile: 0.0f32,
+            feerate_package_95th_percentile: 0.0f32,
+            feerate_package_max: 0.0f32,
+            feerate_package_avg: 0.0f32,
+            zero_fee_tx,
+            below_1_sat_vbyte,
+            feerate_1_2_sat_vbyte,
+            feerate_2_5_sat_vbyte,
+            feerate_5_10_sat_vbyte,
+            feerate_10_25_sat_vbyte,
+            feerate_25_50_sat_vbyte,
+            feerate_50_100_sat_vbyte,
+            feerate_100_250_sat_vbyte,
+            feerate_250_500_sat_vbyte,
+            feerate_500_1000_sat_vbyte,
+            feerate_1000_plus_sat_vbyte,
+        }
+    }
+}
+
+/// A feerate percentile of the non-coinbase transactions of a block, for the
+/// percentiles configured with --feerate-percentiles. Complements the fixed
+/// percentile columns of feerate_stats.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::feerate_percentiles)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FeeratePercentile {
+    pub height: i64,
+    /// percentile between 0 and 100
+    pub percentile: i32,
+    /// feerate in sat/vbyte, 0 for blocks with only a coinbase
+    pub value: f32,
+}
+
+impl FeeratePercentile {
+    pub fn from_block(block: &Block, percentiles: &[u8]) -> Vec<FeeratePercentile> {
+        let mut feerates: Data<Vec<f64>> = Data::new(
+            block
+                .txdata
+                .iter()
+                .skip(1)
+                .map(|tx| tx.fee.unwrap_or_default().to_sat() as f64 / tx.vsize as f64)
+                .collect(),
+        );
+        percentiles
+            .iter()
+            .map(|percentile| FeeratePercentile {
+                height: block.height,
+                percentile: *percentile as i32,
+                value: f64_nan_as_0(feerates.percentile(*percentile as usize)) as f32,
+            })
+            .collect()
+    }
+}
+
+stats_table! {
+    table = input_size_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct InputSizeStats {
+        height: i64,
+        date: String,
+
+        /// size of the P2PK inputs
+        size_p2pk_10th_percentile: f32,
+        size_p2pk_50th_percentile: f32,
+        size_p2pk_90th_percentile: f32,
+
+        /// size of the P2PKH inputs
+        size_p2pkh_10th_percentile: f32,
+        size_p2pkh_50th_percentile: f32,
+        size_p2pkh_90th_percentile: f32,
+
+        /// size of the nested P2WPKH inputs
+        size_nested_p2wpkh_10th_percentile: f32,
+        size_nested_p2wpkh_50th_percentile: f32,
+        size_nested_p2wpkh_90th_percentile: f32,
+
+        /// size of the P2WPKH inputs
+        size_p2wpkh_10th_percentile: f32,
+        size_p2wpkh_50th_percentile: f32,
+        size_p2wpkh_90th_percentile: f32,
+
+        /// size of the bare multisig inputs
+        size_p2ms_10th_percentile: f32,
+        size_p2ms_50th_percentile: f32,
+        size_p2ms_90th_percentile: f32,
+
+        /// size of the P2SH inputs
+        size_p2sh_10th_percentile: f32,
+        size_p2sh_50th_percentile: f32,
+        size_p2sh_90th_percentile: f32,
+
+        /// size of the nested P2WSH inputs
+        size_nested_p2wsh_10th_percentile: f32,
+        size_nested_p2wsh_50th_percentile: f32,
+        size_nested_p2wsh_90th_percentile: f32,
+
+        /// size of the P2WSH inputs
+        size_p2wsh_10th_percentile: f32,
+        size_p2wsh_50th_percentile: f32,
+        size_p2wsh_90th_percentile: f32,
+
+        /// size of the P2TR keypath inputs
+        size_p2tr_keypath_10th_percentile: f32,
+        size_p2tr_keypath_50th_percentile: f32,
+        size_p2tr_keypath_90th_percentile: f32,
+
+        /// size of the P2TR scriptpath inputs
+        size_p2tr_scriptpath_10th_percentile: f32,
+        size_p2tr_scriptpath_50th_percentile: f32,
+        size_p2tr_scriptpath_90th_percentile: f32,
+    }
+}
+
+impl InputSizeStats {
+    /// The size of an input is the size of its scriptSig plus the serialized
+    /// size of its witness in bytes. This is what spending the input costs,
+    /// independent of the rest of the transaction.
+    pub fn from_block(block: &Block, date: String, tx_infos: &[TxInfo]) -> InputSizeStats {
+        let mut sizes: HashMap<&'static str, Vec<f64>> = HashMap::new();
+
+        for (tx, tx_info) in block.txdata.iter().zip(tx_infos.iter()) {
+            for (input, input_info) in tx.input.iter().zip(tx_info.input_infos.iter()) {
+                let InputData::NonCoinbase { script_sig, .. } = &input.data else {
+                    continue;
+                };
+                let input_type = match input_info.in_type {
+                    InputType::P2pk | InputType::P2pkLaxDer => "p2pk",
+                    InputType::P2pkh | InputType::P2pkhLaxDer => "p2pkh",
+                    InputType::P2shP2wpkh => "nested_p2wpkh",
+                    InputType::P2wpkh => "p2wpkh",
+                    InputType::P2ms | InputType::P2msLaxDer => "p2ms",
+                    InputType::P2sh => "p2sh",
+                    InputType::P2shP2wsh => "nested_p2wsh",
+                    InputType::P2wsh => "p2wsh",
+                    InputType::P2trkp => "p2tr_keypath",
+                    InputType::P2trsp => "p2tr_scriptpath",
+                    InputType::Coinbase
+                    | InputType::CoinbaseWitness
+                    | InputType::P2a
+                    | InputType::Unknown => continue,
+                };
+                let witness_size = input.witness.as_ref().map(|w| w.size()).unwrap_or(0);
+                sizes
+                    .entry(input_type)
+                    .or_default()
+                    .push((script_sig.script.len() + witness_size) as f64);
+            }
+        }
+
+        // the 10th, 50th and 90th percentile. 0 if there are no inputs of the type.
+        let mut percentiles = |input_type: &str| -> [f32; 3] {
+            match sizes.remove(input_type) {
+                Some(sizes) => {
+                    let mut data = Data::new(sizes);
+                    [10, 50, 90].map(|p| f64_nan_as_0(data.percentile(p)) as f32)
+                }
+                None => [0.0; 3],
+            }
+        };
+        let p2pk = percentiles("p2pk");
+        let p2pkh = percentiles("p2pkh");
+        let nested_p2wpkh = percentiles("nested_p2wpkh");
+        let p2wpkh = percentiles("p2wpkh");
+        let p2ms = percentiles("p2ms");
+        let p2sh = percentiles("p2sh");
+        let nested_p2wsh = percentiles("nested_p2wsh");
+        let p2wsh = percentiles("p2wsh");
+        let p2tr_keypath = percentiles("p2tr_keypath");
+        let p2tr_scriptpath = percentiles("p2tr_scriptpath");
+
+        InputSizeStats {
+            height: block.height,
+            date,
+            size_p2pk_10th_percentile: p2pk[0],
+            size_p2pk_50th_percentile: p2pk[1],
+            size_p2pk_90th_percentile: p2pk[2],
+            size_p2pkh_10th_percentile: p2pkh[0],
+            size_p2pkh_50th_percentile: p2pkh[1],
+            size_p2pkh_90th_percentile: p2pkh[2],
+            size_nested_p2wpkh_10th_percentile: nested_p2wpkh[0],
+            size_nested_p2wpkh_50th_percentile: nested_p2wpkh[1],
+            size_nested_p2wpkh_90th_percentile: nested_p2wpkh[2],
+            size_p2wpkh_10th_percentile: p2wpkh[0],
+            size_p2wpkh_50th_percentile: p2wpkh[1],
+            size_p2wpkh_90th_percentile: p2wpkh[2],
+            size_p2ms_10th_percentile: p2ms[0],
+            size_p2ms_50th_percentile: p2ms[1],
+            size_p2ms_90th_percentile: p2ms[2],
+            size_p2sh_10th_percentile: p2sh[0],
+            size_p2sh_50th_percentile: p2sh[1],
+            size_p2sh_90th_percentile: p2sh[2],
+            size_nested_p2wsh_10th_percentile: nested_p2wsh[0],
+            size_nested_p2wsh_50th_percentile: nested_p2wsh[1],
+            size_nested_p2wsh_90th_percentile: nested_p2wsh[2],
+            size_p2wsh_10th_percentile: p2wsh[0],
+            size_p2wsh_50th_percentile: p2wsh[1],
+            size_p2wsh_90th_percentile: p2wsh[2],
+            size_p2tr_keypath_10th_percentile: p2tr_keypath[0],
+            size_p2tr_keypath_50th_percentile: p2tr_keypath[1],
+            size_p2tr_keypath_90th_percentile: p2tr_keypath[2],
+            size_p2tr_scriptpath_10th_percentile: p2tr_scriptpath[0],
+            size_p2tr_scriptpath_50th_percentile: p2tr_scriptpath[1],
+            size_p2tr_scriptpath_90th_percentile: p2tr_scriptpath[2],
+        }
+    }
+}
+
+stats_table! {
+    table = dormant_spends;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct DormantSpends {
+        pub height: i64,
+        pub date: String,
+
+        /// number of inputs spending an output created at least 5 years
+        /// (in blocks) before this block
+        pub spends_5y: i32,
+        /// value of the inputs spending 5 year old outputs in sat
+        pub spends_5y_amount: i64,
+        /// number of inputs spending an output created at least 10 years
+        /// (in blocks) before this block
+        pub spends_10y: i32,
+        /// value of the inputs spending 10 year old outputs in sat
+        pub spends_10y_amount: i64,
+        /// number of inputs spending an output created before 2011
+        pub spends_pre_2011: i32,
+        /// value of the inputs spending outputs created before 2011 in sat
+        pub spends_pre_2011_amount: i64,
+    }
+}
+
+impl DormantSpends {
+    pub fn from_block(block: &Block, date: String) -> DormantSpends {
+        let mut s = DormantSpends {
+            height: block.height,
+            date,
+            ..Default::default()
+        };
+
+        for tx in block.txdata.iter() {
+            for input in tx.input.iter() {
+                let InputData::NonCoinbase { prevout, .. } = &input.data else {
+                    continue;
+                };
+                let age = block.height - prevout.height;
+                let value = prevout.value;
+                if age >= 5 * BLOCKS_PER_YEAR {
+                    s.spends_5y += 1;
+                    add_sats(&mut s.spends_5y_amount, value);
+                }
+                if age >= 10 * BLOCKS_PER_YEAR {
+                    s.spends_10y += 1;
+                    add_sats(&mut s.spends_10y_amount, value);
+                }
+                if prevout.height < PRE_2011_HEIGHT {
+                    s.spends_pre_2011 += 1;
+                    add_sats(&mut s.spends_pre_2011_amount, value);
+                }
+            }
+        }
+        s
+    }
+}
+
+/// A transaction that contributed to a tx_stats counter of a block. Recorded
+/// when capturing the contributors of a counter to verify it, e.g. against a
+/// block explorer.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::stat_contributors)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct StatContributor {
+    pub height: i64,
+    /// the tx_stats column
+    pub stat: String,
+    pub txid: String,
+}
+
+/// The tx_stats counters whose contributing transactions can be captured.
+pub fn capturable_stats() -> impl Iterator<Item = &'static str> {
+    TxStats::COLUMNS
+        .iter()
+        .filter(|col| col.sql_type == "Integer")
+        .map(|col| col.name)
+}
+
+/// The raw coinbase transaction of a block.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::coinbases)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Coinbase {
+    pub height: i64,
+    /// the consensus serialized transaction, including the witness
+    pub raw: Vec<u8>,
+}
+
+impl Coinbase {
+    pub fn from_block(block: &Block) -> Result<Coinbase, StatsError> {
+        let coinbase = block.txdata[0].transaction()?;
+        Ok(Coinbase {
+            height: block.height,
+            raw: bitcoin::consensus::serialize(coinbase.as_ref()),
+        })
+    }
+}
+
+/// A coinbase output spent in a later block: the first hop of a miner payout.
+/// The pool is the pool that mined the block at `coinbase_height`.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::coinbase_spends)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CoinbaseSpend {
+    pub coinbase_height: i64,
+    pub vout: i32,
+    pub spend_height: i64,
+    /// the spending transaction
+    pub txid: String,
+    /// value of the coinbase output in sat
+    pub value: i64,
+    /// number of outputs of the spending transaction
+    pub outputs: i32,
+    /// the script type of the outputs of the spending transaction, or `mixed`
+    /// if they are of different types
+    pub destination_type: String,
+}
+
+impl CoinbaseSpend {
+    pub fn from_block(block: &Block) -> Result<Vec<CoinbaseSpend>, StatsError> {
+        if block.without_prevout_heights {
+            return Err(StatsError::MissingPrevoutHeights(block.height));
+        }
+        let mut spends = vec![];
+        for tx in block.txdata.iter() {
+            let destination_type = match tx.output.split_first() {
+                Some((first, rest))
+                    if rest
+                        .iter()
+                        .all(|o| o.script_pub_key.type_ == first.script_pub_key.type_) =>
+                {
+                    first.script_pub_key.type_.name()
+                }
+                _ => "mixed",
+            };
+            for input in tx.input.iter() {
+                let InputData::NonCoinbase { vout, prevout, .. } = &input.data else {
+                    continue;
+                };
+                if !prevout.generated {
+                    continue;
+                }
+                spends.push(CoinbaseSpend {
+                    coinbase_height: prevout.height,
+                    vout: *vout as i32,
+                    spend_height: block.height,
+                    txid: tx.txid.to_string(),
+                    value: sats(prevout.value),
+                    outputs: tx.output.len() as i32,
+                    destination_type: destination_type.to_string(),
+                });
+            }
+        }
+        Ok(spends)
+    }
+}
+
+/// The number of times an opcode is present in the scripts of one kind revealed
+/// when spending in a block. Opcodes aren't necessarily executed, e.g. when
+/// they are in an unexecuted OP_IF branch. Pushes are counted by their push
+/// opcode (e.g. OP_PUSHBYTES_32).
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::opcode_stats)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct OpcodeCount {
+    pub height: i64,
+    /// `redeem_script` (P2SH), `witness_script` (P2WSH and nested P2WSH) or
+    /// `tapscript` (P2TR script path)
+    pub script_type: String,
+    pub opcode: String,
+    pub count: i32,
+}
+
+impl OpcodeCount {
+    pub fn from_block(block: &Block) -> Vec<OpcodeCount> {
+        let mut counts: BTreeMap<(&str, String), i32> = BTreeMap::new();
+        for input in block.txdata.iter().flat_map(|tx| tx.input.iter()) {
+            let InputData::NonCoinbase {
+                script_sig,
+                prevout,
+                ..
+            } = &input.data
+            else {
+                continue;
+            };
+            for (script_type, script) in revealed_scripts(
+                &prevout.script_pub_key.type_,
+                &script_sig.script,
+                input.witness.as_ref(),
+            ) {
+                // The valid prefix of scripts with an invalid push is counted.
+                for (index, _) in script.instruction_indices().map_while(Result::ok) {
+                    let opcode = bitcoin::Opcode::from(script.as_bytes()[index]);
+                    *counts.entry((script_type, opcode.to_string())).or_default() += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .map(|((script_type, opcode), count)| OpcodeCount {
+                height: block.height,
+                script_type: script_type.to_string(),
+                opcode,
+                count,
+            })
+            .collect()
+    }
+}
+
+/// The number of inputs spending an output of `from_type` in transactions
+/// creating an output of `to_type` in a block. An input is counted once for
+/// each distinct output type of its transaction. The types are named like in
+/// Bitcoin Core (e.g. `pubkeyhash` or `witness_v1_taproot`).
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::script_type_transitions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScriptTypeTransition {
+    pub height: i64,
+    pub from_type: String,
+    pub to_type: String,
+    pub count: i32,
+}
+
+impl ScriptTypeTransition {
+    pub fn from_block(block: &Block) -> Vec<ScriptTypeTransition> {
+        let mut counts: BTreeMap<(&str, &str), i32> = BTreeMap::new();
+        for tx in block.txdata.iter() {
+            let to_types: BTreeSet<&str> = tx
+                .output
+                .iter()
+                .map(|output| output.script_pub_key.type_.name())
+                .collect();
+            for input in tx.input.iter() {
+                let InputData::NonCoinbase { prevout, .. } = &input.data else {
+                    continue;
+                };
+                let from_type = prevout.script_pub_key.type_.name();
+                for to_type in to_types.iter() {
+                    *counts.entry((from_type, to_type)).or_default() += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .map(|((from_type, to_type), count)| ScriptTypeTransition {
+                height: block.height,
+                from_type: from_type.to_string(),
+                to_type: to_type.to_string(),
+                count,
+            })
+            .collect()
+    }
+}
+
+/// An output with a non-standard script. Of all outputs with the same
+/// template, the table keeps the first one.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = crate::schema::novel_scripts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NovelScript {
+    /// the script with its data pushes replaced by their length, e.g.
+    /// `OP_HASH256 <32> OP_EQUAL`
+    pub template: String,
+    pub height: i64,
+    pub txid: String,
+    pub vout: i32,
+    /// the script as hex
+    pub script: String,
+}
+
+impl NovelScript {
+    /// The first output of each non-standard script template in the block.
+    pub fn from_block(block: &Block) -> Vec<NovelScript> {
+        let mut scripts: Vec<NovelScript> = vec![];
+        for tx in block.txdata.iter() {
+            for output in tx.output.iter() {
+                if output.script_pub_key.type_ != ScriptPubkeyType::Nonstandard {
+                    continue;
+                }
+                let template = script_template(&output.script_pub_key.script);
+                if scripts.iter().any(|s| s.template == template) {
+                    continue;
+                }
+                scripts.push(NovelScript {
+                    template,
+                    height: block.height,
+                    txid: tx.txid.to_string(),
+                    vout: output.n as i32,
+                    script: output.script_pub_key.script.to_hex_string(),
+                });
+            }
+        }
+        scripts
+    }
+}
+
+/// Normalizes a script to a template: opcodes are kept and data pushes are
+/// replaced by their length in bytes. A push running past the end of the
+/// script ends the template with `<invalid>`.
+fn script_template(script: &bitcoin::Script) -> String {
+    let mut template: Vec<String> = vec![];
+    for instruction in script.instructions() {
+        match instruction {
+            Ok(Instruction::Op(opcode)) => template.push(opcode.to_string()),
+            Ok(Instruction::PushBytes(push)) if push.is_empty() => {
+                template.push("OP_0".to_string())
+            }
+            Ok(Instruction::PushBytes(push)) => template.push(format!("<{}>", push.len())),
+            Err(_) => {
+                template.push("<invalid>".to_string());
+                break;
+            }
+        }
+    }
+    template.join(" ")
+}
+
+/// A revealed script that is a P2SH script or a witness program itself, i.e.
+/// an output script used where the script to execute is expected. The inner
+/// script is never executed.
+fn is_nested_program(script: &bitcoin::Script) -> bool {
+    script.is_p2sh() || script.is_witness_program()
+}
+
+/// A P2SH redeem script that is a witness program other than the standard
+/// P2SH-P2WPKH and P2SH-P2WSH wrappers. E.g. P2SH-wrapped taproot isn't
+/// spent as taproot and is anyone-can-spend.
+fn is_nonstandard_wrapped_program(redeem_script: &bitcoin::Script) -> bool {
+    redeem_script.is_witness_program() && !redeem_script.is_p2wpkh() && !redeem_script.is_p2wsh()
+}
+
+/// Returns the scripts revealed by an input spending an output of the given
+/// type. The redeem scripts of nested SegWit inputs are witness programs and
+/// not returned, but the witness script of nested P2WSH is.
+fn revealed_scripts<'a>(
+    spent_type: &ScriptPubkeyType,
+    script_sig: &'a bitcoin::Script,
+    witness: Option<&'a bitcoin::Witness>,
+) -> Vec<(&'static str, &'a bitcoin::Script)> {
+    match spent_type {
+        ScriptPubkeyType::ScriptHash => {
+            let Some(Ok(Instruction::PushBytes(redeem_script))) = script_sig.instructions().last()
+            else {
+                return vec![];
+            };
+            let redeem_script = bitcoin::Script::from_bytes(redeem_script.as_bytes());
+            if redeem_script.is_p2wsh() {
+                witness
+                    .and_then(|w| w.witness_script())
+                    .map(|s| vec![("witness_script", s)])
+                    .unwrap_or_default()
+            } else if redeem_script.is_witness_program() {
+                vec![]
+            } else {
+                vec![("redeem_script", redeem_script)]
+            }
         }
+        ScriptPubkeyType::Witness_v0_ScriptHash => witness
+            .and_then(|w| w.witness_script())
+            .map(|s| vec![("witness_script", s)])
+            .unwrap_or_default(),
+        ScriptPubkeyType::Witness_v1_Taproot => witness
+            .and_then(|w| w.tapscript())
+            .map(|s| vec![("tapscript", s)])
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
 
-        let mut fees_data: Data<Vec<f64>> = Data::new(fees_sat.iter().map(|f| *f as f64).collect());
-        let mut sizes_data: Data<Vec<f64>> = Data::new(sizes.iter().map(|f| *f as f64).collect());
-        let mut feerates_data: Data<Vec<f64>> = Data::new(feerates.clone());
+stats_table! {
+    table = truc_stats;
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct TrucStats {
+        height: i64,
+        date: String,
 
-        let fee_sum: u64 = fees_sat.iter().sum();
-        let fee_avg = match num_tx_without_coinbase {
-            0 => 0.0f32,
-            _ => fee_sum as f32 / num_tx_without_coinbase as f32,
-        };
+        /// number of version 3 (TRUC) transactions
+        tx_v3: i32,
+        /// number of v3 transactions spending an output created in this block
+        tx_v3_with_parent_in_block: i32,
+        /// number of v3 transactions spending an output of a v3 transaction
+        /// created in this block, i.e. a TRUC child in a TRUC package
+        tx_v3_with_v3_parent_in_block: i32,
+        /// number of v3 transactions creating a P2A anchor output
+        tx_v3_creating_p2a: i32,
+        /// number of v3 transactions spending a P2A anchor output
+        tx_v3_spending_p2a: i32,
+        /// number of v3 transactions larger than 1000 vbyte, which is the
+        /// maximum size of a TRUC child
+        tx_v3_above_1000_vbyte: i32,
+
+        /// vsize of v3 transactions
+        v3_vsize_avg: f32,
+        v3_vsize_median: f32,
+        v3_vsize_max: i64,
+
+        /// vsize of the packages of v3 transactions with parents in this block
+        /// (the child and its in-block parents)
+        v3_package_vsize_avg: f32,
+        v3_package_vsize_max: i64,
+    }
+}
 
-        let size_sum: u64 = sizes.iter().sum();
-        let size_avg = match num_tx_without_coinbase {
-            0 => 0.0f32,
-            _ => size_sum as f32 / num_tx_without_coinbase as f32,
+impl TrucStats {
+    pub fn from_block(block: &Block, date: String) -> TrucStats {
+        let mut s = TrucStats {
+            height: block.height,
+            date,
+            ..Default::default()
         };
 
-        let feerate_sum: f64 = feerates.iter().sum();
-        let feerate_avg = match num_tx_without_coinbase {
-            0 => 0.0f32,
-            _ => feerate_sum as f32 / num_tx_without_coinbase as f32,
-        };
+        // version and vsize of the transactions in this block by txid
+        let mut txs_in_this_block: HashMap<&Txid, (u32, u32)> =
+            HashMap::with_capacity(block.txdata.len());
+        let mut vsizes: Vec<f64> = Vec::new();
+        let mut package_vsizes: Vec<u64> = Vec::new();
+
+        // skip the coinbase
+        for tx in block.txdata.iter().skip(1) {
+            if tx.version == 3 {
+                s.tx_v3 += 1;
+                vsizes.push(tx.vsize as f64);
+                if tx.vsize > 1_000 {
+                    s.tx_v3_above_1000_vbyte += 1;
+                }
 
-        FeerateStats {
-            height: block.height,
-            date,
-            fee_min: *(fees_sat.iter().min().unwrap_or(&0)) as i64,
-            fee_5th_percentile: fees_data.percentile(5) as i64,
-            fee_10th_percentile: fees_data.percentile(10) as i64,
-            fee_25th_percentile: fees_data.percentile(25) as i64,
-            fee_35th_percentile: fees_data.percentile(35) as i64,
-            fee_50th_percentile: fees_data.percentile(50) as i64,
-            fee_65th_percentile: fees_data.percentile(65) as i64,
-            fee_75th_percentile: fees_data.percentile(75) as i64,
-            fee_90th_percentile: fees_data.percentile(90) as i64,
-            fee_95th_percentile: fees_data.percentile(95) as i64,
-            fee_max: *(fees_sat.iter().max().unwrap_or(&0)) as i64,
-            fee_sum: fee_sum as i64,
-            fee_avg,
-            size_min: *(sizes.iter().min().unwrap_or(&0)) as i32,
-            size_5th_percentile: sizes_data.percentile(5) as i32,
-            size_10th_percentile: sizes_data.percentile(10) as i32,
-            size_25th_percentile: sizes_data.percentile(25) as i32,
-            size_35th_percentile: sizes_data.percentile(35) as i32,
-            size_50th_percentile: sizes_data.percentile(50) as i32,
-            size_65th_percentile: sizes_data.percentile(65) as i32,
-            size_75th_percentile: sizes_data.percentile(75) as i32,
-            size_90th_percentile: sizes_data.percentile(90) as i32,
-            size_95th_percentile: sizes_data.percentile(95) as i32,
-            size_max: *(sizes.iter().max().unwrap_or(&0)) as i32,
-            size_avg,
-            size_sum: size_sum as i64,
-            feerate_min: *(feerates
-                .iter()
-                .filter(|x| !x.is_nan())
-                .min_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap_or(&0.0)) as f32,
-            feerate_5th_percentile: f64_nan_as_0(feerates_data.percentile(5)) as f32,
-            feerate_10th_percentile: f64_nan_as_0(feerates_data.percentile(10)) as f32,
-            feerate_25th_percentile: f64_nan_as_0(feerates_data.percentile(25)) as f32,
-            feerate_35th_percentile: f64_nan_as_0(feerates_data.percentile(35)) as f32,
-            feerate_50th_percentile: f64_nan_as_0(feerates_data.percentile(50)) as f32,
-            feerate_65th_percentile: f64_nan_as_0(feerates_data.percentile(65)) as f32,
-            feerate_75th_percentile: f64_nan_as_0(feerates_data.percentile(75)) as f32,
-            feerate_90th_percentile: f64_nan_as_0(feerates_data.percentile(90)) as f32,
-            feerate_95th_percentile: f64_nan_as_0(feerates_data.percentile(95)) as f32,
-            feerate_max: *(feerates
-                .iter()
-                .filter(|x| !x.is_nan())
-                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap_or(&0.0)) as f32,
-            feerate_avg,
-            // TODO: Transaction package feerate stats are not yet implemented.
-            feerate_package_min: 0.0f32,
-            feerate_package_5th_percentile: 0.0f32,
-            feerate_package_10th_percentile: 0.0f32,
-            feerate_package_25th_percentile: 0.0f32,
-            feerate_package_35th_percentile: 0.0f32,
-            feerate_package_50th_percentile: 0.0f32,
-            feerate_package_65th_percentile: 0.0f32,
-            feerate_package_75th_percentile: 0.0f32,
-            feerate_package_90th_percentile: 0.0f32,
-            feerate_package_95th_percentile: 0.0f32,
-            feerate_package_max: 0.0f32,
-            feerate_package_avg: 0.0f32,
-            zero_fee_tx,
-            below_1_sat_vbyte,
-            feerate_1_2_sat_vbyte,
-            feerate_2_5_sat_vbyte,
-            feerate_5_10_sat_vbyte,
-            feerate_10_25_sat_vbyte,
-            feerate_25_50_sat_vbyte,
-            feerate_50_100_sat_vbyte,
-            feerate_100_250_sat_vbyte,
-            feerate_250_500_sat_vbyte,
-            feerate_500_1000_sat_vbyte,
-            feerate_1000_plus_sat_vbyte,
+                let mut parents: HashSet<&Txid> = HashSet::new();
+                let mut spending_p2a = false;
+                for input in tx.input.iter() {
+                    let InputData::NonCoinbase { txid, prevout, .. } = &input.data else {
+                        continue;
+                    };
+                    if txs_in_this_block.contains_key(txid) {
+                        parents.insert(txid);
+                    }
+                    spending_p2a |=
+                        matches!(prevout.script_pub_key.type_, ScriptPubkeyType::Anchor);
+                }
+
+                if !parents.is_empty() {
+                    s.tx_v3_with_parent_in_block += 1;
+                    if parents.iter().any(|txid| txs_in_this_block[*txid].0 == 3) {
+                        s.tx_v3_with_v3_parent_in_block += 1;
+                    }
+                    let parents_vsize: u64 = parents
+                        .iter()
+                        .map(|txid| txs_in_this_block[*txid].1 as u64)
+                        .sum();
+                    package_vsizes.push(parents_vsize + tx.vsize as u64);
+                }
+                if spending_p2a {
+                    s.tx_v3_spending_p2a += 1;
+                }
+                if tx
+                    .output
+                    .iter()
+                    .any(|output| matches!(output.script_pub_key.type_, ScriptPubkeyType::Anchor))
+                {
+                    s.tx_v3_creating_p2a += 1;
+                }
+            }
+
+            // A parent is always ordered before the child in the transaction list of a block
+            txs_in_this_block.insert(&tx.txid, (tx.version, tx.vsize));
+        }
+
+        if !vsizes.is_empty() {
+            s.v3_vsize_avg = (vsizes.iter().sum::<f64>() / vsizes.len() as f64) as f32;
+            s.v3_vsize_max = vsizes.iter().fold(0f64, |a, b| a.max(*b)) as i64;
+            s.v3_vsize_median = Data::new(vsizes).median() as f32;
         }
+        if !package_vsizes.is_empty() {
+            s.v3_package_vsize_avg =
+                (package_vsizes.iter().sum::<u64>() as f64 / package_vsizes.len() as f64) as f32;
+            s.v3_package_vsize_max = *package_vsizes.iter().max().unwrap_or(&0) as i64;
+        }
+        s
     }
 }
 
@@ -1179,13 +3882,20 @@ impl FeerateStats {
 mod tests {
     use crate::rest::Block;
     use crate::stats::{
-        BlockStats, FeerateStats, InputStats, OutputStats, ScriptStats, TxStats, STATS_VERSION,
+        BlockStats, DormantSpends, FeerateStats, InputSizeStats, InputStats, OutputStats,
+        ScriptStats, TrucStats, TxStats,
     };
-    use crate::Stats;
+    use crate::stats::{PartialStats, Stats, StatsTable};
     use serde::Deserialize;
+    use std::collections::BTreeSet;
     use std::fs::File;
     use std::io::BufReader;
 
+    fn load_test_block(height: u64) -> Block {
+        let buffer = BufReader::new(File::open(format!("./testdata/{}.json", height)).unwrap());
+        serde_json::from_reader(buffer).expect("test block json to be valid")
+    }
+
     // helper to make diffs in large Stats structs better visible
     fn diff_stats(got: &Stats, expected: &Stats) {
         let got_str = format!("{:#?}", got);
@@ -1200,6 +3910,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_columns_match_migrations() {
+        let mut conn =
+            crate::db::open_db_and_run_migrations(std::path::Path::new(":memory:")).unwrap();
+        for table in StatsTable::ALL {
+            let db_columns: BTreeSet<String> =
+                crate::db::list_column_names(&mut conn, table.table_name())
+                    .unwrap()
+                    .into_iter()
+                    .map(|col| col.name)
+                    .collect();
+            let struct_columns: BTreeSet<String> = table
+                .columns()
+                .iter()
+                .map(|col| col.name.to_string())
+                .collect();
+            assert_eq!(db_columns, struct_columns, "{}", table.table_name());
+        }
+    }
+
+    #[test]
+    fn test_partial_stats() {
+        let load_block = || {
+            let buffer = BufReader::new(File::open("./testdata/739990.json").unwrap());
+            let mut de = serde_json::Deserializer::from_reader(buffer);
+            Block::deserialize(&mut de).expect("test block json to be valid")
+        };
+        let stats = Stats::from_block(load_block()).expect("testdata blocks should not error");
+        let tables = [StatsTable::Output, StatsTable::Feerate]
+            .into_iter()
+            .collect();
+        let partial = PartialStats::from_block(load_block(), &tables)
+            .expect("testdata blocks should not error");
+
+        assert_eq!(partial.height, 739990);
+        assert_eq!(partial.tables(), tables);
+        assert_eq!(partial.output, Some(stats.output));
+        assert_eq!(partial.feerate, Some(stats.feerate));
+        assert_eq!(partial.block, None);
+        assert_eq!(partial.tx, None);
+        assert_eq!(partial.input, None);
+        assert_eq!(partial.script, None);
+    }
+
+    #[test]
+    fn test_truc_stats() {
+        let load_block = |height: u64| {
+            let buffer = BufReader::new(File::open(format!("./testdata/{}.json", height)).unwrap());
+            let mut de = serde_json::Deserializer::from_reader(buffer);
+            Block::deserialize(&mut de).expect("test block json to be valid")
+        };
+
+        // https://mempool.space/tx/c660274eea2851d78fc8beffc0a2ff5420599d371560fb6a46a8d0254fa8840d
+        // spends the P2A ephemeral dust output of its v3 parent
+        let stats_920533 = TrucStats::from_block(&load_block(920533), "2025-10-24".to_string());
+        assert_eq!(stats_920533.tx_v3, 2);
+        assert_eq!(stats_920533.tx_v3_with_parent_in_block, 1);
+        assert_eq!(stats_920533.tx_v3_with_v3_parent_in_block, 1);
+        assert_eq!(stats_920533.tx_v3_creating_p2a, 1);
+        assert_eq!(stats_920533.tx_v3_spending_p2a, 1);
+        assert_eq!(stats_920533.v3_vsize_max, 217);
+        assert_eq!(stats_920533.v3_package_vsize_max, 370);
+
+        let stats_913612 = TrucStats::from_block(&load_block(913612), "2025-09-07".to_string());
+        assert_eq!(stats_913612.tx_v3, 4);
+        assert_eq!(stats_913612.tx_v3_with_v3_parent_in_block, 2);
+        assert_eq!(stats_913612.tx_v3_creating_p2a, 0);
+        assert_eq!(stats_913612.v3_vsize_median, 209.0);
+        assert_eq!(stats_913612.v3_package_vsize_avg, 447.0);
+    }
+
     #[test]
     fn test_ephemeral_dust() {
         // potential false positives
@@ -1241,6 +4022,8 @@ mod tests {
         };
         // https://mempool.space/tx/c660274eea2851d78fc8beffc0a2ff5420599d371560fb6a46a8d0254fa8840d#vin=0
         assert_eq!(stats_920533.tx.tx_spending_ephemeral_dust, 1);
+        assert_eq!(stats_920533.tx.tx_ephemeral_dust_unspent, 0);
+        assert_eq!(stats_920533.tx.tx_spending_p2a_truc_child, 1);
 
         let stats_913612 = {
             let buffer = BufReader::new(File::open("./testdata/913612.json").unwrap());
@@ -1276,13 +4059,13 @@ mod tests {
 
         let expected_stats = Stats {
             block: BlockStats {
-                stats_version: STATS_VERSION,
                 height: 888395,
                 date: "2025-03-18".to_string(),
                 version: 0x24cda000,
                 nonce: 0x03a672d8,
                 bits: 0x17028281,
                 difficulty: 112149504190349,
+                difficulty_exact: "112149504190349.2863005453517427".to_string(),
                 log2_work: 78.67244,
                 size: 1858801,
                 stripped_size: 711367,
@@ -1303,6 +4086,26 @@ mod tests {
                 // This block was mined by MaraPool which has the ID 140
                 // https://github.com/bitcoin-data/mining-pools/blob/7eb988330043456189ba6d01fd32811a1f234f2a/pool-list.json#L1518
                 pool_id: 140,
+                block_count: 1,
+                feerate_min_nonzero: 1.0,
+                feerate_vsize_weighted_5th_percentile: 1.0297971,
+                feerate_vsize_weighted_10th_percentile: 1.0297971,
+                feerate_vsize_weighted_25th_percentile: 1.0297971,
+                feerate_vsize_weighted_50th_percentile: 1.0297971,
+                feerate_vsize_weighted_75th_percentile: 1.0297971,
+                feerate_vsize_weighted_90th_percentile: 1.0297971,
+                feerate_vsize_weighted_95th_percentile: 1.0297971,
+                time: 1742341568,
+                coinbase_script_height: 888395,
+                coinbase_bip34_height: true,
+                coinbase_extra_size: 51,
+                version_rolling_bits: 0x04cda000,
+                version_rolling: true,
+                coinbase_weight_share: 0.00019634842,
+                coinbase_large_displacing: false,
+                input_amount: 342288354,
+                output_amount: 341253712,
+                inputs_per_output: 153.65178,
             },
             tx: TxStats {
                 height: 888395,
@@ -1320,18 +4123,52 @@ mod tests {
                 tx_spending_nested_segwit: 0,
                 tx_spending_native_segwit: 65,
                 tx_spending_taproot: 51,
+                tx_spending_multisig: 0,
+                tx_spending_segwit_amount: 321678158,
+                tx_spending_taproot_amount: 7085839,
+                tx_spending_multisig_amount: 0,
+                tx_spending_segwit_vsize: 996429,
+                tx_spending_taproot_vsize: 984145,
+                tx_spending_only_legacy_vsize: 1545,
                 tx_bip69_compliant: 35,
                 tx_signaling_explicit_rbf: 66,
+                tx_signaling_explicit_rbf_amount: 108257551,
                 tx_1_input: 51,
                 tx_1_output: 48,
                 tx_1_input_1_output: 29,
                 tx_1_input_2_output: 8,
                 tx_spending_newly_created_utxos: 9,
                 tx_spending_ephemeral_dust: 0,
+                tx_ephemeral_dust_unspent: 0,
+                tx_spending_p2a_ln_anchor: 0,
+                tx_spending_p2a_truc_child: 0,
+                tx_spending_p2a_other: 1,
+                tx_vsize_0_140: 1,
+                tx_vsize_141_250: 52,
+                tx_vsize_251_500: 1,
+                tx_vsize_501_1000: 1,
+                tx_vsize_1001_10000: 1,
+                tx_vsize_10001_100000: 17,
+                tx_vsize_100001_plus: 0,
                 tx_timelock_height: 6,
                 tx_timelock_timestamp: 1,
                 tx_timelock_not_enforced: 1,
                 tx_timelock_too_high: 0,
+                tx_txid_leading_zero_bytes_2: 0,
+                tx_txid_leading_zero_bytes_3: 0,
+                tx_txid_leading_zero_bytes_4: 0,
+                tx_silent_payments_eligible: 53,
+                tx_silent_payments_eligible_outputs_p2tr: 54,
+                tx_silent_payments_eligible_inputs_p2tr: 17034,
+                tx_silent_payments_eligible_inputs_p2wpkh: 144,
+                tx_silent_payments_eligible_inputs_p2sh_p2wpkh: 0,
+                tx_silent_payments_eligible_inputs_p2pkh: 0,
+                tx_scriptsig_non_push: 0,
+                tx_scriptsig_non_minimal_push: 0,
+                tx_payment_like: 38,
+                tx_data_embedding: 35,
+                tx_data_embedding_inscription: 34,
+                tx_data_embedding_opreturn: 14,
             },
             input: InputStats {
                 height: 888395,
@@ -1346,6 +4183,11 @@ mod tests {
                 inputs_spending_p2sh_multisig: 0,
                 inputs_spending_nested_p2wsh_multisig: 0,
                 inputs_spending_p2wsh_multisig: 0,
+                inputs_spending_multisig_amount: 0,
+                inputs_spending_p2ms_multisig_amount: 0,
+                inputs_spending_p2sh_multisig_amount: 0,
+                inputs_spending_nested_p2wsh_multisig_amount: 0,
+                inputs_spending_p2wsh_multisig_amount: 0,
                 inputs_p2pk: 0,
                 inputs_p2pkh: 8,
                 inputs_nested_p2wpkh: 0,
@@ -1361,6 +4203,11 @@ mod tests {
                 inputs_p2a: 1,
                 inputs_p2a_dust: 0,
                 inputs_unknown: 0,
+                inputs_p2pk_amount: 0,
+                inputs_p2pkh_amount: 19577890,
+                inputs_nested_p2wpkh_amount: 0,
+                inputs_p2wpkh_amount: 314610156,
+                inputs_p2tr_keypath_amount: 5610000,
                 inputs_spend_in_same_block: 9,
             },
             output: OutputStats {
@@ -1407,6 +4254,23 @@ mod tests {
                 outputs_coinbase_p2tr: 0,
                 outputs_coinbase_opreturn: 1,
                 outputs_coinbase_unknown: 0,
+                outputs_burned: 15,
+                outputs_burned_amount: 0,
+                outputs_opreturn_counterparty: 0,
+                outputs_opreturn_timestamping: 0,
+                outputs_p2ms_counterparty: 0,
+                outputs_p2ms_data: 0,
+                outputs_opreturn_nonstandard_at_the_time: 0,
+                outputs_dust_at_the_time: 0,
+                outputs_p2pk_fake_pubkey: 0,
+                outputs_p2pkh_fake_pubkey_hash: 0,
+                outputs_p2ms_fake_pubkey: 0,
+                outputs_fake_pubkey_amount: 0,
+                outputs_utxo_net_inscription: 13,
+                outputs_utxo_net_runestone: 0,
+                outputs_utxo_net_counterparty: 0,
+                outputs_utxo_net_other_data: 0,
+                outputs_utxo_net_ordinary: -17123,
             },
             script: ScriptStats {
                 height: 888395,
@@ -1419,6 +4283,8 @@ mod tests {
                 pubkeys_compressed_outputs: 54,
                 pubkeys_uncompressed_outputs: 0,
                 sigs_schnorr: 17034,
+                sigs_schnorr_64byte: 17034,
+                sigs_schnorr_65byte: 0,
                 sigs_ecdsa: 174,
                 sigs_ecdsa_not_strict_der: 0,
                 sigs_ecdsa_strict_der: 174,
@@ -1444,6 +4310,31 @@ mod tests {
                 sigs_sighash_all_acp: 0,
                 sigs_sighash_none_acp: 0,
                 sigs_sighash_single_acp: 0,
+                sigs_schnorr_keypath_sighash_default: 17000,
+                sigs_schnorr_keypath_sighash_all: 0,
+                sigs_schnorr_scriptpath_sighash_default: 34,
+                sigs_schnorr_scriptpath_sighash_all: 0,
+                multisig_dummy_null: 0,
+                multisig_dummy_not_null: 0,
+                tx_multiple_p2tr_keypath_inputs: 17,
+                inputs_p2tr_keypath_in_multi_keypath_tx: 17000,
+                sigs_schnorr_aggregatable: 16983,
+                outputs_bare_ctv: 0,
+                outputs_nop4: 0,
+                outputs_reserved_nop: 0,
+                scripts_nop4: 0,
+                scripts_reserved_nop: 0,
+                tapscripts_op_success: 0,
+                tapscripts_op_cat: 0,
+                redeem_scripts_nonstandard_witness_program: 0,
+                redeem_scripts_p2sh: 0,
+                witness_scripts_nested_program: 0,
+                tapscripts_nested_program: 0,
+                witness_bytes: 1112636,
+                witness_bytes_signatures: 1102036,
+                witness_bytes_pubkeys: 5478,
+                witness_bytes_scripts: 5122,
+                witness_bytes_data: 0,
             },
             feerate: FeerateStats {
                 height: 888395,
@@ -1513,6 +4404,38 @@ mod tests {
                 feerate_500_1000_sat_vbyte: 0,
                 feerate_1000_plus_sat_vbyte: 0,
             },
+            truc: TrucStats {
+                height: 888395,
+                date: "2025-03-18".to_string(),
+                ..Default::default()
+            },
+            input_size: InputSizeStats {
+                height: 888395,
+                date: "2025-03-18".to_string(),
+                size_p2pkh_10th_percentile: 106.166664,
+                size_p2pkh_50th_percentile: 107.0,
+                size_p2pkh_90th_percentile: 107.0,
+                size_p2wpkh_10th_percentile: 107.0,
+                size_p2wpkh_50th_percentile: 107.0,
+                size_p2wpkh_90th_percentile: 108.0,
+                size_p2tr_keypath_10th_percentile: 66.0,
+                size_p2tr_keypath_50th_percentile: 66.0,
+                size_p2tr_keypath_90th_percentile: 66.0,
+                size_p2tr_scriptpath_10th_percentile: 210.0,
+                size_p2tr_scriptpath_50th_percentile: 224.0,
+                size_p2tr_scriptpath_90th_percentile: 224.0,
+                ..Default::default()
+            },
+            dormant: DormantSpends {
+                height: 888395,
+                date: "2025-03-18".to_string(),
+                spends_5y: 1,
+                spends_5y_amount: 14955120,
+                spends_10y: 0,
+                spends_10y_amount: 0,
+                spends_pre_2011: 0,
+                spends_pre_2011_amount: 0,
+            },
         };
 
         diff_stats(&stats, &expected_stats);
@@ -1528,13 +4451,13 @@ mod tests {
 
         let expected_stats = Stats {
             block: BlockStats {
-                stats_version: STATS_VERSION,
                 height: 739990,
                 date: "2022-06-09".to_string(),
                 version: 0x20000000,
                 nonce: 0x33ca7510,
                 bits: 0x17094b6a,
                 difficulty: 30283293547736,
+                difficulty_exact: "30283293547736.6751924876463152".to_string(),
                 log2_work: 76.78361,
                 size: 536844,
                 stripped_size: 225535,
@@ -1555,6 +4478,26 @@ mod tests {
                 // This block was mined by Binance Pool which has the ID 123
                 // https://github.com/bitcoin-data/mining-pools/blob/7eb988330043456189ba6d01fd32811a1f234f2a/pool-list.json#L1330C11-L1330C14
                 pool_id: 123,
+                block_count: 1,
+                feerate_min_nonzero: 1.0,
+                feerate_vsize_weighted_5th_percentile: 1.0063062,
+                feerate_vsize_weighted_10th_percentile: 1.0063509,
+                feerate_vsize_weighted_25th_percentile: 1.0064951,
+                feerate_vsize_weighted_50th_percentile: 2.1495328,
+                feerate_vsize_weighted_75th_percentile: 8.735599,
+                feerate_vsize_weighted_90th_percentile: 11.0536585,
+                feerate_vsize_weighted_95th_percentile: 19.837399,
+                time: 1654745578,
+                coinbase_script_height: 739990,
+                coinbase_bip34_height: true,
+                coinbase_extra_size: 76,
+                version_rolling_bits: 0,
+                version_rolling: false,
+                coinbase_weight_share: 0.0010482518,
+                coinbase_large_displacing: false,
+                input_amount: 124429585129,
+                output_amount: 124427602128,
+                inputs_per_output: 1.154952,
             },
             tx: TxStats {
                 height: 739990,
@@ -1572,18 +4515,52 @@ mod tests {
                 tx_spending_nested_segwit: 126,
                 tx_spending_native_segwit: 443,
                 tx_spending_taproot: 1,
+                tx_spending_multisig: 79,
+                tx_spending_segwit_amount: 93287995723,
+                tx_spending_taproot_amount: 228375,
+                tx_spending_multisig_amount: 20604888230,
+                tx_spending_segwit_vsize: 262119,
+                tx_spending_taproot_vsize: 155,
+                tx_spending_only_legacy_vsize: 41158,
                 tx_bip69_compliant: 391,
                 tx_signaling_explicit_rbf: 210,
+                tx_signaling_explicit_rbf_amount: 52989277702,
                 tx_1_input: 499,
                 tx_1_output: 177,
                 tx_1_input_1_output: 112,
                 tx_1_input_2_output: 339,
                 tx_spending_newly_created_utxos: 110,
                 tx_spending_ephemeral_dust: 0,
+                tx_ephemeral_dust_unspent: 0,
+                tx_spending_p2a_ln_anchor: 0,
+                tx_spending_p2a_truc_child: 0,
+                tx_spending_p2a_other: 0,
+                tx_vsize_0_140: 83,
+                tx_vsize_141_250: 437,
+                tx_vsize_251_500: 69,
+                tx_vsize_501_1000: 32,
+                tx_vsize_1001_10000: 17,
+                tx_vsize_10001_100000: 6,
+                tx_vsize_100001_plus: 0,
                 tx_timelock_height: 209,
                 tx_timelock_timestamp: 0,
                 tx_timelock_not_enforced: 22,
                 tx_timelock_too_high: 0,
+                tx_txid_leading_zero_bytes_2: 0,
+                tx_txid_leading_zero_bytes_3: 0,
+                tx_txid_leading_zero_bytes_4: 0,
+                tx_silent_payments_eligible: 1,
+                tx_silent_payments_eligible_outputs_p2tr: 1,
+                tx_silent_payments_eligible_inputs_p2tr: 1,
+                tx_silent_payments_eligible_inputs_p2wpkh: 0,
+                tx_silent_payments_eligible_inputs_p2sh_p2wpkh: 0,
+                tx_silent_payments_eligible_inputs_p2pkh: 0,
+                tx_scriptsig_non_push: 0,
+                tx_scriptsig_non_minimal_push: 0,
+                tx_payment_like: 634,
+                tx_data_embedding: 10,
+                tx_data_embedding_inscription: 0,
+                tx_data_embedding_opreturn: 10,
             },
             input: InputStats {
                 height: 739990,
@@ -1598,6 +4575,11 @@ mod tests {
                 inputs_spending_p2sh_multisig: 28,
                 inputs_spending_nested_p2wsh_multisig: 672,
                 inputs_spending_p2wsh_multisig: 38,
+                inputs_spending_multisig_amount: 20605267881,
+                inputs_spending_p2ms_multisig_amount: 0,
+                inputs_spending_p2sh_multisig_amount: 1033290684,
+                inputs_spending_nested_p2wsh_multisig_amount: 7721248496,
+                inputs_spending_p2wsh_multisig_amount: 11850728701,
                 inputs_p2pk: 0,
                 inputs_p2pkh: 211,
                 inputs_nested_p2wpkh: 654,
@@ -1613,6 +4595,11 @@ mod tests {
                 inputs_p2a: 0,
                 inputs_p2a_dust: 0,
                 inputs_unknown: 0,
+                inputs_p2pk_amount: 0,
+                inputs_p2pkh_amount: 30319466348,
+                inputs_nested_p2wpkh_amount: 6823902999,
+                inputs_p2wpkh_amount: 66679854408,
+                inputs_p2tr_keypath_amount: 228547,
                 inputs_spend_in_same_block: 110,
             },
             output: OutputStats {
@@ -1659,6 +4646,23 @@ mod tests {
                 outputs_coinbase_p2tr: 0,
                 outputs_coinbase_opreturn: 3,
                 outputs_coinbase_unknown: 0,
+                outputs_burned: 13,
+                outputs_burned_amount: 0,
+                outputs_opreturn_counterparty: 2,
+                outputs_opreturn_timestamping: 0,
+                outputs_p2ms_counterparty: 0,
+                outputs_p2ms_data: 0,
+                outputs_opreturn_nonstandard_at_the_time: 0,
+                outputs_dust_at_the_time: 0,
+                outputs_p2pk_fake_pubkey: 0,
+                outputs_p2pkh_fake_pubkey_hash: 0,
+                outputs_p2ms_fake_pubkey: 0,
+                outputs_fake_pubkey_amount: 0,
+                outputs_utxo_net_inscription: 0,
+                outputs_utxo_net_runestone: 0,
+                outputs_utxo_net_counterparty: 0,
+                outputs_utxo_net_other_data: 13,
+                outputs_utxo_net_ordinary: -313,
             },
             script: ScriptStats {
                 height: 739990,
@@ -1671,6 +4675,8 @@ mod tests {
                 pubkeys_compressed_outputs: 7,
                 pubkeys_uncompressed_outputs: 0,
                 sigs_schnorr: 1,
+                sigs_schnorr_64byte: 0,
+                sigs_schnorr_65byte: 1,
                 sigs_ecdsa: 2912,
                 sigs_ecdsa_not_strict_der: 0,
                 sigs_ecdsa_strict_der: 2912,
@@ -1696,6 +4702,31 @@ mod tests {
                 sigs_sighash_all_acp: 2,
                 sigs_sighash_none_acp: 0,
                 sigs_sighash_single_acp: 0,
+                sigs_schnorr_keypath_sighash_default: 0,
+                sigs_schnorr_keypath_sighash_all: 1,
+                sigs_schnorr_scriptpath_sighash_default: 0,
+                sigs_schnorr_scriptpath_sighash_all: 0,
+                multisig_dummy_null: 738,
+                multisig_dummy_not_null: 0,
+                tx_multiple_p2tr_keypath_inputs: 0,
+                inputs_p2tr_keypath_in_multi_keypath_tx: 0,
+                sigs_schnorr_aggregatable: 0,
+                outputs_bare_ctv: 0,
+                outputs_nop4: 0,
+                outputs_reserved_nop: 0,
+                scripts_nop4: 0,
+                scripts_reserved_nop: 0,
+                tapscripts_op_success: 0,
+                tapscripts_op_cat: 0,
+                redeem_scripts_nonstandard_witness_program: 0,
+                redeem_scripts_p2sh: 0,
+                witness_scripts_nested_program: 0,
+                tapscripts_nested_program: 0,
+                witness_bytes: 302896,
+                witness_bytes_signatures: 188577,
+                witness_bytes_pubkeys: 39963,
+                witness_bytes_scripts: 74196,
+                witness_bytes_data: 0,
             },
             feerate: FeerateStats {
                 height: 739990,
@@ -1765,6 +4796,42 @@ mod tests {
                 feerate_500_1000_sat_vbyte: 0,
                 feerate_1000_plus_sat_vbyte: 0,
             },
+            truc: TrucStats {
+                height: 739990,
+                date: "2022-06-09".to_string(),
+                ..Default::default()
+            },
+            input_size: InputSizeStats {
+                height: 739990,
+                date: "2022-06-09".to_string(),
+                size_p2pkh_10th_percentile: 106.0,
+                size_p2pkh_50th_percentile: 107.0,
+                size_p2pkh_90th_percentile: 107.0,
+                size_nested_p2wpkh_10th_percentile: 130.0,
+                size_nested_p2wpkh_50th_percentile: 130.0,
+                size_nested_p2wpkh_90th_percentile: 131.0,
+                size_p2wpkh_10th_percentile: 107.0,
+                size_p2wpkh_50th_percentile: 107.0,
+                size_p2wpkh_90th_percentile: 108.0,
+                size_p2sh_10th_percentile: 252.0,
+                size_p2sh_50th_percentile: 253.0,
+                size_p2sh_90th_percentile: 253.0,
+                size_nested_p2wsh_10th_percentile: 287.0,
+                size_nested_p2wsh_50th_percentile: 287.0,
+                size_nested_p2wsh_90th_percentile: 288.0,
+                size_p2wsh_10th_percentile: 218.0,
+                size_p2wsh_50th_percentile: 252.0,
+                size_p2wsh_90th_percentile: 310.0,
+                size_p2tr_keypath_10th_percentile: 67.0,
+                size_p2tr_keypath_50th_percentile: 67.0,
+                size_p2tr_keypath_90th_percentile: 67.0,
+                ..Default::default()
+            },
+            dormant: DormantSpends {
+                height: 739990,
+                date: "2022-06-09".to_string(),
+                ..Default::default()
+            },
         };
 
         diff_stats(&stats, &expected_stats);
@@ -1780,13 +4847,13 @@ mod tests {
 
         let expected_stats = Stats {
             block: BlockStats {
-                stats_version: STATS_VERSION,
                 height: 361582,
                 date: "2015-06-19".to_string(),
                 version: 2,
                 nonce: 0x444386f8,
                 bits: 0x18162043,
                 difficulty: 49692386354,
+                difficulty_exact: "49692386354.8938347685702089".to_string(),
                 log2_work: 67.532326,
                 size: 163491,
                 stripped_size: 163491,
@@ -1807,6 +4874,26 @@ mod tests {
                 // This block was mined by MegaBigPower which has the ID 39
                 // https://github.com/bitcoin-data/mining-pools/blob/7eb988330043456189ba6d01fd32811a1f234f2a/pool-list.json#L388-L401
                 pool_id: 39,
+                block_count: 1,
+                feerate_min_nonzero: 1.00492,
+                feerate_vsize_weighted_5th_percentile: 1.0324293,
+                feerate_vsize_weighted_10th_percentile: 1.0324293,
+                feerate_vsize_weighted_25th_percentile: 1.0324293,
+                feerate_vsize_weighted_50th_percentile: 15.320652,
+                feerate_vsize_weighted_75th_percentile: 26.88172,
+                feerate_vsize_weighted_90th_percentile: 44.444443,
+                feerate_vsize_weighted_95th_percentile: 44.84305,
+                time: 1434694400,
+                coinbase_script_height: 361582,
+                coinbase_bip34_height: true,
+                coinbase_extra_size: 13,
+                version_rolling_bits: 0,
+                version_rolling: false,
+                coinbase_weight_share: 0.0006238876,
+                coinbase_large_displacing: false,
+                input_amount: 303329530827,
+                output_amount: 303325843318,
+                inputs_per_output: 1.5559322,
             },
             tx: TxStats {
                 height: 361582,
@@ -1824,18 +4911,52 @@ mod tests {
                 tx_spending_nested_segwit: 0,
                 tx_spending_native_segwit: 0,
                 tx_spending_taproot: 0,
+                tx_spending_multisig: 14,
+                tx_spending_segwit_amount: 0,
+                tx_spending_taproot_amount: 0,
+                tx_spending_multisig_amount: 58760295515,
+                tx_spending_segwit_vsize: 0,
+                tx_spending_taproot_vsize: 0,
+                tx_spending_only_legacy_vsize: 163306,
                 tx_bip69_compliant: 116,
                 tx_signaling_explicit_rbf: 0,
+                tx_signaling_explicit_rbf_amount: 0,
                 tx_1_input: 146,
                 tx_1_output: 31,
                 tx_1_input_1_output: 16,
                 tx_1_input_2_output: 125,
                 tx_spending_newly_created_utxos: 45,
                 tx_spending_ephemeral_dust: 0,
+                tx_ephemeral_dust_unspent: 0,
+                tx_spending_p2a_ln_anchor: 0,
+                tx_spending_p2a_truc_child: 0,
+                tx_spending_p2a_other: 0,
+                tx_vsize_0_140: 1,
+                tx_vsize_141_250: 107,
+                tx_vsize_251_500: 114,
+                tx_vsize_501_1000: 42,
+                tx_vsize_1001_10000: 11,
+                tx_vsize_10001_100000: 1,
+                tx_vsize_100001_plus: 0,
                 tx_timelock_height: 1,
                 tx_timelock_timestamp: 0,
                 tx_timelock_not_enforced: 0,
                 tx_timelock_too_high: 0,
+                tx_txid_leading_zero_bytes_2: 0,
+                tx_txid_leading_zero_bytes_3: 0,
+                tx_txid_leading_zero_bytes_4: 0,
+                tx_silent_payments_eligible: 0,
+                tx_silent_payments_eligible_outputs_p2tr: 0,
+                tx_silent_payments_eligible_inputs_p2tr: 0,
+                tx_silent_payments_eligible_inputs_p2wpkh: 0,
+                tx_silent_payments_eligible_inputs_p2sh_p2wpkh: 0,
+                tx_silent_payments_eligible_inputs_p2pkh: 0,
+                tx_scriptsig_non_push: 0,
+                tx_scriptsig_non_minimal_push: 0,
+                tx_payment_like: 276,
+                tx_data_embedding: 0,
+                tx_data_embedding_inscription: 0,
+                tx_data_embedding_opreturn: 0,
             },
             input: InputStats {
                 height: 361582,
@@ -1850,6 +4971,11 @@ mod tests {
                 inputs_spending_p2sh_multisig: 19,
                 inputs_spending_nested_p2wsh_multisig: 0,
                 inputs_spending_p2wsh_multisig: 0,
+                inputs_spending_multisig_amount: 58760390515,
+                inputs_spending_p2ms_multisig_amount: 0,
+                inputs_spending_p2sh_multisig_amount: 58760390515,
+                inputs_spending_nested_p2wsh_multisig_amount: 0,
+                inputs_spending_p2wsh_multisig_amount: 0,
                 inputs_p2pk: 0,
                 inputs_p2pkh: 898,
                 inputs_nested_p2wpkh: 0,
@@ -1865,6 +4991,11 @@ mod tests {
                 inputs_p2a: 0,
                 inputs_p2a_dust: 0,
                 inputs_unknown: 0,
+                inputs_p2pk_amount: 0,
+                inputs_p2pkh_amount: 244569120312,
+                inputs_nested_p2wpkh_amount: 0,
+                inputs_p2wpkh_amount: 0,
+                inputs_p2tr_keypath_amount: 0,
                 inputs_spend_in_same_block: 52,
             },
             output: OutputStats {
@@ -1911,6 +5042,23 @@ mod tests {
                 outputs_coinbase_p2tr: 0,
                 outputs_coinbase_opreturn: 0,
                 outputs_coinbase_unknown: 0,
+                outputs_burned: 0,
+                outputs_burned_amount: 0,
+                outputs_opreturn_counterparty: 0,
+                outputs_opreturn_timestamping: 0,
+                outputs_p2ms_counterparty: 0,
+                outputs_p2ms_data: 0,
+                outputs_opreturn_nonstandard_at_the_time: 0,
+                outputs_dust_at_the_time: 0,
+                outputs_p2pk_fake_pubkey: 0,
+                outputs_p2pkh_fake_pubkey_hash: 0,
+                outputs_p2ms_fake_pubkey: 0,
+                outputs_fake_pubkey_amount: 0,
+                outputs_utxo_net_inscription: 0,
+                outputs_utxo_net_runestone: 0,
+                outputs_utxo_net_counterparty: 0,
+                outputs_utxo_net_other_data: 0,
+                outputs_utxo_net_ordinary: -327,
             },
             script: ScriptStats {
                 height: 361582,
@@ -1923,6 +5071,8 @@ mod tests {
                 pubkeys_compressed_outputs: 0,
                 pubkeys_uncompressed_outputs: 0,
                 sigs_schnorr: 0,
+                sigs_schnorr_64byte: 0,
+                sigs_schnorr_65byte: 0,
                 sigs_ecdsa: 935,
                 sigs_ecdsa_not_strict_der: 0,
                 sigs_ecdsa_strict_der: 935,
@@ -1948,6 +5098,31 @@ mod tests {
                 sigs_sighash_all_acp: 0,
                 sigs_sighash_none_acp: 0,
                 sigs_sighash_single_acp: 0,
+                sigs_schnorr_keypath_sighash_default: 0,
+                sigs_schnorr_keypath_sighash_all: 0,
+                sigs_schnorr_scriptpath_sighash_default: 0,
+                sigs_schnorr_scriptpath_sighash_all: 0,
+                multisig_dummy_null: 19,
+                multisig_dummy_not_null: 0,
+                tx_multiple_p2tr_keypath_inputs: 0,
+                inputs_p2tr_keypath_in_multi_keypath_tx: 0,
+                sigs_schnorr_aggregatable: 0,
+                outputs_bare_ctv: 0,
+                outputs_nop4: 0,
+                outputs_reserved_nop: 0,
+                scripts_nop4: 0,
+                scripts_reserved_nop: 0,
+                tapscripts_op_success: 0,
+                tapscripts_op_cat: 0,
+                redeem_scripts_nonstandard_witness_program: 0,
+                redeem_scripts_p2sh: 0,
+                witness_scripts_nested_program: 0,
+                tapscripts_nested_program: 0,
+                witness_bytes: 0,
+                witness_bytes_signatures: 0,
+                witness_bytes_pubkeys: 0,
+                witness_bytes_scripts: 0,
+                witness_bytes_data: 0,
             },
             feerate: FeerateStats {
                 height: 361582,
@@ -2017,9 +5192,708 @@ mod tests {
                 feerate_500_1000_sat_vbyte: 0,
                 feerate_1000_plus_sat_vbyte: 0,
             },
+            truc: TrucStats {
+                height: 361582,
+                date: "2015-06-19".to_string(),
+                ..Default::default()
+            },
+            input_size: InputSizeStats {
+                height: 361582,
+                date: "2015-06-19".to_string(),
+                size_p2pkh_10th_percentile: 106.0,
+                size_p2pkh_50th_percentile: 107.0,
+                size_p2pkh_90th_percentile: 108.0,
+                size_p2sh_10th_percentile: 150.86667,
+                size_p2sh_50th_percentile: 252.0,
+                size_p2sh_90th_percentile: 253.0,
+                ..Default::default()
+            },
+            dormant: DormantSpends {
+                height: 361582,
+                date: "2015-06-19".to_string(),
+                ..Default::default()
+            },
         };
 
         diff_stats(&stats, &expected_stats);
         assert_eq!(stats, expected_stats, "see diff above");
     }
+
+    #[test]
+    fn test_capture_contributors() {
+        let block = load_test_block(739990);
+        let stats = PartialStats::from_block(block, &StatsTable::ALL.into_iter().collect())
+            .expect("testdata blocks should not error");
+        assert!(stats.contributors.is_none());
+
+        let block = load_test_block(739990);
+        let captured = PartialStats::from_block_capturing(
+            block,
+            &StatsTable::ALL.into_iter().collect(),
+            Some("tx_1_input_2_output"),
+        )
+        .expect("testdata blocks should not error");
+        let contributors = captured.contributors.clone().unwrap();
+        let tx = captured.tx.clone().unwrap();
+        assert_eq!(contributors.len(), tx.tx_1_input_2_output as usize);
+        assert!(contributors
+            .iter()
+            .all(|c| c.height == 739990 && c.stat == "tx_1_input_2_output"));
+        // capturing doesn't change the stats
+        assert_eq!(
+            PartialStats {
+                contributors: None,
+                ..captured
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn test_coinbase() {
+        use crate::stats::Coinbase;
+
+        let buffer = BufReader::new(File::open("./testdata/925262.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let coinbase = Coinbase::from_block(&block).expect("coinbase should serialize");
+        assert_eq!(coinbase.height, 925262);
+        assert_eq!(coinbase.raw, block.txdata[0].raw);
+    }
+
+    #[test]
+    fn test_coinbase_spends() {
+        use crate::stats::CoinbaseSpend;
+
+        let buffer = BufReader::new(File::open("./testdata/925262.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        assert_eq!(
+            CoinbaseSpend::from_block(&block).unwrap(),
+            vec![CoinbaseSpend {
+                coinbase_height: 925160,
+                vout: 1,
+                spend_height: 925262,
+                txid: "22ca2f5127d3f8ea1b23aa6c8f585e4cc488281bbc78c814e37e881443dd7a9c"
+                    .to_string(),
+                value: 317922371,
+                outputs: 1,
+                destination_type: "witness_v0_keyhash".to_string(),
+            }]
+        );
+
+        let buffer = BufReader::new(File::open("./testdata/888395.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        assert!(CoinbaseSpend::from_block(&block).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_opcode_counts() {
+        use crate::stats::OpcodeCount;
+
+        let count = |counts: &[OpcodeCount], script_type: &str, opcode: &str| {
+            counts
+                .iter()
+                .find(|c| c.script_type == script_type && c.opcode == opcode)
+                .map(|c| c.count)
+        };
+
+        let buffer = BufReader::new(File::open("./testdata/361582.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let counts = OpcodeCount::from_block(&block);
+        assert!(counts.iter().all(|c| c.script_type == "redeem_script"));
+        assert_eq!(
+            count(&counts, "redeem_script", "OP_CHECKMULTISIG"),
+            Some(19)
+        );
+
+        let buffer = BufReader::new(File::open("./testdata/739990.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let counts = OpcodeCount::from_block(&block);
+        assert_eq!(
+            count(&counts, "redeem_script", "OP_CHECKMULTISIG"),
+            Some(28)
+        );
+        assert_eq!(
+            count(&counts, "witness_script", "OP_CHECKMULTISIG"),
+            Some(711)
+        );
+        assert_eq!(count(&counts, "witness_script", "OP_CLTV"), Some(5));
+        assert_eq!(count(&counts, "tapscript", "OP_CHECKSIG"), None);
+
+        // inscription envelopes
+        let buffer = BufReader::new(File::open("./testdata/888395.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let counts = OpcodeCount::from_block(&block);
+        assert_eq!(count(&counts, "tapscript", "OP_CHECKSIG"), Some(34));
+        assert_eq!(count(&counts, "tapscript", "OP_IF"), Some(34));
+        assert!(counts.iter().all(|c| c.height == 888395));
+    }
+
+    #[test]
+    fn test_silent_payments_input() {
+        use crate::rest::Input;
+        use crate::stats::{SilentPaymentsInput, TAPROOT_NUMS_POINT};
+        use bitcoin::hex::DisplayHex;
+
+        let input = |spk_type: &str, spk: &str, script_sig: &str, witness: Vec<String>| -> Input {
+            serde_json::from_value(serde_json::json!({
+                "txid": "c660274eea2851d78fc8beffc0a2ff5420599d371560fb6a46a8d0254fa8840d",
+                "vout": 0,
+                "scriptSig": { "asm": "", "hex": script_sig },
+                "txinwitness": witness,
+                "prevout": {
+                    "generated": false,
+                    "height": 1,
+                    "value": 0.001,
+                    "scriptPubKey": { "asm": "", "hex": spk, "type": spk_type },
+                },
+                "sequence": 4294967295u32,
+            }))
+            .expect("valid input json")
+        };
+        let p2tr = format!("5120{}", "11".repeat(32));
+        let signature = "22".repeat(64);
+        let pubkey = format!("02{}", "33".repeat(32));
+
+        let key_path = input("witness_v1_taproot", &p2tr, "", vec![signature.clone()]);
+        assert_eq!(
+            SilentPaymentsInput::classify(&key_path),
+            Some(SilentPaymentsInput::P2tr)
+        );
+        let control_block = |internal_key: &str| format!("c0{}", internal_key);
+        let script_path = input(
+            "witness_v1_taproot",
+            &p2tr,
+            "",
+            vec![
+                signature.clone(),
+                "51".to_string(),
+                control_block(&"44".repeat(32)),
+            ],
+        );
+        assert_eq!(
+            SilentPaymentsInput::classify(&script_path),
+            Some(SilentPaymentsInput::P2tr)
+        );
+        let nums_script_path = input(
+            "witness_v1_taproot",
+            &p2tr,
+            "",
+            vec![
+                signature.clone(),
+                "51".to_string(),
+                control_block(&TAPROOT_NUMS_POINT.to_lower_hex_string()),
+                // the annex
+                "50".to_string(),
+            ],
+        );
+        assert_eq!(SilentPaymentsInput::classify(&nums_script_path), None);
+
+        let p2wpkh = format!("0014{}", "55".repeat(20));
+        let p2sh_p2wpkh = input(
+            "scripthash",
+            &format!("a914{}87", "66".repeat(20)),
+            &format!("16{}", p2wpkh),
+            vec![signature.clone(), pubkey.clone()],
+        );
+        assert_eq!(
+            SilentPaymentsInput::classify(&p2sh_p2wpkh),
+            Some(SilentPaymentsInput::P2shP2wpkh)
+        );
+        let uncompressed_p2pkh = input(
+            "pubkeyhash",
+            &format!("76a914{}88ac", "55".repeat(20)),
+            &format!("41{}4104{}", "22".repeat(65), "33".repeat(64)),
+            vec![],
+        );
+        assert_eq!(SilentPaymentsInput::classify(&uncompressed_p2pkh), None);
+
+        let segwit_v2 = input(
+            "witness_unknown",
+            &format!("5220{}", "11".repeat(32)),
+            "",
+            vec![],
+        );
+        assert!(SilentPaymentsInput::is_future_segwit(&segwit_v2));
+        assert!(!SilentPaymentsInput::is_future_segwit(&key_path));
+    }
+
+    #[test]
+    fn test_coinbase_script_height() {
+        use crate::stats::coinbase_script_height;
+        use bitcoin::script::Builder;
+        use bitcoin::ScriptBuf;
+
+        let bip34 = Builder::new()
+            .push_int(739990)
+            .push_slice(b"extranonce")
+            .into_script();
+        assert_eq!(coinbase_script_height(&bip34), Some(739990));
+        assert_eq!(
+            coinbase_script_height(&Builder::new().push_int(5).into_script()),
+            Some(5)
+        );
+        // the genesis block coinbase starts with the bits 0x1d00ffff
+        let genesis = ScriptBuf::from_hex("04ffff001d0104").unwrap();
+        assert_eq!(coinbase_script_height(&genesis), Some(0x1d00ffff));
+        // not minimally encoded
+        let non_minimal = ScriptBuf::from_bytes(vec![0x02, 0x01, 0x00]);
+        assert_eq!(coinbase_script_height(&non_minimal), None);
+        assert_eq!(coinbase_script_height(&ScriptBuf::new()), None);
+    }
+
+    #[test]
+    fn test_fake_pubkeys() {
+        use crate::stats::{is_fake_pubkey, is_pattern_data};
+
+        // the compressed generator point
+        let generator: Vec<u8> = bitcoin::hex::FromHex::from_hex(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        assert!(!is_fake_pubkey(&generator));
+        let mut zero = vec![0x02];
+        zero.extend([0u8; 32]);
+        assert!(is_fake_pubkey(&zero));
+        let mut ascii = vec![0x02];
+        ascii.extend(b"Hello, world! This is not a key.");
+        assert!(is_fake_pubkey(&ascii));
+        assert!(is_fake_pubkey(&[0x04; 65]));
+
+        assert!(is_pattern_data(&[0u8; 20]));
+        assert!(is_pattern_data(&[0xff; 20]));
+        assert!(is_pattern_data(b"BurnBurnBurnBurnBurn"));
+        assert!(!is_pattern_data(&generator[1..21]));
+    }
+
+    #[test]
+    fn test_scriptsig_malleability() {
+        use crate::stats::scriptsig_malleability;
+        use bitcoin::opcodes::all::OP_NOP;
+        use bitcoin::script::Builder;
+        use bitcoin::ScriptBuf;
+
+        let p2pkh = Builder::new()
+            .push_slice([0x30; 71])
+            .push_slice([0x02; 33])
+            .into_script();
+        assert_eq!(scriptsig_malleability(&p2pkh), (false, false));
+        let with_nop = Builder::new()
+            .push_opcode(OP_NOP)
+            .push_slice([0x30; 71])
+            .into_script();
+        assert_eq!(scriptsig_malleability(&with_nop), (true, false));
+        // 33 bytes pushed with OP_PUSHDATA1
+        let mut bytes = vec![0x4c, 33];
+        bytes.extend([0x02; 33]);
+        let pushdata1 = ScriptBuf::from_bytes(bytes);
+        assert_eq!(scriptsig_malleability(&pushdata1), (false, true));
+        // OP_0 and OP_1 are pushes
+        let multisig = Builder::new().push_int(0).push_int(1).into_script();
+        assert_eq!(scriptsig_malleability(&multisig), (false, false));
+        assert_eq!(scriptsig_malleability(&ScriptBuf::new()), (false, false));
+    }
+
+    #[test]
+    fn test_coinbase_extra_size() {
+        use crate::stats::coinbase_extra_size;
+        use bitcoin::ScriptBuf;
+
+        // height push, 4 byte push and 4 garbage bytes
+        let script_sig = ScriptBuf::from_hex("036e8405045583b024acec8dee").unwrap();
+        assert_eq!(coinbase_extra_size(&script_sig), 9);
+        // OP_PUSHDATA1 with 3 bytes
+        let script_sig = ScriptBuf::from_hex("4c036e8405aabb").unwrap();
+        assert_eq!(coinbase_extra_size(&script_sig), 2);
+        // OP_1 as height
+        let script_sig = ScriptBuf::from_hex("51aabb").unwrap();
+        assert_eq!(coinbase_extra_size(&script_sig), 2);
+        // a push beyond the end of the script
+        let script_sig = ScriptBuf::from_hex("05aabb").unwrap();
+        assert_eq!(coinbase_extra_size(&script_sig), 3);
+        assert_eq!(coinbase_extra_size(&ScriptBuf::new()), 0);
+    }
+
+    #[test]
+    fn test_exact_difficulty() {
+        use crate::stats::exact_difficulty;
+        use bitcoin::CompactTarget;
+
+        // the maximum target
+        assert_eq!(
+            exact_difficulty(CompactTarget::from_consensus(0x1d00ffff)).unwrap(),
+            "1.0000000000000000"
+        );
+        // the first retarget at height 32256
+        assert_eq!(
+            exact_difficulty(CompactTarget::from_consensus(0x1d00d86a)).unwrap(),
+            "1.1828995343128406"
+        );
+        // regtest
+        assert_eq!(
+            exact_difficulty(CompactTarget::from_consensus(0x207fffff)).unwrap(),
+            "0.0000000004656542"
+        );
+        assert_eq!(
+            exact_difficulty(CompactTarget::from_consensus(0x1d000000)),
+            None
+        );
+        assert_eq!(
+            exact_difficulty(CompactTarget::from_consensus(0x1d800001)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_saturating_amounts() {
+        use crate::stats::{add_sats, sats, saturating_i64, sum_sats};
+        use bitcoin::Amount;
+
+        assert_eq!(sats(Amount::MAX_MONEY), 2_100_000_000_000_000);
+        assert_eq!(sats(Amount::MAX), i64::MAX);
+        assert_eq!(saturating_i64(i64::MAX as u64), i64::MAX);
+        assert_eq!(saturating_i64(u64::MAX), i64::MAX);
+
+        let mut sum = i64::MAX - 1;
+        add_sats(&mut sum, Amount::from_sat(1));
+        assert_eq!(sum, i64::MAX);
+        add_sats(&mut sum, Amount::MAX_MONEY);
+        assert_eq!(sum, i64::MAX);
+
+        assert_eq!(
+            sum_sats([Amount::ONE_SAT, Amount::ONE_BTC].into_iter()),
+            100_000_001
+        );
+        assert_eq!(
+            sum_sats(std::iter::repeat_n(Amount::MAX_MONEY, 5000)),
+            i64::MAX
+        );
+        assert_eq!(sum_sats(std::iter::empty()), 0);
+    }
+
+    #[test]
+    fn test_script_nesting() {
+        use crate::stats::{is_nested_program, is_nonstandard_wrapped_program};
+        use bitcoin::hashes::Hash;
+        use bitcoin::opcodes::all::{OP_CHECKSIG, OP_PUSHNUM_1};
+        use bitcoin::script::Builder;
+        use bitcoin::{ScriptBuf, ScriptHash, WPubkeyHash, WScriptHash};
+
+        let p2wpkh = ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros());
+        let p2wsh = ScriptBuf::new_p2wsh(&WScriptHash::all_zeros());
+        let p2sh = ScriptBuf::new_p2sh(&ScriptHash::all_zeros());
+        let p2tr = Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice([0x79; 32])
+            .into_script();
+        let checksig = Builder::new()
+            .push_slice([0x02; 33])
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        assert!(!is_nonstandard_wrapped_program(&p2wpkh));
+        assert!(!is_nonstandard_wrapped_program(&p2wsh));
+        assert!(is_nonstandard_wrapped_program(&p2tr));
+        assert!(!is_nonstandard_wrapped_program(&checksig));
+
+        assert!(is_nested_program(&p2sh));
+        assert!(is_nested_program(&p2wpkh));
+        assert!(is_nested_program(&p2tr));
+        assert!(!is_nested_program(&checksig));
+    }
+
+    #[test]
+    fn test_vsize_weighted_feerates() {
+        use crate::stats::VsizeWeightedFeerates;
+
+        // a large low feerate transaction and two small high feerate ones
+        let feerates = VsizeWeightedFeerates {
+            feerates: vec![(1.0, 800), (10.0, 100), (50.0, 100)],
+            total_vsize: 1000,
+        };
+        assert_eq!(feerates.percentile(0), 1.0);
+        assert_eq!(feerates.percentile(50), 1.0);
+        assert_eq!(feerates.percentile(80), 1.0);
+        assert_eq!(feerates.percentile(85), 10.0);
+        assert_eq!(feerates.percentile(95), 50.0);
+        assert_eq!(feerates.percentile(100), 50.0);
+
+        let empty = VsizeWeightedFeerates {
+            feerates: vec![],
+            total_vsize: 0,
+        };
+        assert_eq!(empty.percentile(50), 0.0);
+    }
+
+    #[test]
+    fn test_feerate_percentiles() {
+        use crate::stats::{FeeratePercentile, Stats};
+
+        let buffer = BufReader::new(File::open("./testdata/739990.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let percentiles = FeeratePercentile::from_block(&block, &[0, 50, 95]);
+        let feerate = Stats::from_block(block).unwrap().feerate;
+        assert_eq!(
+            percentiles
+                .iter()
+                .map(|p| (p.percentile, p.value))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, feerate.feerate_min),
+                (50, feerate.feerate_50th_percentile),
+                (95, feerate.feerate_95th_percentile),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_template() {
+        use crate::stats::{script_template, NovelScript};
+        use bitcoin::opcodes::all::{OP_EQUAL, OP_HASH256};
+        use bitcoin::script::Builder;
+        use bitcoin::ScriptBuf;
+
+        let hash_puzzle = Builder::new()
+            .push_opcode(OP_HASH256)
+            .push_slice([1u8; 32])
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        assert_eq!(script_template(&hash_puzzle), "OP_HASH256 <32> OP_EQUAL");
+        assert_eq!(
+            script_template(&Builder::new().push_int(0).push_int(5).into_script()),
+            "OP_0 OP_PUSHNUM_5"
+        );
+        // OP_PUSHBYTES_2 with only one byte left
+        assert_eq!(
+            script_template(&ScriptBuf::from_bytes(vec![0x87, 0x02, 0x01])),
+            "OP_EQUAL <invalid>"
+        );
+
+        let buffer = BufReader::new(File::open("./testdata/739990.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        assert_eq!(NovelScript::from_block(&block), vec![]);
+    }
+
+    #[test]
+    fn test_witness_bytes() {
+        use crate::rest::ScriptPubkeyType;
+        use crate::stats::WitnessBytes;
+        use bitcoin::opcodes::all::{OP_CHECKSIG, OP_ENDIF, OP_IF};
+        use bitcoin::opcodes::OP_FALSE;
+        use bitcoin::script::{Builder, PushBytesBuf};
+        use bitcoin::{ScriptBuf, Witness};
+
+        let mut der_sig = vec![0x30; 71];
+        der_sig[70] = 0x01;
+        let p2wpkh = Witness::from_slice(&[der_sig, vec![0x02; 33]]);
+        assert_eq!(
+            WitnessBytes::of_input(
+                &ScriptPubkeyType::Witness_v0_KeyHash,
+                &ScriptBuf::new(),
+                &p2wpkh
+            ),
+            WitnessBytes {
+                total: 104,
+                signatures: 71,
+                pubkeys: 33,
+                scripts: 0,
+                data: 0,
+            }
+        );
+
+        // inscription-like envelope with a 200 byte data push
+        let tapscript = Builder::new()
+            .push_slice([1u8; 32])
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(PushBytesBuf::try_from(vec![2u8; 200]).unwrap())
+            .push_opcode(OP_ENDIF)
+            .into_script();
+        let script_path =
+            Witness::from_slice(&[vec![3u8; 64], tapscript.to_bytes(), vec![0xc0; 33]]);
+        assert_eq!(
+            WitnessBytes::of_input(
+                &ScriptPubkeyType::Witness_v1_Taproot,
+                &ScriptBuf::new(),
+                &script_path
+            ),
+            WitnessBytes {
+                total: 64 + tapscript.len() + 33,
+                signatures: 64,
+                pubkeys: 0,
+                scripts: tapscript.len() - 200 + 33,
+                data: 200,
+            }
+        );
+
+        // key-path spend with an annex
+        let key_path = Witness::from_slice(&[vec![3u8; 64], vec![0x50, 0x00]]);
+        assert_eq!(
+            WitnessBytes::of_input(
+                &ScriptPubkeyType::Witness_v1_Taproot,
+                &ScriptBuf::new(),
+                &key_path
+            ),
+            WitnessBytes {
+                total: 66,
+                signatures: 64,
+                pubkeys: 0,
+                scripts: 0,
+                data: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_proposed_opcodes() {
+        use crate::stats::{is_bare_ctv, ProposedOpcodes};
+        use bitcoin::opcodes::all::{OP_CAT, OP_CHECKSIG, OP_DROP, OP_EQUAL, OP_NOP10, OP_NOP4};
+        use bitcoin::opcodes::OP_TRUE;
+        use bitcoin::script::Builder;
+
+        let bare_ctv = Builder::new()
+            .push_slice([1u8; 32])
+            .push_opcode(OP_NOP4)
+            .into_script();
+        assert!(is_bare_ctv(&bare_ctv));
+        assert_eq!(
+            ProposedOpcodes::in_script(&bare_ctv),
+            ProposedOpcodes {
+                nop4: true,
+                ..Default::default()
+            }
+        );
+
+        let cat = Builder::new()
+            .push_opcode(OP_CAT)
+            .push_slice([1u8; 2])
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        assert!(!is_bare_ctv(&cat));
+        assert_eq!(
+            ProposedOpcodes::in_script(&cat),
+            ProposedOpcodes {
+                op_success: true,
+                op_cat: true,
+                ..Default::default()
+            }
+        );
+
+        let reserved_nop = Builder::new()
+            .push_opcode(OP_NOP10)
+            .push_opcode(OP_DROP)
+            .push_opcode(OP_TRUE)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        assert_eq!(
+            ProposedOpcodes::in_script(&reserved_nop),
+            ProposedOpcodes {
+                reserved_nop: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_txid_leading_zero_bytes() {
+        use crate::stats::txid_leading_zero_bytes;
+        use bitcoin::Txid;
+        use std::str::FromStr;
+
+        let txid = |hex: &str| Txid::from_str(hex).unwrap();
+        assert_eq!(
+            txid_leading_zero_bytes(&txid(
+                "22ca2f5127d3f8ea1b23aa6c8f585e4cc488281bbc78c814e37e881443dd7a9c"
+            )),
+            0
+        );
+        assert_eq!(
+            txid_leading_zero_bytes(&txid(
+                "000000f127d3f8ea1b23aa6c8f585e4cc488281bbc78c814e37e881443dd7a00"
+            )),
+            3
+        );
+    }
+
+    #[test]
+    fn test_script_type_transitions() {
+        use crate::stats::ScriptTypeTransition;
+
+        let buffer = BufReader::new(File::open("./testdata/925262.json").unwrap());
+        let block: Block = serde_json::from_reader(buffer).expect("test block json to be valid");
+        let transitions = ScriptTypeTransition::from_block(&block);
+        let count = |from_type: &str, to_type: &str| {
+            transitions
+                .iter()
+                .find(|t| t.from_type == from_type && t.to_type == to_type)
+                .map(|t| t.count)
+        };
+        assert_eq!(transitions.len(), 29);
+        assert_eq!(count("pubkeyhash", "witness_v1_taproot"), Some(8));
+        assert_eq!(count("witness_v1_taproot", "witness_v1_taproot"), Some(772));
+        assert_eq!(count("witness_v1_taproot", "multisig"), None);
+        assert!(transitions.iter().all(|t| t.height == 925262));
+    }
+
+    #[test]
+    fn test_is_burned() {
+        use crate::stats::{is_burned, BURN_ADDRESSES, MAX_SCRIPT_SIZE};
+        use bitcoin::opcodes::all::OP_RETURN;
+        use bitcoin::script::Builder;
+        use bitcoin::{Address, ScriptBuf};
+
+        let op_return = Builder::new().push_opcode(OP_RETURN).into_script();
+        assert!(is_burned(&op_return));
+        assert!(is_burned(&ScriptBuf::from_bytes(vec![
+            0x51;
+            MAX_SCRIPT_SIZE + 1
+        ])));
+        for address in BURN_ADDRESSES {
+            let script_pubkey = address
+                .parse::<Address<_>>()
+                .unwrap()
+                .assume_checked()
+                .script_pubkey();
+            assert!(is_burned(&script_pubkey));
+        }
+        let p2pkh = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+            .parse::<Address<_>>()
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        assert!(!is_burned(&p2pkh));
+    }
+
+    #[test]
+    fn test_data_protocols() {
+        use crate::stats::{arc4, is_counterparty_multisig, is_counterparty_opreturn};
+
+        // RC4 test vector
+        assert_eq!(
+            arc4(b"Key", b"Plaintext"),
+            [0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3]
+        );
+
+        let key = [7u8; 32];
+        let mut payload = b"CNTRPRTY".to_vec();
+        payload.extend([0, 0, 0, 20]);
+        assert!(is_counterparty_opreturn(&payload, None));
+        assert!(is_counterparty_opreturn(&arc4(&key, &payload), Some(&key)));
+        assert!(!is_counterparty_opreturn(&arc4(&key, &payload), None));
+
+        // two data pubkeys and the sender pubkey
+        let mut data = vec![payload.len() as u8];
+        data.extend(&payload);
+        data.resize(62, 0);
+        let obfuscated = arc4(&key, &data);
+        let to_pubkey = |chunk: &[u8]| [&[0x02], chunk, &[0x00]].concat();
+        let pubkeys = [
+            to_pubkey(&obfuscated[..31]),
+            to_pubkey(&obfuscated[31..]),
+            vec![0x03; 33],
+        ];
+        let pubkeys: Vec<&[u8]> = pubkeys.iter().map(|p| p.as_slice()).collect();
+        assert!(is_counterparty_multisig(&pubkeys, Some(&key)));
+        assert!(!is_counterparty_multisig(&pubkeys[1..], Some(&key)));
+    }
 }
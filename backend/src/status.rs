@@ -0,0 +1,93 @@
+use crate::manifest::read_manifest;
+use crate::rest::RestClient;
+use crate::stats::StatsTable;
+use crate::{db, MainError};
+use diesel::SqliteConnection;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Returns a JSON overview of the database: the heights covered by each stats
+/// table and the versions of their stats, the last sync run, the freshness of
+/// the CSV export and how far the database is behind the node's tip. The node
+/// being unreachable is reported, not returned as an error.
+pub fn status(
+    conn: &mut SqliteConnection,
+    client: &RestClient,
+    csv_path: &Path,
+) -> Result<Value, MainError> {
+    let mut tables = serde_json::Map::new();
+    for table in StatsTable::ALL {
+        let coverage = db::table_coverage(conn, table)?;
+        let versions: serde_json::Map<String, Value> = db::stats_version_counts(conn, table)?
+            .iter()
+            .map(|v| (v.version.to_string(), json!(v.heights)))
+            .collect();
+        tables.insert(
+            table.table_name().to_string(),
+            json!({
+                "rows": coverage.rows,
+                "min_height": coverage.min_height,
+                "max_height": coverage.max_height,
+                "version": table.version(),
+                "heights_by_version": versions,
+            }),
+        );
+    }
+
+    let last_sync_run = db::last_sync_run(conn)?.map(|run| {
+        json!({
+            "id": run.id,
+            "started_at": run.started_at,
+            "finished_at": run.finished_at,
+            "software_version": run.software_version,
+            "node_implementation": run.node_implementation,
+            "min_height": run.min_height,
+            "max_height": run.max_height,
+            "blocks_processed": run.blocks_processed,
+            "blocks_failed_validation": run.blocks_failed_validation,
+            "pruned_min_height": run.pruned_min_height,
+            "pruned_max_height": run.pruned_max_height,
+            "error": run.error,
+        })
+    });
+
+    let csv = match read_manifest(csv_path) {
+        Ok(manifest) => json!({
+            "path": csv_path.display().to_string(),
+            "generated_at": manifest.generated_at,
+            "max_height": manifest.max_height,
+            "last_date": manifest.last_date,
+            "files": manifest.files.len(),
+        }),
+        Err(e) => json!({
+            "path": csv_path.display().to_string(),
+            "error": e.to_string(),
+        }),
+    };
+
+    let db_height = db::get_db_block_height(conn)?;
+    let node = match client.chain_info() {
+        Ok(chain_info) => json!({
+            "chain": chain_info.chain,
+            "tip": chain_info.blocks,
+            "db_height": db_height,
+            "lag": match db_height {
+                Some(height) => chain_info.blocks.saturating_sub(height as u64),
+                None => chain_info.blocks + 1,
+            },
+        }),
+        Err(e) => json!({
+            "endpoint": client.endpoint(),
+            "db_height": db_height,
+            "error": e.to_string(),
+        }),
+    };
+
+    Ok(json!({
+        "tables": tables,
+        "pruned_below_height": db::pruned_below_height(conn)?,
+        "last_sync_run": last_sync_run,
+        "csv": csv,
+        "node": node,
+    }))
+}
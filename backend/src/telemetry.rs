@@ -0,0 +1,37 @@
+use opentelemetry::trace::{TraceContextExt, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+
+/// Sets up exporting the pipeline spans to an OpenTelemetry collector (e.g.
+/// Jaeger or Tempo) via OTLP/HTTP at `endpoint`, e.g.
+/// `http://localhost:4318/v1/traces`. Without it, the spans are no-ops. The
+/// returned provider has to be shut down to export the remaining spans.
+pub fn init_tracing(endpoint: &str) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+/// Starts a span as a child of the span in `parent`. The span ends once the
+/// returned context and all its clones are dropped.
+pub fn span(name: &'static str, parent: &Context, attributes: Vec<KeyValue>) -> Context {
+    let tracer = global::tracer(env!("CARGO_PKG_NAME"));
+    let span = tracer
+        .span_builder(name)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, parent);
+    parent.with_span(span)
+}
@@ -0,0 +1,316 @@
+use crate::manifest::{Manifest, LAST_UPDATED_FILENAME, MANIFEST_FILENAME};
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use bitcoin::hex::DisplayHex;
+use chrono::Utc;
+use log::{debug, info};
+use std::path::Path;
+use std::{env, error, fmt};
+
+const S3_SCHEME: &str = "s3://";
+const S3_DEFAULT_REGION: &str = "us-east-1";
+
+#[derive(Debug)]
+pub enum UploadError {
+    MinReq(minreq::Error),
+    Http(i32, String),
+    InvalidUrl(String),
+    MissingEnv(&'static str),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UploadError::MinReq(e) => write!(f, "MinReq HTTP PUT request error: {:?}", e),
+            UploadError::Http(code, msg) => write!(f, "HTTP error: {} {}", code, msg),
+            UploadError::InvalidUrl(url) => write!(f, "Invalid upload URL: {}", url),
+            UploadError::MissingEnv(var) => {
+                write!(f, "Missing environment variable: {}", var)
+            }
+        }
+    }
+}
+
+impl error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            UploadError::MinReq(ref e) => Some(e),
+            UploadError::Http(_, _) => None,
+            UploadError::InvalidUrl(_) => None,
+            UploadError::MissingEnv(_) => None,
+        }
+    }
+}
+
+impl From<minreq::Error> for UploadError {
+    fn from(e: minreq::Error) -> Self {
+        UploadError::MinReq(e)
+    }
+}
+
+/// An S3 compatible object storage bucket. Objects are uploaded with
+/// AWS Signature Version 4 signed PUT requests.
+struct S3Destination {
+    /// scheme and host, e.g. `https://examplebucket.s3.us-east-1.amazonaws.com`
+    base_url: String,
+    /// host (and port, if explicitly set) used in the signed `host` header
+    host: String,
+    /// path prepended to the object names, e.g. `/bucket/prefix`
+    base_path: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+enum Destination {
+    /// Files are uploaded with a plain HTTP PUT to `<url>/<filename>`.
+    Http(String),
+    S3(S3Destination),
+}
+
+fn required_env(var: &'static str) -> Result<String, UploadError> {
+    env::var(var).map_err(|_| UploadError::MissingEnv(var))
+}
+
+impl Destination {
+    /// Parses an upload URL. `s3://bucket/prefix` uploads to S3, using the
+    /// usual `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`,
+    /// `AWS_REGION` and `AWS_ENDPOINT_URL` environment variables. A custom
+    /// endpoint (e.g. for MinIO or R2) is addressed path-style.
+    /// `http://` and `https://` URLs are used as-is for plain PUT requests.
+    fn from_url(url: &str) -> Result<Destination, UploadError> {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return Ok(Destination::Http(url.trim_end_matches('/').to_string()));
+        }
+
+        let Some(bucket_and_prefix) = url.strip_prefix(S3_SCHEME) else {
+            return Err(UploadError::InvalidUrl(url.to_string()));
+        };
+        let bucket_and_prefix = bucket_and_prefix.trim_end_matches('/');
+        let (bucket, prefix) = match bucket_and_prefix.split_once('/') {
+            Some((bucket, prefix)) => (bucket, format!("/{}", prefix)),
+            None => (bucket_and_prefix, String::new()),
+        };
+        if bucket.is_empty() {
+            return Err(UploadError::InvalidUrl(url.to_string()));
+        }
+
+        let region = env::var("AWS_REGION").unwrap_or(S3_DEFAULT_REGION.to_string());
+        let (base_url, host, base_path) = match env::var("AWS_ENDPOINT_URL") {
+            Ok(endpoint) => {
+                let endpoint = endpoint.trim_end_matches('/');
+                let (scheme, rest) = endpoint
+                    .split_once("://")
+                    .ok_or(UploadError::InvalidUrl(endpoint.to_string()))?;
+                let host = rest.split('/').next().unwrap_or_default().to_string();
+                (
+                    format!("{}://{}", scheme, host),
+                    host,
+                    format!("/{}{}", bucket, prefix),
+                )
+            }
+            Err(_) => {
+                let host = format!("{}.s3.{}.amazonaws.com", bucket, region);
+                (format!("https://{}", host), host, prefix)
+            }
+        };
+
+        Ok(Destination::S3(S3Destination {
+            base_url,
+            host,
+            base_path,
+            region,
+            access_key_id: required_env("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: required_env("AWS_SECRET_ACCESS_KEY")?,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        }))
+    }
+
+    fn put(&self, filename: &str, content_type: &str, body: Vec<u8>) -> Result<(), UploadError> {
+        let request = match self {
+            Destination::Http(url) => minreq::put(format!("{}/{}", url, filename)),
+            Destination::S3(s3) => s3.signed_put(filename, &body),
+        };
+        let response = request
+            .with_header("Content-Type", content_type)
+            .with_body(body)
+            .send()?;
+        if !(200..300).contains(&response.status_code) {
+            return Err(UploadError::Http(
+                response.status_code,
+                response.reason_phrase,
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// URI-encodes a path as required for the SigV4 canonical request.
+fn uri_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Calculates an AWS Signature Version 4 signature for a request without
+/// a query string. The headers must be lowercase and sorted by name.
+fn sigv4_signature(
+    method: &str,
+    canonical_uri: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    amz_date: &str,
+    region: &str,
+    secret_access_key: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method, canonical_uri, canonical_headers, signed_headers, payload_hash
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}/{}/s3/aws4_request\n{}",
+        amz_date,
+        date,
+        region,
+        sha256::Hash::hash(canonical_request.as_bytes())
+    );
+
+    let key_date = hmac_sha256(
+        format!("AWS4{}", secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key_region = hmac_sha256(&key_date, region.as_bytes());
+    let key_service = hmac_sha256(&key_region, b"s3");
+    let key_signing = hmac_sha256(&key_service, b"aws4_request");
+    hmac_sha256(&key_signing, string_to_sign.as_bytes()).to_lower_hex_string()
+}
+
+impl S3Destination {
+    fn signed_put(&self, filename: &str, body: &[u8]) -> minreq::Request {
+        let canonical_uri = uri_encode_path(&format!("{}/{}", self.base_path, filename));
+        let payload_hash = sha256::Hash::hash(body).to_string();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers: Vec<(&str, &str)> = vec![
+            ("host", &self.host),
+            ("x-amz-content-sha256", &payload_hash),
+            ("x-amz-date", &amz_date),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+
+        let signature = sigv4_signature(
+            "PUT",
+            &canonical_uri,
+            &headers,
+            &payload_hash,
+            &amz_date,
+            &self.region,
+            &self.secret_access_key,
+        );
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}/{}/s3/aws4_request, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            &amz_date[..8],
+            self.region,
+            headers
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<&str>>()
+                .join(";"),
+            signature
+        );
+
+        let mut request = minreq::put(format!("{}{}", self.base_url, canonical_uri))
+            .with_header("x-amz-content-sha256", payload_hash.clone())
+            .with_header("x-amz-date", amz_date.clone())
+            .with_header("Authorization", authorization);
+        if let Some(token) = &self.session_token {
+            request = request.with_header("x-amz-security-token", token.clone());
+        }
+        request
+    }
+}
+
+/// Uploads all files listed in the manifest of the export in `csv_path` and
+/// the manifest itself. The manifest is uploaded last, so consumers never see
+/// a manifest referencing files that haven't been uploaded yet. The
+/// last_updated.json summarizing the manifest follows it, if present.
+pub fn upload_export(upload_url: &str, csv_path: &Path) -> Result<(), crate::MainError> {
+    let destination = Destination::from_url(upload_url)?;
+    let manifest_content = std::fs::read(csv_path.join(MANIFEST_FILENAME))?;
+    let manifest: Manifest = serde_json::from_slice(&manifest_content)?;
+
+    info!(
+        "Uploading {} files and the manifest to {}",
+        manifest.files.len(),
+        upload_url
+    );
+    for file in manifest.files.iter() {
+        debug!("uploading {}..", file.name);
+        let content = std::fs::read(csv_path.join(&file.name))?;
+        destination.put(&file.name, "text/csv", content)?;
+    }
+    destination.put(MANIFEST_FILENAME, "application/json", manifest_content)?;
+    let last_updated_path = csv_path.join(LAST_UPDATED_FILENAME);
+    if last_updated_path.exists() {
+        let content = std::fs::read(last_updated_path)?;
+        destination.put(LAST_UPDATED_FILENAME, "application/json", content)?;
+    }
+    info!("Uploaded the export to {}", upload_url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sigv4_signature;
+
+    #[test]
+    fn test_sigv4_signature() {
+        // "Example: GET Object" from the AWS Signature Version 4 documentation
+        // https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+        let empty_payload_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let signature = sigv4_signature(
+            "GET",
+            "/test.txt",
+            &[
+                ("host", "examplebucket.s3.amazonaws.com"),
+                ("range", "bytes=0-9"),
+                ("x-amz-content-sha256", empty_payload_hash),
+                ("x-amz-date", "20130524T000000Z"),
+            ],
+            empty_payload_hash,
+            "20130524T000000Z",
+            "us-east-1",
+            "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+        );
+        assert_eq!(
+            signature,
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use bitcoin::consensus::encode;
+use bitcoin::hashes::Hash;
+use bitcoin::script::Instruction;
+use bitcoin::{
+    block::Header, error::UnprefixedHexError, opcodes, BlockHash, CompactTarget, Script, ScriptBuf,
+    Target, Transaction, Witness,
+};
+use std::{error, fmt};
+
+use crate::rest::Block;
+
+/// Blocks per difficulty adjustment period.
+pub const RETARGET_INTERVAL: i64 = 2016;
+
+/// Start of the witness commitment output scriptPubKey: OP_RETURN, a 36 byte
+/// push and the commitment header (BIP141).
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Header of the push in the witness commitment holding the signet solution
+/// (BIP325).
+const SIGNET_HEADER: [u8; 4] = [0xec, 0xc7, 0xda, 0xa2];
+
+/// A soft integrity check of a block returned by the REST interface failed.
+/// The stats are still calculated, but the block likely isn't what the node
+/// has stored on disk.
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The bits field isn't valid hex.
+    InvalidBits(UnprefixedHexError),
+    /// The hash of the header fields doesn't match the stated block hash.
+    HashMismatch {
+        stated: BlockHash,
+        computed: BlockHash,
+    },
+    /// The block hash doesn't meet the target of the stated bits.
+    InsufficientWork,
+    /// The coinbase transaction can't be deserialized.
+    InvalidCoinbase(encode::Error),
+    /// The coinbase has no signet solution, but the challenge requires one.
+    MissingSignetSolution,
+    /// The signet solution isn't a scriptSig followed by a witness.
+    InvalidSignetSolution(encode::Error),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::InvalidBits(e) => write!(f, "invalid bits: {}", e),
+            ValidationError::HashMismatch { stated, computed } => write!(
+                f,
+                "header hashes to {} but the block hash is {}",
+                computed, stated
+            ),
+            ValidationError::InsufficientWork => {
+                write!(f, "block hash doesn't meet the target")
+            }
+            ValidationError::InvalidCoinbase(e) => write!(f, "invalid coinbase: {}", e),
+            ValidationError::MissingSignetSolution => {
+                write!(f, "coinbase has no signet solution")
+            }
+            ValidationError::InvalidSignetSolution(e) => {
+                write!(f, "invalid signet solution: {}", e)
+            }
+        }
+    }
+}
+
+impl error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ValidationError::InvalidBits(ref e) => Some(e),
+            ValidationError::HashMismatch { .. } => None,
+            ValidationError::InsufficientWork => None,
+            ValidationError::InvalidCoinbase(ref e) => Some(e),
+            ValidationError::MissingSignetSolution => None,
+            ValidationError::InvalidSignetSolution(ref e) => Some(e),
+        }
+    }
+}
+
+/// Rebuilds the header of the block from the REST response and checks that it
+/// hashes to the stated block hash and that the hash meets the stated target.
+pub fn validate_header(block: &Block) -> Result<(), ValidationError> {
+    let bits =
+        CompactTarget::from_unprefixed_hex(&block.bits).map_err(ValidationError::InvalidBits)?;
+    let header = Header {
+        version: block.version,
+        // the genesis block has no previous block
+        prev_blockhash: block.previous_block_hash.unwrap_or(BlockHash::all_zeros()),
+        merkle_root: block.merkle_root,
+        time: block.time,
+        bits,
+        nonce: block.nonce,
+    };
+
+    let computed = header.block_hash();
+    if computed != block.hash {
+        return Err(ValidationError::HashMismatch {
+            stated: block.hash,
+            computed,
+        });
+    }
+    if !Target::from(bits).is_met_by(computed) {
+        return Err(ValidationError::InsufficientWork);
+    }
+    Ok(())
+}
+
+/// Checks that the witness commitment of the coinbase carries a signet
+/// solution for the given challenge. Only the structure of the solution is
+/// checked, the challenge script isn't executed. The genesis block has no
+/// solution and shouldn't be passed.
+pub fn validate_signet_solution(block: &Block, challenge: &Script) -> Result<(), ValidationError> {
+    let coinbase = block.txdata[0]
+        .transaction()
+        .map_err(ValidationError::InvalidCoinbase)?;
+    check_signet_solution(&coinbase, challenge)
+}
+
+fn check_signet_solution(
+    coinbase: &Transaction,
+    challenge: &Script,
+) -> Result<(), ValidationError> {
+    // the last output with the prefix is the commitment
+    let solution = coinbase
+        .output
+        .iter()
+        .rev()
+        .find(|output| {
+            output
+                .script_pubkey
+                .as_bytes()
+                .starts_with(&WITNESS_COMMITMENT_PREFIX)
+        })
+        .and_then(|output| {
+            output
+                .script_pubkey
+                .instructions()
+                .filter_map(|instruction| match instruction {
+                    Ok(Instruction::PushBytes(push)) => {
+                        push.as_bytes().strip_prefix(&SIGNET_HEADER)
+                    }
+                    _ => None,
+                })
+                .next()
+        });
+
+    match solution {
+        Some(solution) => {
+            let (_script_sig, consumed) = encode::deserialize_partial::<ScriptBuf>(solution)
+                .map_err(ValidationError::InvalidSignetSolution)?;
+            encode::deserialize::<Witness>(&solution[consumed..])
+                .map_err(ValidationError::InvalidSignetSolution)?;
+            Ok(())
+        }
+        // an empty solution only satisfies a trivially true challenge
+        None if challenge.as_bytes() == [opcodes::OP_TRUE.to_u8()] => Ok(()),
+        None => Err(ValidationError::MissingSignetSolution),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_signet_solution, validate_header, ValidationError};
+    use super::{SIGNET_HEADER, WITNESS_COMMITMENT_PREFIX};
+    use crate::rest::Block;
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::{absolute, opcodes, transaction, Amount, ScriptBuf, Transaction, TxOut, Witness};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn load_block(height: u64) -> Block {
+        let buffer = BufReader::new(File::open(format!("./testdata/{}.json", height)).unwrap());
+        serde_json::from_reader(buffer).expect("test block json to be valid")
+    }
+
+    #[test]
+    fn test_validate_header() {
+        let block = load_block(739990);
+        assert!(validate_header(&block).is_ok());
+
+        let mut tampered = load_block(739990);
+        tampered.nonce += 1;
+        assert!(matches!(
+            validate_header(&tampered),
+            Err(ValidationError::HashMismatch { .. })
+        ));
+
+        // a stated hash matching the tampered header doesn't meet the target
+        let mut tampered = load_block(739990);
+        tampered.nonce += 1;
+        tampered.hash = match validate_header(&tampered) {
+            Err(ValidationError::HashMismatch { computed, .. }) => computed,
+            _ => panic!("expected a hash mismatch"),
+        };
+        assert!(matches!(
+            validate_header(&tampered),
+            Err(ValidationError::InsufficientWork)
+        ));
+    }
+
+    #[test]
+    fn test_check_signet_solution() {
+        let coinbase = |solution: Option<Vec<u8>>| {
+            let mut builder = Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_slice(
+                    PushBytesBuf::try_from([&WITNESS_COMMITMENT_PREFIX[2..], &[0u8; 32]].concat())
+                        .unwrap(),
+                );
+            if let Some(solution) = solution {
+                builder = builder.push_slice(
+                    PushBytesBuf::try_from([&SIGNET_HEADER[..], &solution].concat()).unwrap(),
+                );
+            }
+            Transaction {
+                version: transaction::Version::TWO,
+                lock_time: absolute::LockTime::ZERO,
+                input: vec![],
+                output: vec![TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: builder.into_script(),
+                }],
+            }
+        };
+        // the default signet challenge, a 1-of-2 multisig
+        let challenge = ScriptBuf::from_hex("512103ad5e0edad18cb1f0fc0d28a3d4f1f3e445640337489abb10404f2d1e086be430210359ef5021964fe22d6f8e05b2463c9540ce96883fe3b278760f048f5189f2e6c452ae").unwrap();
+        let op_true = ScriptBuf::from_bytes(vec![opcodes::OP_TRUE.to_u8()]);
+
+        let mut solution = bitcoin::consensus::serialize(&ScriptBuf::new());
+        solution.extend(bitcoin::consensus::serialize(&Witness::from_slice(&[
+            vec![],
+            vec![0x30; 71],
+        ])));
+        assert!(check_signet_solution(&coinbase(Some(solution.clone())), &challenge).is_ok());
+        assert!(matches!(
+            check_signet_solution(&coinbase(Some(solution[..10].to_vec())), &challenge),
+            Err(ValidationError::InvalidSignetSolution(_))
+        ));
+        assert!(matches!(
+            check_signet_solution(&coinbase(None), &challenge),
+            Err(ValidationError::MissingSignetSolution)
+        ));
+        assert!(check_signet_solution(&coinbase(None), &op_true).is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::db;
+use crate::stats::StatsTable;
+use crate::MainError;
+use diesel::SqliteConnection;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+/// Number of violating heights listed per invariant in the report.
+const MAX_LISTED_HEIGHTS: usize = 10;
+
+/// A relation between the counters of different stats tables that holds for
+/// every block. A violation means one of the tables was calculated by a
+/// buggy or different version.
+struct Invariant {
+    name: &'static str,
+    description: &'static str,
+    tables: &'static [StatsTable],
+    /// SQL condition over the joined tables, referenced by their table names
+    holds: &'static str,
+}
+
+const INVARIANTS: [Invariant; 5] = [
+    Invariant {
+        name: "spends_in_same_block",
+        description: "transactions spending outputs created in the same block have at least one such input each",
+        tables: &[StatsTable::Tx, StatsTable::Input],
+        holds: "tx_stats.tx_spending_newly_created_utxos <= input_stats.inputs_spend_in_same_block
+            AND (tx_stats.tx_spending_newly_created_utxos = 0) = (input_stats.inputs_spend_in_same_block = 0)",
+    },
+    Invariant {
+        name: "input_types",
+        description: "the input type counts add up to the inputs of the block",
+        tables: &[StatsTable::Block, StatsTable::Input],
+        holds: "input_stats.inputs_p2pk + input_stats.inputs_p2pkh + input_stats.inputs_nested_p2wpkh
+            + input_stats.inputs_p2wpkh + input_stats.inputs_p2ms + input_stats.inputs_p2sh
+            + input_stats.inputs_nested_p2wsh + input_stats.inputs_p2wsh + input_stats.inputs_coinbase
+            + input_stats.inputs_witness_coinbase + input_stats.inputs_p2tr_keypath
+            + input_stats.inputs_p2tr_scriptpath + input_stats.inputs_unknown + input_stats.inputs_p2a
+            = block_stats.inputs",
+    },
+    Invariant {
+        name: "output_types",
+        description: "the output type counts add up to the outputs of the block",
+        tables: &[StatsTable::Block, StatsTable::Output],
+        holds: "output_stats.outputs_p2pk + output_stats.outputs_p2pkh + output_stats.outputs_p2wpkh
+            + output_stats.outputs_p2ms + output_stats.outputs_p2sh + output_stats.outputs_p2wsh
+            + output_stats.outputs_opreturn + output_stats.outputs_p2tr + output_stats.outputs_unknown
+            + output_stats.outputs_p2a
+            = block_stats.outputs",
+    },
+    Invariant {
+        name: "tx_versions",
+        description: "the transaction version counts add up to the transactions of the block",
+        tables: &[StatsTable::Block, StatsTable::Tx],
+        holds: "tx_stats.tx_version_1 + tx_stats.tx_version_2 + tx_stats.tx_version_3
+            + tx_stats.tx_version_unknown
+            = block_stats.transactions",
+    },
+    Invariant {
+        name: "tx_vsize_classes",
+        description: "the vsize class counts add up to the non-coinbase transactions of the block",
+        tables: &[StatsTable::Block, StatsTable::Tx],
+        holds: "tx_stats.tx_vsize_0_140 + tx_stats.tx_vsize_141_250 + tx_stats.tx_vsize_251_500
+            + tx_stats.tx_vsize_501_1000 + tx_stats.tx_vsize_1001_10000
+            + tx_stats.tx_vsize_10001_100000 + tx_stats.tx_vsize_100001_plus
+            = block_stats.transactions - 1",
+    },
+];
+
+/// Cross-checks related counters of the stats tables over the whole database,
+/// e.g. that the input type counts add up to the inputs of the block, and
+/// returns a JSON report of the heights where an invariant fails. Violations
+/// at heights where one of the tables has outdated stats usually go away with
+/// a re-sync, violations with current stats point to a bug. Returns whether
+/// all invariants hold and the report.
+pub fn verify(conn: &mut SqliteConnection) -> Result<(bool, Value), MainError> {
+    let mut invariants = serde_json::Map::new();
+    let mut all_hold = true;
+    for invariant in INVARIANTS.iter() {
+        let checked = db::invariant_checked_heights(conn, invariant.tables)?;
+        let violations = db::invariant_violations(conn, invariant.tables, invariant.holds)?;
+        let current = violations.iter().filter(|v| v.current).count();
+        if violations.is_empty() {
+            info!("{} holds at {} heights", invariant.name, checked);
+        } else {
+            all_hold = false;
+            warn!(
+                "{} fails at {} of {} heights ({} with current stats), e.g. at height {}",
+                invariant.name,
+                violations.len(),
+                checked,
+                current,
+                violations[0].height
+            );
+        }
+        invariants.insert(
+            invariant.name.to_string(),
+            json!({
+                "description": invariant.description,
+                "tables": invariant.tables.iter().map(|t| t.table_name()).collect::<Vec<_>>(),
+                "checked_heights": checked,
+                "violations": violations.len(),
+                "violations_with_current_stats": current,
+                "first_heights": violations
+                    .iter()
+                    .take(MAX_LISTED_HEIGHTS)
+                    .map(|v| v.height)
+                    .collect::<Vec<_>>(),
+            }),
+        );
+    }
+    Ok((all_hold, json!({ "invariants": invariants })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify;
+    use crate::db::test_utils::{stats_db, TEST_HEIGHTS};
+    use diesel::prelude::*;
+    use diesel::sql_query;
+
+    #[test]
+    fn test_verify() {
+        let (_dir, mut conn) = stats_db("verify-test", &TEST_HEIGHTS);
+
+        let (all_hold, report) = verify(&mut conn).unwrap();
+        assert!(all_hold, "{:#}", report);
+        assert_eq!(report["invariants"]["input_types"]["checked_heights"], 3);
+
+        sql_query("UPDATE block_stats SET inputs = inputs + 1 WHERE height = 739990")
+            .execute(&mut conn)
+            .unwrap();
+        let (all_hold, report) = verify(&mut conn).unwrap();
+        assert!(!all_hold);
+        let input_types = &report["invariants"]["input_types"];
+        assert_eq!(input_types["violations"], 1);
+        assert_eq!(input_types["violations_with_current_stats"], 1);
+        assert_eq!(input_types["first_heights"][0], 739990);
+        assert_eq!(report["invariants"]["output_types"]["violations"], 0);
+    }
+}
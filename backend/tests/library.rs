@@ -0,0 +1,22 @@
+use mainnet_observer_backend::{Block, Stats};
+use std::fs::File;
+use std::io::BufReader;
+
+fn load_block(height: u64) -> Block {
+    let buffer = BufReader::new(File::open(format!("./testdata/{}.json", height)).unwrap());
+    serde_json::from_reader(buffer).expect("test block json to be valid")
+}
+
+#[test]
+fn stats_from_block() {
+    let stats = Stats::from_block(load_block(739990)).expect("testdata blocks should not error");
+    assert_eq!(stats.block.height, 739990);
+    assert_eq!(stats.tx.height, 739990);
+
+    let mut parsed = load_block(739990);
+    parsed
+        .parse_transactions()
+        .expect("testdata transactions should be valid");
+    let stats_parsed = Stats::from_block(parsed).expect("testdata blocks should not error");
+    assert_eq!(stats, stats_parsed);
+}
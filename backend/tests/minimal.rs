@@ -1,10 +1,15 @@
+use bitcoin::Network;
 use corepc_node as bitcoind;
 use diesel::SqliteConnection;
 use log::{error, info};
-use mainnet_observer_backend::{collect_statistics, db, write_csv_files, REORG_SAFETY_MARGIN};
+use mainnet_observer_backend::{
+    check_sync_lag, collect_statistics, db, write_csv_files, NodeImplementation, RestClient,
+    SyncOptions, REORG_SAFETY_MARGIN,
+};
 use rand::distr::{Alphanumeric, SampleString};
 use std::env;
 use std::fs;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 fn init_logger() {
@@ -65,7 +70,7 @@ fn rest_host_and_port(node: &corepc_node::Node) -> (String, u16) {
 }
 
 fn setup_db() -> Arc<Mutex<SqliteConnection>> {
-    let conn = match db::open_db_and_run_migrations(":memory:") {
+    let conn = match db::open_db_and_run_migrations(Path::new(":memory:")) {
         Ok(conn) => conn,
         Err(e) => {
             panic!("Could not open database: {}", e);
@@ -85,12 +90,25 @@ fn test_integration_minimal() {
     setup_chain(&node, BLOCKS_TO_MINE as usize);
 
     let (rest_host, rest_port) = rest_host_and_port(&node);
-    if let Err(e) = collect_statistics(
-        &rest_host,
+    let options = SyncOptions {
+        rest_host: rest_host.clone(),
         rest_port,
-        Arc::clone(&conn),
-        10, // Bitcoin Core v29 has 16, in the test use just use 10 of them.
-    ) {
+        node_implementation: NodeImplementation::BitcoinCore,
+        network: Network::Regtest,
+        num_threads: 10, // Bitcoin Core v29 has 16, in the test use just use 10 of them.
+        max_requests_per_second: None,
+        max_memory_mb: None,
+        parse_in_fetcher: false,
+        track_coinbase_spends: false,
+        capture_contributors: None,
+        track_opcodes: false,
+        track_script_transitions: false,
+        track_novel_scripts: false,
+        feerate_percentiles: vec![],
+        skip: vec![],
+        skip_pruned: false,
+    };
+    if let Err(e) = collect_statistics(&options, Arc::clone(&conn)) {
         panic!("Failed to collect statistics: {:?}", e);
     }
 
@@ -105,6 +123,21 @@ fn test_integration_minimal() {
         );
     }
 
+    let client = RestClient::new(&rest_host, rest_port, None);
+    // the most recent blocks are skipped as reorg safety margin
+    assert_eq!(
+        check_sync_lag(&client, Arc::clone(&conn), 100, None).unwrap(),
+        REORG_SAFETY_MARGIN
+    );
+
+    let headers = client
+        .headers_at_height_range(10, 20)
+        .expect("headers should be fetched");
+    assert_eq!(headers.len(), 20);
+    for pair in headers.windows(2) {
+        assert_eq!(pair[1].prev_blockhash, pair[0].block_hash());
+    }
+
     let mut dir = env::temp_dir();
     dir.push(format!(
         "mainnet-observer-integration-tests-{}",
@@ -114,7 +147,7 @@ fn test_integration_minimal() {
     info!("Using temp directory {} for csv files", dir.display());
 
     let mut failed = false;
-    if let Err(e) = write_csv_files(&dir.to_string_lossy(), Arc::clone(&conn)) {
+    if let Err(e) = write_csv_files(&dir, Arc::clone(&conn), None, None) {
         failed = true;
         error!("Failed to write csv files: {:?}", e);
     }